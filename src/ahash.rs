@@ -0,0 +1,73 @@
+// Copyright (c) 2016 multimap developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! MultiMap hashing keys with aHash, enabled by the `ahash` feature.
+//!
+//! aHash is much faster than the default SipHash for short keys such as
+//! integers while still being randomly seeded. The default hasher of
+//! `MultiMap` is left unchanged, since a feature switching it would change
+//! the type of `MultiMap<K, V>` for every crate in the build.
+
+extern crate ahash;
+
+use std::hash::Hash;
+
+use MultiMap;
+
+/// A MultiMap using aHash's `RandomState` to hash keys.
+pub type AHashMultiMap<K, V> = MultiMap<K, V, self::ahash::RandomState>;
+
+impl<K, V> MultiMap<K, V, self::ahash::RandomState>
+where
+    K: Eq + Hash,
+{
+    /// Creates an empty MultiMap hashing keys with aHash.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::ahash::AHashMultiMap;
+    /// use multimap::MultiMap;
+    ///
+    /// let mut map: AHashMultiMap<u32, &str> = MultiMap::with_ahash();
+    /// map.insert(1, "a");
+    /// assert_eq!(map.get_vec(&1), Some(&vec!["a"]));
+    /// ```
+    pub fn with_ahash() -> Self {
+        MultiMap::with_hasher(Default::default())
+    }
+
+    /// Creates an empty MultiMap with the given initial capacity, hashing keys
+    /// with aHash.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::ahash::AHashMultiMap;
+    /// use multimap::MultiMap;
+    ///
+    /// let map: AHashMultiMap<u32, &str> = MultiMap::with_capacity_and_ahash(20);
+    /// assert!(map.capacity() >= 20);
+    /// ```
+    pub fn with_capacity_and_ahash(capacity: usize) -> Self {
+        MultiMap::with_capacity_and_hasher(capacity, Default::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn collects_with_ahash() {
+        let map: AHashMultiMap<u32, u32> = (0..100).map(|i| (i % 10, i)).collect();
+        assert_eq!(map.len(), 10);
+        assert_eq!(map.total_len(), 100);
+        assert_eq!(map.values_of(&3), [3, 13, 23, 33, 43, 53, 63, 73, 83, 93]);
+    }
+}
@@ -0,0 +1,72 @@
+// Copyright (c) 2016 multimap developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! Arbitrary implementation for MultiMap, enabled by the `arbitrary` feature.
+//!
+//! The map is generated as a sequence of keys, each followed by a non-empty
+//! list of values whose length is drawn from the remaining input, so a
+//! single key can take up most of it. Keys may repeat, in which case their
+//! values are appended. Keys never end up with an empty vector.
+
+extern crate arbitrary;
+
+use self::arbitrary::{Arbitrary, Result, Unstructured};
+use std::hash::{BuildHasher, Hash};
+
+use MultiMap;
+
+impl<'a, K, V, S> Arbitrary<'a> for MultiMap<K, V, S>
+where
+    K: Arbitrary<'a> + Eq + Hash,
+    V: Arbitrary<'a>,
+    S: BuildHasher + Default,
+{
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let mut map = MultiMap::with_hasher(S::default());
+        while u.arbitrary()? {
+            let key = K::arbitrary(u)?;
+            let len = u.arbitrary_len::<V>()?.max(1);
+            let values = map.inner.entry(key).or_default();
+            values.reserve(len);
+            for _ in 0..len {
+                values.push(V::arbitrary(u)?);
+            }
+        }
+        Ok(map)
+    }
+
+    fn arbitrary_take_rest(mut u: Unstructured<'a>) -> Result<Self> {
+        Self::arbitrary(&mut u)
+    }
+
+    fn size_hint(_depth: usize) -> (usize, Option<usize>) {
+        (0, None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn never_holds_empty_vectors() {
+        let data: Vec<u8> = (0..4096u32).map(|i| (i * 7919 % 251) as u8).collect();
+        let mut u = Unstructured::new(&data);
+
+        let mut saw_many_values = false;
+        while !u.is_empty() {
+            let map = MultiMap::<u8, u16>::arbitrary(&mut u).unwrap();
+            assert!(map.iter_all().all(|(_, values)| !values.is_empty()));
+            saw_many_values |= map.iter_all().any(|(_, values)| values.len() > 1);
+        }
+        assert!(saw_many_values);
+
+        let map = MultiMap::<u8, u16>::arbitrary_take_rest(Unstructured::new(&data)).unwrap();
+        assert!(map.iter_all().all(|(_, values)| !values.is_empty()));
+    }
+}
@@ -0,0 +1,541 @@
+// Copyright (c) 2016 multimap developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! A multimap which can be queried in both directions.
+//!
+//! BiMultiMap keeps two MultiMaps in sync: one mapping every key to its
+//! values and one mapping every value to the keys it is stored under. Every
+//! key-value-pair is therefore stored twice, which is why both keys and
+//! values have to be `Clone`.
+//!
+//! # Examples
+//!
+//! ```
+//! use multimap::BiMultiMap;
+//!
+//! let mut authors = BiMultiMap::new();
+//! authors.insert("Good Omens", "Pratchett");
+//! authors.insert("Good Omens", "Gaiman");
+//! authors.insert("Mort", "Pratchett");
+//!
+//! assert_eq!(authors.get_vec("Good Omens"), Some(&vec!["Pratchett", "Gaiman"]));
+//! assert_eq!(authors.get_by_value("Pratchett"), Some(&vec!["Good Omens", "Mort"]));
+//!
+//! authors.remove("Good Omens");
+//! assert_eq!(authors.get_by_value("Pratchett"), Some(&vec!["Mort"]));
+//! assert_eq!(authors.get_by_value("Gaiman"), None);
+//! ```
+
+use std::borrow::Borrow;
+use std::collections::hash_map::{Keys, RandomState};
+use std::fmt::{self, Debug};
+use std::hash::{BuildHasher, Hash};
+use std::iter::{FromIterator, IntoIterator, Iterator};
+use std::vec::Vec;
+
+use IterAll;
+use MultiMap;
+
+#[derive(Clone)]
+pub struct BiMultiMap<K, V, S = RandomState> {
+    forward: MultiMap<K, V, S>,
+    backward: MultiMap<V, K, S>,
+}
+
+impl<K, V> BiMultiMap<K, V>
+where
+    K: Eq + Hash + Clone,
+    V: Eq + Hash + Clone,
+{
+    /// Creates an empty BiMultiMap
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::BiMultiMap;
+    ///
+    /// let mut map: BiMultiMap<&str, isize> = BiMultiMap::new();
+    /// ```
+    pub fn new() -> BiMultiMap<K, V> {
+        BiMultiMap {
+            forward: MultiMap::new(),
+            backward: MultiMap::new(),
+        }
+    }
+}
+
+impl<K, V, S> BiMultiMap<K, V, S>
+where
+    K: Eq + Hash + Clone,
+    V: Eq + Hash + Clone,
+    S: BuildHasher + Clone,
+{
+    /// Creates an empty BiMultiMap which will use the given hash builder to hash
+    /// both keys and values.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::BiMultiMap;
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let s = RandomState::new();
+    /// let mut map: BiMultiMap<&str, isize> = BiMultiMap::with_hasher(s);
+    /// ```
+    pub fn with_hasher(hash_builder: S) -> BiMultiMap<K, V, S> {
+        BiMultiMap {
+            forward: MultiMap::with_hasher(hash_builder.clone()),
+            backward: MultiMap::with_hasher(hash_builder),
+        }
+    }
+
+    /// Inserts a key-value pair into both directions of the map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::BiMultiMap;
+    ///
+    /// let mut map = BiMultiMap::new();
+    /// map.insert("key", 42);
+    /// assert_eq!(map.get_by_value(&42), Some(&vec!["key"]));
+    /// ```
+    pub fn insert(&mut self, k: K, v: V) {
+        self.backward.insert(v.clone(), k.clone());
+        self.forward.insert(k, v);
+    }
+
+    /// Returns true if the map contains a value for the specified key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::BiMultiMap;
+    ///
+    /// let mut map = BiMultiMap::new();
+    /// map.insert(1, 42);
+    /// assert_eq!(map.contains_key(&1), true);
+    /// assert_eq!(map.contains_key(&42), false);
+    /// ```
+    pub fn contains_key<Q>(&self, k: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        self.forward.contains_key(k)
+    }
+
+    /// Returns true if the value is stored under any key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::BiMultiMap;
+    ///
+    /// let mut map = BiMultiMap::new();
+    /// map.insert(1, 42);
+    /// assert_eq!(map.contains_value(&42), true);
+    /// assert_eq!(map.contains_value(&1), false);
+    /// ```
+    pub fn contains_value<W>(&self, v: &W) -> bool
+    where
+        V: Borrow<W>,
+        W: ?Sized + Eq + Hash,
+    {
+        self.backward.contains_key(v)
+    }
+
+    /// Returns a reference to the values stored under the key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::BiMultiMap;
+    ///
+    /// let mut map = BiMultiMap::new();
+    /// map.insert(1, 42);
+    /// map.insert(1, 1337);
+    /// assert_eq!(map.get_vec(&1), Some(&vec![42, 1337]));
+    /// ```
+    pub fn get_vec<Q>(&self, k: &Q) -> Option<&Vec<V>>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        self.forward.get_vec(k)
+    }
+
+    /// Returns a reference to the keys the value is stored under, in the
+    /// order in which the pairs were inserted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::BiMultiMap;
+    ///
+    /// let mut map = BiMultiMap::new();
+    /// map.insert(1, 42);
+    /// map.insert(2, 42);
+    /// assert_eq!(map.get_by_value(&42), Some(&vec![1, 2]));
+    /// ```
+    pub fn get_by_value<W>(&self, v: &W) -> Option<&Vec<K>>
+    where
+        V: Borrow<W>,
+        W: ?Sized + Eq + Hash,
+    {
+        self.backward.get_vec(v)
+    }
+
+    /// Removes a key from the map, returning its values. Each of the
+    /// key-value pairs is removed from the inverse index as well.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::BiMultiMap;
+    ///
+    /// let mut map = BiMultiMap::new();
+    /// map.insert(1, 42);
+    /// map.insert(2, 42);
+    /// assert_eq!(map.remove(&1), Some(vec![42]));
+    /// assert_eq!(map.get_by_value(&42), Some(&vec![2]));
+    /// ```
+    pub fn remove<Q>(&mut self, k: &Q) -> Option<Vec<V>>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        let values = self.forward.remove(k)?;
+        for v in &values {
+            remove_one(&mut self.backward, v, |key| key.borrow() == k);
+        }
+        Some(values)
+    }
+
+    /// Removes a value from every key it is stored under, returning those
+    /// keys.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::BiMultiMap;
+    ///
+    /// let mut map = BiMultiMap::new();
+    /// map.insert(1, 42);
+    /// map.insert(1, 1337);
+    /// map.insert(2, 42);
+    /// assert_eq!(map.remove_by_value(&42), Some(vec![1, 2]));
+    /// assert_eq!(map.get_vec(&1), Some(&vec![1337]));
+    /// assert!(!map.contains_key(&2));
+    /// ```
+    pub fn remove_by_value<W>(&mut self, v: &W) -> Option<Vec<K>>
+    where
+        V: Borrow<W>,
+        W: ?Sized + Eq + Hash,
+    {
+        let keys = self.backward.remove(v)?;
+        for k in &keys {
+            remove_one(&mut self.forward, k, |value| value.borrow() == v);
+        }
+        Some(keys)
+    }
+
+    /// Removes a single occurrence of the key-value pair from both directions
+    /// of the map. Returns true if the pair was present.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::BiMultiMap;
+    ///
+    /// let mut map = BiMultiMap::new();
+    /// map.insert(1, 42);
+    /// map.insert(1, 1337);
+    /// assert!(map.remove_pair(&1, &42));
+    /// assert!(!map.remove_pair(&1, &42));
+    /// assert!(!map.contains_value(&42));
+    /// ```
+    pub fn remove_pair(&mut self, k: &K, v: &V) -> bool {
+        if remove_one(&mut self.forward, k, |value| value == v) {
+            remove_one(&mut self.backward, v, |key| key == k);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Returns the number of keys in the map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::BiMultiMap;
+    ///
+    /// let mut map = BiMultiMap::new();
+    /// map.insert(1, 42);
+    /// map.insert(2, 42);
+    /// assert_eq!(map.len(), 2);
+    /// ```
+    pub fn len(&self) -> usize {
+        self.forward.len()
+    }
+
+    /// Returns the number of distinct values in the map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::BiMultiMap;
+    ///
+    /// let mut map = BiMultiMap::new();
+    /// map.insert(1, 42);
+    /// map.insert(2, 42);
+    /// assert_eq!(map.values_len(), 1);
+    /// ```
+    pub fn values_len(&self) -> usize {
+        self.backward.len()
+    }
+
+    /// Returns true if the map contains no elements.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::BiMultiMap;
+    ///
+    /// let mut map = BiMultiMap::new();
+    /// assert!(map.is_empty());
+    /// map.insert(1, 42);
+    /// assert!(!map.is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.forward.is_empty()
+    }
+
+    /// Clears the map, removing all key-value pairs.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::BiMultiMap;
+    ///
+    /// let mut map = BiMultiMap::new();
+    /// map.insert(1, 42);
+    /// map.clear();
+    /// assert!(map.is_empty());
+    /// assert!(!map.contains_value(&42));
+    /// ```
+    pub fn clear(&mut self) {
+        self.forward.clear();
+        self.backward.clear();
+    }
+
+    /// An iterator visiting all keys in arbitrary order.
+    pub fn keys(&self) -> Keys<'_, K, Vec<V>> {
+        self.forward.keys()
+    }
+
+    /// An iterator visiting all distinct values in arbitrary order.
+    pub fn values(&self) -> Keys<'_, V, Vec<K>> {
+        self.backward.keys()
+    }
+
+    /// An iterator visiting all keys and their values in arbitrary order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::BiMultiMap;
+    ///
+    /// let mut map = BiMultiMap::new();
+    /// map.insert(1, 42);
+    /// map.insert(1, 1337);
+    ///
+    /// let pairs: Vec<_> = map.iter_all().collect();
+    /// assert_eq!(pairs, [(&1, &vec![42, 1337])]);
+    /// ```
+    pub fn iter_all(&self) -> IterAll<'_, K, Vec<V>> {
+        self.forward.iter_all()
+    }
+
+    /// An iterator visiting all values and the keys they are stored under, in
+    /// arbitrary order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::BiMultiMap;
+    ///
+    /// let mut map = BiMultiMap::new();
+    /// map.insert(1, 42);
+    /// map.insert(2, 42);
+    ///
+    /// let pairs: Vec<_> = map.iter_all_by_value().collect();
+    /// assert_eq!(pairs, [(&42, &vec![1, 2])]);
+    /// ```
+    pub fn iter_all_by_value(&self) -> IterAll<'_, V, Vec<K>> {
+        self.backward.iter_all()
+    }
+
+    /// An iterator visiting every key-value pair in arbitrary order.
+    pub fn flat_iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.forward.flat_iter()
+    }
+
+    /// Returns a reference to the map from keys to values.
+    pub fn as_multimap(&self) -> &MultiMap<K, V, S> {
+        &self.forward
+    }
+
+    /// Returns a reference to the map from values to keys.
+    pub fn as_inverse(&self) -> &MultiMap<V, K, S> {
+        &self.backward
+    }
+}
+
+// Removes the first value under `key` which matches the predicate and drops the
+// key if that leaves it without values. Returns true if a value was removed.
+fn remove_one<K, V, S, Q, F>(map: &mut MultiMap<K, V, S>, key: &Q, f: F) -> bool
+where
+    K: Eq + Hash + Borrow<Q>,
+    Q: ?Sized + Eq + Hash,
+    S: BuildHasher,
+    F: FnMut(&V) -> bool,
+{
+    let now_empty = match map.get_vec_mut(key) {
+        Some(values) => match values.iter().position(f) {
+            Some(index) => {
+                values.remove(index);
+                values.is_empty()
+            }
+            None => return false,
+        },
+        None => return false,
+    };
+    if now_empty {
+        map.remove(key);
+    }
+    true
+}
+
+impl<K, V, S> Debug for BiMultiMap<K, V, S>
+where
+    K: Eq + Hash + Debug,
+    V: Debug,
+    S: BuildHasher,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.forward.fmt(f)
+    }
+}
+
+impl<K, V, S> PartialEq for BiMultiMap<K, V, S>
+where
+    K: Eq + Hash,
+    V: PartialEq,
+    S: BuildHasher,
+{
+    fn eq(&self, other: &BiMultiMap<K, V, S>) -> bool {
+        self.forward == other.forward
+    }
+}
+
+impl<K, V, S> Eq for BiMultiMap<K, V, S>
+where
+    K: Eq + Hash,
+    V: Eq,
+    S: BuildHasher,
+{
+}
+
+impl<K, V, S> Default for BiMultiMap<K, V, S>
+where
+    K: Eq + Hash,
+    V: Eq + Hash,
+    S: BuildHasher + Default,
+{
+    fn default() -> BiMultiMap<K, V, S> {
+        BiMultiMap {
+            forward: Default::default(),
+            backward: Default::default(),
+        }
+    }
+}
+
+impl<K, V, S> FromIterator<(K, V)> for BiMultiMap<K, V, S>
+where
+    K: Eq + Hash + Clone,
+    V: Eq + Hash + Clone,
+    S: BuildHasher + Clone + Default,
+{
+    fn from_iter<T: IntoIterator<Item = (K, V)>>(iterable: T) -> BiMultiMap<K, V, S> {
+        let mut map = BiMultiMap::default();
+        map.extend(iterable);
+        map
+    }
+}
+
+impl<K, V, S> Extend<(K, V)> for BiMultiMap<K, V, S>
+where
+    K: Eq + Hash + Clone,
+    V: Eq + Hash + Clone,
+    S: BuildHasher + Clone,
+{
+    fn extend<T: IntoIterator<Item = (K, V)>>(&mut self, iter: T) {
+        for (k, v) in iter {
+            self.insert(k, v);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn duplicate_pairs_stay_in_sync() {
+        let mut m = BiMultiMap::new();
+        m.insert(1, 42);
+        m.insert(1, 42);
+        assert_eq!(m.get_by_value(&42), Some(&vec![1, 1]));
+        assert!(m.remove_pair(&1, &42));
+        assert_eq!(m.get_vec(&1), Some(&vec![42]));
+        assert_eq!(m.get_by_value(&42), Some(&vec![1]));
+    }
+
+    #[test]
+    fn remove_cleans_inverse() {
+        let mut m = BiMultiMap::new();
+        m.insert("a", 1);
+        m.insert("a", 2);
+        m.insert("b", 2);
+        assert_eq!(m.remove("a"), Some(vec![1, 2]));
+        assert!(!m.contains_value(&1));
+        assert_eq!(m.get_by_value(&2), Some(&vec!["b"]));
+        assert_eq!(m.values_len(), 1);
+    }
+
+    #[test]
+    fn remove_by_value_cleans_forward() {
+        let mut m = BiMultiMap::new();
+        m.insert("a", 1);
+        m.insert("b", 1);
+        m.insert("b", 2);
+        assert_eq!(m.remove_by_value(&1), Some(vec!["a", "b"]));
+        assert!(!m.contains_key("a"));
+        assert_eq!(m.get_vec("b"), Some(&vec![2]));
+        assert_eq!(m.remove_by_value(&1), None);
+    }
+
+    #[test]
+    fn from_iterator() {
+        let m: BiMultiMap<&str, i32> = vec![("a", 1), ("b", 1)].into_iter().collect();
+        assert_eq!(m.len(), 2);
+        assert_eq!(m.values_len(), 1);
+    }
+}
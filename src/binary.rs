@@ -0,0 +1,337 @@
+// Copyright (c) 2016 multimap developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! A compact binary format for persisting a MultiMap without a serde
+//! framework.
+//!
+//! The map is written as the number of keys followed by each key, the
+//! number of its values and the values themselves. Counts and lengths are
+//! LEB128 varints, as is `usize`; other integers and floats are
+//! little-endian and strings are length-prefixed UTF-8. Keys and values are
+//! written through the `Encode` and `Decode` traits, which are implemented
+//! for the primitive types, `String` and `Vec<T>`.
+//!
+//! # Examples
+//!
+//! ```
+//! use multimap::MultiMap;
+//!
+//! let mut map = MultiMap::new();
+//! map.insert_many("ports".to_string(), vec![80u16, 443]);
+//!
+//! let mut bytes = Vec::new();
+//! map.write_to(&mut bytes).unwrap();
+//!
+//! let read: MultiMap<String, u16> = MultiMap::read_from(&bytes[..]).unwrap();
+//! assert_eq!(read, map);
+//! ```
+
+use std::hash::{BuildHasher, Hash};
+use std::io::{self, Read, Write};
+
+use MultiMap;
+
+/// The largest number of elements reserved up front from a count read from
+/// the input, so a corrupt count can't trigger a huge allocation.
+const MAX_PREALLOCATION: usize = 4096;
+
+/// A type which can be written in the binary format.
+pub trait Encode {
+    /// Writes the value to `writer`.
+    fn encode<W: Write + ?Sized>(&self, writer: &mut W) -> io::Result<()>;
+}
+
+/// A type which can be read from the binary format.
+pub trait Decode: Sized {
+    /// Reads a value from `reader`.
+    fn decode<R: Read + ?Sized>(reader: &mut R) -> io::Result<Self>;
+}
+
+macro_rules! impl_le_bytes {
+    ($($t:ty),*) => {
+        $(
+            impl Encode for $t {
+                fn encode<W: Write + ?Sized>(&self, writer: &mut W) -> io::Result<()> {
+                    writer.write_all(&self.to_le_bytes())
+                }
+            }
+
+            impl Decode for $t {
+                fn decode<R: Read + ?Sized>(reader: &mut R) -> io::Result<Self> {
+                    let mut bytes = [0; ::std::mem::size_of::<$t>()];
+                    reader.read_exact(&mut bytes)?;
+                    Ok(<$t>::from_le_bytes(bytes))
+                }
+            }
+        )*
+    };
+}
+
+impl_le_bytes!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128, f32, f64);
+
+impl Encode for usize {
+    fn encode<W: Write + ?Sized>(&self, writer: &mut W) -> io::Result<()> {
+        write_varint(writer, *self as u64)
+    }
+}
+
+impl Decode for usize {
+    fn decode<R: Read + ?Sized>(reader: &mut R) -> io::Result<Self> {
+        read_len(reader)
+    }
+}
+
+impl Encode for bool {
+    fn encode<W: Write + ?Sized>(&self, writer: &mut W) -> io::Result<()> {
+        (*self as u8).encode(writer)
+    }
+}
+
+impl Decode for bool {
+    fn decode<R: Read + ?Sized>(reader: &mut R) -> io::Result<Self> {
+        match u8::decode(reader)? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(invalid_data("invalid bool")),
+        }
+    }
+}
+
+impl Encode for char {
+    fn encode<W: Write + ?Sized>(&self, writer: &mut W) -> io::Result<()> {
+        (*self as u32).encode(writer)
+    }
+}
+
+impl Decode for char {
+    fn decode<R: Read + ?Sized>(reader: &mut R) -> io::Result<Self> {
+        char::from_u32(u32::decode(reader)?).ok_or_else(|| invalid_data("invalid char"))
+    }
+}
+
+impl Encode for str {
+    fn encode<W: Write + ?Sized>(&self, writer: &mut W) -> io::Result<()> {
+        write_varint(writer, self.len() as u64)?;
+        writer.write_all(self.as_bytes())
+    }
+}
+
+impl Encode for String {
+    fn encode<W: Write + ?Sized>(&self, writer: &mut W) -> io::Result<()> {
+        self.as_str().encode(writer)
+    }
+}
+
+impl Decode for String {
+    fn decode<R: Read + ?Sized>(reader: &mut R) -> io::Result<Self> {
+        let len = read_len(reader)?;
+        let mut bytes = Vec::with_capacity(len.min(MAX_PREALLOCATION));
+        reader.take(len as u64).read_to_end(&mut bytes)?;
+        if bytes.len() != len {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        String::from_utf8(bytes).map_err(|_| invalid_data("invalid UTF-8"))
+    }
+}
+
+impl<T: Encode + ?Sized> Encode for &T {
+    fn encode<W: Write + ?Sized>(&self, writer: &mut W) -> io::Result<()> {
+        (**self).encode(writer)
+    }
+}
+
+impl<T: Encode> Encode for [T] {
+    fn encode<W: Write + ?Sized>(&self, writer: &mut W) -> io::Result<()> {
+        write_varint(writer, self.len() as u64)?;
+        for item in self {
+            item.encode(writer)?;
+        }
+        Ok(())
+    }
+}
+
+impl<T: Encode> Encode for Vec<T> {
+    fn encode<W: Write + ?Sized>(&self, writer: &mut W) -> io::Result<()> {
+        self.as_slice().encode(writer)
+    }
+}
+
+impl<T: Decode> Decode for Vec<T> {
+    fn decode<R: Read + ?Sized>(reader: &mut R) -> io::Result<Self> {
+        let len = read_len(reader)?;
+        let mut items = Vec::with_capacity(len.min(MAX_PREALLOCATION));
+        for _ in 0..len {
+            items.push(T::decode(reader)?);
+        }
+        Ok(items)
+    }
+}
+
+impl<K, V, S> MultiMap<K, V, S>
+where
+    K: Eq + Hash,
+    S: BuildHasher,
+{
+    /// Writes the map to `writer` in the binary format described in the
+    /// `binary` module. Keys are written in arbitrary order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiMap;
+    ///
+    /// let mut map = MultiMap::new();
+    /// map.insert(1u8, 2u8);
+    /// map.insert(1u8, 3u8);
+    ///
+    /// let mut bytes = Vec::new();
+    /// map.write_to(&mut bytes).unwrap();
+    /// assert_eq!(bytes, [1, 1, 2, 2, 3]);
+    /// ```
+    pub fn write_to<W>(&self, mut writer: W) -> io::Result<()>
+    where
+        K: Encode,
+        V: Encode,
+        W: Write,
+    {
+        write_varint(&mut writer, self.inner.len() as u64)?;
+        for (key, values) in &self.inner {
+            key.encode(&mut writer)?;
+            values.encode(&mut writer)?;
+        }
+        writer.flush()
+    }
+
+    /// Reads a map written by `write_to` from `reader`. Fails with
+    /// `ErrorKind::InvalidData` if the input is malformed and with
+    /// `ErrorKind::UnexpectedEof` if it ends early.
+    ///
+    /// Values of a key which appears more than once are appended in order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiMap;
+    ///
+    /// let map: MultiMap<u8, u8> = MultiMap::read_from(&[1, 1, 2, 2, 3][..]).unwrap();
+    /// assert_eq!(map.get_vec(&1), Some(&vec![2, 3]));
+    ///
+    /// assert!(MultiMap::<u8, u8>::read_from(&[1, 1, 2][..]).is_err());
+    /// ```
+    pub fn read_from<R>(mut reader: R) -> io::Result<MultiMap<K, V, S>>
+    where
+        K: Decode,
+        V: Decode,
+        S: Default,
+        R: Read,
+    {
+        let len = read_len(&mut reader)?;
+        let mut map = MultiMap::with_capacity_and_hasher(len.min(MAX_PREALLOCATION), S::default());
+        for _ in 0..len {
+            let key = K::decode(&mut reader)?;
+            let values = Vec::<V>::decode(&mut reader)?;
+            map.inner.entry(key).or_default().extend(values);
+        }
+        Ok(map)
+    }
+}
+
+fn write_varint<W: Write + ?Sized>(writer: &mut W, mut value: u64) -> io::Result<()> {
+    let mut bytes = [0u8; 10];
+    let mut len = 0;
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            bytes[len] = byte;
+            len += 1;
+            break;
+        }
+        bytes[len] = byte | 0x80;
+        len += 1;
+    }
+    writer.write_all(&bytes[..len])
+}
+
+fn read_varint<R: Read + ?Sized>(reader: &mut R) -> io::Result<u64> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let byte = u8::decode(reader)?;
+        if shift == 63 && byte > 1 {
+            break;
+        }
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(invalid_data("varint overflows u64"))
+}
+
+fn read_len<R: Read + ?Sized>(reader: &mut R) -> io::Result<usize> {
+    let len = read_varint(reader)?;
+    if len > usize::MAX as u64 {
+        return Err(invalid_data("length overflows usize"));
+    }
+    Ok(len as usize)
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let mut map: MultiMap<String, Vec<i32>> = MultiMap::new();
+        map.insert("a".to_string(), vec![-1, 300, i32::MAX]);
+        map.insert("a".to_string(), vec![]);
+        map.insert_many("ü".repeat(100), vec![]);
+        for key in 0..200 {
+            map.insert(key.to_string(), vec![key]);
+        }
+
+        let mut bytes = Vec::new();
+        map.write_to(&mut bytes).unwrap();
+        let read: MultiMap<String, Vec<i32>> = MultiMap::read_from(&bytes[..]).unwrap();
+        assert_eq!(read, map);
+
+        for len in 0..bytes.len() {
+            let error = MultiMap::<String, Vec<i32>>::read_from(&bytes[..len]).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+        }
+    }
+
+    #[test]
+    fn varints() {
+        for &value in &[0, 1, 127, 128, 300, u32::MAX as u64, u64::MAX] {
+            let mut bytes = Vec::new();
+            write_varint(&mut bytes, value).unwrap();
+            assert_eq!(read_varint(&mut &bytes[..]).unwrap(), value);
+        }
+
+        let overflow = [0xff; 11];
+        let error = read_varint(&mut &overflow[..]).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn invalid_values() {
+        let error = MultiMap::<bool, u8>::read_from(&[1, 2, 0][..]).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        let error = MultiMap::<String, u8>::read_from(&[1, 1, 0xff, 0][..]).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        let error = MultiMap::<char, u8>::read_from(&[1, 0, 0xd8, 0, 0, 0][..]).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+}
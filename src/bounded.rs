@@ -0,0 +1,505 @@
+// Copyright (c) 2016 multimap developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! A multimap which stores at most a fixed number of values per key.
+//!
+//! The values of each key are kept in a std::collections::VecDeque in
+//! insertion order. Once a key holds as many values as the limit allows, the
+//! configured Overflow policy decides whether inserting another value evicts
+//! the oldest value of the key or rejects the new value.
+//!
+//! # Examples
+//!
+//! ```
+//! use multimap::BoundedMultiMap;
+//!
+//! // keep the last two events per user.
+//! let mut events = BoundedMultiMap::with_value_limit(2);
+//! assert_eq!(events.insert("alice", "login"), None);
+//! assert_eq!(events.insert("alice", "view"), None);
+//! assert_eq!(events.insert("alice", "logout"), Some("login"));
+//!
+//! let last: Vec<_> = events.get_values("alice").unwrap().iter().collect();
+//! assert_eq!(last, [&"view", &"logout"]);
+//! ```
+
+use std::borrow::Borrow;
+use std::collections::hash_map::{self, Keys, RandomState};
+use std::collections::{HashMap, VecDeque};
+use std::fmt::{self, Debug};
+use std::hash::{BuildHasher, Hash};
+use std::iter::{IntoIterator, Iterator};
+
+pub use std::collections::hash_map::Iter as IterAll;
+
+/// What a BoundedMultiMap does when a value is inserted for a key which
+/// already holds the maximum number of values.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Overflow {
+    /// Remove the oldest value of the key to make room for the new one.
+    EvictOldest,
+
+    /// Leave the key untouched and hand the new value back.
+    Reject,
+}
+
+#[derive(Clone)]
+pub struct BoundedMultiMap<K, V, S = RandomState> {
+    inner: HashMap<K, VecDeque<V>, S>,
+    limit: usize,
+    overflow: Overflow,
+}
+
+impl<K, V> BoundedMultiMap<K, V>
+where
+    K: Eq + Hash,
+{
+    /// Creates an empty BoundedMultiMap which keeps at most `limit` values per
+    /// key, evicting the oldest value when a key is full.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `limit` is zero.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::BoundedMultiMap;
+    ///
+    /// let mut map: BoundedMultiMap<&str, isize> = BoundedMultiMap::with_value_limit(10);
+    /// assert_eq!(map.value_limit(), 10);
+    /// ```
+    pub fn with_value_limit(limit: usize) -> BoundedMultiMap<K, V> {
+        BoundedMultiMap::with_value_limit_and_hasher(
+            limit,
+            Overflow::EvictOldest,
+            RandomState::default(),
+        )
+    }
+
+    /// Creates an empty BoundedMultiMap which keeps at most `limit` values per
+    /// key and handles full keys according to `overflow`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `limit` is zero.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::bounded::{BoundedMultiMap, Overflow};
+    ///
+    /// let mut map = BoundedMultiMap::with_overflow(1, Overflow::Reject);
+    /// assert_eq!(map.insert("key", 42), None);
+    /// assert_eq!(map.insert("key", 43), Some(43));
+    /// assert_eq!(map.get("key"), Some(&42));
+    /// ```
+    pub fn with_overflow(limit: usize, overflow: Overflow) -> BoundedMultiMap<K, V> {
+        BoundedMultiMap::with_value_limit_and_hasher(limit, overflow, RandomState::default())
+    }
+}
+
+impl<K, V, S> BoundedMultiMap<K, V, S>
+where
+    K: Eq + Hash,
+    S: BuildHasher,
+{
+    /// Creates an empty BoundedMultiMap which keeps at most `limit` values per
+    /// key, handles full keys according to `overflow` and uses the given hash
+    /// builder to hash keys.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `limit` is zero.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::bounded::{BoundedMultiMap, Overflow};
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let s = RandomState::new();
+    /// let mut map: BoundedMultiMap<&str, isize> =
+    ///     BoundedMultiMap::with_value_limit_and_hasher(10, Overflow::EvictOldest, s);
+    /// ```
+    pub fn with_value_limit_and_hasher(
+        limit: usize,
+        overflow: Overflow,
+        hash_builder: S,
+    ) -> BoundedMultiMap<K, V, S> {
+        assert!(limit > 0, "value limit must be non-zero");
+        BoundedMultiMap {
+            inner: HashMap::with_hasher(hash_builder),
+            limit,
+            overflow,
+        }
+    }
+
+    /// Returns the maximum number of values stored per key.
+    pub fn value_limit(&self) -> usize {
+        self.limit
+    }
+
+    /// Returns the policy applied when inserting into a full key.
+    pub fn overflow(&self) -> Overflow {
+        self.overflow
+    }
+
+    /// Inserts a key-value pair into the multimap. If the key already holds
+    /// the maximum number of values, the overflow policy decides what happens:
+    /// with `Overflow::EvictOldest` the oldest value of the key is removed and
+    /// returned, with `Overflow::Reject` the given value is returned without
+    /// being inserted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::BoundedMultiMap;
+    ///
+    /// let mut map = BoundedMultiMap::with_value_limit(1);
+    /// assert_eq!(map.insert("key", 42), None);
+    /// assert_eq!(map.insert("key", 43), Some(42));
+    /// assert_eq!(map.get("key"), Some(&43));
+    /// ```
+    pub fn insert(&mut self, k: K, v: V) -> Option<V> {
+        let limit = self.limit;
+        let values = self.inner.entry(k).or_default();
+        if values.len() < limit {
+            values.push_back(v);
+            return None;
+        }
+        match self.overflow {
+            Overflow::EvictOldest => {
+                let evicted = values.pop_front();
+                values.push_back(v);
+                evicted
+            }
+            Overflow::Reject => Some(v),
+        }
+    }
+
+    /// Returns true if the map contains a value for the specified key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::BoundedMultiMap;
+    ///
+    /// let mut map = BoundedMultiMap::with_value_limit(2);
+    /// map.insert(1, 42);
+    /// assert_eq!(map.contains_key(&1), true);
+    /// assert_eq!(map.contains_key(&2), false);
+    /// ```
+    pub fn contains_key<Q>(&self, k: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        self.inner.contains_key(k)
+    }
+
+    /// Returns a reference to the oldest value stored under the key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::BoundedMultiMap;
+    ///
+    /// let mut map = BoundedMultiMap::with_value_limit(2);
+    /// map.insert(1, 42);
+    /// map.insert(1, 1337);
+    /// assert_eq!(map.get(&1), Some(&42));
+    /// ```
+    pub fn get<Q>(&self, k: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        self.inner.get(k)?.front()
+    }
+
+    /// Returns a reference to the newest value stored under the key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::BoundedMultiMap;
+    ///
+    /// let mut map = BoundedMultiMap::with_value_limit(2);
+    /// map.insert(1, 42);
+    /// map.insert(1, 1337);
+    /// assert_eq!(map.get_newest(&1), Some(&1337));
+    /// ```
+    pub fn get_newest<Q>(&self, k: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        self.inner.get(k)?.back()
+    }
+
+    /// Returns a reference to the values stored under the key, oldest first.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::BoundedMultiMap;
+    ///
+    /// let mut map = BoundedMultiMap::with_value_limit(2);
+    /// map.insert(1, 42);
+    /// map.insert(1, 1337);
+    /// map.insert(1, 2332);
+    /// assert_eq!(map.get_values(&1).unwrap(), &[1337, 2332]);
+    /// ```
+    pub fn get_values<Q>(&self, k: &Q) -> Option<&VecDeque<V>>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        self.inner.get(k)
+    }
+
+    /// Removes a key from the map, returning its values if the key was
+    /// previously in the map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::BoundedMultiMap;
+    ///
+    /// let mut map = BoundedMultiMap::with_value_limit(2);
+    /// map.insert(1, 42);
+    /// assert_eq!(map.remove(&1).unwrap(), &[42]);
+    /// assert_eq!(map.remove(&1), None);
+    /// ```
+    pub fn remove<Q>(&mut self, k: &Q) -> Option<VecDeque<V>>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        self.inner.remove(k)
+    }
+
+    /// Returns the number of keys in the map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::BoundedMultiMap;
+    ///
+    /// let mut map = BoundedMultiMap::with_value_limit(2);
+    /// map.insert(1, 42);
+    /// map.insert(2, 1337);
+    /// assert_eq!(map.len(), 2);
+    /// ```
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// Returns true if the map contains no elements.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::BoundedMultiMap;
+    ///
+    /// let mut map = BoundedMultiMap::with_value_limit(2);
+    /// assert!(map.is_empty());
+    /// map.insert(1, 42);
+    /// assert!(!map.is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    /// Clears the map, removing all key-value pairs.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::BoundedMultiMap;
+    ///
+    /// let mut map = BoundedMultiMap::with_value_limit(2);
+    /// map.insert(1, 42);
+    /// map.clear();
+    /// assert!(map.is_empty());
+    /// ```
+    pub fn clear(&mut self) {
+        self.inner.clear();
+    }
+
+    /// An iterator visiting all keys in arbitrary order.
+    pub fn keys(&self) -> Keys<'_, K, VecDeque<V>> {
+        self.inner.keys()
+    }
+
+    /// An iterator visiting all keys and their values in arbitrary order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::BoundedMultiMap;
+    ///
+    /// let mut map = BoundedMultiMap::with_value_limit(2);
+    /// map.insert(1, 42);
+    /// map.insert(1, 1337);
+    ///
+    /// for (key, values) in map.iter_all() {
+    ///     assert_eq!(values.len(), 2);
+    /// }
+    /// ```
+    pub fn iter_all(&self) -> IterAll<'_, K, VecDeque<V>> {
+        self.inner.iter()
+    }
+
+    /// An iterator visiting every key-value pair. Keys are visited in
+    /// arbitrary order, the values of a key oldest first.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::BoundedMultiMap;
+    ///
+    /// let mut map = BoundedMultiMap::with_value_limit(2);
+    /// map.insert(1, 42);
+    /// map.insert(1, 1337);
+    ///
+    /// let pairs: Vec<_> = map.flat_iter().collect();
+    /// assert_eq!(pairs, [(&1, &42), (&1, &1337)]);
+    /// ```
+    pub fn flat_iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.iter_all()
+            .flat_map(|(k, v)| v.iter().map(move |i| (k, i)))
+    }
+
+    /// Retains only the elements specified by the predicate. Keys left
+    /// without values are removed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::BoundedMultiMap;
+    ///
+    /// let mut map = BoundedMultiMap::with_value_limit(2);
+    /// map.insert(1, 42);
+    /// map.insert(1, 99);
+    /// map.insert(2, 42);
+    /// map.retain(|&k, &v| k == 1 && v == 42);
+    /// assert_eq!(map.len(), 1);
+    /// assert_eq!(map.get(&1), Some(&42));
+    /// ```
+    pub fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&K, &V) -> bool,
+    {
+        for (key, values) in &mut self.inner {
+            values.retain(|value| f(key, value));
+        }
+        self.inner.retain(|_, v| !v.is_empty());
+    }
+}
+
+impl<K, V, S> Debug for BoundedMultiMap<K, V, S>
+where
+    K: Eq + Hash + Debug,
+    V: Debug,
+    S: BuildHasher,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_map().entries(self.iter_all()).finish()
+    }
+}
+
+impl<K, V, S> PartialEq for BoundedMultiMap<K, V, S>
+where
+    K: Eq + Hash,
+    V: PartialEq,
+    S: BuildHasher,
+{
+    fn eq(&self, other: &BoundedMultiMap<K, V, S>) -> bool {
+        self.limit == other.limit && self.overflow == other.overflow && self.inner == other.inner
+    }
+}
+
+impl<K, V, S> Eq for BoundedMultiMap<K, V, S>
+where
+    K: Eq + Hash,
+    V: Eq,
+    S: BuildHasher,
+{
+}
+
+impl<K, V, S> Extend<(K, V)> for BoundedMultiMap<K, V, S>
+where
+    K: Eq + Hash,
+    S: BuildHasher,
+{
+    fn extend<T: IntoIterator<Item = (K, V)>>(&mut self, iter: T) {
+        for (k, v) in iter {
+            self.insert(k, v);
+        }
+    }
+}
+
+impl<'a, K, V, S> IntoIterator for &'a BoundedMultiMap<K, V, S>
+where
+    K: Eq + Hash,
+    S: BuildHasher,
+{
+    type Item = (&'a K, &'a VecDeque<V>);
+    type IntoIter = IterAll<'a, K, VecDeque<V>>;
+
+    fn into_iter(self) -> IterAll<'a, K, VecDeque<V>> {
+        self.iter_all()
+    }
+}
+
+impl<K, V, S> IntoIterator for BoundedMultiMap<K, V, S> {
+    type Item = (K, VecDeque<V>);
+    type IntoIter = hash_map::IntoIter<K, VecDeque<V>>;
+
+    fn into_iter(self) -> hash_map::IntoIter<K, VecDeque<V>> {
+        self.inner.into_iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::vec::Vec;
+
+    #[test]
+    #[should_panic(expected = "value limit must be non-zero")]
+    fn zero_limit_panics() {
+        let _: BoundedMultiMap<u8, u8> = BoundedMultiMap::with_value_limit(0);
+    }
+
+    #[test]
+    fn evicts_oldest() {
+        let mut m = BoundedMultiMap::with_value_limit(3);
+        let evicted: Vec<_> = (0..6).filter_map(|i| m.insert("k", i)).collect();
+        assert_eq!(evicted, [0, 1, 2]);
+        assert_eq!(m.get_values("k").unwrap(), &[3, 4, 5]);
+    }
+
+    #[test]
+    fn rejects_new() {
+        let mut m = BoundedMultiMap::with_overflow(2, Overflow::Reject);
+        let rejected: Vec<_> = (0..4).filter_map(|i| m.insert("k", i)).collect();
+        assert_eq!(rejected, [2, 3]);
+        assert_eq!(m.get_values("k").unwrap(), &[0, 1]);
+    }
+
+    #[test]
+    fn limit_is_per_key() {
+        let mut m = BoundedMultiMap::with_value_limit(1);
+        assert_eq!(m.insert(1, 'a'), None);
+        assert_eq!(m.insert(2, 'b'), None);
+        assert_eq!(m.flat_iter().count(), 2);
+    }
+}
@@ -0,0 +1,1095 @@
+// Copyright (c) 2016 multimap developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! An ordered multimap backed by std::collections::BTreeMap.
+//!
+//! BTreeMultiMap offers the same interface as MultiMap, but its keys are kept
+//! in sorted order and every iterator visits them from the smallest to the
+//! largest key. Values are, just like in MultiMap, kept in insertion order.
+//!
+//! # Examples
+//!
+//! ```
+//! use multimap::BTreeMultiMap;
+//!
+//! let mut map = BTreeMultiMap::new();
+//! map.insert("b", 2);
+//! map.insert("a", 1);
+//! map.insert("b", 3);
+//!
+//! let keys: Vec<_> = map.keys().collect();
+//! assert_eq!(keys, [&"a", &"b"]);
+//! assert_eq!(map.get_vec("b"), Some(&vec![2, 3]));
+//! ```
+
+use std::borrow::Borrow;
+use std::collections::btree_map::{IntoIter, Keys};
+use std::collections::BTreeMap;
+use std::fmt::{self, Debug};
+use std::iter::{FromIterator, FusedIterator, IntoIterator, Iterator};
+use std::ops::Index;
+use std::vec::Vec;
+
+pub use std::collections::btree_map::Iter as IterAll;
+pub use std::collections::btree_map::IterMut as IterAllMut;
+
+pub use self::entry::{Entry, OccupiedEntry, VacantEntry};
+
+mod entry;
+
+#[derive(Clone)]
+pub struct BTreeMultiMap<K, V> {
+    pub(crate) inner: BTreeMap<K, Vec<V>>,
+}
+
+impl<K, V> BTreeMultiMap<K, V>
+where
+    K: Ord,
+{
+    /// Creates an empty BTreeMultiMap
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::BTreeMultiMap;
+    ///
+    /// let mut map: BTreeMultiMap<&str, isize> = BTreeMultiMap::new();
+    /// ```
+    pub fn new() -> BTreeMultiMap<K, V> {
+        BTreeMultiMap {
+            inner: BTreeMap::new(),
+        }
+    }
+
+    /// Inserts a key-value pair into the multimap. If the key does exist in
+    /// the map then the value is pushed to that key's vector. If the key doesn't
+    /// exist in the map a new vector with the given value is inserted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::BTreeMultiMap;
+    ///
+    /// let mut map = BTreeMultiMap::new();
+    /// map.insert("key", 42);
+    /// ```
+    pub fn insert(&mut self, k: K, v: V) {
+        match self.entry(k) {
+            Entry::Occupied(mut entry) => {
+                entry.get_vec_mut().push(v);
+            }
+            Entry::Vacant(entry) => {
+                entry.insert_vec(vec![v]);
+            }
+        }
+    }
+
+    /// Inserts multiple key-value pairs into the multimap. If the key does exist in
+    /// the map then the values are extended into that key's vector. If the key
+    /// doesn't exist in the map a new vector collected from the given values is inserted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::BTreeMultiMap;
+    ///
+    /// let mut map = BTreeMultiMap::<&str, &usize>::new();
+    /// map.insert_many("key", &[42, 43]);
+    /// ```
+    pub fn insert_many<I: IntoIterator<Item = V>>(&mut self, k: K, v: I) {
+        match self.entry(k) {
+            Entry::Occupied(mut entry) => {
+                entry.get_vec_mut().extend(v);
+            }
+            Entry::Vacant(entry) => {
+                entry.insert_vec(v.into_iter().collect::<Vec<_>>());
+            }
+        }
+    }
+
+    /// Inserts multiple key-value pairs into the multimap. If the key does exist in
+    /// the map then the values are extended into that key's vector. If the key
+    /// doesn't exist in the map a new vector collected from the given values is inserted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::BTreeMultiMap;
+    ///
+    /// let mut map = BTreeMultiMap::<&str, usize>::new();
+    /// map.insert_many_from_slice("key", &[42, 43]);
+    /// ```
+    pub fn insert_many_from_slice(&mut self, k: K, v: &[V])
+    where
+        V: Clone,
+    {
+        match self.entry(k) {
+            Entry::Occupied(mut entry) => {
+                entry.get_vec_mut().extend_from_slice(v);
+            }
+            Entry::Vacant(entry) => {
+                entry.insert_vec(v.to_vec());
+            }
+        }
+    }
+
+    /// Returns true if the map contains a value for the specified key.
+    ///
+    /// The key may be any borrowed form of the map's key type, but the ordering
+    /// on the borrowed form must match the ordering on the key type.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::BTreeMultiMap;
+    ///
+    /// let mut map = BTreeMultiMap::new();
+    /// map.insert(1, 42);
+    /// assert_eq!(map.contains_key(&1), true);
+    /// assert_eq!(map.contains_key(&2), false);
+    /// ```
+    pub fn contains_key<Q>(&self, k: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Ord,
+    {
+        self.inner.contains_key(k)
+    }
+
+    /// Returns the number of elements in the map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::BTreeMultiMap;
+    ///
+    /// let mut map = BTreeMultiMap::new();
+    /// map.insert(1, 42);
+    /// map.insert(2, 1337);
+    /// assert_eq!(map.len(), 2);
+    /// ```
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// Removes a key from the map, returning the vector of values at
+    /// the key if the key was previously in the map.
+    ///
+    /// The key may be any borrowed form of the map's key type, but the ordering
+    /// on the borrowed form must match the ordering on the key type.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::BTreeMultiMap;
+    ///
+    /// let mut map = BTreeMultiMap::new();
+    /// map.insert(1, 42);
+    /// map.insert(1, 1337);
+    /// assert_eq!(map.remove(&1), Some(vec![42, 1337]));
+    /// assert_eq!(map.remove(&1), None);
+    /// ```
+    pub fn remove<Q>(&mut self, k: &Q) -> Option<Vec<V>>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Ord,
+    {
+        self.inner.remove(k)
+    }
+
+    /// Returns a reference to the first item in the vector corresponding to
+    /// the key.
+    ///
+    /// The key may be any borrowed form of the map's key type, but the ordering
+    /// on the borrowed form must match the ordering on the key type.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::BTreeMultiMap;
+    ///
+    /// let mut map = BTreeMultiMap::new();
+    /// map.insert(1, 42);
+    /// map.insert(1, 1337);
+    /// assert_eq!(map.get(&1), Some(&42));
+    /// ```
+    pub fn get<Q>(&self, k: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Ord,
+    {
+        self.inner.get(k)?.first()
+    }
+
+    /// Returns a mutable reference to the first item in the vector corresponding to
+    /// the key.
+    ///
+    /// The key may be any borrowed form of the map's key type, but the ordering
+    /// on the borrowed form must match the ordering on the key type.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::BTreeMultiMap;
+    ///
+    /// let mut map = BTreeMultiMap::new();
+    /// map.insert(1, 42);
+    /// map.insert(1, 1337);
+    /// if let Some(v) = map.get_mut(&1) {
+    ///     *v = 99;
+    /// }
+    /// assert_eq!(map[&1], 99);
+    /// ```
+    pub fn get_mut<Q>(&mut self, k: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Ord,
+    {
+        self.inner.get_mut(k)?.first_mut()
+    }
+
+    /// Returns a reference to the vector corresponding to the key.
+    ///
+    /// The key may be any borrowed form of the map's key type, but the ordering
+    /// on the borrowed form must match the ordering on the key type.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::BTreeMultiMap;
+    ///
+    /// let mut map = BTreeMultiMap::new();
+    /// map.insert(1, 42);
+    /// map.insert(1, 1337);
+    /// assert_eq!(map.get_vec(&1), Some(&vec![42, 1337]));
+    /// ```
+    pub fn get_vec<Q>(&self, k: &Q) -> Option<&Vec<V>>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Ord,
+    {
+        self.inner.get(k)
+    }
+
+    /// Returns a mutable reference to the vector corresponding to the key.
+    ///
+    /// The key may be any borrowed form of the map's key type, but the ordering
+    /// on the borrowed form must match the ordering on the key type.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::BTreeMultiMap;
+    ///
+    /// let mut map = BTreeMultiMap::new();
+    /// map.insert(1, 42);
+    /// map.insert(1, 1337);
+    /// if let Some(v) = map.get_vec_mut(&1) {
+    ///     (*v)[0] = 1991;
+    ///     (*v)[1] = 2332;
+    /// }
+    /// assert_eq!(map.get_vec(&1), Some(&vec![1991, 2332]));
+    /// ```
+    pub fn get_vec_mut<Q>(&mut self, k: &Q) -> Option<&mut Vec<V>>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Ord,
+    {
+        self.inner.get_mut(k)
+    }
+
+    /// Returns true if the key is multi-valued.
+    ///
+    /// The key may be any borrowed form of the map's key type, but the ordering
+    /// on the borrowed form must match the ordering on the key type.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::BTreeMultiMap;
+    ///
+    /// let mut map = BTreeMultiMap::new();
+    /// map.insert(1, 42);
+    /// map.insert(1, 1337);
+    /// map.insert(2, 2332);
+    ///
+    /// assert_eq!(map.is_vec(&1), true);   // key is multi-valued
+    /// assert_eq!(map.is_vec(&2), false);  // key is single-valued
+    /// assert_eq!(map.is_vec(&3), false);  // key not in map
+    /// ```
+    pub fn is_vec<Q>(&self, k: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Ord,
+    {
+        match self.get_vec(k) {
+            Some(val) => val.len() > 1,
+            None => false,
+        }
+    }
+
+    /// Returns true if the map contains no elements.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::BTreeMultiMap;
+    ///
+    /// let mut map = BTreeMultiMap::new();
+    /// assert!(map.is_empty());
+    /// map.insert(1,42);
+    /// assert!(!map.is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    /// Clears the map, removing all key-value pairs.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::BTreeMultiMap;
+    ///
+    /// let mut map = BTreeMultiMap::new();
+    /// map.insert(1,42);
+    /// map.clear();
+    /// assert!(map.is_empty());
+    /// ```
+    pub fn clear(&mut self) {
+        self.inner.clear();
+    }
+
+    /// An iterator visiting all keys in sorted order.
+    /// Iterator element type is &'a K.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::BTreeMultiMap;
+    ///
+    /// let mut map = BTreeMultiMap::new();
+    /// map.insert(4,1991);
+    /// map.insert(1,42);
+    /// map.insert(1,1337);
+    /// map.insert(2,1337);
+    ///
+    /// let keys: Vec<_> = map.keys().collect();
+    /// assert_eq!(keys, [&1, &2, &4]);
+    /// ```
+    pub fn keys(&self) -> Keys<'_, K, Vec<V>> {
+        self.inner.keys()
+    }
+
+    /// An iterator visiting all key-value pairs in sorted key order. The iterator returns
+    /// a reference to the key and the first element in the corresponding key's vector.
+    /// Iterator element type is (&'a K, &'a V).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::BTreeMultiMap;
+    ///
+    /// let mut map = BTreeMultiMap::new();
+    /// map.insert(4,1991);
+    /// map.insert(1,42);
+    /// map.insert(1,1337);
+    /// map.insert(3,2332);
+    ///
+    /// let pairs: Vec<_> = map.iter().collect();
+    /// assert_eq!(pairs, [(&1, &42), (&3, &2332), (&4, &1991)]);
+    /// ```
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter {
+            inner: self.inner.iter(),
+            len: self.inner.values().filter(|v| !v.is_empty()).count(),
+        }
+    }
+
+    /// An iterator visiting all key-value pairs in sorted key order. The iterator returns
+    /// a reference to the key and a mutable reference to the first element in the
+    /// corresponding key's vector. Iterator element type is (&'a K, &'a mut V).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::BTreeMultiMap;
+    ///
+    /// let mut map = BTreeMultiMap::new();
+    /// map.insert(1,42);
+    /// map.insert(1,1337);
+    /// map.insert(3,2332);
+    ///
+    /// for (_, value) in map.iter_mut() {
+    ///     *value *= *value;
+    /// }
+    ///
+    /// let pairs: Vec<_> = map.iter_mut().collect();
+    /// assert_eq!(pairs, [(&1, &mut 1764), (&3, &mut 5438224)]);
+    /// ```
+    pub fn iter_mut(&mut self) -> IterMut<'_, K, V> {
+        IterMut {
+            len: self.inner.values().filter(|v| !v.is_empty()).count(),
+            inner: self.inner.iter_mut(),
+        }
+    }
+
+    /// An iterator visiting all key-value pairs in sorted key order. The iterator returns
+    /// a reference to the key and the corresponding key's vector.
+    /// Iterator element type is (&'a K, &'a V).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::BTreeMultiMap;
+    ///
+    /// let mut map = BTreeMultiMap::new();
+    /// map.insert(4,1991);
+    /// map.insert(1,42);
+    /// map.insert(1,1337);
+    /// map.insert(3,2332);
+    ///
+    /// let pairs: Vec<_> = map.iter_all().collect();
+    /// assert_eq!(pairs, [(&1, &vec![42, 1337]), (&3, &vec![2332]), (&4, &vec![1991])]);
+    /// ```
+    pub fn iter_all(&self) -> IterAll<'_, K, Vec<V>> {
+        self.inner.iter()
+    }
+
+    /// An iterator visiting all key-value pairs in sorted key order. The iterator returns
+    /// a reference to the key and the corresponding key's vector.
+    /// Iterator element type is (&'a K, &'a mut V).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::BTreeMultiMap;
+    ///
+    /// let mut map = BTreeMultiMap::new();
+    /// map.insert(1,42);
+    /// map.insert(1,1337);
+    /// map.insert(3,2332);
+    ///
+    /// for (key, values) in map.iter_all_mut() {
+    ///     for value in values.iter_mut() {
+    ///         *value = 99;
+    ///     }
+    /// }
+    ///
+    /// let pairs: Vec<_> = map.iter_all_mut().collect();
+    /// assert_eq!(pairs, [(&1, &mut vec![99, 99]), (&3, &mut vec![99])]);
+    /// ```
+    pub fn iter_all_mut(&mut self) -> IterAllMut<'_, K, Vec<V>> {
+        self.inner.iter_mut()
+    }
+
+    /// An iterator visiting every key-value pair in sorted key order, values of a
+    /// key in insertion order. Iterator element type is (&'a K, &'a V).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::BTreeMultiMap;
+    ///
+    /// let mut map = BTreeMultiMap::new();
+    /// map.insert(3,2332);
+    /// map.insert(1,42);
+    /// map.insert(1,1337);
+    ///
+    /// let pairs: Vec<_> = map.flat_iter().collect();
+    /// assert_eq!(pairs, [(&1, &42), (&1, &1337), (&3, &2332)]);
+    /// ```
+    pub fn flat_iter(&self) -> impl DoubleEndedIterator<Item = (&K, &V)> + FusedIterator {
+        self.iter_all()
+            .flat_map(|(k, v)| v.iter().map(move |i| (k, i)))
+    }
+
+    /// An iterator visiting every key-value pair in sorted key order, values of a
+    /// key in insertion order. Iterator element type is (&'a K, &'a mut V).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::BTreeMultiMap;
+    ///
+    /// let mut map = BTreeMultiMap::new();
+    /// map.insert(1,42);
+    /// map.insert(1,1337);
+    /// map.insert(3,2332);
+    ///
+    /// for (_, value) in map.flat_iter_mut() {
+    ///     *value += 1;
+    /// }
+    /// assert_eq!(map.get_vec(&1), Some(&vec![43, 1338]));
+    /// ```
+    pub fn flat_iter_mut(
+        &mut self,
+    ) -> impl DoubleEndedIterator<Item = (&K, &mut V)> + FusedIterator {
+        self.iter_all_mut()
+            .flat_map(|(k, v)| v.iter_mut().map(move |i| (k, i)))
+    }
+
+    /// Gets the specified key's corresponding entry in the map for in-place manipulation.
+    /// It's possible to both manipulate the vector and the 'value' (the first value in the
+    /// vector).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::BTreeMultiMap;
+    ///
+    /// let mut m = BTreeMultiMap::new();
+    /// m.insert(1, 42);
+    ///
+    /// {
+    ///     let mut v = m.entry(1).or_insert(43);
+    ///     assert_eq!(v, &42);
+    ///     *v = 44;
+    /// }
+    /// assert_eq!(m.entry(2).or_insert(666), &666);
+    ///
+    /// {
+    ///     let mut v = m.entry(1).or_insert_vec(vec![43]);
+    ///     assert_eq!(v, &vec![44]);
+    ///     v.push(50);
+    /// }
+    /// assert_eq!(m.entry(2).or_insert_vec(vec![667]), &vec![666]);
+    ///
+    /// assert_eq!(m.get_vec(&1), Some(&vec![44, 50]));
+    /// ```
+    pub fn entry(&mut self, k: K) -> Entry<'_, K, V> {
+        use std::collections::btree_map::Entry as BTreeMapEntry;
+        match self.inner.entry(k) {
+            BTreeMapEntry::Occupied(entry) => Entry::Occupied(OccupiedEntry { inner: entry }),
+            BTreeMapEntry::Vacant(entry) => Entry::Vacant(VacantEntry { inner: entry }),
+        }
+    }
+
+    /// Retains only the elements specified by the predicate.
+    ///
+    /// In other words, remove all pairs `(k, v)` such that `f(&k,&mut v)` returns `false`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::BTreeMultiMap;
+    ///
+    /// let mut m = BTreeMultiMap::new();
+    /// m.insert(1, 42);
+    /// m.insert(1, 99);
+    /// m.insert(2, 42);
+    /// m.retain(|&k, &v| { k == 1 && v == 42 });
+    /// assert_eq!(1, m.len());
+    /// assert_eq!(Some(&42), m.get(&1));
+    /// ```
+    pub fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&K, &V) -> bool,
+    {
+        for (key, vector) in &mut self.inner {
+            vector.retain(|value| f(key, value));
+        }
+        self.inner.retain(|_, v| !v.is_empty());
+    }
+
+    /// Splits the map in two at the given key. Returns everything after the
+    /// given key, including the key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::BTreeMultiMap;
+    ///
+    /// let mut a = BTreeMultiMap::new();
+    /// a.insert(1, 42);
+    /// a.insert(2, 43);
+    /// a.insert(3, 44);
+    /// a.insert(3, 45);
+    ///
+    /// let b = a.split_off(&2);
+    /// assert_eq!(a.keys().collect::<Vec<_>>(), [&1]);
+    /// assert_eq!(b.keys().collect::<Vec<_>>(), [&2, &3]);
+    /// assert_eq!(b.get_vec(&3), Some(&vec![44, 45]));
+    /// ```
+    pub fn split_off<Q>(&mut self, key: &Q) -> BTreeMultiMap<K, V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Ord,
+    {
+        BTreeMultiMap {
+            inner: self.inner.split_off(key),
+        }
+    }
+
+    /// Splits the map in two by key. The first map contains the keys for which
+    /// the predicate returns true, the second map all other keys.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::BTreeMultiMap;
+    ///
+    /// let mut m = BTreeMultiMap::new();
+    /// m.insert(1, 42);
+    /// m.insert(2, 43);
+    /// m.insert(3, 44);
+    ///
+    /// let (odd, even) = m.partition(|&k| k % 2 == 1);
+    /// assert_eq!(odd.keys().collect::<Vec<_>>(), [&1, &3]);
+    /// assert_eq!(even.keys().collect::<Vec<_>>(), [&2]);
+    /// ```
+    pub fn partition<F>(self, mut f: F) -> (BTreeMultiMap<K, V>, BTreeMultiMap<K, V>)
+    where
+        F: FnMut(&K) -> bool,
+    {
+        let (left, right) = self.inner.into_iter().partition(|(k, _)| f(k));
+        (
+            BTreeMultiMap { inner: left },
+            BTreeMultiMap { inner: right },
+        )
+    }
+}
+
+impl<K, V, Q> Index<&Q> for BTreeMultiMap<K, V>
+where
+    K: Ord + Borrow<Q>,
+    Q: ?Sized + Ord,
+{
+    type Output = V;
+
+    fn index(&self, index: &Q) -> &V {
+        self.inner
+            .get(index)
+            .expect("no entry found for key")
+            .first()
+            .expect("no value found for key")
+    }
+}
+
+impl<K, V> Debug for BTreeMultiMap<K, V>
+where
+    K: Ord + Debug,
+    V: Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_map().entries(self.iter_all()).finish()
+    }
+}
+
+impl<K, V> PartialEq for BTreeMultiMap<K, V>
+where
+    K: Ord,
+    V: PartialEq,
+{
+    fn eq(&self, other: &BTreeMultiMap<K, V>) -> bool {
+        self.inner == other.inner
+    }
+}
+
+impl<K, V> Eq for BTreeMultiMap<K, V>
+where
+    K: Ord,
+    V: Eq,
+{
+}
+
+impl<K, V> Default for BTreeMultiMap<K, V>
+where
+    K: Ord,
+{
+    fn default() -> BTreeMultiMap<K, V> {
+        BTreeMultiMap {
+            inner: Default::default(),
+        }
+    }
+}
+
+impl<K, V> FromIterator<(K, V)> for BTreeMultiMap<K, V>
+where
+    K: Ord,
+{
+    fn from_iter<T: IntoIterator<Item = (K, V)>>(iterable: T) -> BTreeMultiMap<K, V> {
+        let mut multimap = BTreeMultiMap::new();
+        for (k, v) in iterable {
+            multimap.insert(k, v);
+        }
+
+        multimap
+    }
+}
+
+impl<K, V> FromIterator<(K, Vec<V>)> for BTreeMultiMap<K, V>
+where
+    K: Ord,
+{
+    fn from_iter<T: IntoIterator<Item = (K, Vec<V>)>>(iterable: T) -> BTreeMultiMap<K, V> {
+        let mut multimap = BTreeMultiMap::new();
+        multimap.extend(iterable);
+        multimap
+    }
+}
+
+impl<'a, K, V> IntoIterator for &'a BTreeMultiMap<K, V>
+where
+    K: Ord,
+{
+    type Item = (&'a K, &'a Vec<V>);
+    type IntoIter = IterAll<'a, K, Vec<V>>;
+
+    fn into_iter(self) -> IterAll<'a, K, Vec<V>> {
+        self.iter_all()
+    }
+}
+
+impl<'a, K, V> IntoIterator for &'a mut BTreeMultiMap<K, V>
+where
+    K: Ord,
+{
+    type Item = (&'a K, &'a mut Vec<V>);
+    type IntoIter = IterAllMut<'a, K, Vec<V>>;
+
+    fn into_iter(self) -> IterAllMut<'a, K, Vec<V>> {
+        self.inner.iter_mut()
+    }
+}
+
+impl<K, V> IntoIterator for BTreeMultiMap<K, V>
+where
+    K: Ord,
+{
+    type Item = (K, Vec<V>);
+    type IntoIter = IntoIter<K, Vec<V>>;
+
+    fn into_iter(self) -> IntoIter<K, Vec<V>> {
+        self.inner.into_iter()
+    }
+}
+
+impl<K, V> Extend<(K, V)> for BTreeMultiMap<K, V>
+where
+    K: Ord,
+{
+    fn extend<T: IntoIterator<Item = (K, V)>>(&mut self, iter: T) {
+        for (k, v) in iter {
+            self.insert(k, v);
+        }
+    }
+}
+
+impl<'a, K, V> Extend<(&'a K, &'a V)> for BTreeMultiMap<K, V>
+where
+    K: Ord + Clone,
+    V: Clone,
+{
+    fn extend<T: IntoIterator<Item = (&'a K, &'a V)>>(&mut self, iter: T) {
+        self.extend(
+            iter.into_iter()
+                .map(|(key, value)| (key.clone(), value.clone())),
+        );
+    }
+}
+
+impl<K, V> Extend<(K, Vec<V>)> for BTreeMultiMap<K, V>
+where
+    K: Ord,
+{
+    fn extend<T: IntoIterator<Item = (K, Vec<V>)>>(&mut self, iter: T) {
+        for (k, values) in iter {
+            match self.entry(k) {
+                Entry::Occupied(mut entry) => {
+                    entry.get_vec_mut().extend(values);
+                }
+                Entry::Vacant(entry) => {
+                    entry.insert_vec(values);
+                }
+            }
+        }
+    }
+}
+
+impl<'a, K, V> Extend<(&'a K, &'a Vec<V>)> for BTreeMultiMap<K, V>
+where
+    K: Ord + Clone,
+    V: Clone,
+{
+    fn extend<T: IntoIterator<Item = (&'a K, &'a Vec<V>)>>(&mut self, iter: T) {
+        self.extend(
+            iter.into_iter()
+                .map(|(key, values)| (key.clone(), values.clone())),
+        );
+    }
+}
+
+#[derive(Clone)]
+pub struct Iter<'a, K: 'a, V: 'a> {
+    inner: IterAll<'a, K, Vec<V>>,
+    // The number of keys left with a non-empty vector.
+    len: usize,
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<(&'a K, &'a V)> {
+        loop {
+            let (k, v) = self.inner.next()?;
+            if let Some(v) = v.first() {
+                self.len -= 1;
+                return Some((k, v));
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<'a, K, V> DoubleEndedIterator for Iter<'a, K, V> {
+    fn next_back(&mut self) -> Option<(&'a K, &'a V)> {
+        loop {
+            let (k, v) = self.inner.next_back()?;
+            if let Some(v) = v.first() {
+                self.len -= 1;
+                return Some((k, v));
+            }
+        }
+    }
+}
+
+impl<K, V> ExactSizeIterator for Iter<'_, K, V> {
+    fn len(&self) -> usize {
+        self.len
+    }
+}
+
+impl<K, V> FusedIterator for Iter<'_, K, V> {}
+
+pub struct IterMut<'a, K: 'a, V: 'a> {
+    inner: IterAllMut<'a, K, Vec<V>>,
+    // The number of keys left with a non-empty vector.
+    len: usize,
+}
+
+impl<'a, K, V> Iterator for IterMut<'a, K, V> {
+    type Item = (&'a K, &'a mut V);
+
+    fn next(&mut self) -> Option<(&'a K, &'a mut V)> {
+        loop {
+            let (k, v) = self.inner.next()?;
+            if let Some(v) = v.first_mut() {
+                self.len -= 1;
+                return Some((k, v));
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<'a, K, V> DoubleEndedIterator for IterMut<'a, K, V> {
+    fn next_back(&mut self) -> Option<(&'a K, &'a mut V)> {
+        loop {
+            let (k, v) = self.inner.next_back()?;
+            if let Some(v) = v.first_mut() {
+                self.len -= 1;
+                return Some((k, v));
+            }
+        }
+    }
+}
+
+impl<K, V> ExactSizeIterator for IterMut<'_, K, V> {
+    fn len(&self) -> usize {
+        self.len
+    }
+}
+
+impl<K, V> FusedIterator for IterMut<'_, K, V> {}
+
+#[cfg(test)]
+mod tests {
+    use std::string::{String, ToString};
+
+    use super::*;
+
+    #[test]
+    fn new() {
+        let _: BTreeMultiMap<usize, usize> = BTreeMultiMap::new();
+    }
+
+    #[test]
+    fn insert_identical() {
+        let mut m = BTreeMultiMap::new();
+        m.insert(1, 42);
+        m.insert(1, 42);
+        assert_eq!(m.get_vec(&1), Some(&vec![42, 42]));
+    }
+
+    #[test]
+    fn insert_many_again() {
+        let mut m: BTreeMultiMap<usize, usize> = BTreeMultiMap::new();
+        m.insert(1, 2);
+        m.insert_many(1, vec![3, 4]);
+        assert_eq!(Some(&vec![2, 3, 4]), m.get_vec(&1));
+    }
+
+    #[test]
+    #[should_panic(expected = "no entry found for key")]
+    fn index_no_entry() {
+        let m: BTreeMultiMap<usize, usize> = BTreeMultiMap::new();
+        let _ = &m[&1];
+    }
+
+    #[test]
+    fn get_empty() {
+        let mut m: BTreeMultiMap<usize, usize> = BTreeMultiMap::new();
+        m.insert(1, 42);
+        m.get_vec_mut(&1).unwrap().clear();
+        assert_eq!(m.get(&1), None);
+        assert_eq!(m.iter().count(), 0);
+    }
+
+    #[test]
+    fn keys_sorted() {
+        let mut m = BTreeMultiMap::new();
+        m.insert("c", 3);
+        m.insert("a", 1);
+        m.insert("b", 2);
+        m.insert("a", 4);
+
+        let keys: Vec<_> = m.keys().cloned().collect();
+        assert_eq!(keys, ["a", "b", "c"]);
+    }
+
+    #[test]
+    fn intoiterator_consuming_sorted() {
+        let mut m = BTreeMultiMap::new();
+        m.insert(8, 42);
+        m.insert(1, 42);
+        m.insert(1, 43);
+        m.insert(4, 42);
+
+        let groups: Vec<_> = m.into_iter().collect();
+        assert_eq!(groups, [(1, vec![42, 43]), (4, vec![42]), (8, vec![42])]);
+    }
+
+    #[test]
+    fn test_fmt_debug() {
+        let mut map = BTreeMultiMap::new();
+        map.insert(3, 4);
+        map.insert(1, 2);
+        map.insert(1, 5);
+
+        assert_eq!(format!("{:?}", map), "{1: [2, 5], 3: [4]}");
+    }
+
+    #[test]
+    fn test_eq() {
+        let mut m1 = BTreeMultiMap::new();
+        m1.insert(1, 2);
+        m1.insert(2, 3);
+        let mut m2 = BTreeMultiMap::new();
+        m2.insert(2, 3);
+        assert_ne!(m1, m2);
+        m2.insert(1, 2);
+        assert_eq!(m1, m2);
+    }
+
+    #[test]
+    fn test_from_iterator() {
+        let vals = vec![("foo", 123), ("bar", 456), ("foo", 789)];
+        let map: BTreeMultiMap<&str, i64> = vals.into_iter().collect();
+
+        assert_eq!(map.get_vec("foo"), Some(&vec![123, 789]));
+        assert_eq!(map.get_vec("bar"), Some(&vec![456]));
+    }
+
+    #[test]
+    fn retain_also_removes_empty_vector() {
+        let mut m = BTreeMultiMap::new();
+        m.insert(1, 42);
+        m.insert(1, 99);
+        m.insert(2, 42);
+        m.retain(|&k, &v| k == 1 && v == 42);
+        assert_eq!(1, m.len());
+        assert_eq!(Some(&42), m.get(&1));
+    }
+
+    #[test]
+    fn split_off_and_partition() {
+        let mut m: BTreeMultiMap<usize, usize> = (0..6).map(|i| (i / 2, i)).collect();
+        let upper = m.split_off(&1);
+        assert_eq!(m.get_vec(&0), Some(&vec![0, 1]));
+        assert_eq!(m.len(), 1);
+        assert_eq!(upper.len(), 2);
+
+        let (one, two) = upper.partition(|&k| k == 1);
+        assert_eq!(one.get_vec(&1), Some(&vec![2, 3]));
+        assert_eq!(two.get_vec(&2), Some(&vec![4, 5]));
+    }
+
+    #[test]
+    fn iterators_run_backwards() {
+        let mut m: BTreeMultiMap<usize, usize> = BTreeMultiMap::new();
+        m.insert_many(1, vec![10, 11]);
+        m.insert_many(2, vec![]);
+        m.insert_many(3, vec![30]);
+
+        let pairs: Vec<_> = m.iter().rev().collect();
+        assert_eq!(pairs, [(&3, &30), (&1, &10)]);
+
+        let flat: Vec<_> = m.flat_iter().rev().collect();
+        assert_eq!(flat, [(&3, &30), (&1, &11), (&1, &10)]);
+
+        let mut iter = m.iter_mut();
+        assert_eq!(iter.next_back(), Some((&3, &mut 30)));
+        assert_eq!(iter.next(), Some((&1, &mut 10)));
+        assert_eq!(iter.next_back(), None);
+        assert_eq!(iter.next(), None);
+
+        for (_, v) in m.flat_iter_mut().rev() {
+            *v += 1;
+        }
+        assert_eq!(m.get_vec(&1), Some(&vec![11, 12]));
+    }
+
+    #[test]
+    fn iter_size_hint_with_empty_vec() {
+        let mut m: BTreeMultiMap<usize, usize> = BTreeMultiMap::new();
+        m.insert(1, 1);
+        m.insert_many(2, vec![]);
+
+        assert_eq!(m.iter().len(), 1);
+        assert_eq!(m.iter().count(), 1);
+        assert_eq!(m.iter_mut().len(), 1);
+        assert_eq!(m.iter_mut().count(), 1);
+
+        let mut iter = m.iter();
+        assert_eq!(iter.next_back(), Some((&1, &1)));
+        assert_eq!(iter.size_hint(), (0, Some(0)));
+    }
+
+    #[test]
+    fn extend_from_borrowed_clones() {
+        let mut source: BTreeMultiMap<String, String> = BTreeMultiMap::new();
+        source.insert("a".to_string(), "1".to_string());
+        source.insert("a".to_string(), "2".to_string());
+        source.insert("b".to_string(), "3".to_string());
+
+        let mut flat: BTreeMultiMap<String, String> = BTreeMultiMap::new();
+        flat.extend(source.flat_iter());
+        assert_eq!(flat, source);
+
+        let mut grouped: BTreeMultiMap<String, String> = BTreeMultiMap::new();
+        grouped.extend(source.iter_all());
+        assert_eq!(grouped, source);
+    }
+}
@@ -0,0 +1,53 @@
+// Copyright (c) 2016 multimap developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use std::hash::Hash;
+
+use MultiMap;
+
+/// An extension trait for iterators of key-value pairs, grouping them into a
+/// MultiMap in the way of itertools' `into_group_map`.
+///
+/// # Examples
+///
+/// ```
+/// use multimap::IntoMultiMap;
+///
+/// let map = vec![("a", 1), ("b", 2), ("a", 3)].into_iter().collect_multimap();
+/// assert_eq!(map.get_vec("a"), Some(&vec![1, 3]));
+/// assert_eq!(map.get_vec("b"), Some(&vec![2]));
+/// ```
+pub trait IntoMultiMap<K, V>: Iterator<Item = (K, V)> + Sized {
+    /// Collects the pairs into a MultiMap. Values of a key keep their
+    /// iteration order.
+    fn collect_multimap(self) -> MultiMap<K, V>;
+}
+
+impl<K, V, I> IntoMultiMap<K, V> for I
+where
+    K: Eq + Hash,
+    I: Iterator<Item = (K, V)>,
+{
+    fn collect_multimap(self) -> MultiMap<K, V> {
+        self.collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn collect_multimap_keeps_value_order() {
+        let map = (0..10).map(|i| (i % 3, i)).collect_multimap();
+        assert_eq!(map.len(), 3);
+        assert_eq!(map.get_vec(&0), Some(&vec![0, 3, 6, 9]));
+        assert_eq!(map.get_vec(&1), Some(&vec![1, 4, 7]));
+        assert_eq!(map.get_vec(&2), Some(&vec![2, 5, 8]));
+    }
+}
@@ -0,0 +1,558 @@
+// Copyright (c) 2016 multimap developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! A multimap which can be shared and modified between threads.
+//!
+//! The keys are spread over a number of shards, each of which is a regular
+//! MultiMap behind its own `RwLock`. Two threads only contend when they touch
+//! keys living in the same shard, which makes this map a better fit than a
+//! MultiMap behind a single `Mutex` for write heavy workloads.
+//!
+//! Since no reference into the map can outlive the lock of its shard, values
+//! are either returned by cloning them or handed to a closure.
+//!
+//! # Examples
+//!
+//! ```
+//! use multimap::concurrent::MultiMap;
+//! use std::thread;
+//!
+//! let map = MultiMap::new();
+//!
+//! thread::scope(|s| {
+//!     for i in 0..4 {
+//!         let map = &map;
+//!         s.spawn(move || map.insert(i % 2, i));
+//!     }
+//! });
+//!
+//! assert_eq!(map.len(), 2);
+//! assert_eq!(map.with_vec(&0, |values| values.map(|v| v.len())), Some(2));
+//! ```
+
+use std::borrow::Borrow;
+use std::collections::hash_map::RandomState;
+use std::fmt::{self, Debug};
+use std::hash::{BuildHasher, Hash};
+use std::iter::FromIterator;
+use std::sync::{PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::thread;
+
+use MultiMap as Shard;
+
+pub struct MultiMap<K, V, S = RandomState> {
+    shards: Box<[RwLock<Shard<K, V, S>>]>,
+    shift: u32,
+    hash_builder: S,
+}
+
+impl<K, V> MultiMap<K, V>
+where
+    K: Eq + Hash,
+{
+    /// Creates an empty concurrent MultiMap. The number of shards is derived
+    /// from the available parallelism.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::concurrent::MultiMap;
+    ///
+    /// let map: MultiMap<&str, isize> = MultiMap::new();
+    /// ```
+    pub fn new() -> MultiMap<K, V> {
+        MultiMap::with_hasher(RandomState::new())
+    }
+
+    /// Creates an empty concurrent MultiMap with (at least) the given number of
+    /// shards. The number of shards is rounded up to a power of two.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::concurrent::MultiMap;
+    ///
+    /// let map: MultiMap<&str, isize> = MultiMap::with_shard_amount(16);
+    /// assert_eq!(map.shard_amount(), 16);
+    /// ```
+    pub fn with_shard_amount(shard_amount: usize) -> MultiMap<K, V> {
+        MultiMap::with_shard_amount_and_hasher(shard_amount, RandomState::new())
+    }
+}
+
+impl<K, V, S> MultiMap<K, V, S>
+where
+    K: Eq + Hash,
+    S: BuildHasher + Clone,
+{
+    /// Creates an empty concurrent MultiMap which will use the given hash
+    /// builder to hash keys.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::concurrent::MultiMap;
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let s = RandomState::new();
+    /// let map: MultiMap<&str, isize> = MultiMap::with_hasher(s);
+    /// ```
+    pub fn with_hasher(hash_builder: S) -> MultiMap<K, V, S> {
+        let parallelism = thread::available_parallelism().map_or(1, usize::from);
+        MultiMap::with_shard_amount_and_hasher(parallelism * 4, hash_builder)
+    }
+
+    /// Creates an empty concurrent MultiMap with (at least) the given number of
+    /// shards, which will use the given hash builder to hash keys.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::concurrent::MultiMap;
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let s = RandomState::new();
+    /// let map: MultiMap<&str, isize> = MultiMap::with_shard_amount_and_hasher(3, s);
+    /// assert_eq!(map.shard_amount(), 4);
+    /// ```
+    pub fn with_shard_amount_and_hasher(shard_amount: usize, hash_builder: S) -> MultiMap<K, V, S> {
+        let shard_amount = shard_amount.max(1).next_power_of_two();
+        let shards = (0..shard_amount)
+            .map(|_| RwLock::new(Shard::with_hasher(hash_builder.clone())))
+            .collect();
+        MultiMap {
+            shards,
+            shift: shard_amount.trailing_zeros(),
+            hash_builder,
+        }
+    }
+
+    /// Returns the number of shards the keys are spread over.
+    pub fn shard_amount(&self) -> usize {
+        self.shards.len()
+    }
+
+    /// Inserts a key-value pair into the multimap. If the key does exist in
+    /// the map then the value is pushed to that key's vector. If the key doesn't
+    /// exist in the map a new vector with the given value is inserted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::concurrent::MultiMap;
+    ///
+    /// let map = MultiMap::new();
+    /// map.insert("key", 42);
+    /// ```
+    pub fn insert(&self, k: K, v: V) {
+        self.write_shard(&k).insert(k, v);
+    }
+
+    /// Inserts multiple key-value pairs into the multimap. If the key does exist in
+    /// the map then the values are extended into that key's vector. If the key
+    /// doesn't exist in the map a new vector collected from the given values is inserted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::concurrent::MultiMap;
+    ///
+    /// let map = MultiMap::new();
+    /// map.insert_many("key", vec![42, 43]);
+    /// assert_eq!(map.get_vec("key"), Some(vec![42, 43]));
+    /// ```
+    pub fn insert_many<I: IntoIterator<Item = V>>(&self, k: K, v: I) {
+        self.write_shard(&k).insert_many(k, v);
+    }
+
+    /// Returns true if the map contains a value for the specified key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::concurrent::MultiMap;
+    ///
+    /// let map = MultiMap::new();
+    /// map.insert(1, 42);
+    /// assert_eq!(map.contains_key(&1), true);
+    /// assert_eq!(map.contains_key(&2), false);
+    /// ```
+    pub fn contains_key<Q>(&self, k: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        self.read_shard(k).contains_key(k)
+    }
+
+    /// Returns a clone of the first item in the vector corresponding to the key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::concurrent::MultiMap;
+    ///
+    /// let map = MultiMap::new();
+    /// map.insert(1, 42);
+    /// map.insert(1, 1337);
+    /// assert_eq!(map.get(&1), Some(42));
+    /// ```
+    pub fn get<Q>(&self, k: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+        V: Clone,
+    {
+        self.read_shard(k).get(k).cloned()
+    }
+
+    /// Returns a clone of the vector corresponding to the key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::concurrent::MultiMap;
+    ///
+    /// let map = MultiMap::new();
+    /// map.insert(1, 42);
+    /// map.insert(1, 1337);
+    /// assert_eq!(map.get_vec(&1), Some(vec![42, 1337]));
+    /// ```
+    pub fn get_vec<Q>(&self, k: &Q) -> Option<Vec<V>>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+        V: Clone,
+    {
+        self.read_shard(k).get_vec(k).cloned()
+    }
+
+    /// Calls the closure with a reference to the vector corresponding to the
+    /// key, while holding a read lock on the key's shard.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::concurrent::MultiMap;
+    ///
+    /// let map = MultiMap::new();
+    /// map.insert(1, 42);
+    /// map.insert(1, 1337);
+    /// assert_eq!(map.with_vec(&1, |v| v.map(|v| v.iter().sum::<i32>())), Some(1379));
+    /// ```
+    pub fn with_vec<Q, F, R>(&self, k: &Q, f: F) -> R
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+        F: FnOnce(Option<&Vec<V>>) -> R,
+    {
+        f(self.read_shard(k).get_vec(k))
+    }
+
+    /// Calls the closure with a mutable reference to the vector corresponding
+    /// to the key, while holding a write lock on the key's shard. If the
+    /// closure leaves the vector empty, the key is removed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::concurrent::MultiMap;
+    ///
+    /// let map = MultiMap::new();
+    /// map.insert(1, 42);
+    /// map.insert(1, 1337);
+    /// map.with_vec_mut(&1, |v| v.map(|v| v.pop()));
+    /// assert_eq!(map.get_vec(&1), Some(vec![42]));
+    /// ```
+    pub fn with_vec_mut<Q, F, R>(&self, k: &Q, f: F) -> R
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+        F: FnOnce(Option<&mut Vec<V>>) -> R,
+    {
+        let mut shard = self.write_shard(k);
+        let result = f(shard.get_vec_mut(k));
+        if shard.get_vec(k).is_some_and(|v| v.is_empty()) {
+            shard.remove(k);
+        }
+        result
+    }
+
+    /// Removes a key from the map, returning the vector of values at
+    /// the key if the key was previously in the map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::concurrent::MultiMap;
+    ///
+    /// let map = MultiMap::new();
+    /// map.insert(1, 42);
+    /// map.insert(1, 1337);
+    /// assert_eq!(map.remove(&1), Some(vec![42, 1337]));
+    /// assert_eq!(map.remove(&1), None);
+    /// ```
+    pub fn remove<Q>(&self, k: &Q) -> Option<Vec<V>>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        self.write_shard(k).remove(k)
+    }
+
+    /// Returns the number of keys in the map. Since other threads may modify
+    /// the map while the shards are visited, the result is only a snapshot.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::concurrent::MultiMap;
+    ///
+    /// let map = MultiMap::new();
+    /// map.insert(1, 42);
+    /// map.insert(2, 1337);
+    /// assert_eq!(map.len(), 2);
+    /// ```
+    pub fn len(&self) -> usize {
+        self.shards.iter().map(|shard| read(shard).len()).sum()
+    }
+
+    /// Returns true if the map contains no elements.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::concurrent::MultiMap;
+    ///
+    /// let map = MultiMap::new();
+    /// assert!(map.is_empty());
+    /// map.insert(1, 42);
+    /// assert!(!map.is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.shards.iter().all(|shard| read(shard).is_empty())
+    }
+
+    /// Clears the map, removing all key-value pairs.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::concurrent::MultiMap;
+    ///
+    /// let map = MultiMap::new();
+    /// map.insert(1, 42);
+    /// map.clear();
+    /// assert!(map.is_empty());
+    /// ```
+    pub fn clear(&self) {
+        for shard in self.shards.iter() {
+            write(shard).clear();
+        }
+    }
+
+    /// Retains only the elements specified by the predicate. The shards are
+    /// locked one after another.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::concurrent::MultiMap;
+    ///
+    /// let map = MultiMap::new();
+    /// map.insert(1, 42);
+    /// map.insert(1, 99);
+    /// map.insert(2, 42);
+    /// map.retain(|&k, &v| k == 1 && v == 42);
+    /// assert_eq!(map.get_vec(&1), Some(vec![42]));
+    /// assert!(!map.contains_key(&2));
+    /// ```
+    pub fn retain<F>(&self, mut f: F)
+    where
+        F: FnMut(&K, &V) -> bool,
+    {
+        for shard in self.shards.iter() {
+            write(shard).retain(&mut f);
+        }
+    }
+
+    /// Consumes the concurrent map and merges its shards into a single MultiMap.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::concurrent::MultiMap;
+    ///
+    /// let map = MultiMap::new();
+    /// map.insert(1, 42);
+    /// map.insert(2, 1337);
+    ///
+    /// let map = map.into_multimap();
+    /// assert_eq!(map.get_vec(&1), Some(&vec![42]));
+    /// ```
+    pub fn into_multimap(self) -> Shard<K, V, S> {
+        let len = self.len();
+        let mut result = Shard::with_capacity_and_hasher(len, self.hash_builder);
+        for shard in self.shards.into_vec() {
+            let shard = shard.into_inner().unwrap_or_else(PoisonError::into_inner);
+            result.extend(shard);
+        }
+        result
+    }
+
+    fn shard_index<Q>(&self, k: &Q) -> usize
+    where
+        Q: ?Sized + Hash,
+    {
+        if self.shift == 0 {
+            return 0;
+        }
+        // Skip the topmost bits, which the shard's own table uses to tell
+        // apart keys within a bucket.
+        ((self.hash_builder.hash_one(k) << 7) >> (64 - self.shift)) as usize
+    }
+
+    fn read_shard<Q>(&self, k: &Q) -> RwLockReadGuard<'_, Shard<K, V, S>>
+    where
+        Q: ?Sized + Hash,
+    {
+        read(&self.shards[self.shard_index(k)])
+    }
+
+    fn write_shard<Q>(&self, k: &Q) -> RwLockWriteGuard<'_, Shard<K, V, S>>
+    where
+        Q: ?Sized + Hash,
+    {
+        write(&self.shards[self.shard_index(k)])
+    }
+}
+
+// A panic while a shard is locked can't leave it in an inconsistent state, the
+// worst case is a vector that is missing the value being inserted. The poison
+// flag is therefore ignored.
+fn read<T>(lock: &RwLock<T>) -> RwLockReadGuard<'_, T> {
+    lock.read().unwrap_or_else(PoisonError::into_inner)
+}
+
+fn write<T>(lock: &RwLock<T>) -> RwLockWriteGuard<'_, T> {
+    lock.write().unwrap_or_else(PoisonError::into_inner)
+}
+
+impl<K, V, S> Debug for MultiMap<K, V, S>
+where
+    K: Eq + Hash + Debug,
+    V: Debug,
+    S: BuildHasher,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut map = f.debug_map();
+        for shard in self.shards.iter() {
+            map.entries(read(shard).iter_all());
+        }
+        map.finish()
+    }
+}
+
+impl<K, V, S> Default for MultiMap<K, V, S>
+where
+    K: Eq + Hash,
+    S: BuildHasher + Clone + Default,
+{
+    fn default() -> MultiMap<K, V, S> {
+        MultiMap::with_hasher(S::default())
+    }
+}
+
+impl<K, V, S> From<Shard<K, V, S>> for MultiMap<K, V, S>
+where
+    K: Eq + Hash,
+    S: BuildHasher + Clone,
+{
+    fn from(map: Shard<K, V, S>) -> MultiMap<K, V, S> {
+        let concurrent = MultiMap::with_hasher(map.inner.hasher().clone());
+        for (k, values) in map {
+            concurrent.write_shard(&k).insert_many(k, values);
+        }
+        concurrent
+    }
+}
+
+impl<K, V, S> FromIterator<(K, V)> for MultiMap<K, V, S>
+where
+    K: Eq + Hash,
+    S: BuildHasher + Clone + Default,
+{
+    fn from_iter<T: IntoIterator<Item = (K, V)>>(iterable: T) -> MultiMap<K, V, S> {
+        let multimap = MultiMap::default();
+        for (k, v) in iterable {
+            multimap.insert(k, v);
+        }
+        multimap
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+
+    #[test]
+    fn shard_amount_is_power_of_two() {
+        let m: MultiMap<usize, usize> = MultiMap::with_shard_amount(0);
+        assert_eq!(m.shard_amount(), 1);
+        let m: MultiMap<usize, usize> = MultiMap::with_shard_amount(5);
+        assert_eq!(m.shard_amount(), 8);
+    }
+
+    #[test]
+    fn insert_from_many_threads() {
+        let m = Arc::new(MultiMap::with_shard_amount(8));
+        let handles: Vec<_> = (0..8)
+            .map(|t| {
+                let m = Arc::clone(&m);
+                thread::spawn(move || {
+                    for i in 0..100 {
+                        m.insert(i, t);
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        assert_eq!(m.len(), 100);
+        for i in 0..100 {
+            let mut values = m.get_vec(&i).unwrap();
+            values.sort();
+            assert_eq!(values, (0..8).collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn with_vec_mut_removes_empty_vector() {
+        let m = MultiMap::new();
+        m.insert(1, 42);
+        m.with_vec_mut(&1, |v| v.unwrap().clear());
+        assert!(!m.contains_key(&1));
+        assert!(m.is_empty());
+    }
+
+    #[test]
+    fn roundtrip_multimap() {
+        let mut map = Shard::new();
+        map.insert("a", 1);
+        map.insert("a", 2);
+        map.insert("b", 3);
+
+        let concurrent = MultiMap::from(map.clone());
+        assert_eq!(concurrent.get_vec("a"), Some(vec![1, 2]));
+        assert_eq!(concurrent.into_multimap(), map);
+    }
+}
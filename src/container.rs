@@ -0,0 +1,266 @@
+// Copyright (c) 2016 multimap developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use std::collections::{BTreeSet, HashSet, VecDeque};
+use std::hash::{BuildHasher, Hash};
+use std::vec::Vec;
+
+#[cfg(feature = "smallvec")]
+use smallvec::{Array, SmallVec};
+
+/// A collection which can hold the values of a single key in a MultiMap.
+///
+/// MultiMap stores the values of each key in a `Vec` by default. Any type
+/// implementing this trait can be used instead through the last type
+/// parameter, e.g. `MultiMap<K, V, RandomState, VecDeque<V>>`. Implementations
+/// are provided for `Vec`, `VecDeque`, `BTreeSet` and `HashSet`, and for
+/// `SmallVec` when the `smallvec` feature is enabled.
+///
+/// With another container than `Vec`, the map supports inserting, looking
+/// up, removing and retaining values, and iterating over them, e.g. through
+/// `insert`, `insert_many`, `get`, `contains_pair`, `remove`, `retain`,
+/// `iter_all` and `flat_iter`. The entry API and the methods handing out the
+/// `Vec` of a key, like `entry`, `get_vec`, `get_vec_mut` and `get_many_mut`,
+/// are only available for `Vec`.
+///
+/// # Examples
+///
+/// ```
+/// use multimap::MultiMap;
+/// use std::collections::hash_map::RandomState;
+/// use std::collections::BTreeSet;
+///
+/// let mut map: MultiMap<&str, usize, RandomState, BTreeSet<usize>> = MultiMap::with_container();
+/// map.insert("key", 2);
+/// map.insert("key", 1);
+/// map.insert("key", 2);
+///
+/// let values: Vec<_> = map.flat_iter().map(|(_, v)| *v).collect();
+/// assert_eq!(values, [1, 2]);
+/// ```
+pub trait Container<V>: Default + Extend<V> {
+    /// Iterator over references to the values in the container.
+    type Iter<'a>: Iterator<Item = &'a V>
+    where
+        Self: 'a,
+        V: 'a;
+
+    /// Adds a value to the container.
+    fn push(&mut self, value: V);
+
+    /// Returns the number of values in the container.
+    fn len(&self) -> usize;
+
+    /// Returns true if the container holds no values.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns an iterator over the values in the container.
+    fn iter(&self) -> Self::Iter<'_>;
+
+    /// Retains only the values specified by the predicate.
+    fn retain<F>(&mut self, f: F)
+    where
+        F: FnMut(&V) -> bool;
+
+    /// Creates a container holding the given values. Used when a key is
+    /// first inserted, so implementations should avoid over-allocating.
+    fn from_values<I>(values: I) -> Self
+    where
+        I: IntoIterator<Item = V>,
+    {
+        let mut container = Self::default();
+        container.extend(values);
+        container
+    }
+}
+
+impl<V> Container<V> for Vec<V> {
+    type Iter<'a>
+        = ::std::slice::Iter<'a, V>
+    where
+        V: 'a;
+
+    fn push(&mut self, value: V) {
+        Vec::push(self, value)
+    }
+
+    fn len(&self) -> usize {
+        Vec::len(self)
+    }
+
+    fn iter(&self) -> Self::Iter<'_> {
+        self.as_slice().iter()
+    }
+
+    fn retain<F>(&mut self, f: F)
+    where
+        F: FnMut(&V) -> bool,
+    {
+        Vec::retain(self, f)
+    }
+
+    fn from_values<I>(values: I) -> Self
+    where
+        I: IntoIterator<Item = V>,
+    {
+        values.into_iter().collect()
+    }
+}
+
+impl<V> Container<V> for VecDeque<V> {
+    type Iter<'a>
+        = ::std::collections::vec_deque::Iter<'a, V>
+    where
+        V: 'a;
+
+    fn push(&mut self, value: V) {
+        self.push_back(value)
+    }
+
+    fn len(&self) -> usize {
+        VecDeque::len(self)
+    }
+
+    fn iter(&self) -> Self::Iter<'_> {
+        VecDeque::iter(self)
+    }
+
+    fn retain<F>(&mut self, f: F)
+    where
+        F: FnMut(&V) -> bool,
+    {
+        VecDeque::retain(self, f)
+    }
+
+    fn from_values<I>(values: I) -> Self
+    where
+        I: IntoIterator<Item = V>,
+    {
+        values.into_iter().collect()
+    }
+}
+
+impl<V: Ord> Container<V> for BTreeSet<V> {
+    type Iter<'a>
+        = ::std::collections::btree_set::Iter<'a, V>
+    where
+        V: 'a;
+
+    fn push(&mut self, value: V) {
+        self.insert(value);
+    }
+
+    fn len(&self) -> usize {
+        BTreeSet::len(self)
+    }
+
+    fn iter(&self) -> Self::Iter<'_> {
+        BTreeSet::iter(self)
+    }
+
+    fn retain<F>(&mut self, f: F)
+    where
+        F: FnMut(&V) -> bool,
+    {
+        BTreeSet::retain(self, f)
+    }
+}
+
+impl<V, S> Container<V> for HashSet<V, S>
+where
+    V: Eq + Hash,
+    S: BuildHasher + Default,
+{
+    type Iter<'a>
+        = ::std::collections::hash_set::Iter<'a, V>
+    where
+        V: 'a,
+        S: 'a;
+
+    fn push(&mut self, value: V) {
+        self.insert(value);
+    }
+
+    fn len(&self) -> usize {
+        HashSet::len(self)
+    }
+
+    fn iter(&self) -> Self::Iter<'_> {
+        HashSet::iter(self)
+    }
+
+    fn retain<F>(&mut self, f: F)
+    where
+        F: FnMut(&V) -> bool,
+    {
+        HashSet::retain(self, f)
+    }
+}
+
+/// Stores up to `A::size()` values inline before spilling to the heap, which
+/// avoids a separate allocation for keys with only a few values.
+///
+/// With `SmallVec<[V; 1]>`, keys holding a single value never allocate. The
+/// values of a key are read as a slice with `MultiMap::values_of` regardless
+/// of whether they spilled.
+///
+/// # Examples
+///
+/// ```
+/// # extern crate multimap;
+/// # extern crate smallvec;
+/// use multimap::MultiMap;
+/// use smallvec::SmallVec;
+/// use std::collections::hash_map::RandomState;
+///
+/// # fn main() {
+/// let mut map: MultiMap<&str, u32, RandomState, SmallVec<[u32; 2]>> = MultiMap::with_container();
+/// map.insert("key", 1);
+/// map.insert("key", 2);
+/// assert!(!map.iter_all().any(|(_, values)| values.spilled()));
+///
+/// map.insert("key", 3);
+/// assert!(map.iter_all().all(|(_, values)| values.spilled()));
+/// assert_eq!(map.values_of("key"), [1, 2, 3]);
+///
+/// let mut single: MultiMap<&str, u32, RandomState, SmallVec<[u32; 1]>> = MultiMap::with_container();
+/// single.insert("a", 1);
+/// single.insert("b", 2);
+/// assert!(!single.iter_all().any(|(_, values)| values.spilled()));
+/// assert_eq!(single.values_of("a"), [1]);
+/// assert!(single.values_of("c").is_empty());
+/// # }
+/// ```
+#[cfg(feature = "smallvec")]
+impl<A: Array> Container<A::Item> for SmallVec<A> {
+    type Iter<'a>
+        = ::std::slice::Iter<'a, A::Item>
+    where
+        A: 'a;
+
+    fn push(&mut self, value: A::Item) {
+        SmallVec::push(self, value)
+    }
+
+    fn len(&self) -> usize {
+        SmallVec::len(self)
+    }
+
+    fn iter(&self) -> Self::Iter<'_> {
+        self.as_slice().iter()
+    }
+
+    fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&A::Item) -> bool,
+    {
+        SmallVec::retain(self, |value| f(value))
+    }
+}
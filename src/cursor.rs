@@ -0,0 +1,172 @@
+// Copyright (c) 2016 multimap developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! A cursor for editing the values of a single key while walking over them.
+//!
+//! A CursorMut is created by `MultiMap::cursor_mut`. It points at one value of
+//! the key at a time and can remove that value or insert new values around it
+//! without invalidating the walk, which is awkward to do with indices into the
+//! vector returned by `get_vec_mut`.
+//!
+//! Removing every value through the cursor leaves the key in the map with an
+//! empty vector; use `MultiMap::prune_empty` or `remove` to get rid of it.
+//!
+//! # Examples
+//!
+//! ```
+//! use multimap::MultiMap;
+//!
+//! let mut map = MultiMap::new();
+//! map.insert_many("key", vec![1, 2, 3, 4]);
+//!
+//! let mut cursor = map.cursor_mut("key").unwrap();
+//! while let Some(&value) = cursor.current() {
+//!     if value % 2 == 0 {
+//!         cursor.remove_current();
+//!     } else {
+//!         cursor.insert_after(value * 10);
+//!         cursor.move_next();
+//!         cursor.move_next();
+//!     }
+//! }
+//! assert_eq!(map.get_vec("key"), Some(&vec![1, 10, 3, 30]));
+//! ```
+
+use std::vec::Vec;
+
+/// A cursor over the values of a single key in a MultiMap, with editing
+/// operations.
+///
+/// The cursor points either at a value or one past the last value.
+pub struct CursorMut<'a, V: 'a> {
+    values: &'a mut Vec<V>,
+    index: usize,
+}
+
+impl<'a, V: 'a> CursorMut<'a, V> {
+    pub(crate) fn new(values: &'a mut Vec<V>) -> CursorMut<'a, V> {
+        CursorMut { values, index: 0 }
+    }
+
+    /// Returns the position of the cursor. This is equal to the number of
+    /// values when the cursor is past the last value.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Returns a reference to the value the cursor points at, or `None` if it
+    /// is past the last value.
+    pub fn current(&self) -> Option<&V> {
+        self.values.get(self.index)
+    }
+
+    /// Returns a mutable reference to the value the cursor points at, or
+    /// `None` if it is past the last value.
+    pub fn current_mut(&mut self) -> Option<&mut V> {
+        self.values.get_mut(self.index)
+    }
+
+    /// Moves the cursor to the next value. Does nothing if the cursor is past
+    /// the last value.
+    pub fn move_next(&mut self) {
+        if self.index < self.values.len() {
+            self.index += 1;
+        }
+    }
+
+    /// Moves the cursor to the previous value. Does nothing if the cursor is
+    /// at the first value.
+    pub fn move_prev(&mut self) {
+        self.index = self.index.saturating_sub(1);
+    }
+
+    /// Removes and returns the value the cursor points at. The cursor then
+    /// points at the value which followed the removed one.
+    pub fn remove_current(&mut self) -> Option<V> {
+        if self.index < self.values.len() {
+            Some(self.values.remove(self.index))
+        } else {
+            None
+        }
+    }
+
+    /// Inserts a value before the one the cursor points at. The cursor keeps
+    /// pointing at the same value.
+    pub fn insert_before(&mut self, value: V) {
+        self.values.insert(self.index, value);
+        self.index += 1;
+    }
+
+    /// Inserts a value after the one the cursor points at. If the cursor is
+    /// past the last value the value is appended and the cursor stays past
+    /// the end, so this behaves like `insert_before` there.
+    pub fn insert_after(&mut self, value: V) {
+        if self.index < self.values.len() {
+            self.values.insert(self.index + 1, value);
+        } else {
+            self.insert_before(value);
+        }
+    }
+
+    /// Returns the values of the key as a slice.
+    pub fn as_slice(&self) -> &[V] {
+        self.values
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use MultiMap;
+
+    #[test]
+    fn walk_and_edit() {
+        let mut map: MultiMap<usize, usize> = MultiMap::new();
+        map.insert_many(1, vec![1, 2, 3]);
+        assert!(map.cursor_mut(&2).is_none());
+
+        let mut cursor = map.cursor_mut(&1).unwrap();
+        assert_eq!(cursor.current(), Some(&1));
+        cursor.insert_before(0);
+        assert_eq!(cursor.index(), 1);
+        assert_eq!(cursor.current(), Some(&1));
+
+        cursor.move_next();
+        assert_eq!(cursor.remove_current(), Some(2));
+        assert_eq!(cursor.current(), Some(&3));
+        *cursor.current_mut().unwrap() = 30;
+
+        cursor.move_next();
+        cursor.move_next();
+        assert_eq!(cursor.index(), 3);
+        assert_eq!(cursor.current(), None);
+        assert_eq!(cursor.remove_current(), None);
+        cursor.insert_after(40);
+        cursor.insert_before(50);
+        assert_eq!(cursor.current(), None);
+        assert_eq!(cursor.as_slice(), &[0, 1, 30, 40, 50]);
+
+        cursor.move_prev();
+        assert_eq!(cursor.current(), Some(&50));
+        cursor.insert_after(45);
+        assert_eq!(map.get_vec(&1), Some(&vec![0, 1, 30, 40, 50, 45]));
+    }
+
+    #[test]
+    fn remove_everything() {
+        let mut map: MultiMap<usize, usize> = MultiMap::new();
+        map.insert_many(1, vec![1, 2]);
+
+        let mut cursor = map.cursor_mut(&1).unwrap();
+        while cursor.remove_current().is_some() {}
+        cursor.move_prev();
+        assert_eq!(cursor.index(), 0);
+
+        assert_eq!(map.get_vec(&1), Some(&vec![]));
+        assert_eq!(map.prune_empty(), 1);
+    }
+}
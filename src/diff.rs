@@ -0,0 +1,94 @@
+// Copyright (c) 2016 multimap developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! The changes needed to turn one MultiMap into another.
+//!
+//! A MultiMapDiff is created by `MultiMap::diff` and replayed with
+//! `MultiMap::apply`. It only contains the keys which differ between the two
+//! maps, which makes it a compact way to keep a copy of a map up to date.
+//!
+//! # Examples
+//!
+//! ```
+//! use multimap::MultiMap;
+//!
+//! let mut old = MultiMap::new();
+//! old.insert("a", 1);
+//! old.insert("b", 2);
+//!
+//! let mut new = old.clone();
+//! new.insert("a", 3);
+//! new.remove("b");
+//! new.insert("c", 4);
+//!
+//! let diff = old.diff(&new);
+//! assert_eq!(diff.added, [("c", vec![4])]);
+//! assert_eq!(diff.removed, ["b"]);
+//! assert_eq!(diff.changed, [("a", vec![1, 3])]);
+//!
+//! old.apply(diff);
+//! assert_eq!(old, new);
+//! ```
+
+use std::vec::Vec;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MultiMapDiff<K, V> {
+    /// Keys which are only present in the new map, with their values.
+    pub added: Vec<(K, Vec<V>)>,
+
+    /// Keys which are only present in the old map.
+    pub removed: Vec<K>,
+
+    /// Keys present in both maps whose values differ, with the new values.
+    pub changed: Vec<(K, Vec<V>)>,
+}
+
+impl<K, V> MultiMapDiff<K, V> {
+    /// Returns true if the diff contains no changes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiMap;
+    ///
+    /// let mut map = MultiMap::new();
+    /// map.insert(1, 42);
+    /// assert!(map.diff(&map.clone()).is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+
+    /// Returns the number of keys affected by the diff.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiMap;
+    ///
+    /// let mut a = MultiMap::new();
+    /// a.insert(1, 42);
+    /// let mut b = MultiMap::new();
+    /// b.insert(2, 42);
+    /// assert_eq!(a.diff(&b).len(), 2);
+    /// ```
+    pub fn len(&self) -> usize {
+        self.added.len() + self.removed.len() + self.changed.len()
+    }
+}
+
+impl<K, V> Default for MultiMapDiff<K, V> {
+    fn default() -> MultiMapDiff<K, V> {
+        MultiMapDiff {
+            added: Vec::new(),
+            removed: Vec::new(),
+            changed: Vec::new(),
+        }
+    }
+}
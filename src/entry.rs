@@ -7,30 +7,75 @@
 // modified, or distributed except according to those terms.
 
 use std::collections::hash_map::OccupiedEntry as HashMapOccupiedEntry;
+use std::collections::hash_map::RandomState;
 use std::collections::hash_map::VacantEntry as HashMapVacantEntry;
+#[cfg(not(feature = "std"))]
+use std::collections::hash_map::VacantEntryRef as HashMapVacantEntryRef;
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hash};
+use std::marker::PhantomData;
+use std::vec::Vec;
+
+// Inserting through a vacant hashbrown entry needs the key to be hashable
+// and the hash builder to build hashers, while std's entries need neither.
+// These traits carry those bounds only for the hashbrown backend, so that
+// with std the entry types stay as unconstrained as in std's HashMap.
+#[cfg(feature = "std")]
+pub trait EntryKey {}
+#[cfg(feature = "std")]
+impl<K> EntryKey for K {}
+#[cfg(not(feature = "std"))]
+pub trait EntryKey: Hash {}
+#[cfg(not(feature = "std"))]
+impl<K: Hash> EntryKey for K {}
+
+#[cfg(feature = "std")]
+pub trait EntryHasher {}
+#[cfg(feature = "std")]
+impl<S> EntryHasher for S {}
+#[cfg(not(feature = "std"))]
+pub trait EntryHasher: BuildHasher {}
+#[cfg(not(feature = "std"))]
+impl<S: BuildHasher> EntryHasher for S {}
 
 /// A view into a single occupied location in a MultiMap.
-pub struct OccupiedEntry<'a, K: 'a, V: 'a> {
+pub struct OccupiedEntry<'a, K: 'a, V: 'a, S: 'a = RandomState> {
     #[doc(hidden)]
+    #[cfg(feature = "std")]
     pub inner: HashMapOccupiedEntry<'a, K, Vec<V>>,
+    #[doc(hidden)]
+    #[cfg(not(feature = "std"))]
+    pub inner: HashMapOccupiedEntry<'a, K, Vec<V>, S>,
+    pub(crate) marker: PhantomData<&'a S>,
 }
 
 /// A view into a single empty location in a MultiMap.
-pub struct VacantEntry<'a, K: 'a, V: 'a> {
+pub struct VacantEntry<'a, K: 'a, V: 'a, S: 'a = RandomState> {
     #[doc(hidden)]
+    #[cfg(feature = "std")]
     pub inner: HashMapVacantEntry<'a, K, Vec<V>>,
+    #[doc(hidden)]
+    #[cfg(not(feature = "std"))]
+    pub inner: HashMapVacantEntry<'a, K, Vec<V>, S>,
+    pub(crate) marker: PhantomData<&'a S>,
 }
 
 /// A view into a single location in a map, which may be vacant or occupied.
-pub enum Entry<'a, K: 'a, V: 'a> {
+pub enum Entry<'a, K: 'a, V: 'a, S: 'a = RandomState> {
     /// An occupied Entry.
-    Occupied(OccupiedEntry<'a, K, V>),
+    Occupied(OccupiedEntry<'a, K, V, S>),
 
     /// A vacant Entry.
-    Vacant(VacantEntry<'a, K, V>),
+    Vacant(VacantEntry<'a, K, V, S>),
 }
 
-impl<'a, K: 'a, V: 'a> OccupiedEntry<'a, K, V> {
+impl<'a, K: 'a, V: 'a, S: 'a> OccupiedEntry<'a, K, V, S> {
+    /// Gets a reference to the key in the entry.
+    pub fn key(&self) -> &K {
+        self.inner.key()
+    }
+
     /// Gets a reference to the first item in value in the vector corresponding to entry.
     ///
     /// # Panics
@@ -62,6 +107,31 @@ impl<'a, K: 'a, V: 'a> OccupiedEntry<'a, K, V> {
         self.inner.get_mut()
     }
 
+    /// Returns the number of values in the entry.
+    pub fn len(&self) -> usize {
+        self.get_vec().len()
+    }
+
+    /// Returns true if the entry holds no values.
+    pub fn is_empty(&self) -> bool {
+        self.get_vec().is_empty()
+    }
+
+    /// Returns an iterator over the values in the entry.
+    pub fn iter(&self) -> ::std::slice::Iter<'_, V> {
+        self.get_vec().iter()
+    }
+
+    /// Returns an iterator over mutable references to the values in the entry.
+    pub fn iter_mut(&mut self) -> ::std::slice::IterMut<'_, V> {
+        self.get_vec_mut().iter_mut()
+    }
+
+    /// Gets a mut reference to the values corresponding to entry as a slice.
+    pub fn get_slice_mut(&mut self) -> &mut [V] {
+        self.inner.get_mut()
+    }
+
     /// Converts the OccupiedEntry into a mutable reference to the first item in value in the entry
     /// with a lifetime bound to the map itself
     pub fn into_mut(self) -> &'a mut V {
@@ -84,13 +154,70 @@ impl<'a, K: 'a, V: 'a> OccupiedEntry<'a, K, V> {
         self.get_vec_mut().extend(values);
     }
 
+    /// Replaces the values (vector) of the entry, and returns the old values.
+    pub fn replace_values(&mut self, values: Vec<V>) -> Vec<V> {
+        self.inner.insert(values)
+    }
+
     /// Takes the values (vector) out of the entry, and returns it
     pub fn remove(self) -> Vec<V> {
         self.inner.remove()
     }
+
+    /// Removes and returns the last value in the vector of the entry. The entry is
+    /// removed from the map if its vector is empty afterwards.
+    pub fn pop(mut self) -> Option<V> {
+        let value = self.get_vec_mut().pop();
+        self.remove_if_empty();
+        value
+    }
+
+    /// Removes and returns the first value in the vector of the entry. The entry is
+    /// removed from the map if its vector is empty afterwards.
+    pub fn remove_first(mut self) -> Option<V> {
+        let values = self.get_vec_mut();
+        let value = if values.is_empty() {
+            None
+        } else {
+            Some(values.remove(0))
+        };
+        self.remove_if_empty();
+        value
+    }
+
+    /// Retains only the values specified by the predicate. The entry is removed from
+    /// the map if its vector is empty afterwards.
+    pub fn retain<F>(mut self, f: F)
+    where
+        F: FnMut(&V) -> bool,
+    {
+        self.get_vec_mut().retain(f);
+        self.remove_if_empty();
+    }
+
+    fn remove_if_empty(self) {
+        if self.get_vec().is_empty() {
+            self.inner.remove();
+        }
+    }
 }
 
-impl<'a, K: 'a, V: 'a> VacantEntry<'a, K, V> {
+impl<'a, K: 'a, V: 'a, S: 'a> VacantEntry<'a, K, V, S>
+where
+    K: EntryKey,
+    S: EntryHasher,
+{
+    /// Gets a reference to the key that would be used when inserting a value
+    /// through the VacantEntry.
+    pub fn key(&self) -> &K {
+        self.inner.key()
+    }
+
+    /// Takes ownership of the key.
+    pub fn into_key(self) -> K {
+        self.inner.into_key()
+    }
+
     /// Sets the first value in the vector of the entry with the VacantEntry's key,
     /// and returns a mutable reference to it.
     pub fn insert(self, value: V) -> &'a mut V {
@@ -102,9 +229,43 @@ impl<'a, K: 'a, V: 'a> VacantEntry<'a, K, V> {
     pub fn insert_vec(self, values: Vec<V>) -> &'a mut Vec<V> {
         self.inner.insert(values)
     }
+
+    /// Sets values in the entry with the VacantEntry's key from an iterator,
+    /// and returns a mutable reference to them. The values are collected
+    /// straight into the vector stored in the map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::{Entry, MultiMap};
+    ///
+    /// let mut map: MultiMap<&str, u32> = MultiMap::new();
+    /// if let Entry::Vacant(entry) = map.entry("key") {
+    ///     entry.insert_iter((1..4).map(|v| v * 10)).push(40);
+    /// }
+    /// assert_eq!(map.get_vec("key"), Some(&vec![10, 20, 30, 40]));
+    /// ```
+    pub fn insert_iter<I>(self, values: I) -> &'a mut Vec<V>
+    where
+        I: IntoIterator<Item = V>,
+    {
+        self.inner.insert(values.into_iter().collect())
+    }
 }
 
-impl<'a, K: 'a, V: 'a> Entry<'a, K, V> {
+impl<'a, K: 'a, V: 'a, S: 'a> Entry<'a, K, V, S>
+where
+    K: EntryKey,
+    S: EntryHasher,
+{
+    /// Returns a reference to this entry's key.
+    pub fn key(&self) -> &K {
+        match *self {
+            Entry::Occupied(ref entry) => entry.key(),
+            Entry::Vacant(ref entry) => entry.key(),
+        }
+    }
+
     /// Ensures a value is in the entry by inserting the default if empty, and returns
     /// a mutable reference to the value in the entry. This will return a mutable reference to the
     /// first value in the vector corresponding to the specified key.
@@ -124,4 +285,234 @@ impl<'a, K: 'a, V: 'a> Entry<'a, K, V> {
             Entry::Vacant(entry) => entry.insert_vec(defaults),
         }
     }
+
+    /// Ensures a value is in the entry by inserting the result of the default function
+    /// if empty, and returns a mutable reference to the first value in the entry.
+    pub fn or_insert_with<F>(self, default: F) -> &'a mut V
+    where
+        F: FnOnce() -> V,
+    {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(default()),
+        }
+    }
+
+    /// Ensures a value is in the entry by inserting the result of the default function
+    /// if empty, and returns a mutable reference to the first value in the entry. The
+    /// function is given a reference to the entry's key.
+    pub fn or_insert_with_key<F>(self, default: F) -> &'a mut V
+    where
+        F: FnOnce(&K) -> V,
+    {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                let value = default(entry.key());
+                entry.insert(value)
+            }
+        }
+    }
+
+    /// Ensures a value is in the entry by inserting the default value if empty, and
+    /// returns a mutable reference to the first value in the entry.
+    pub fn or_default(self) -> &'a mut V
+    where
+        V: Default,
+    {
+        self.or_insert_with(V::default)
+    }
+
+    /// Ensures values are in the entry by inserting the result of the default function
+    /// if empty, and returns a mutable reference to the values (vector) in the entry.
+    pub fn or_insert_vec_with<F>(self, defaults: F) -> &'a mut Vec<V>
+    where
+        F: FnOnce() -> Vec<V>,
+    {
+        match self {
+            Entry::Occupied(entry) => entry.into_vec_mut(),
+            Entry::Vacant(entry) => entry.insert_vec(defaults()),
+        }
+    }
+
+    /// Provides in-place mutable access to the values (vector) of an occupied entry
+    /// before any potential inserts into the map.
+    pub fn and_modify<F>(self, f: F) -> Self
+    where
+        F: FnOnce(&mut Vec<V>),
+    {
+        match self {
+            Entry::Occupied(mut entry) => {
+                f(entry.get_vec_mut());
+                Entry::Occupied(entry)
+            }
+            Entry::Vacant(entry) => Entry::Vacant(entry),
+        }
+    }
+}
+
+/// A view into a single occupied location in a MultiMap, found through
+/// `MultiMap::entry_ref`.
+pub struct OccupiedEntryRef<'a, K: 'a, V: 'a> {
+    pub(crate) values: &'a mut Vec<V>,
+    pub(crate) marker: PhantomData<&'a K>,
+}
+
+/// A view into a single empty location in a MultiMap, found through
+/// `MultiMap::entry_ref`. The key is only converted into an owned key when a
+/// value is inserted.
+pub struct VacantEntryRef<'a, 'b, K: 'a, V: 'a, Q: 'b + ?Sized, S: 'a = RandomState> {
+    #[cfg(feature = "std")]
+    pub(crate) map: &'a mut HashMap<K, Vec<V>, S>,
+    #[cfg(feature = "std")]
+    pub(crate) key: &'b Q,
+    #[cfg(not(feature = "std"))]
+    pub(crate) inner: HashMapVacantEntryRef<'a, 'b, K, Q, Vec<V>, S>,
+}
+
+/// A view into a single location in a map found with a borrowed key, which may
+/// be vacant or occupied.
+pub enum EntryRef<'a, 'b, K: 'a, V: 'a, Q: 'b + ?Sized, S: 'a = RandomState> {
+    /// An occupied EntryRef.
+    Occupied(OccupiedEntryRef<'a, K, V>),
+
+    /// A vacant EntryRef.
+    Vacant(VacantEntryRef<'a, 'b, K, V, Q, S>),
+}
+
+impl<'a, K: 'a, V: 'a> OccupiedEntryRef<'a, K, V> {
+    /// Gets a reference to the first item in value in the vector corresponding to entry.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if the key has zero values.
+    pub fn get(&self) -> &V {
+        self.values.first().expect("no values in entry")
+    }
+
+    /// Gets a reference to the values (vector) corresponding to entry.
+    pub fn get_vec(&self) -> &Vec<V> {
+        self.values
+    }
+
+    /// Gets a mut reference to the values (vector) corresponding to entry.
+    pub fn get_vec_mut(&mut self) -> &mut Vec<V> {
+        self.values
+    }
+
+    /// Converts the OccupiedEntryRef into a mutable reference to the first item in value
+    /// in the entry with a lifetime bound to the map itself
+    pub fn into_mut(self) -> &'a mut V {
+        &mut self.values[0]
+    }
+
+    /// Converts the OccupiedEntryRef into a mutable reference to the values (vector) in
+    /// the entry with a lifetime bound to the map itself
+    pub fn into_vec_mut(self) -> &'a mut Vec<V> {
+        self.values
+    }
+
+    /// Inserts a new value onto the vector of the entry.
+    pub fn insert(&mut self, value: V) {
+        self.values.push(value);
+    }
+
+    /// Extends the existing vector with the specified values.
+    pub fn insert_vec(&mut self, values: Vec<V>) {
+        self.values.extend(values);
+    }
+}
+
+impl<'a, 'b, K: 'a, V: 'a, Q: 'b + ?Sized, S: 'a> VacantEntryRef<'a, 'b, K, V, Q, S>
+where
+    K: Eq + Hash + From<&'b Q>,
+    S: BuildHasher,
+{
+    /// Gets a reference to the borrowed key that would be used when inserting a value
+    /// through the VacantEntryRef.
+    pub fn key(&self) -> &'b Q {
+        #[cfg(feature = "std")]
+        return self.key;
+        #[cfg(not(feature = "std"))]
+        return self.inner.key();
+    }
+
+    /// Sets the first value in the vector of the entry with an owned copy of the
+    /// VacantEntryRef's key, and returns a mutable reference to it.
+    pub fn insert(self, value: V) -> &'a mut V {
+        &mut self.insert_vec(vec![value])[0]
+    }
+
+    /// Sets values in the entry with an owned copy of the VacantEntryRef's key,
+    /// and returns a mutable reference to it.
+    pub fn insert_vec(self, values: Vec<V>) -> &'a mut Vec<V> {
+        // std has no entry API for borrowed keys, so the owned key is hashed
+        // again here.
+        #[cfg(feature = "std")]
+        return self.map.entry(K::from(self.key)).or_insert(values);
+        #[cfg(not(feature = "std"))]
+        return self.inner.insert(values);
+    }
+}
+
+impl<'a, 'b, K: 'a, V: 'a, Q: 'b + ?Sized, S: 'a> EntryRef<'a, 'b, K, V, Q, S>
+where
+    K: Eq + Hash + From<&'b Q>,
+    S: BuildHasher,
+{
+    /// Ensures a value is in the entry by inserting the default if empty, and returns
+    /// a mutable reference to the value in the entry. This will return a mutable reference to the
+    /// first value in the vector corresponding to the specified key.
+    pub fn or_insert(self, default: V) -> &'a mut V {
+        match self {
+            EntryRef::Occupied(entry) => entry.into_mut(),
+            EntryRef::Vacant(entry) => entry.insert(default),
+        }
+    }
+
+    /// Ensures a value is in the entry by inserting the result of the default function
+    /// if empty, and returns a mutable reference to the first value in the entry.
+    pub fn or_insert_with<F>(self, default: F) -> &'a mut V
+    where
+        F: FnOnce() -> V,
+    {
+        match self {
+            EntryRef::Occupied(entry) => entry.into_mut(),
+            EntryRef::Vacant(entry) => entry.insert(default()),
+        }
+    }
+
+    /// Ensures a value is in the entry by inserting the default value if empty, and
+    /// returns a mutable reference to the first value in the entry.
+    pub fn or_default(self) -> &'a mut V
+    where
+        V: Default,
+    {
+        self.or_insert_with(V::default)
+    }
+
+    /// Ensures a value is in the entry by inserting the default values if empty, and returns
+    /// a mutable reference to the values (the corresponding vector to the specified key) in
+    /// the entry.
+    pub fn or_insert_vec(self, defaults: Vec<V>) -> &'a mut Vec<V> {
+        match self {
+            EntryRef::Occupied(entry) => entry.into_vec_mut(),
+            EntryRef::Vacant(entry) => entry.insert_vec(defaults),
+        }
+    }
+
+    /// Provides in-place mutable access to the values (vector) of an occupied entry
+    /// before any potential inserts into the map.
+    pub fn and_modify<F>(self, f: F) -> Self
+    where
+        F: FnOnce(&mut Vec<V>),
+    {
+        match self {
+            EntryRef::Occupied(mut entry) => {
+                f(entry.get_vec_mut());
+                EntryRef::Occupied(entry)
+            }
+            EntryRef::Vacant(entry) => EntryRef::Vacant(entry),
+        }
+    }
 }
@@ -0,0 +1,497 @@
+// Copyright (c) 2016 multimap developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! A multimap whose values expire after a given time to live.
+//!
+//! Every value is stored together with its deadline. A value is expired once
+//! its deadline is reached. Values whose time to live reaches past the range
+//! of `Instant` have no deadline and never expire. Expired values are pruned lazily: looking up a key
+//! through a `&mut self` method drops the key's expired values first, and
+//! `purge_expired` drops the expired values of every key. A key is removed
+//! together with its last value.
+//!
+//! # Examples
+//!
+//! ```
+//! use multimap::ExpiringMultiMap;
+//! use std::time::Duration;
+//!
+//! let mut sessions = ExpiringMultiMap::new();
+//! sessions.insert("alice", "laptop", Duration::from_secs(3600));
+//! sessions.insert("alice", "phone", Duration::from_secs(0));
+//!
+//! let devices: Vec<_> = sessions.get_values("alice").collect();
+//! assert_eq!(devices, [&"laptop"]);
+//! ```
+
+use std::borrow::Borrow;
+use std::collections::hash_map::{Keys, RandomState};
+use std::collections::HashMap;
+use std::fmt::{self, Debug};
+use std::hash::{BuildHasher, Hash};
+use std::iter::Iterator;
+use std::slice;
+use std::time::{Duration, Instant};
+
+#[derive(Clone)]
+pub struct ExpiringMultiMap<K, V, S = RandomState> {
+    inner: HashMap<K, Vec<(Option<Instant>, V)>, S>,
+}
+
+impl<K, V> ExpiringMultiMap<K, V>
+where
+    K: Eq + Hash,
+{
+    /// Creates an empty ExpiringMultiMap
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::ExpiringMultiMap;
+    ///
+    /// let mut map: ExpiringMultiMap<&str, isize> = ExpiringMultiMap::new();
+    /// ```
+    pub fn new() -> ExpiringMultiMap<K, V> {
+        ExpiringMultiMap {
+            inner: HashMap::new(),
+        }
+    }
+}
+
+impl<K, V, S> ExpiringMultiMap<K, V, S>
+where
+    K: Eq + Hash,
+    S: BuildHasher,
+{
+    /// Creates an empty ExpiringMultiMap which will use the given hash builder
+    /// to hash keys.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::ExpiringMultiMap;
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let s = RandomState::new();
+    /// let mut map: ExpiringMultiMap<&str, isize> = ExpiringMultiMap::with_hasher(s);
+    /// ```
+    pub fn with_hasher(hash_builder: S) -> ExpiringMultiMap<K, V, S> {
+        ExpiringMultiMap {
+            inner: HashMap::with_hasher(hash_builder),
+        }
+    }
+
+    /// Inserts a key-value pair which expires once the given time to live has
+    /// passed. If the deadline can't be represented as an `Instant`, e.g. for
+    /// `Duration::MAX`, the value never expires.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::ExpiringMultiMap;
+    /// use std::time::Duration;
+    ///
+    /// let mut map = ExpiringMultiMap::new();
+    /// map.insert("key", 42, Duration::from_secs(60));
+    /// assert_eq!(map.get("key"), Some(&42));
+    /// ```
+    pub fn insert(&mut self, k: K, v: V, ttl: Duration) {
+        let deadline = Instant::now().checked_add(ttl);
+        self.inner.entry(k).or_default().push((deadline, v));
+    }
+
+    /// Inserts a key-value pair which expires at the given deadline.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::ExpiringMultiMap;
+    /// use std::time::{Duration, Instant};
+    ///
+    /// let mut map = ExpiringMultiMap::new();
+    /// map.insert_until("key", 42, Instant::now() + Duration::from_secs(60));
+    /// assert_eq!(map.get("key"), Some(&42));
+    /// ```
+    pub fn insert_until(&mut self, k: K, v: V, deadline: Instant) {
+        self.inner.entry(k).or_default().push((Some(deadline), v));
+    }
+
+    /// Returns true if the key has a value which hasn't expired yet. Expired
+    /// values of the key are pruned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::ExpiringMultiMap;
+    /// use std::time::Duration;
+    ///
+    /// let mut map = ExpiringMultiMap::new();
+    /// map.insert(1, 42, Duration::from_secs(60));
+    /// map.insert(2, 42, Duration::from_secs(0));
+    /// assert_eq!(map.contains_key(&1), true);
+    /// assert_eq!(map.contains_key(&2), false);
+    /// ```
+    pub fn contains_key<Q>(&mut self, k: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        self.prune_key(k, Instant::now());
+        self.inner.contains_key(k)
+    }
+
+    /// Returns a reference to the oldest value of the key which hasn't expired
+    /// yet. Expired values of the key are pruned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::ExpiringMultiMap;
+    /// use std::time::Duration;
+    ///
+    /// let mut map = ExpiringMultiMap::new();
+    /// map.insert(1, 42, Duration::from_secs(0));
+    /// map.insert(1, 1337, Duration::from_secs(60));
+    /// assert_eq!(map.get(&1), Some(&1337));
+    /// ```
+    pub fn get<Q>(&mut self, k: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        self.get_values(k).next()
+    }
+
+    /// An iterator over the values of the key which haven't expired yet, in
+    /// insertion order. Expired values of the key are pruned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::ExpiringMultiMap;
+    /// use std::time::Duration;
+    ///
+    /// let mut map = ExpiringMultiMap::new();
+    /// map.insert(1, 42, Duration::from_secs(60));
+    /// map.insert(1, 43, Duration::from_secs(0));
+    /// map.insert(1, 44, Duration::from_secs(60));
+    ///
+    /// let values: Vec<_> = map.get_values(&1).collect();
+    /// assert_eq!(values, [&42, &44]);
+    /// ```
+    pub fn get_values<Q>(&mut self, k: &Q) -> Values<'_, V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        self.prune_key(k, Instant::now());
+        Values {
+            inner: self.inner.get(k).map_or([].iter(), |values| values.iter()),
+        }
+    }
+
+    /// Returns the deadlines of the values of the key, in insertion order.
+    /// Nothing is pruned, so deadlines which have passed are included. Values
+    /// which never expire have no deadline.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::ExpiringMultiMap;
+    /// use std::time::{Duration, Instant};
+    ///
+    /// let deadline = Instant::now() + Duration::from_secs(60);
+    /// let mut map = ExpiringMultiMap::new();
+    /// map.insert_until(1, 42, deadline);
+    /// map.insert(1, 43, Duration::MAX);
+    /// assert_eq!(map.deadlines(&1).collect::<Vec<_>>(), [Some(deadline), None]);
+    /// ```
+    pub fn deadlines<Q>(&self, k: &Q) -> impl Iterator<Item = Option<Instant>> + '_
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        self.inner
+            .get(k)
+            .into_iter()
+            .flat_map(|values| values.iter().map(|&(deadline, _)| deadline))
+    }
+
+    /// Removes a key from the map, returning the values which hadn't expired
+    /// yet.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::ExpiringMultiMap;
+    /// use std::time::Duration;
+    ///
+    /// let mut map = ExpiringMultiMap::new();
+    /// map.insert(1, 42, Duration::from_secs(60));
+    /// map.insert(1, 43, Duration::from_secs(0));
+    /// assert_eq!(map.remove(&1), Some(vec![42]));
+    /// assert_eq!(map.remove(&1), None);
+    /// ```
+    pub fn remove<Q>(&mut self, k: &Q) -> Option<Vec<V>>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        self.prune_key(k, Instant::now());
+        let values = self.inner.remove(k)?;
+        Some(values.into_iter().map(|(_, v)| v).collect())
+    }
+
+    /// Removes every value which has expired, returning the number of removed
+    /// values.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::ExpiringMultiMap;
+    /// use std::time::Duration;
+    ///
+    /// let mut map = ExpiringMultiMap::new();
+    /// map.insert(1, 42, Duration::from_secs(0));
+    /// map.insert(2, 43, Duration::from_secs(0));
+    /// map.insert(2, 44, Duration::from_secs(60));
+    /// assert_eq!(map.purge_expired(), 2);
+    /// assert_eq!(map.len(), 1);
+    /// ```
+    pub fn purge_expired(&mut self) -> usize {
+        self.purge_expired_at(Instant::now())
+    }
+
+    /// Removes every value whose deadline is at or before the given instant,
+    /// returning the number of removed values.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::ExpiringMultiMap;
+    /// use std::time::{Duration, Instant};
+    ///
+    /// let now = Instant::now();
+    /// let mut map = ExpiringMultiMap::new();
+    /// map.insert_until(1, 42, now + Duration::from_secs(10));
+    /// map.insert_until(1, 43, now + Duration::from_secs(20));
+    /// assert_eq!(map.purge_expired_at(now + Duration::from_secs(15)), 1);
+    /// assert_eq!(map.purge_expired_at(now + Duration::from_secs(15)), 0);
+    /// ```
+    pub fn purge_expired_at(&mut self, now: Instant) -> usize {
+        let mut removed = 0;
+        self.inner.retain(|_, values| {
+            let before = values.len();
+            values.retain(|&(deadline, _)| is_live(deadline, now));
+            removed += before - values.len();
+            !values.is_empty()
+        });
+        removed
+    }
+
+    /// Returns the number of keys in the map. Keys whose values have all
+    /// expired are counted until they are pruned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::ExpiringMultiMap;
+    /// use std::time::Duration;
+    ///
+    /// let mut map = ExpiringMultiMap::new();
+    /// map.insert(1, 42, Duration::from_secs(60));
+    /// map.insert(2, 1337, Duration::from_secs(60));
+    /// assert_eq!(map.len(), 2);
+    /// ```
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// Returns true if the map contains no elements. Expired values which
+    /// haven't been pruned yet still count as elements.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::ExpiringMultiMap;
+    /// use std::time::Duration;
+    ///
+    /// let mut map = ExpiringMultiMap::new();
+    /// assert!(map.is_empty());
+    /// map.insert(1, 42, Duration::from_secs(60));
+    /// assert!(!map.is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    /// Clears the map, removing all key-value pairs.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::ExpiringMultiMap;
+    /// use std::time::Duration;
+    ///
+    /// let mut map = ExpiringMultiMap::new();
+    /// map.insert(1, 42, Duration::from_secs(60));
+    /// map.clear();
+    /// assert!(map.is_empty());
+    /// ```
+    pub fn clear(&mut self) {
+        self.inner.clear();
+    }
+
+    /// An iterator visiting all keys in arbitrary order, including keys whose
+    /// values have all expired but haven't been pruned yet.
+    pub fn keys(&self) -> Keys<'_, K, Vec<(Option<Instant>, V)>> {
+        self.inner.keys()
+    }
+
+    /// An iterator visiting every key-value pair which hasn't expired at the
+    /// time of the call, in arbitrary order. Nothing is pruned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::ExpiringMultiMap;
+    /// use std::time::Duration;
+    ///
+    /// let mut map = ExpiringMultiMap::new();
+    /// map.insert(1, 42, Duration::from_secs(60));
+    /// map.insert(1, 43, Duration::from_secs(0));
+    ///
+    /// let pairs: Vec<_> = map.flat_iter().collect();
+    /// assert_eq!(pairs, [(&1, &42)]);
+    /// ```
+    pub fn flat_iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        let now = Instant::now();
+        self.inner.iter().flat_map(move |(k, values)| {
+            values
+                .iter()
+                .filter(move |&&(deadline, _)| is_live(deadline, now))
+                .map(move |(_, v)| (k, v))
+        })
+    }
+
+    fn prune_key<Q>(&mut self, k: &Q, now: Instant)
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        let now_empty = match self.inner.get_mut(k) {
+            Some(values) => {
+                values.retain(|&(deadline, _)| is_live(deadline, now));
+                values.is_empty()
+            }
+            None => return,
+        };
+        if now_empty {
+            self.inner.remove(k);
+        }
+    }
+}
+
+impl<K, V, S> Debug for ExpiringMultiMap<K, V, S>
+where
+    K: Eq + Hash + Debug,
+    V: Debug,
+    S: BuildHasher,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_map().entries(self.inner.iter()).finish()
+    }
+}
+
+impl<K, V, S> Default for ExpiringMultiMap<K, V, S>
+where
+    K: Eq + Hash,
+    S: BuildHasher + Default,
+{
+    fn default() -> ExpiringMultiMap<K, V, S> {
+        ExpiringMultiMap {
+            inner: Default::default(),
+        }
+    }
+}
+
+// A value without a deadline never expires.
+fn is_live(deadline: Option<Instant>, now: Instant) -> bool {
+    deadline.is_none_or(|deadline| deadline > now)
+}
+
+/// An iterator over the values of a key in an ExpiringMultiMap.
+pub struct Values<'a, V: 'a> {
+    inner: slice::Iter<'a, (Option<Instant>, V)>,
+}
+
+impl<'a, V> Iterator for Values<'a, V> {
+    type Item = &'a V;
+
+    fn next(&mut self) -> Option<&'a V> {
+        self.inner.next().map(|(_, v)| v)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<V> ExactSizeIterator for Values<'_, V> {
+    fn len(&self) -> usize {
+        self.inner.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn purge_removes_empty_keys() {
+        let now = Instant::now();
+        let mut m = ExpiringMultiMap::new();
+        m.insert_until(1, 'a', now + Duration::from_secs(1));
+        m.insert_until(1, 'b', now + Duration::from_secs(3));
+        m.insert_until(2, 'c', now + Duration::from_secs(2));
+
+        assert_eq!(m.purge_expired_at(now), 0);
+        assert_eq!(m.purge_expired_at(now + Duration::from_secs(2)), 2);
+        assert_eq!(m.len(), 1);
+        assert_eq!(m.deadlines(&1).count(), 1);
+        assert_eq!(m.purge_expired_at(now + Duration::from_secs(3)), 1);
+        assert!(m.is_empty());
+    }
+
+    #[test]
+    fn access_prunes_key() {
+        let mut m = ExpiringMultiMap::new();
+        m.insert(1, 42, Duration::from_secs(0));
+        assert_eq!(m.len(), 1);
+        assert_eq!(m.get(&1), None);
+        assert_eq!(m.len(), 0);
+    }
+
+    #[test]
+    fn insert_with_unrepresentable_ttl_never_expires() {
+        let mut m = ExpiringMultiMap::new();
+        m.insert(1, 42, Duration::MAX);
+        assert_eq!(m.deadlines(&1).collect::<Vec<_>>(), [None]);
+        assert_eq!(
+            m.purge_expired_at(Instant::now() + Duration::from_secs(3600)),
+            0
+        );
+        assert_eq!(m.get(&1), Some(&42));
+    }
+
+    #[test]
+    fn get_values_missing_key() {
+        let mut m: ExpiringMultiMap<u8, u8> = ExpiringMultiMap::new();
+        assert_eq!(m.get_values(&1).len(), 0);
+    }
+}
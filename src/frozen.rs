@@ -0,0 +1,393 @@
+// Copyright (c) 2016 multimap developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! A read-only multimap with all values stored in one contiguous buffer.
+//!
+//! FrozenMultiMap is built once from a [`MultiMap`](../struct.MultiMap.html)
+//! and can not be modified afterwards. Instead of one `Vec` per key it keeps
+//! every value in a single boxed slice and maps each key to the range of its
+//! values, which saves the per-key allocation and keeps the values of a key
+//! next to each other in memory.
+//!
+//! # Examples
+//!
+//! ```
+//! use multimap::{FrozenMultiMap, MultiMap};
+//!
+//! let mut map = MultiMap::new();
+//! map.insert("colors", "red");
+//! map.insert("colors", "blue");
+//! map.insert("shapes", "circle");
+//!
+//! let frozen = FrozenMultiMap::from(map);
+//! assert_eq!(frozen.get_slice("colors"), Some(&["red", "blue"][..]));
+//! assert_eq!(frozen.values_len(), 3);
+//! ```
+
+use std::borrow::Borrow;
+use std::boxed::Box;
+use std::collections::hash_map::{self, RandomState};
+use std::collections::HashMap;
+use std::fmt::{self, Debug};
+use std::hash::{BuildHasher, Hash};
+use std::iter::{FromIterator, IntoIterator, Iterator};
+use std::marker::PhantomData;
+use std::ops::Range;
+use std::vec::Vec;
+
+use MultiMap;
+
+#[derive(Clone)]
+pub struct FrozenMultiMap<K, V, S = RandomState> {
+    index: HashMap<K, (usize, usize), S>,
+    values: Box<[V]>,
+}
+
+impl<K, V, S> FrozenMultiMap<K, V, S>
+where
+    K: Eq + Hash,
+    S: BuildHasher,
+{
+    fn range<Q>(&self, k: &Q) -> Option<Range<usize>>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        let &(offset, len) = self.index.get(k)?;
+        Some(offset..offset + len)
+    }
+
+    /// Returns true if the map contains a value for the specified key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::FrozenMultiMap;
+    ///
+    /// let frozen: FrozenMultiMap<_, _> = vec![(1, 42)].into_iter().collect();
+    /// assert_eq!(frozen.contains_key(&1), true);
+    /// assert_eq!(frozen.contains_key(&2), false);
+    /// ```
+    pub fn contains_key<Q>(&self, k: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        self.index.contains_key(k)
+    }
+
+    /// Returns a reference to the first value corresponding to the key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::FrozenMultiMap;
+    ///
+    /// let frozen: FrozenMultiMap<_, _> = vec![(1, 42), (1, 1337)].into_iter().collect();
+    /// assert_eq!(frozen.get(&1), Some(&42));
+    /// assert_eq!(frozen.get(&2), None);
+    /// ```
+    pub fn get<Q>(&self, k: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        self.get_slice(k)?.first()
+    }
+
+    /// Returns the values corresponding to the key as a slice.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::FrozenMultiMap;
+    ///
+    /// let frozen: FrozenMultiMap<_, _> = vec![(1, 42), (1, 1337)].into_iter().collect();
+    /// assert_eq!(frozen.get_slice(&1), Some(&[42, 1337][..]));
+    /// ```
+    pub fn get_slice<Q>(&self, k: &Q) -> Option<&[V]>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        self.range(k).map(|range| &self.values[range])
+    }
+
+    /// Returns the number of keys in the map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::FrozenMultiMap;
+    ///
+    /// let frozen: FrozenMultiMap<_, _> = vec![(1, 42), (1, 43), (2, 1337)].into_iter().collect();
+    /// assert_eq!(frozen.len(), 2);
+    /// ```
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    /// Returns the total number of values in the map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::FrozenMultiMap;
+    ///
+    /// let frozen: FrozenMultiMap<_, _> = vec![(1, 42), (1, 43), (2, 1337)].into_iter().collect();
+    /// assert_eq!(frozen.values_len(), 3);
+    /// ```
+    pub fn values_len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Returns true if the map contains no elements.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::{FrozenMultiMap, MultiMap};
+    ///
+    /// let frozen = FrozenMultiMap::from(MultiMap::<usize, usize>::new());
+    /// assert!(frozen.is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+    /// Returns every value in the map as one slice. The values of each key
+    /// are adjacent, but the order of the keys is arbitrary.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::FrozenMultiMap;
+    ///
+    /// let frozen: FrozenMultiMap<_, _> = vec![(1, 42), (1, 43)].into_iter().collect();
+    /// assert_eq!(frozen.values(), &[42, 43]);
+    /// ```
+    pub fn values(&self) -> &[V] {
+        &self.values
+    }
+
+    /// An iterator visiting all keys in arbitrary order.
+    /// Iterator element type is &'a K.
+    pub fn keys(&self) -> hash_map::Keys<'_, K, (usize, usize)> {
+        self.index.keys()
+    }
+
+    /// An iterator visiting all keys and their values in arbitrary order.
+    /// Iterator element type is (&'a K, &'a [V]).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::FrozenMultiMap;
+    ///
+    /// let frozen: FrozenMultiMap<_, _> = vec![(1, 42), (1, 1337)].into_iter().collect();
+    /// let pairs: Vec<_> = frozen.iter_all().collect();
+    /// assert_eq!(pairs, [(&1, &[42, 1337][..])]);
+    /// ```
+    pub fn iter_all(&self) -> IterAll<'_, K, V> {
+        IterAll {
+            index: self.index.iter(),
+            values: &self.values,
+        }
+    }
+
+    /// An iterator visiting every key-value pair in arbitrary order.
+    pub fn flat_iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.iter_all()
+            .flat_map(|(k, v)| v.iter().map(move |i| (k, i)))
+    }
+
+    /// Converts the map back into a mutable [`MultiMap`](../struct.MultiMap.html).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::FrozenMultiMap;
+    ///
+    /// let frozen: FrozenMultiMap<_, _> = vec![(1, 42)].into_iter().collect();
+    /// let mut map = frozen.into_multimap();
+    /// map.insert(1, 43);
+    /// assert_eq!(map.get_vec(&1), Some(&vec![42, 43]));
+    /// ```
+    pub fn into_multimap(self) -> MultiMap<K, V, S>
+    where
+        S: Clone,
+    {
+        let mut entries: Vec<_> = self.index.iter().map(|(_, &range)| range).collect();
+        entries.sort_unstable();
+        let mut values = self.values.into_vec().into_iter();
+        let mut groups = HashMap::with_capacity(entries.len());
+        for (offset, len) in entries {
+            groups.insert((offset, len), values.by_ref().take(len).collect::<Vec<_>>());
+        }
+        let mut inner =
+            HashMap::with_capacity_and_hasher(self.index.len(), self.index.hasher().clone());
+        for (k, range) in self.index {
+            let vec = groups.remove(&range).unwrap_or_default();
+            inner.insert(k, vec);
+        }
+        MultiMap {
+            inner,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<K, V, S> From<MultiMap<K, V, S>> for FrozenMultiMap<K, V, S>
+where
+    K: Eq + Hash,
+    S: BuildHasher + Clone,
+{
+    fn from(map: MultiMap<K, V, S>) -> FrozenMultiMap<K, V, S> {
+        let total = map.inner.values().map(Vec::len).sum();
+        let mut index = HashMap::with_capacity_and_hasher(map.len(), map.inner.hasher().clone());
+        let mut values = Vec::with_capacity(total);
+        for (k, v) in map.inner {
+            index.insert(k, (values.len(), v.len()));
+            values.extend(v);
+        }
+        FrozenMultiMap {
+            index,
+            values: values.into_boxed_slice(),
+        }
+    }
+}
+
+impl<K, V, S> FromIterator<(K, V)> for FrozenMultiMap<K, V, S>
+where
+    K: Eq + Hash,
+    S: BuildHasher + Clone + Default,
+{
+    fn from_iter<T: IntoIterator<Item = (K, V)>>(iterable: T) -> FrozenMultiMap<K, V, S> {
+        FrozenMultiMap::from(iterable.into_iter().collect::<MultiMap<K, V, S>>())
+    }
+}
+
+impl<K, V, S> Debug for FrozenMultiMap<K, V, S>
+where
+    K: Eq + Hash + Debug,
+    V: Debug,
+    S: BuildHasher,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_map().entries(self.iter_all()).finish()
+    }
+}
+
+impl<K, V, S> PartialEq for FrozenMultiMap<K, V, S>
+where
+    K: Eq + Hash,
+    V: PartialEq,
+    S: BuildHasher,
+{
+    fn eq(&self, other: &FrozenMultiMap<K, V, S>) -> bool {
+        self.len() == other.len()
+            && self.values_len() == other.values_len()
+            && self
+                .iter_all()
+                .all(|(k, v)| other.get_slice(k).is_some_and(|o| v == o))
+    }
+}
+
+impl<K, V, S> Eq for FrozenMultiMap<K, V, S>
+where
+    K: Eq + Hash,
+    V: Eq,
+    S: BuildHasher,
+{
+}
+
+impl<'a, K, V, S> IntoIterator for &'a FrozenMultiMap<K, V, S>
+where
+    K: Eq + Hash,
+    S: BuildHasher,
+{
+    type Item = (&'a K, &'a [V]);
+    type IntoIter = IterAll<'a, K, V>;
+
+    fn into_iter(self) -> IterAll<'a, K, V> {
+        self.iter_all()
+    }
+}
+
+#[derive(Clone)]
+pub struct IterAll<'a, K: 'a, V: 'a> {
+    index: hash_map::Iter<'a, K, (usize, usize)>,
+    values: &'a [V],
+}
+
+impl<'a, K, V> Iterator for IterAll<'a, K, V> {
+    type Item = (&'a K, &'a [V]);
+
+    fn next(&mut self) -> Option<(&'a K, &'a [V])> {
+        let (k, &(offset, len)) = self.index.next()?;
+        Some((k, &self.values[offset..offset + len]))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.index.size_hint()
+    }
+}
+
+impl<K, V> ExactSizeIterator for IterAll<'_, K, V> {
+    fn len(&self) -> usize {
+        self.index.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn values_are_contiguous_per_key() {
+        let mut map = MultiMap::new();
+        map.insert_many(1, vec![1, 2, 3]);
+        map.insert_many(2, vec![4, 5]);
+        map.insert(3, 6);
+        let frozen = FrozenMultiMap::from(map);
+
+        assert_eq!(frozen.len(), 3);
+        assert_eq!(frozen.values_len(), 6);
+        assert_eq!(frozen.get_slice(&1), Some(&[1, 2, 3][..]));
+        assert_eq!(frozen.get_slice(&2), Some(&[4, 5][..]));
+        assert_eq!(frozen.get_slice(&3), Some(&[6][..]));
+        assert_eq!(frozen.get_slice(&4), None);
+    }
+
+    #[test]
+    fn roundtrip_multimap() {
+        let mut map = MultiMap::new();
+        map.insert_many("a", vec![1, 2]);
+        map.insert_many("b", vec![3]);
+        let frozen = FrozenMultiMap::from(map.clone());
+        assert_eq!(frozen.clone().into_multimap(), map);
+        assert_eq!(frozen, FrozenMultiMap::from(map));
+    }
+
+    #[test]
+    fn roundtrip_with_empty_vec() {
+        let mut map = MultiMap::new();
+        map.insert_many(1, vec![1, 2]);
+        map.insert(2, 3);
+        map.get_vec_mut(&2).unwrap().clear();
+        let frozen = FrozenMultiMap::from(map.clone());
+        assert_eq!(frozen.get_slice(&2), Some(&[][..]));
+        assert_eq!(frozen.into_multimap(), map);
+    }
+
+    #[test]
+    fn debug_output() {
+        let frozen: FrozenMultiMap<_, _> = vec![(1, 2), (1, 3)].into_iter().collect();
+        assert_eq!(format!("{:?}", frozen), "{1: [2, 3]}");
+    }
+}
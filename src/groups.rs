@@ -0,0 +1,190 @@
+// Copyright (c) 2016 multimap developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! A lending iterator over the groups of a MultiMap which can remove the
+//! group it is currently looking at.
+//!
+//! GroupsMut is created by `MultiMap::groups_mut`. It is not an `Iterator`:
+//! each call to `next` returns a GroupMut borrowing the iterator, which has to
+//! be dropped (or consumed by `remove`) before the next group is fetched.
+//! The map can be used again once the GroupsMut itself is dropped.
+//!
+//! # Examples
+//!
+//! ```
+//! use multimap::MultiMap;
+//!
+//! let mut map = MultiMap::new();
+//! map.insert_many("a", vec![1, 2, 3]);
+//! map.insert_many("b", vec![4]);
+//! map.insert_many("c", vec![]);
+//!
+//! let mut removed = Vec::new();
+//! let mut groups = map.groups_mut();
+//! while let Some(mut group) = groups.next() {
+//!     group.values_mut().retain(|value| value % 2 == 1);
+//!     if group.values().is_empty() {
+//!         removed.push(group.remove().0);
+//!     }
+//! }
+//! drop(groups);
+//!
+//! removed.sort();
+//! assert_eq!(removed, ["b", "c"]);
+//! assert_eq!(map.get_vec("a"), Some(&vec![1, 3]));
+//! ```
+
+use std::collections::hash_map::IntoIter;
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hash};
+use std::vec::Vec;
+
+/// A lending iterator over the groups of a MultiMap. See the module
+/// documentation for details.
+///
+/// The groups are moved out of the map while walking and put back as the
+/// walk moves past them. Groups which have not been visited yet are put back
+/// when the GroupsMut is dropped. Each group which is kept is thus hashed and
+/// inserted again, so a walk costs time linear in the number of keys however
+/// many groups it visits.
+pub struct GroupsMut<'a, K, V, S>
+where
+    K: 'a + Eq + Hash,
+    V: 'a,
+    S: 'a + BuildHasher,
+{
+    map: &'a mut HashMap<K, Vec<V>, S>,
+    rest: IntoIter<K, Vec<V>>,
+    current: Option<(K, Vec<V>)>,
+}
+
+impl<'a, K, V, S> GroupsMut<'a, K, V, S>
+where
+    K: Eq + Hash,
+    S: BuildHasher,
+{
+    pub(crate) fn new(map: &'a mut HashMap<K, Vec<V>, S>) -> GroupsMut<'a, K, V, S>
+    where
+        S: Clone,
+    {
+        let hash_builder = map.hasher().clone();
+        let capacity = map.len();
+        let rest = ::std::mem::replace(
+            map,
+            HashMap::with_capacity_and_hasher(capacity, hash_builder),
+        )
+        .into_iter();
+        GroupsMut {
+            map,
+            rest,
+            current: None,
+        }
+    }
+
+    /// Advances to the next group, putting the previous one back into the
+    /// map unless it was removed.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Option<GroupMut<'_, K, V>> {
+        if let Some((key, values)) = self.current.take() {
+            self.map.insert(key, values);
+        }
+        self.current = self.rest.next();
+        if self.current.is_some() {
+            Some(GroupMut {
+                slot: &mut self.current,
+            })
+        } else {
+            None
+        }
+    }
+}
+
+impl<'a, K, V, S> Drop for GroupsMut<'a, K, V, S>
+where
+    K: Eq + Hash,
+    S: BuildHasher,
+{
+    fn drop(&mut self) {
+        self.map.extend(self.current.take());
+        self.map.extend(&mut self.rest);
+    }
+}
+
+/// The group GroupsMut is currently at.
+pub struct GroupMut<'b, K: 'b, V: 'b> {
+    slot: &'b mut Option<(K, Vec<V>)>,
+}
+
+impl<'b, K, V> GroupMut<'b, K, V> {
+    /// Returns the key of the group.
+    pub fn key(&self) -> &K {
+        &self.slot.as_ref().expect("group is present").0
+    }
+
+    /// Returns the values of the group.
+    pub fn values(&self) -> &Vec<V> {
+        &self.slot.as_ref().expect("group is present").1
+    }
+
+    /// Returns the values of the group mutably.
+    pub fn values_mut(&mut self) -> &mut Vec<V> {
+        &mut self.slot.as_mut().expect("group is present").1
+    }
+
+    /// Removes the group from the map, returning its key and values.
+    pub fn remove(self) -> (K, Vec<V>) {
+        self.slot.take().expect("group is present")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use MultiMap;
+
+    #[test]
+    fn remove_while_walking() {
+        let mut map: MultiMap<usize, usize> = MultiMap::new();
+        for key in 0..20 {
+            map.insert_many(key, 0..key);
+        }
+
+        let mut groups = map.groups_mut();
+        while let Some(mut group) = groups.next() {
+            let key = *group.key();
+            if key % 2 == 0 {
+                assert_eq!(group.remove(), (key, (0..key).collect()));
+            } else {
+                group.values_mut().push(100);
+            }
+        }
+        drop(groups);
+
+        assert_eq!(map.len(), 10);
+        for (key, values) in map.iter_all() {
+            assert_eq!(key % 2, 1);
+            assert_eq!(values.len(), key + 1);
+        }
+    }
+
+    #[test]
+    fn dropping_early_keeps_the_rest() {
+        let mut map: MultiMap<usize, usize> = MultiMap::new();
+        for key in 0..10 {
+            map.insert(key, key);
+        }
+
+        {
+            let mut groups = map.groups_mut();
+            groups.next().unwrap().remove();
+            let _ = groups.next().unwrap();
+        }
+
+        assert_eq!(map.len(), 9);
+        assert_eq!(map.total_len(), 9);
+    }
+}
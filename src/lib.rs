@@ -1,4 +1,5 @@
 #![forbid(unsafe_code)]
+#![cfg_attr(not(feature = "std"), no_std)]
 // Copyright (c) 2016 multimap developers
 //
 // Licensed under the Apache License, Version 2.0
@@ -14,8 +15,17 @@
 //! implemented mostly as a thin wrapper around std::collections::HashMap and
 //! stores its values as a std::Vec per key.
 //!
+//! The container used for the values of a key can be changed through the last
+//! type parameter, see the Container trait. Containers other than `Vec` only
+//! support part of the API, which the Container trait lists.
+//!
+//! The crate supports `no_std` targets with an allocator: disable the default
+//! `std` feature and enable `hashbrown`, which then provides the HashMap used
+//! for storage. The `concurrent` and `expiring` modules require `std`.
+//!
 //! Values are guaranteed to be in insertion order as long as not manually
-//! changed. Keys are not ordered. Multiple idential key-value-pairs can exist
+//! changed. Keys are not ordered; use BTreeMultiMap if the keys should be
+//! iterated in sorted order. Multiple idential key-value-pairs can exist
 //! in the MultiMap. A key can exist in the MultiMap with no associated value.
 //!
 //! # Examples
@@ -66,27 +76,144 @@
 //! assert_eq!(map.get_vec("key1"), Some(&vec![42, 1337]));
 //! ```
 
+#[cfg(not(feature = "std"))]
+#[macro_use]
+extern crate alloc;
+#[cfg(not(feature = "std"))]
+extern crate hashbrown;
+#[cfg(feature = "smallvec")]
+extern crate smallvec;
+
+#[cfg(not(any(feature = "std", feature = "hashbrown")))]
+compile_error!("the `hashbrown` feature is required when the `std` feature is disabled");
+
+/// The parts of `std` used by this crate, assembled from `core`, `alloc` and
+/// `hashbrown` when building without `std`.
+#[cfg(not(feature = "std"))]
+mod std {
+    pub use alloc::{borrow, boxed, fmt, slice, string, vec};
+    pub use core::*;
+
+    #[cfg(test)]
+    pub use alloc::rc;
+
+    pub mod sync {
+        pub use alloc::sync::*;
+        #[cfg(test)]
+        pub use core::sync::atomic;
+    }
+
+    pub mod collections {
+        pub use alloc::collections::*;
+        pub use hashbrown::{HashMap, HashSet};
+
+        pub mod hash_map {
+            pub use hashbrown::hash_map::*;
+            pub use hashbrown::DefaultHashBuilder as RandomState;
+        }
+
+        pub mod hash_set {
+            pub use hashbrown::hash_set::*;
+        }
+    }
+}
+
 use std::borrow::Borrow;
-use std::collections::hash_map::{IntoIter, Keys, RandomState};
-use std::collections::HashMap;
+use std::cmp::{Ordering, Reverse};
+use std::collections::hash_map::{IntoIter, IntoKeys, IntoValues, Keys, RandomState};
+#[cfg(feature = "std")]
+use std::collections::TryReserveError;
+use std::collections::{BTreeMap, BinaryHeap, HashMap, HashSet};
 use std::fmt::{self, Debug};
+#[cfg(feature = "std")]
+use std::hash::Hasher;
 use std::hash::{BuildHasher, Hash};
-use std::iter::{FromIterator, IntoIterator, Iterator};
+use std::iter::{FromIterator, FusedIterator, IntoIterator, Iterator};
+use std::marker::PhantomData;
 use std::ops::Index;
+use std::vec::{Drain as VecDrain, Vec};
 
+#[cfg(not(feature = "std"))]
+pub use hashbrown::Equivalent;
+pub use std::collections::hash_map::Drain;
 pub use std::collections::hash_map::Iter as IterAll;
 pub use std::collections::hash_map::IterMut as IterAllMut;
 
-pub use entry::{Entry, OccupiedEntry, VacantEntry};
-
+pub use bimap::BiMultiMap;
+pub use bounded::BoundedMultiMap;
+pub use btree::BTreeMultiMap;
+pub use collect::IntoMultiMap;
+pub use container::Container;
+pub use cursor::CursorMut;
+pub use diff::MultiMapDiff;
+pub use entry::{Entry, EntryRef, OccupiedEntry, OccupiedEntryRef, VacantEntry, VacantEntryRef};
+#[cfg(feature = "std")]
+pub use expiring::ExpiringMultiMap;
+pub use frozen::FrozenMultiMap;
+pub use groups::{GroupMut, GroupsMut};
+pub use memory::{HeapSize, MemoryStats};
+pub use multiset::MultiSet;
+pub use persistent::PersistentMultiMap;
+pub use seeded::SeededState;
+pub use set::SetMultiMap;
+pub use sorted::SortedMultiMap;
+
+#[cfg(feature = "arbitrary")]
+pub mod arbitrary;
+pub mod bimap;
+#[cfg(feature = "std")]
+pub mod binary;
+pub mod bounded;
+pub mod btree;
+mod collect;
+#[cfg(feature = "std")]
+pub mod concurrent;
+mod container;
+pub mod cursor;
+pub mod diff;
 mod entry;
+#[cfg(feature = "std")]
+pub mod expiring;
+pub mod frozen;
+pub mod groups;
+pub mod memory;
+pub mod multiset;
+pub mod persistent;
+pub mod seeded;
+pub mod set;
+pub mod sorted;
+
+#[cfg(feature = "ahash")]
+pub mod ahash;
+
+#[cfg(feature = "proptest")]
+pub mod proptest;
+
+#[cfg(feature = "quickcheck")]
+pub mod quickcheck;
+
+#[cfg(feature = "rand")]
+pub mod rand;
+
+#[cfg(feature = "rayon")]
+pub mod rayon;
+
+#[cfg(feature = "rkyv")]
+pub mod rkyv;
+
+#[cfg(feature = "rustc-hash")]
+pub mod rustc_hash;
+
+#[cfg(feature = "schemars")]
+pub mod schemars;
 
 #[cfg(feature = "serde_impl")]
 pub mod serde;
 
 #[derive(Clone)]
-pub struct MultiMap<K, V, S = RandomState> {
-    inner: HashMap<K, Vec<V>, S>,
+pub struct MultiMap<K, V, S = RandomState, C = Vec<V>> {
+    inner: HashMap<K, C, S>,
+    marker: PhantomData<V>,
 }
 
 impl<K, V> MultiMap<K, V>
@@ -105,6 +232,7 @@ where
     pub fn new() -> MultiMap<K, V> {
         MultiMap {
             inner: HashMap::new(),
+            marker: PhantomData,
         }
     }
 
@@ -120,7 +248,97 @@ where
     pub fn with_capacity(capacity: usize) -> MultiMap<K, V> {
         MultiMap {
             inner: HashMap::with_capacity(capacity),
+            marker: PhantomData,
+        }
+    }
+
+    /// Creates a multimap holding an empty vector for each of the given keys,
+    /// each with capacity for at least `values_per_key` values, so that
+    /// loading the values of those keys doesn't reallocate their vectors. A
+    /// key given more than once gets a single vector.
+    ///
+    /// Like other keys without values, the keys count towards `len` until
+    /// values are inserted for them; `prune_empty` removes the unused ones.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiMap;
+    ///
+    /// let mut map = MultiMap::with_key_and_value_capacity(["a", "b"], 100);
+    /// assert!(map.get_vec("a").unwrap().capacity() >= 100);
+    ///
+    /// map.insert_many("a", 0..100);
+    /// assert!(map.get_vec("a").unwrap().capacity() >= 100);
+    /// assert_eq!(map.prune_empty(), 1);
+    /// ```
+    pub fn with_key_and_value_capacity<I>(keys: I, values_per_key: usize) -> MultiMap<K, V>
+    where
+        I: IntoIterator<Item = K>,
+    {
+        let keys = keys.into_iter();
+        let mut inner = HashMap::with_capacity(keys.size_hint().0);
+        for key in keys {
+            inner
+                .entry(key)
+                .or_insert_with(|| Vec::with_capacity(values_per_key));
+        }
+        MultiMap {
+            inner,
+            marker: PhantomData,
+        }
+    }
+
+    /// Creates a multimap from an iterator of items, grouping each item
+    /// under the key returned by `f`. Items keep their iteration order
+    /// within each key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiMap;
+    ///
+    /// let words = ["apple", "avocado", "banana", "blueberry", "cherry"];
+    /// let map = MultiMap::from_grouped_by(words.iter().cloned(), |word| word.as_bytes()[0]);
+    ///
+    /// assert_eq!(map.get_vec(&b'a'), Some(&vec!["apple", "avocado"]));
+    /// assert_eq!(map.get_vec(&b'b'), Some(&vec!["banana", "blueberry"]));
+    /// assert_eq!(map.get_vec(&b'c'), Some(&vec!["cherry"]));
+    /// ```
+    pub fn from_grouped_by<I, F>(iter: I, mut f: F) -> MultiMap<K, V>
+    where
+        I: IntoIterator<Item = V>,
+        F: FnMut(&V) -> K,
+    {
+        let iter = iter.into_iter();
+        let mut multimap = MultiMap::with_capacity(iter.size_hint().0);
+        for value in iter {
+            multimap.insert(f(&value), value);
         }
+        multimap
+    }
+
+    /// Creates a multimap from key-value pairs sorted or grouped by key, i.e.
+    /// with all pairs of a key next to each other. See
+    /// `from_sorted_pairs_with_hasher` for details.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiMap;
+    ///
+    /// let rows = vec![("a", 1), ("a", 2), ("b", 3), ("c", 4), ("c", 5)];
+    /// let map = MultiMap::from_sorted_pairs(rows);
+    ///
+    /// assert_eq!(map.get_vec("a"), Some(&vec![1, 2]));
+    /// assert_eq!(map.get_vec("b"), Some(&vec![3]));
+    /// assert_eq!(map.get_vec("c"), Some(&vec![4, 5]));
+    /// ```
+    pub fn from_sorted_pairs<I>(iter: I) -> MultiMap<K, V>
+    where
+        I: IntoIterator<Item = (K, V)>,
+    {
+        MultiMap::from_sorted_pairs_with_hasher(iter, Default::default())
     }
 }
 
@@ -143,6 +361,7 @@ where
     pub fn with_hasher(hash_builder: S) -> MultiMap<K, V, S> {
         MultiMap {
             inner: HashMap::with_hasher(hash_builder),
+            marker: PhantomData,
         }
     }
 
@@ -160,54 +379,62 @@ where
     pub fn with_capacity_and_hasher(capacity: usize, hash_builder: S) -> MultiMap<K, V, S> {
         MultiMap {
             inner: HashMap::with_capacity_and_hasher(capacity, hash_builder),
+            marker: PhantomData,
         }
     }
 
-    /// Inserts a key-value pair into the multimap. If the key does exist in
-    /// the map then the value is pushed to that key's vector. If the key doesn't
-    /// exist in the map a new vector with the given value is inserted.
+    /// Creates a MultiMap from a map of keys to their vectors of values, such
+    /// as the one returned by itertools' `into_group_map`. The map is used
+    /// as is, without copying.
     ///
     /// # Examples
     ///
     /// ```
     /// use multimap::MultiMap;
+    /// use std::collections::HashMap;
     ///
-    /// let mut map = MultiMap::new();
-    /// map.insert("key", 42);
+    /// let mut groups = HashMap::new();
+    /// groups.insert("a", vec![1, 3]);
+    /// groups.insert("b", vec![2]);
+    ///
+    /// let map = MultiMap::from_group_map(groups);
+    /// assert_eq!(map.get_vec("a"), Some(&vec![1, 3]));
+    /// assert_eq!(map.total_len(), 3);
     /// ```
-    pub fn insert(&mut self, k: K, v: V) {
-        match self.entry(k) {
-            Entry::Occupied(mut entry) => {
-                entry.get_vec_mut().push(v);
-            }
-            Entry::Vacant(entry) => {
-                entry.insert_vec(vec![v]);
-            }
+    pub fn from_group_map(map: HashMap<K, Vec<V>, S>) -> MultiMap<K, V, S> {
+        MultiMap {
+            inner: map,
+            marker: PhantomData,
         }
     }
 
-    /// Inserts multiple key-value pairs into the multimap. If the key does exist in
-    /// the map then the values are extended into that key's vector. If the key
-    /// doesn't exist in the map a new vector collected from the given values is inserted.
+    /// Creates a MultiMap holding a single value for every key of the map.
+    /// The map's hasher is kept.
     ///
-    /// This may be more efficient than inserting values independently.
+    /// A map of keys to vectors of values is taken as is by `from_group_map`
+    /// and `From<HashMap<K, Vec<V>, S>>`.
     ///
     /// # Examples
     ///
     /// ```
     /// use multimap::MultiMap;
+    /// use std::collections::HashMap;
     ///
-    /// let mut map = MultiMap::<&str, &usize>::new();
-    /// map.insert_many("key", &[42, 43]);
+    /// let mut hash_map = HashMap::new();
+    /// hash_map.insert("a", 1);
+    ///
+    /// let map = MultiMap::from_single_valued(hash_map);
+    /// assert_eq!(map.get_vec("a"), Some(&vec![1]));
     /// ```
-    pub fn insert_many<I: IntoIterator<Item = V>>(&mut self, k: K, v: I) {
-        match self.entry(k) {
-            Entry::Occupied(mut entry) => {
-                entry.get_vec_mut().extend(v);
-            }
-            Entry::Vacant(entry) => {
-                entry.insert_vec(v.into_iter().collect::<Vec<_>>());
-            }
+    pub fn from_single_valued(map: HashMap<K, V, S>) -> MultiMap<K, V, S>
+    where
+        S: Clone,
+    {
+        let mut inner = HashMap::with_capacity_and_hasher(map.len(), map.hasher().clone());
+        inner.extend(map.into_iter().map(|(k, v)| (k, vec![v])));
+        MultiMap {
+            inner,
+            marker: PhantomData,
         }
     }
 
@@ -239,7 +466,77 @@ where
         }
     }
 
-    /// Returns true if the map contains a value for the specified key.
+    /// Creates a multimap from key-value pairs sorted or grouped by key, i.e.
+    /// with all pairs of a key next to each other, which will use the given
+    /// hash builder to hash keys. Each run of equal keys is gathered in a
+    /// buffer and then moved into a vector of exactly its length, so a key is
+    /// hashed once per run rather than once per pair. The table is pre-sized
+    /// from the lower bound of the iterator's size hint.
+    ///
+    /// Pairs don't have to be grouped for the result to be correct: a key
+    /// appearing in more than one run gets the values of all of them, in
+    /// order, at the cost of another lookup per run.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiMap;
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let rows = vec![("a", 1), ("a", 2), ("b", 3)];
+    /// let map = MultiMap::from_sorted_pairs_with_hasher(rows, RandomState::new());
+    ///
+    /// assert_eq!(map.get_vec("a"), Some(&vec![1, 2]));
+    /// assert_eq!(map.get_vec("b"), Some(&vec![3]));
+    /// ```
+    pub fn from_sorted_pairs_with_hasher<I>(iter: I, hash_builder: S) -> MultiMap<K, V, S>
+    where
+        I: IntoIterator<Item = (K, V)>,
+    {
+        let iter = iter.into_iter();
+        let mut multimap = MultiMap::with_capacity_and_hasher(iter.size_hint().0, hash_builder);
+        let mut run: Option<K> = None;
+        let mut buffer = Vec::new();
+        for (key, value) in iter {
+            match run {
+                Some(ref current) if *current == key => {}
+                _ => {
+                    if let Some(previous) = run.replace(key) {
+                        multimap.insert_run(previous, &mut buffer);
+                    }
+                }
+            }
+            buffer.push(value);
+        }
+        if let Some(key) = run {
+            multimap.insert_run(key, &mut buffer);
+        }
+        multimap
+    }
+
+    /// Moves the values of a run of equal keys out of `buffer`, into a new
+    /// vector of exactly their number if the key is new.
+    fn insert_run(&mut self, k: K, buffer: &mut Vec<V>) {
+        use std::collections::hash_map::Entry as HashMapEntry;
+        match self.inner.entry(k) {
+            HashMapEntry::Occupied(mut entry) => {
+                entry.get_mut().append(buffer);
+            }
+            HashMapEntry::Vacant(entry) => {
+                let mut values = Vec::with_capacity(buffer.len());
+                values.append(buffer);
+                entry.insert(values);
+            }
+        }
+    }
+
+    /// Reserves capacity for at least `additional` more values in the vector
+    /// of the key.
+    ///
+    /// Does nothing if the key is not in the map: the key is only borrowed,
+    /// so there is no owned key to insert. To pre-size the vectors of new
+    /// keys, use `with_key_and_value_capacity` or the entry API, e.g.
+    /// `map.entry(k).or_insert_vec(Vec::with_capacity(n))`.
     ///
     /// The key may be any borrowed form of the map's key type, but Hash and Eq
     /// on the borrowed form must match those for the key type.
@@ -250,19 +547,77 @@ where
     /// use multimap::MultiMap;
     ///
     /// let mut map = MultiMap::new();
-    /// map.insert(1, 42);
-    /// assert_eq!(map.contains_key(&1), true);
-    /// assert_eq!(map.contains_key(&2), false);
+    /// map.insert("key", 42);
+    /// map.reserve_values("key", 100);
+    /// assert!(map.get_vec("key").unwrap().capacity() >= 101);
+    ///
+    /// map.entry("other").or_insert_vec(Vec::with_capacity(100));
+    /// assert!(map.get_vec("other").unwrap().capacity() >= 100);
     /// ```
-    pub fn contains_key<Q: ?Sized>(&self, k: &Q) -> bool
+    pub fn reserve_values<Q>(&mut self, k: &Q, additional: usize)
     where
         K: Borrow<Q>,
-        Q: Eq + Hash,
+        Q: ?Sized + Eq + Hash,
     {
-        self.inner.contains_key(k)
+        if let Some(values) = self.inner.get_mut(k) {
+            values.reserve(additional);
+        }
     }
 
-    /// Returns the number of elements in the map.
+    /// Shrinks the capacity of the map and of every key's vector as much as
+    /// possible.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiMap;
+    ///
+    /// let mut map: MultiMap<usize, usize> = MultiMap::with_capacity(100);
+    /// map.insert_many(1, 0..100);
+    /// map.retain(|_, &v| v < 2);
+    ///
+    /// map.shrink_to_fit();
+    /// assert!(map.capacity() < 100);
+    /// assert_eq!(map.get_vec(&1).unwrap().capacity(), 2);
+    /// ```
+    pub fn shrink_to_fit(&mut self) {
+        self.shrink_values_to_fit();
+        self.inner.shrink_to_fit();
+    }
+
+    /// Shrinks the capacity of every key's vector as much as possible,
+    /// leaving the capacity of the map itself unchanged.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiMap;
+    ///
+    /// let mut map: MultiMap<usize, usize> = MultiMap::with_capacity(100);
+    /// map.insert_many(1, 0..100);
+    /// map.retain(|_, &v| v < 2);
+    ///
+    /// map.shrink_values_to_fit();
+    /// assert!(map.capacity() >= 100);
+    /// assert_eq!(map.get_vec(&1).unwrap().capacity(), 2);
+    /// ```
+    pub fn shrink_values_to_fit(&mut self) {
+        for values in self.inner.values_mut() {
+            values.shrink_to_fit();
+        }
+    }
+
+    /// Inserts a key-value pair into the multimap like `insert`, but returns an
+    /// error instead of aborting if memory for the map or the key's vector
+    /// can't be allocated. The map's contents are left unchanged on error.
+    ///
+    /// Room for one more key is reserved before the key is looked up, so the
+    /// key is hashed once, but the table may grow even if the key is already
+    /// in the map.
+    ///
+    /// Only available with the `std` feature. The hashbrown backend reports
+    /// a failure to grow the table with its own error type, which can't be
+    /// converted into the `TryReserveError` returned for the vectors.
     ///
     /// # Examples
     ///
@@ -270,16 +625,32 @@ where
     /// use multimap::MultiMap;
     ///
     /// let mut map = MultiMap::new();
-    /// map.insert(1, 42);
-    /// map.insert(2, 1337);
-    /// assert_eq!(map.len(), 2);
+    /// map.try_insert("key", 42).expect("out of memory");
+    /// map.try_insert("key", 43).expect("out of memory");
+    /// assert_eq!(map.get_vec("key"), Some(&vec![42, 43]));
     /// ```
-    pub fn len(&self) -> usize {
-        self.inner.len()
+    #[cfg(feature = "std")]
+    pub fn try_insert(&mut self, k: K, v: V) -> Result<(), TryReserveError> {
+        use std::collections::hash_map::Entry as HashMapEntry;
+        self.inner.try_reserve(1)?;
+        match self.inner.entry(k) {
+            HashMapEntry::Occupied(mut entry) => {
+                let values = entry.get_mut();
+                values.try_reserve(1)?;
+                values.push(v);
+            }
+            HashMapEntry::Vacant(entry) => {
+                let mut values = Vec::new();
+                values.try_reserve_exact(1)?;
+                values.push(v);
+                entry.insert(values);
+            }
+        }
+        Ok(())
     }
 
-    /// Removes a key from the map, returning the vector of values at
-    /// the key if the key was previously in the map.
+    /// Returns a mutable reference to the first item in the vector corresponding to
+    /// the key.
     ///
     /// The key may be any borrowed form of the map's key type, but Hash and Eq
     /// on the borrowed form must match those for the key type.
@@ -292,19 +663,22 @@ where
     /// let mut map = MultiMap::new();
     /// map.insert(1, 42);
     /// map.insert(1, 1337);
-    /// assert_eq!(map.remove(&1), Some(vec![42, 1337]));
-    /// assert_eq!(map.remove(&1), None);
+    /// if let Some(v) = map.get_mut(&1) {
+    ///     *v = 99;
+    /// }
+    /// assert_eq!(map[&1], 99);
     /// ```
-    pub fn remove<Q: ?Sized>(&mut self, k: &Q) -> Option<Vec<V>>
+    pub fn get_mut<Q>(&mut self, k: &Q) -> Option<&mut V>
     where
         K: Borrow<Q>,
-        Q: Eq + Hash,
+        Q: ?Sized + Eq + Hash,
     {
-        self.inner.remove(k)
+        self.inner.get_mut(k)?.get_mut(0)
     }
 
-    /// Returns a reference to the first item in the vector corresponding to
-    /// the key.
+    /// Returns a reference to the value at position `n` in the vector
+    /// corresponding to the key, or `None` if the key is missing or holds
+    /// fewer than `n + 1` values.
     ///
     /// The key may be any borrowed form of the map's key type, but Hash and Eq
     /// on the borrowed form must match those for the key type.
@@ -317,18 +691,20 @@ where
     /// let mut map = MultiMap::new();
     /// map.insert(1, 42);
     /// map.insert(1, 1337);
-    /// assert_eq!(map.get(&1), Some(&42));
+    /// assert_eq!(map.get_nth(&1, 1), Some(&1337));
+    /// assert_eq!(map.get_nth(&1, 2), None);
     /// ```
-    pub fn get<Q: ?Sized>(&self, k: &Q) -> Option<&V>
+    pub fn get_nth<Q>(&self, k: &Q, n: usize) -> Option<&V>
     where
         K: Borrow<Q>,
-        Q: Eq + Hash,
+        Q: ?Sized + Eq + Hash,
     {
-        self.inner.get(k)?.get(0)
+        self.inner.get(k)?.get(n)
     }
 
-    /// Returns a mutable reference to the first item in the vector corresponding to
-    /// the key.
+    /// Returns a mutable reference to the value at position `n` in the vector
+    /// corresponding to the key, or `None` if the key is missing or holds
+    /// fewer than `n + 1` values.
     ///
     /// The key may be any borrowed form of the map's key type, but Hash and Eq
     /// on the borrowed form must match those for the key type.
@@ -341,20 +717,21 @@ where
     /// let mut map = MultiMap::new();
     /// map.insert(1, 42);
     /// map.insert(1, 1337);
-    /// if let Some(v) = map.get_mut(&1) {
+    /// if let Some(v) = map.get_nth_mut(&1, 1) {
     ///     *v = 99;
     /// }
-    /// assert_eq!(map[&1], 99);
+    /// assert_eq!(map.get_vec(&1), Some(&vec![42, 99]));
     /// ```
-    pub fn get_mut<Q: ?Sized>(&mut self, k: &Q) -> Option<&mut V>
+    pub fn get_nth_mut<Q>(&mut self, k: &Q, n: usize) -> Option<&mut V>
     where
         K: Borrow<Q>,
-        Q: Eq + Hash,
+        Q: ?Sized + Eq + Hash,
     {
-        self.inner.get_mut(k)?.get_mut(0)
+        self.inner.get_mut(k)?.get_mut(n)
     }
 
-    /// Returns a reference to the vector corresponding to the key.
+    /// Returns a reference to the first value corresponding to the key. This
+    /// is the same as `get`.
     ///
     /// The key may be any borrowed form of the map's key type, but Hash and Eq
     /// on the borrowed form must match those for the key type.
@@ -367,17 +744,18 @@ where
     /// let mut map = MultiMap::new();
     /// map.insert(1, 42);
     /// map.insert(1, 1337);
-    /// assert_eq!(map.get_vec(&1), Some(&vec![42, 1337]));
+    /// assert_eq!(map.first(&1), Some(&42));
     /// ```
-    pub fn get_vec<Q: ?Sized>(&self, k: &Q) -> Option<&Vec<V>>
+    pub fn first<Q>(&self, k: &Q) -> Option<&V>
     where
         K: Borrow<Q>,
-        Q: Eq + Hash,
+        Q: ?Sized + Eq + Hash,
     {
-        self.inner.get(k)
+        self.inner.get(k)?.first()
     }
 
-    /// Returns a mutable reference to the vector corresponding to the key.
+    /// Returns a mutable reference to the first value corresponding to the
+    /// key. This is the same as `get_mut`.
     ///
     /// The key may be any borrowed form of the map's key type, but Hash and Eq
     /// on the borrowed form must match those for the key type.
@@ -390,21 +768,21 @@ where
     /// let mut map = MultiMap::new();
     /// map.insert(1, 42);
     /// map.insert(1, 1337);
-    /// if let Some(v) = map.get_vec_mut(&1) {
-    ///     (*v)[0] = 1991;
-    ///     (*v)[1] = 2332;
+    /// if let Some(v) = map.first_mut(&1) {
+    ///     *v = 99;
     /// }
-    /// assert_eq!(map.get_vec(&1), Some(&vec![1991, 2332]));
+    /// assert_eq!(map.get_vec(&1), Some(&vec![99, 1337]));
     /// ```
-    pub fn get_vec_mut<Q: ?Sized>(&mut self, k: &Q) -> Option<&mut Vec<V>>
+    pub fn first_mut<Q>(&mut self, k: &Q) -> Option<&mut V>
     where
         K: Borrow<Q>,
-        Q: Eq + Hash,
+        Q: ?Sized + Eq + Hash,
     {
-        self.inner.get_mut(k)
+        self.inner.get_mut(k)?.first_mut()
     }
 
-    /// Returns true if the key is multi-valued.
+    /// Returns a reference to the last, i.e. most recently inserted, value
+    /// corresponding to the key.
     ///
     /// The key may be any borrowed form of the map's key type, but Hash and Eq
     /// on the borrowed form must match those for the key type.
@@ -417,38 +795,47 @@ where
     /// let mut map = MultiMap::new();
     /// map.insert(1, 42);
     /// map.insert(1, 1337);
-    /// map.insert(2, 2332);
-    ///
-    /// assert_eq!(map.is_vec(&1), true);   // key is multi-valued
-    /// assert_eq!(map.is_vec(&2), false);  // key is single-valued
-    /// assert_eq!(map.is_vec(&3), false);  // key not in map
+    /// assert_eq!(map.last(&1), Some(&1337));
     /// ```
-    pub fn is_vec<Q: ?Sized>(&self, k: &Q) -> bool
+    pub fn last<Q>(&self, k: &Q) -> Option<&V>
     where
         K: Borrow<Q>,
-        Q: Eq + Hash,
+        Q: ?Sized + Eq + Hash,
     {
-        match self.get_vec(k) {
-            Some(val) => val.len() > 1,
-            None => false,
-        }
+        self.inner.get(k)?.last()
     }
 
-    /// Returns the number of elements the map can hold without reallocating.
+    /// Returns a mutable reference to the last, i.e. most recently inserted,
+    /// value corresponding to the key.
+    ///
+    /// The key may be any borrowed form of the map's key type, but Hash and Eq
+    /// on the borrowed form must match those for the key type.
     ///
     /// # Examples
     ///
     /// ```
     /// use multimap::MultiMap;
     ///
-    /// let map: MultiMap<usize, usize> = MultiMap::new();
-    /// assert!(map.capacity() >= 0);
+    /// let mut map = MultiMap::new();
+    /// map.insert(1, 42);
+    /// map.insert(1, 1337);
+    /// if let Some(v) = map.last_mut(&1) {
+    ///     *v = 99;
+    /// }
+    /// assert_eq!(map.get_vec(&1), Some(&vec![42, 99]));
     /// ```
-    pub fn capacity(&self) -> usize {
-        self.inner.capacity()
+    pub fn last_mut<Q>(&mut self, k: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        self.inner.get_mut(k)?.last_mut()
     }
 
-    /// Returns true if the map contains no elements.
+    /// Returns a reference to the vector corresponding to the key.
+    ///
+    /// The key may be any borrowed form of the map's key type, but Hash and Eq
+    /// on the borrowed form must match those for the key type.
     ///
     /// # Examples
     ///
@@ -456,16 +843,22 @@ where
     /// use multimap::MultiMap;
     ///
     /// let mut map = MultiMap::new();
-    /// assert!(map.is_empty());
-    /// map.insert(1,42);
-    /// assert!(!map.is_empty());
+    /// map.insert(1, 42);
+    /// map.insert(1, 1337);
+    /// assert_eq!(map.get_vec(&1), Some(&vec![42, 1337]));
     /// ```
-    pub fn is_empty(&self) -> bool {
-        self.inner.is_empty()
+    pub fn get_vec<Q>(&self, k: &Q) -> Option<&Vec<V>>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        self.inner.get(k)
     }
 
-    /// Clears the map, removing all key-value pairs.
-    /// Keeps the allocated memory for reuse.
+    /// Returns the key stored in the map together with its values.
+    ///
+    /// The key may be any borrowed form of the map's key type, but Hash and Eq
+    /// on the borrowed form must match those for the key type.
     ///
     /// # Examples
     ///
@@ -473,16 +866,27 @@ where
     /// use multimap::MultiMap;
     ///
     /// let mut map = MultiMap::new();
-    /// map.insert(1,42);
-    /// map.clear();
-    /// assert!(map.is_empty());
+    /// map.insert("key".to_string(), 42);
+    /// map.insert("key".to_string(), 1337);
+    /// assert_eq!(map.get_key_value("key"), Some((&"key".to_string(), &[42, 1337][..])));
+    /// assert_eq!(map.get_key_value("other"), None);
     /// ```
-    pub fn clear(&mut self) {
-        self.inner.clear();
+    pub fn get_key_value<Q>(&self, k: &Q) -> Option<(&K, &[V])>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        self.inner
+            .get_key_value(k)
+            .map(|(key, values)| (key, values.as_slice()))
     }
 
-    /// An iterator visiting all keys in arbitrary order.
-    /// Iterator element type is &'a K.
+    /// Looks up several keys at once, returning an iterator over the stored
+    /// key and values of every key that is in the map. Keys which are not in
+    /// the map are skipped.
+    ///
+    /// The keys may be any borrowed form of the map's key type, but Hash and Eq
+    /// on the borrowed form must match those for the key type.
     ///
     /// # Examples
     ///
@@ -490,22 +894,24 @@ where
     /// use multimap::MultiMap;
     ///
     /// let mut map = MultiMap::new();
-    /// map.insert(1,42);
-    /// map.insert(1,1337);
-    /// map.insert(2,1337);
-    /// map.insert(4,1991);
+    /// map.insert_many(1, vec![42, 1337]);
+    /// map.insert(2, 7);
     ///
-    /// let mut keys: Vec<_> = map.keys().collect();
-    /// keys.sort();
-    /// assert_eq!(keys, [&1, &2, &4]);
+    /// let found: Vec<_> = map.get_all(&[&2, &3, &1]).collect();
+    /// assert_eq!(found, [(&2, &[7][..]), (&1, &[42, 1337][..])]);
     /// ```
-    pub fn keys(&'_ self) -> Keys<'_, K, Vec<V>> {
-        self.inner.keys()
+    pub fn get_all<'a, Q>(&'a self, keys: &'a [&'a Q]) -> impl Iterator<Item = (&'a K, &'a [V])>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        keys.iter().filter_map(move |k| self.get_key_value(k))
     }
 
-    /// An iterator visiting all key-value pairs in arbitrary order. The iterator returns
-    /// a reference to the key and the first element in the corresponding key's vector.
-    /// Iterator element type is (&'a K, &'a V).
+    /// Returns a mutable reference to the vector corresponding to the key.
+    ///
+    /// The key may be any borrowed form of the map's key type, but Hash and Eq
+    /// on the borrowed form must match those for the key type.
     ///
     /// # Examples
     ///
@@ -513,24 +919,78 @@ where
     /// use multimap::MultiMap;
     ///
     /// let mut map = MultiMap::new();
-    /// map.insert(1,42);
-    /// map.insert(1,1337);
-    /// map.insert(3,2332);
-    /// map.insert(4,1991);
+    /// map.insert(1, 42);
+    /// map.insert(1, 1337);
+    /// if let Some(v) = map.get_vec_mut(&1) {
+    ///     (*v)[0] = 1991;
+    ///     (*v)[1] = 2332;
+    /// }
+    /// assert_eq!(map.get_vec(&1), Some(&vec![1991, 2332]));
+    /// ```
+    pub fn get_vec_mut<Q>(&mut self, k: &Q) -> Option<&mut Vec<V>>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        self.inner.get_mut(k)
+    }
+
+    /// Returns a reference to the vector corresponding to the key, looking it
+    /// up through the `Equivalent` trait instead of `Borrow`. This allows e.g.
+    /// a `(String, u32)` key to be found with a `(&str, u32)` lookalike.
+    ///
+    /// Only available on the hashbrown backend, i.e. without the `std`
+    /// feature.
+    ///
+    /// # Examples
     ///
-    /// let mut pairs: Vec<_> = map.iter().collect();
-    /// pairs.sort_by_key(|p| p.0);
-    /// assert_eq!(pairs, [(&1, &42), (&3, &2332), (&4, &1991)]);
     /// ```
-    pub fn iter(&self) -> Iter<K, V> {
-        Iter {
-            inner: self.inner.iter(),
-        }
+    /// # #[cfg(not(feature = "std"))]
+    /// # {
+    /// use multimap::{Equivalent, MultiMap};
+    ///
+    /// #[derive(Hash)]
+    /// struct Lookup<'a>(&'a str, u32);
+    ///
+    /// impl<'a> Equivalent<(String, u32)> for Lookup<'a> {
+    ///     fn equivalent(&self, key: &(String, u32)) -> bool {
+    ///         self.0 == key.0 && self.1 == key.1
+    ///     }
+    /// }
+    ///
+    /// let mut map: MultiMap<(String, u32), i32> = MultiMap::new();
+    /// map.insert(("a".to_string(), 1), 42);
+    /// assert_eq!(map.get_vec_equivalent(&Lookup("a", 1)), Some(&vec![42]));
+    /// # }
+    /// ```
+    #[cfg(not(feature = "std"))]
+    pub fn get_vec_equivalent<Q>(&self, k: &Q) -> Option<&Vec<V>>
+    where
+        Q: ?Sized + Hash + Equivalent<K>,
+    {
+        self.inner.get(k)
     }
 
-    /// An iterator visiting all key-value pairs in arbitrary order. The iterator returns
-    /// a reference to the key and a mutable reference to the first element in the
-    /// corresponding key's vector. Iterator element type is (&'a K, &'a mut V).
+    /// Returns a mutable reference to the vector corresponding to the key,
+    /// looking it up through the `Equivalent` trait instead of `Borrow`.
+    ///
+    /// Only available on the hashbrown backend, i.e. without the `std`
+    /// feature.
+    #[cfg(not(feature = "std"))]
+    pub fn get_vec_mut_equivalent<Q>(&mut self, k: &Q) -> Option<&mut Vec<V>>
+    where
+        Q: ?Sized + Hash + Equivalent<K>,
+    {
+        self.inner.get_mut(k)
+    }
+
+    /// Returns mutable references to the vectors of several keys at once.
+    ///
+    /// Returns `None` if any of the keys is missing, or if the same key is
+    /// given more than once.
+    ///
+    /// The keys may be any borrowed form of the map's key type, but Hash and Eq
+    /// on the borrowed form must match those for the key type.
     ///
     /// # Examples
     ///
@@ -538,28 +998,48 @@ where
     /// use multimap::MultiMap;
     ///
     /// let mut map = MultiMap::new();
-    /// map.insert(1,42);
-    /// map.insert(1,1337);
-    /// map.insert(3,2332);
-    /// map.insert(4,1991);
+    /// map.insert_many("a", vec![1, 2]);
+    /// map.insert_many("b", vec![3]);
     ///
-    /// for (_, value) in map.iter_mut() {
-    ///     *value *= *value;
+    /// if let Some([a, b]) = map.get_many_mut(["a", "b"]) {
+    ///     b.append(a);
     /// }
+    /// assert_eq!(map.get_vec("a"), Some(&vec![]));
+    /// assert_eq!(map.get_vec("b"), Some(&vec![3, 1, 2]));
     ///
-    /// let mut pairs: Vec<_> = map.iter_mut().collect();
-    /// pairs.sort_by_key(|p| p.0);
-    /// assert_eq!(pairs, [(&1, &mut 1764), (&3, &mut 5438224), (&4, &mut 3964081)]);
+    /// assert!(map.get_many_mut(["a", "a"]).is_none());
+    /// assert!(map.get_many_mut(["a", "c"]).is_none());
     /// ```
-    pub fn iter_mut(&mut self) -> IterMut<K, V> {
-        IterMut {
-            inner: self.inner.iter_mut(),
+    pub fn get_many_mut<Q, const N: usize>(&mut self, ks: [&Q; N]) -> Option<[&mut Vec<V>; N]>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        for (i, k) in ks.iter().enumerate() {
+            if ks[..i].contains(k) {
+                return None;
+            }
+        }
+
+        #[cfg(feature = "std")]
+        let values = self.inner.get_disjoint_mut(ks);
+        #[cfg(not(feature = "std"))]
+        let values = self.inner.get_many_mut(ks);
+
+        if values.iter().any(Option::is_none) {
+            return None;
         }
+        Some(values.map(Option::unwrap))
     }
 
-    /// An iterator visiting all key-value pairs in arbitrary order. The iterator returns
-    /// a reference to the key and the corresponding key's vector.
-    /// Iterator element type is (&'a K, &'a V).
+    /// Calls `f` with mutable references to the vectors of several keys at
+    /// once, using an empty vector for each key which is not in the map yet.
+    ///
+    /// Keys which were missing and which `f` leaves without values are removed
+    /// again before returning, so the map never keeps empty vectors created
+    /// here. Returns `None`, without calling `f` or changing the map, if the
+    /// same key is given more than once. Only keys which are missing from the
+    /// map are cloned.
     ///
     /// # Examples
     ///
@@ -567,22 +1047,52 @@ where
     /// use multimap::MultiMap;
     ///
     /// let mut map = MultiMap::new();
-    /// map.insert(1,42);
-    /// map.insert(1,1337);
-    /// map.insert(3,2332);
-    /// map.insert(4,1991);
+    /// map.insert("in", 1);
     ///
-    /// let mut pairs: Vec<_> = map.iter_all().collect();
-    /// pairs.sort_by_key(|p| p.0);
-    /// assert_eq!(pairs, [(&1, &vec![42, 1337]), (&3, &vec![2332]), (&4, &vec![1991])]);
+    /// map.entries_mut(["in", "out", "unused"], |[incoming, outgoing, _]| {
+    ///     outgoing.push(incoming.len());
+    ///     incoming.push(2);
+    /// });
+    /// assert_eq!(map.get_vec("in"), Some(&vec![1, 2]));
+    /// assert_eq!(map.get_vec("out"), Some(&vec![1]));
+    /// assert!(!map.contains_key("unused"));
+    ///
+    /// assert!(map.entries_mut(["in", "in"], |_| ()).is_none());
     /// ```
-    pub fn iter_all(&self) -> IterAll<K, Vec<V>> {
-        self.inner.iter()
+    pub fn entries_mut<F, R, const N: usize>(&mut self, keys: [K; N], f: F) -> Option<R>
+    where
+        K: Clone,
+        F: FnOnce([&mut Vec<V>; N]) -> R,
+    {
+        for (i, k) in keys.iter().enumerate() {
+            if keys[..i].contains(k) {
+                return None;
+            }
+        }
+
+        let mut inserted = [false; N];
+        for (k, inserted) in keys.iter().zip(inserted.iter_mut()) {
+            if !self.inner.contains_key(k) {
+                self.inner.insert(k.clone(), Vec::new());
+                *inserted = true;
+            }
+        }
+        let result = self.get_many_mut(keys.each_ref()).map(f);
+
+        for (k, inserted) in keys.iter().zip(inserted) {
+            if inserted && self.inner[k].is_empty() {
+                self.inner.remove(k);
+            }
+        }
+        result
     }
 
-    /// An iterator visiting all key-value pairs in arbitrary order. The iterator returns
-    /// a reference to the key and the corresponding key's vector.
-    /// Iterator element type is (&'a K, &'a V).
+    /// Moves all values of the key `from` onto the end of the vector of the
+    /// key `to`, and removes `from` from the map. Does nothing if `from` is not
+    /// in the map.
+    ///
+    /// The key may be any borrowed form of the map's key type, but Hash and Eq
+    /// on the borrowed form must match those for the key type.
     ///
     /// # Examples
     ///
@@ -590,28 +1100,54 @@ where
     /// use multimap::MultiMap;
     ///
     /// let mut map = MultiMap::new();
-    /// map.insert(1,42);
-    /// map.insert(1,1337);
-    /// map.insert(3,2332);
-    /// map.insert(4,1991);
+    /// map.insert_many("old", vec![1, 2]);
+    /// map.insert("new", 3);
     ///
-    /// for (key, values) in map.iter_all_mut() {
-    ///     for value in values.iter_mut() {
-    ///         *value = 99;
-    ///     }
-    /// }
+    /// map.move_values("old", "new");
+    /// assert!(!map.contains_key("old"));
+    /// assert_eq!(map.get_vec("new"), Some(&vec![3, 1, 2]));
+    /// ```
+    pub fn move_values<Q>(&mut self, from: &Q, to: K)
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        use std::collections::hash_map::Entry as HashMapEntry;
+        if let Some(mut values) = self.inner.remove(from) {
+            match self.inner.entry(to) {
+                HashMapEntry::Occupied(entry) => entry.into_mut().append(&mut values),
+                HashMapEntry::Vacant(entry) => {
+                    entry.insert(values);
+                }
+            }
+        }
+    }
+
+    /// Replaces all values of the key with `values`, returning the previous
+    /// vector if the key was in the map.
+    ///
+    /// Unlike `insert_many`, which appends, this behaves like
+    /// `HashMap::insert`.
+    ///
+    /// # Examples
     ///
-    /// let mut pairs: Vec<_> = map.iter_all_mut().collect();
-    /// pairs.sort_by_key(|p| p.0);
-    /// assert_eq!(pairs, [(&1, &mut vec![99, 99]), (&3, &mut vec![99]), (&4, &mut vec![99])]);
     /// ```
-    pub fn iter_all_mut(&mut self) -> IterAllMut<K, Vec<V>> {
-        self.inner.iter_mut()
+    /// use multimap::MultiMap;
+    ///
+    /// let mut map = MultiMap::new();
+    /// assert_eq!(map.replace(1, vec![42]), None);
+    /// assert_eq!(map.replace(1, vec![1337, 7]), Some(vec![42]));
+    /// assert_eq!(map.get_vec(&1), Some(&vec![1337, 7]));
+    /// ```
+    pub fn replace(&mut self, k: K, values: Vec<V>) -> Option<Vec<V>> {
+        self.inner.insert(k, values)
     }
 
-    /// An iterator visiting all key-value pairs in arbitrary order. The iterator returns
-    /// a reference to the key and the first element in the corresponding key's vector.
-    /// Iterator element type is (&'a K, &'a V).
+    /// Returns a cursor pointing at the first value of the key, for editing
+    /// the key's values while walking over them. See `CursorMut` for details.
+    ///
+    /// The key may be any borrowed form of the map's key type, but Hash and Eq
+    /// on the borrowed form must match those for the key type.
     ///
     /// # Examples
     ///
@@ -619,23 +1155,26 @@ where
     /// use multimap::MultiMap;
     ///
     /// let mut map = MultiMap::new();
-    /// map.insert(1,42);
-    /// map.insert(1,1337);
-    /// map.insert(3,2332);
-    /// map.insert(4,1991);
+    /// map.insert_many(1, vec![42, 7, 1337]);
     ///
-    /// for (key, value) in map.flat_iter() {
-    ///     println!("key: {:?}, val: {:?}", key, value);
-    /// }
+    /// let mut cursor = map.cursor_mut(&1).unwrap();
+    /// cursor.move_next();
+    /// assert_eq!(cursor.remove_current(), Some(7));
+    /// assert_eq!(map.get_vec(&1), Some(&vec![42, 1337]));
     /// ```
-    pub fn flat_iter(&self) -> impl Iterator<Item = (&K, &V)> {
-        self.iter_all()
-            .flat_map(|(k, v)| v.iter().map(move |i| (k, i)))
+    pub fn cursor_mut<Q>(&mut self, k: &Q) -> Option<CursorMut<'_, V>>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        self.inner.get_mut(k).map(CursorMut::new)
     }
 
-    /// An iterator visiting all key-value pairs in arbitrary order. The iterator returns
-    /// a reference to the key and the first element in the corresponding key's vector.
-    /// Iterator element type is (&'a K, &'a V).
+    /// Removes and returns the first value of the key's vector. The key is
+    /// removed from the map when its vector becomes empty.
+    ///
+    /// The key may be any borrowed form of the map's key type, but Hash and Eq
+    /// on the borrowed form must match those for the key type.
     ///
     /// # Examples
     ///
@@ -643,995 +1182,5086 @@ where
     /// use multimap::MultiMap;
     ///
     /// let mut map = MultiMap::new();
-    /// map.insert(1,42);
-    /// map.insert(1,1337);
-    /// map.insert(3,2332);
-    /// map.insert(4,1991);
+    /// map.insert(1, 42);
+    /// map.insert(1, 1337);
+    /// assert_eq!(map.remove_first(&1), Some(42));
+    /// assert_eq!(map.remove_first(&1), Some(1337));
+    /// assert_eq!(map.remove_first(&1), None);
+    /// assert!(!map.contains_key(&1));
+    /// ```
+    pub fn remove_first<Q>(&mut self, k: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        self.remove_from_vec(k, |values| {
+            if values.is_empty() {
+                None
+            } else {
+                Some(values.remove(0))
+            }
+        })
+    }
+
+    /// Removes and returns the last value of the key's vector. The key is
+    /// removed from the map when its vector becomes empty.
+    ///
+    /// The key may be any borrowed form of the map's key type, but Hash and Eq
+    /// on the borrowed form must match those for the key type.
+    ///
+    /// # Examples
     ///
-    /// for (key, value) in map.flat_iter_mut() {
-    ///     println!("key: {:?}, val: {:?}", key, value);
-    /// }
     /// ```
-    pub fn flat_iter_mut(&mut self) -> impl Iterator<Item = (&K, &mut V)> {
-        self.iter_all_mut()
-            .flat_map(|(k, v)| v.iter_mut().map(move |i| (k, i)))
+    /// use multimap::MultiMap;
+    ///
+    /// let mut map = MultiMap::new();
+    /// map.insert(1, 42);
+    /// map.insert(1, 1337);
+    /// assert_eq!(map.remove_last(&1), Some(1337));
+    /// assert_eq!(map.remove_last(&1), Some(42));
+    /// assert_eq!(map.remove_last(&1), None);
+    /// assert!(!map.contains_key(&1));
+    /// ```
+    pub fn remove_last<Q>(&mut self, k: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        self.remove_from_vec(k, Vec::pop)
     }
 
-    /// Gets the specified key's corresponding entry in the map for in-place manipulation.
-    /// It's possible to both manipulate the vector and the 'value' (the first value in the
-    /// vector).
+    /// Removes the first value equal to `v` from the key's vector and returns
+    /// it. The key is removed from the map when its vector becomes empty.
+    ///
+    /// The key and value may be any borrowed form of the map's key and value
+    /// types, but Hash and Eq on the borrowed key, and PartialEq on the
+    /// borrowed value, must match those for the owned types.
     ///
     /// # Examples
     ///
     /// ```
     /// use multimap::MultiMap;
     ///
-    /// let mut m = MultiMap::new();
-    /// m.insert(1, 42);
+    /// let mut map = MultiMap::new();
+    /// map.insert("key", "a".to_string());
+    /// map.insert("key", "b".to_string());
+    /// assert_eq!(map.remove_value("key", "a"), Some("a".to_string()));
+    /// assert_eq!(map.remove_value("key", "a"), None);
+    /// assert_eq!(map.remove_value("key", "b"), Some("b".to_string()));
+    /// assert!(map.is_empty());
+    /// ```
+    pub fn remove_value<Q, W>(&mut self, k: &Q, v: &W) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+        V: Borrow<W>,
+        W: ?Sized + PartialEq,
+    {
+        self.remove_from_vec(k, |values| {
+            let index = values.iter().position(|value| value.borrow() == v)?;
+            Some(values.remove(index))
+        })
+    }
+
+    /// Removes all values of the key and returns them as an iterator. The key
+    /// stays in the map with an empty vector, which keeps its allocated
+    /// capacity for reuse; use `remove` to delete the key as well.
     ///
-    /// {
-    ///     let mut v = m.entry(1).or_insert(43);
-    ///     assert_eq!(v, &42);
-    ///     *v = 44;
-    /// }
-    /// assert_eq!(m.entry(2).or_insert(666), &666);
+    /// The key may be any borrowed form of the map's key type, but Hash and Eq
+    /// on the borrowed form must match those for the key type.
     ///
-    /// {
-    ///     let mut v = m.entry(1).or_insert_vec(vec![43]);
-    ///     assert_eq!(v, &vec![44]);
-    ///     v.push(50);
-    /// }
-    /// assert_eq!(m.entry(2).or_insert_vec(vec![667]), &vec![666]);
+    /// # Examples
     ///
-    /// assert_eq!(m.get_vec(&1), Some(&vec![44, 50]));
     /// ```
-    pub fn entry(&mut self, k: K) -> Entry<K, V> {
-        use std::collections::hash_map::Entry as HashMapEntry;
-        match self.inner.entry(k) {
-            HashMapEntry::Occupied(entry) => Entry::Occupied(OccupiedEntry { inner: entry }),
-            HashMapEntry::Vacant(entry) => Entry::Vacant(VacantEntry { inner: entry }),
-        }
+    /// use multimap::MultiMap;
+    ///
+    /// let mut map = MultiMap::new();
+    /// map.insert(1, 42);
+    /// map.insert(1, 1337);
+    ///
+    /// let drained: Vec<_> = map.drain_vec(&1).unwrap().collect();
+    /// assert_eq!(drained, [42, 1337]);
+    /// assert_eq!(map.get_vec(&1), Some(&vec![]));
+    /// assert!(map.drain_vec(&2).is_none());
+    /// ```
+    pub fn drain_vec<Q>(&mut self, k: &Q) -> Option<VecDrain<'_, V>>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        self.inner.get_mut(k).map(|values| values.drain(..))
     }
 
-    /// Retains only the elements specified by the predicate.
+    /// Shortens the key's vector to its first `n` values, dropping the rest.
+    /// Has no effect if the key holds `n` or fewer values. Like `drain_vec`,
+    /// this keeps the key in the map even if its vector ends up empty.
     ///
-    /// In other words, remove all pairs `(k, v)` such that `f(&k,&mut v)` returns `false`.
+    /// The key may be any borrowed form of the map's key type, but Hash and Eq
+    /// on the borrowed form must match those for the key type.
     ///
     /// # Examples
     ///
     /// ```
     /// use multimap::MultiMap;
     ///
-    /// let mut m = MultiMap::new();
-    /// m.insert(1, 42);
-    /// m.insert(1, 99);
-    /// m.insert(2, 42);
-    /// m.retain(|&k, &v| { k == 1 && v == 42 });
-    /// assert_eq!(1, m.len());
-    /// assert_eq!(Some(&42), m.get(&1));
+    /// let mut map = MultiMap::new();
+    /// map.insert_many(1, vec![1, 2, 3, 4]);
+    /// map.truncate_values(&1, 2);
+    /// assert_eq!(map.get_vec(&1), Some(&vec![1, 2]));
     /// ```
-    pub fn retain<F>(&mut self, mut f: F)
+    pub fn truncate_values<Q>(&mut self, k: &Q, n: usize)
     where
-        F: FnMut(&K, &V) -> bool,
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
     {
-        for (key, vector) in &mut self.inner {
-            vector.retain(|value| f(key, value));
+        if let Some(values) = self.inner.get_mut(k) {
+            values.truncate(n);
         }
-        self.inner.retain(|_, v| !v.is_empty());
+    }
+
+    /// Shortens the key's vector to its last `n` values, dropping the oldest.
+    /// Has no effect if the key holds `n` or fewer values. Like `drain_vec`,
+    /// this keeps the key in the map even if its vector ends up empty.
+    ///
+    /// The key may be any borrowed form of the map's key type, but Hash and Eq
+    /// on the borrowed form must match those for the key type.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiMap;
+    ///
+    /// let mut map = MultiMap::new();
+    /// map.insert_many(1, vec![1, 2, 3, 4]);
+    /// map.keep_last(&1, 2);
+    /// assert_eq!(map.get_vec(&1), Some(&vec![3, 4]));
+    /// ```
+    pub fn keep_last<Q>(&mut self, k: &Q, n: usize)
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        if let Some(values) = self.inner.get_mut(k) {
+            let len = values.len();
+            if len > n {
+                values.drain(..len - n);
+            }
+        }
+    }
+
+    /// Removes all values of the key, returning how many were removed. The key
+    /// stays in the map and its vector keeps its allocated capacity.
+    ///
+    /// The key may be any borrowed form of the map's key type, but Hash and Eq
+    /// on the borrowed form must match those for the key type.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiMap;
+    ///
+    /// let mut map = MultiMap::new();
+    /// map.insert_many(1, vec![42, 1337]);
+    ///
+    /// assert_eq!(map.clear_values(&1), 2);
+    /// assert_eq!(map.get_vec(&1), Some(&vec![]));
+    /// assert_eq!(map.clear_values(&2), 0);
+    /// ```
+    pub fn clear_values<Q>(&mut self, k: &Q) -> usize
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        match self.inner.get_mut(k) {
+            Some(values) => {
+                let len = values.len();
+                values.clear();
+                len
+            }
+            None => 0,
+        }
+    }
+
+    /// Removes duplicate values from the key's vector, keeping the first
+    /// occurrence of each value and the order of the remaining values.
+    ///
+    /// The key may be any borrowed form of the map's key type, but Hash and Eq
+    /// on the borrowed form must match those for the key type.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiMap;
+    ///
+    /// let mut map = MultiMap::new();
+    /// map.insert_many(1, vec![3, 1, 3, 2, 1]);
+    /// map.dedup_values(&1);
+    /// assert_eq!(map.get_vec(&1), Some(&vec![3, 1, 2]));
+    /// ```
+    pub fn dedup_values<Q>(&mut self, k: &Q)
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+        V: Eq + Hash,
+    {
+        if let Some(values) = self.inner.get_mut(k) {
+            dedup_vec(values);
+        }
+    }
+
+    /// Removes duplicate values from the vector of every key, keeping the
+    /// first occurrence of each value and the order of the remaining values.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiMap;
+    ///
+    /// let mut map = MultiMap::new();
+    /// map.insert_many(1, vec![3, 1, 3]);
+    /// map.insert_many(2, vec![4, 4]);
+    /// map.dedup_all();
+    /// assert_eq!(map.get_vec(&1), Some(&vec![3, 1]));
+    /// assert_eq!(map.get_vec(&2), Some(&vec![4]));
+    /// ```
+    pub fn dedup_all(&mut self)
+    where
+        V: Eq + Hash,
+    {
+        for values in self.inner.values_mut() {
+            dedup_vec(values);
+        }
+    }
+
+    /// Sorts the key's vector. The sort is stable, so equal values keep their
+    /// insertion order.
+    ///
+    /// The key may be any borrowed form of the map's key type, but Hash and Eq
+    /// on the borrowed form must match those for the key type.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiMap;
+    ///
+    /// let mut map = MultiMap::new();
+    /// map.insert_many(1, vec![3, 1, 2]);
+    /// map.sort_values(&1);
+    /// assert_eq!(map.get_vec(&1), Some(&vec![1, 2, 3]));
+    /// ```
+    pub fn sort_values<Q>(&mut self, k: &Q)
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+        V: Ord,
+    {
+        if let Some(values) = self.inner.get_mut(k) {
+            values.sort();
+        }
+    }
+
+    /// Sorts the key's vector, but might not preserve the order of equal
+    /// values.
+    ///
+    /// The key may be any borrowed form of the map's key type, but Hash and Eq
+    /// on the borrowed form must match those for the key type.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiMap;
+    ///
+    /// let mut map = MultiMap::new();
+    /// map.insert_many(1, vec![3, 1, 2]);
+    /// map.sort_values_unstable(&1);
+    /// assert_eq!(map.get_vec(&1), Some(&vec![1, 2, 3]));
+    /// ```
+    pub fn sort_values_unstable<Q>(&mut self, k: &Q)
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+        V: Ord,
+    {
+        if let Some(values) = self.inner.get_mut(k) {
+            values.sort_unstable();
+        }
+    }
+
+    /// Sorts the vector of every key. The sort is stable, so equal values keep
+    /// their insertion order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiMap;
+    ///
+    /// let mut map = MultiMap::new();
+    /// map.insert_many(1, vec![3, 1, 2]);
+    /// map.insert_many(2, vec![5, 4]);
+    /// map.sort_all_values();
+    /// assert_eq!(map.get_vec(&1), Some(&vec![1, 2, 3]));
+    /// assert_eq!(map.get_vec(&2), Some(&vec![4, 5]));
+    /// ```
+    pub fn sort_all_values(&mut self)
+    where
+        V: Ord,
+    {
+        for values in self.inner.values_mut() {
+            values.sort();
+        }
+    }
+
+    /// Sorts the vector of every key, but might not preserve the order of
+    /// equal values.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiMap;
+    ///
+    /// let mut map = MultiMap::new();
+    /// map.insert_many(1, vec![3, 1, 2]);
+    /// map.sort_all_values_unstable();
+    /// assert_eq!(map.get_vec(&1), Some(&vec![1, 2, 3]));
+    /// ```
+    pub fn sort_all_values_unstable(&mut self)
+    where
+        V: Ord,
+    {
+        for values in self.inner.values_mut() {
+            values.sort_unstable();
+        }
+    }
+
+    /// Sorts the vector of every key with a comparator function. The sort is
+    /// stable, so equal values keep their insertion order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiMap;
+    ///
+    /// let mut map = MultiMap::new();
+    /// map.insert_many(1, vec![1, 3, 2]);
+    /// map.sort_all_values_by(|a, b| b.cmp(a));
+    /// assert_eq!(map.get_vec(&1), Some(&vec![3, 2, 1]));
+    /// ```
+    pub fn sort_all_values_by<F>(&mut self, mut compare: F)
+    where
+        F: FnMut(&V, &V) -> Ordering,
+    {
+        for values in self.inner.values_mut() {
+            values.sort_by(&mut compare);
+        }
+    }
+
+    /// Applies `f` to the key's vector and removes the key if the vector is
+    /// empty afterwards.
+    fn remove_from_vec<Q, F>(&mut self, k: &Q, f: F) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+        F: FnOnce(&mut Vec<V>) -> Option<V>,
+    {
+        let values = self.inner.get_mut(k)?;
+        let removed = f(values);
+        if values.is_empty() {
+            self.inner.remove(k);
+        }
+        removed
+    }
+
+    /// An iterator visiting all key-value pairs in arbitrary order. The iterator returns
+    /// a reference to the key and the first element in the corresponding key's vector.
+    /// Iterator element type is (&'a K, &'a V).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiMap;
+    ///
+    /// let mut map = MultiMap::new();
+    /// map.insert(1,42);
+    /// map.insert(1,1337);
+    /// map.insert(3,2332);
+    /// map.insert(4,1991);
+    ///
+    /// let mut pairs: Vec<_> = map.iter().collect();
+    /// pairs.sort_by_key(|p| p.0);
+    /// assert_eq!(pairs, [(&1, &42), (&3, &2332), (&4, &1991)]);
+    /// ```
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter {
+            inner: self.inner.iter(),
+            len: self.non_empty_len(),
+        }
+    }
+
+    /// An iterator visiting all key-value pairs in arbitrary order. The iterator returns
+    /// a reference to the key and a mutable reference to the first element in the
+    /// corresponding key's vector. Iterator element type is (&'a K, &'a mut V).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiMap;
+    ///
+    /// let mut map = MultiMap::new();
+    /// map.insert(1,42);
+    /// map.insert(1,1337);
+    /// map.insert(3,2332);
+    /// map.insert(4,1991);
+    ///
+    /// for (_, value) in map.iter_mut() {
+    ///     *value *= *value;
+    /// }
+    ///
+    /// let mut pairs: Vec<_> = map.iter_mut().collect();
+    /// pairs.sort_by_key(|p| p.0);
+    /// assert_eq!(pairs, [(&1, &mut 1764), (&3, &mut 5438224), (&4, &mut 3964081)]);
+    /// ```
+    pub fn iter_mut(&mut self) -> IterMut<'_, K, V> {
+        IterMut {
+            len: self.non_empty_len(),
+            inner: self.inner.iter_mut(),
+        }
+    }
+
+    /// An iterator visiting all key-value pairs in arbitrary order. The iterator returns
+    /// a reference to the key and the first element in the corresponding key's vector.
+    /// Iterator element type is (&'a K, &'a V).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiMap;
+    ///
+    /// let mut map = MultiMap::new();
+    /// map.insert(1,42);
+    /// map.insert(1,1337);
+    /// map.insert(3,2332);
+    /// map.insert(4,1991);
+    ///
+    /// for (key, value) in map.flat_iter_mut() {
+    ///     println!("key: {:?}, val: {:?}", key, value);
+    /// }
+    /// ```
+    pub fn flat_iter_mut(&mut self) -> impl FusedIterator<Item = (&K, &mut V)> {
+        self.iter_all_mut()
+            .flat_map(|(k, v)| v.iter_mut().map(move |i| (k, i)))
+    }
+
+    /// An iterator visiting every value in the map mutably, regardless of key.
+    /// Keys are visited in arbitrary order, values of a key in insertion order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiMap;
+    ///
+    /// let mut map = MultiMap::new();
+    /// map.insert(1,42);
+    /// map.insert(1,1337);
+    /// map.insert(3,2332);
+    ///
+    /// for value in map.flat_values_mut() {
+    ///     *value += 1;
+    /// }
+    /// assert_eq!(map.get_vec(&1), Some(&vec![43, 1338]));
+    /// ```
+    pub fn flat_values_mut(&mut self) -> impl FusedIterator<Item = &mut V> {
+        self.inner.values_mut().flat_map(|values| values.iter_mut())
+    }
+
+    /// Consumes the map, returning an iterator over every key-value pair in
+    /// arbitrary order, values of a key in insertion order.
+    ///
+    /// The key is cloned for every value but the last one, which gets the
+    /// original key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiMap;
+    ///
+    /// let mut map = MultiMap::new();
+    /// map.insert("a".to_string(), 1);
+    /// map.insert("a".to_string(), 2);
+    /// map.insert("b".to_string(), 3);
+    ///
+    /// let mut pairs: Vec<_> = map.into_flat_iter().collect();
+    /// pairs.sort();
+    /// assert_eq!(pairs, [("a".to_string(), 1), ("a".to_string(), 2), ("b".to_string(), 3)]);
+    /// ```
+    pub fn into_flat_iter(self) -> impl Iterator<Item = (K, V)>
+    where
+        K: Clone,
+    {
+        flatten_groups(self.inner.into_iter())
+    }
+
+    /// Clears the map, returning every key-value pair as an iterator. Keys are
+    /// visited in arbitrary order, values of a key in insertion order. Keeps
+    /// the allocated memory for reuse.
+    ///
+    /// The key is cloned for every value but the last one, which gets the
+    /// original key. If the iterator is dropped before it is fully consumed,
+    /// the remaining pairs are dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiMap;
+    ///
+    /// let mut map = MultiMap::new();
+    /// map.insert(1, 42);
+    /// map.insert(1, 1337);
+    /// map.insert(2, 2332);
+    ///
+    /// let mut drained: Vec<_> = map.drain_flat().collect();
+    /// drained.sort();
+    /// assert_eq!(drained, [(1, 42), (1, 1337), (2, 2332)]);
+    /// assert!(map.is_empty());
+    /// ```
+    pub fn drain_flat(&mut self) -> impl Iterator<Item = (K, V)> + '_
+    where
+        K: Clone,
+    {
+        flatten_groups(self.inner.drain())
+    }
+
+    /// Consumes the map, returning an iterator over every value in the map,
+    /// regardless of key. Values of a key are returned in insertion order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiMap;
+    ///
+    /// let mut map = MultiMap::new();
+    /// map.insert(1,42);
+    /// map.insert(1,1337);
+    /// map.insert(2,7);
+    ///
+    /// let mut values: Vec<_> = map.into_flat_values().collect();
+    /// values.sort();
+    /// assert_eq!(values, [7, 42, 1337]);
+    /// ```
+    pub fn into_flat_values(self) -> impl FusedIterator<Item = V> {
+        self.inner.into_values().flatten()
+    }
+
+    /// An iterator visiting all keys in arbitrary order, together with their
+    /// values as a slice. This is `iter_all` without exposing the vector
+    /// the values are stored in.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiMap;
+    ///
+    /// let mut map = MultiMap::new();
+    /// map.insert(1,42);
+    /// map.insert(1,1337);
+    /// map.insert(3,2332);
+    ///
+    /// let mut pairs: Vec<_> = map.iter_slices().collect();
+    /// pairs.sort_by_key(|p| p.0);
+    /// assert_eq!(pairs, [(&1, &[42, 1337][..]), (&3, &[2332][..])]);
+    /// ```
+    pub fn iter_slices(&self) -> impl ExactSizeIterator<Item = (&K, &[V])> + FusedIterator {
+        self.inner
+            .iter()
+            .map(|(key, values)| (key, values.as_slice()))
+    }
+
+    /// An iterator visiting all keys in arbitrary order, together with a
+    /// mutable reference to their vector. This is `iter_all_mut` for maps
+    /// storing their values in a vector.
+    ///
+    /// To remove groups while walking over them, use `groups_mut`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiMap;
+    ///
+    /// let mut map = MultiMap::new();
+    /// map.insert_many(1, vec![3, 1, 2]);
+    /// map.insert_many(2, vec![5, 4]);
+    ///
+    /// for (_, values) in map.iter_groups_mut() {
+    ///     values.sort();
+    /// }
+    /// assert_eq!(map.get_vec(&1), Some(&vec![1, 2, 3]));
+    /// assert_eq!(map.get_vec(&2), Some(&vec![4, 5]));
+    /// ```
+    pub fn iter_groups_mut(&mut self) -> IterAllMut<'_, K, Vec<V>> {
+        self.inner.iter_mut()
+    }
+
+    /// Returns a lending iterator over all keys and their vectors, in
+    /// arbitrary order, which can also remove the group it is currently at.
+    /// See `GroupsMut` for details.
+    ///
+    /// Creating the GroupsMut moves the whole table out of the map, and every
+    /// group which isn't removed is hashed and inserted again, even when the
+    /// walk stops after the first group. A walk therefore always costs time
+    /// linear in the number of keys. To change groups without removing any,
+    /// `iter_groups_mut` works in place; to remove groups by a predicate,
+    /// `retain_vec` does.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiMap;
+    ///
+    /// let mut map = MultiMap::new();
+    /// map.insert_many(1, vec![42, 7]);
+    /// map.insert_many(2, vec![1337]);
+    ///
+    /// let mut groups = map.groups_mut();
+    /// while let Some(group) = groups.next() {
+    ///     if group.values().contains(&1337) {
+    ///         group.remove();
+    ///     }
+    /// }
+    /// drop(groups);
+    ///
+    /// assert!(!map.contains_key(&2));
+    /// assert_eq!(map.get_vec(&1), Some(&vec![42, 7]));
+    /// ```
+    pub fn groups_mut(&mut self) -> GroupsMut<'_, K, V, S>
+    where
+        S: Clone,
+    {
+        GroupsMut::new(&mut self.inner)
+    }
+
+    /// Gets the specified key's corresponding entry in the map for in-place manipulation.
+    /// It's possible to both manipulate the vector and the 'value' (the first value in the
+    /// vector).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiMap;
+    ///
+    /// let mut m = MultiMap::new();
+    /// m.insert(1, 42);
+    ///
+    /// {
+    ///     let mut v = m.entry(1).or_insert(43);
+    ///     assert_eq!(v, &42);
+    ///     *v = 44;
+    /// }
+    /// assert_eq!(m.entry(2).or_insert(666), &666);
+    ///
+    /// {
+    ///     let mut v = m.entry(1).or_insert_vec(vec![43]);
+    ///     assert_eq!(v, &vec![44]);
+    ///     v.push(50);
+    /// }
+    /// assert_eq!(m.entry(2).or_insert_vec(vec![667]), &vec![666]);
+    ///
+    /// assert_eq!(m.get_vec(&1), Some(&vec![44, 50]));
+    /// ```
+    pub fn entry(&mut self, k: K) -> Entry<'_, K, V, S> {
+        use std::collections::hash_map::Entry as HashMapEntry;
+        match self.inner.entry(k) {
+            HashMapEntry::Occupied(entry) => Entry::Occupied(OccupiedEntry {
+                inner: entry,
+                marker: PhantomData,
+            }),
+            HashMapEntry::Vacant(entry) => Entry::Vacant(VacantEntry {
+                inner: entry,
+                marker: PhantomData,
+            }),
+        }
+    }
+
+    /// Gets the entry of a key in the map given by a borrowed form of the key.
+    /// Unlike `entry`, an owned key is only created, through `From<&Q>`, when a
+    /// value is inserted into a vacant entry.
+    ///
+    /// The hashbrown backend hashes the key once. std's HashMap has no entry
+    /// API for borrowed keys, so with the `std` feature the key is hashed
+    /// twice: an occupied key is looked up twice, and filling a vacant entry
+    /// hashes the new owned key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiMap;
+    ///
+    /// let mut m: MultiMap<String, usize> = MultiMap::new();
+    /// for word in "a b a c a".split(' ') {
+    ///     m.entry_ref(word).or_insert_vec(vec![]).push(1);
+    /// }
+    /// assert_eq!(m.get_vec("a"), Some(&vec![1, 1, 1]));
+    /// assert_eq!(m.get_vec("c"), Some(&vec![1]));
+    /// ```
+    pub fn entry_ref<'a, 'b, Q>(&'a mut self, k: &'b Q) -> EntryRef<'a, 'b, K, V, Q, S>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        #[cfg(feature = "std")]
+        return if self.inner.contains_key(k) {
+            EntryRef::Occupied(OccupiedEntryRef {
+                values: self.inner.get_mut(k).expect("key is in the map"),
+                marker: PhantomData,
+            })
+        } else {
+            EntryRef::Vacant(VacantEntryRef {
+                map: &mut self.inner,
+                key: k,
+            })
+        };
+        #[cfg(not(feature = "std"))]
+        return match self.inner.entry_ref(k) {
+            ::hashbrown::hash_map::EntryRef::Occupied(entry) => {
+                EntryRef::Occupied(OccupiedEntryRef {
+                    values: entry.into_mut(),
+                    marker: PhantomData,
+                })
+            }
+            ::hashbrown::hash_map::EntryRef::Vacant(entry) => {
+                EntryRef::Vacant(VacantEntryRef { inner: entry })
+            }
+        };
+    }
+
+    /// Retains only the keys specified by the predicate, which gets mutable
+    /// access to the whole vector of each key. Keys for which the predicate
+    /// returns false, or whose vector is empty afterwards, are removed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiMap;
+    ///
+    /// let mut m = MultiMap::new();
+    /// m.insert_many(1, vec![3, 1, 2]);
+    /// m.insert_many(2, vec![4]);
+    /// m.insert_many(3, vec![5, 6]);
+    /// m.retain_vec(|&k, values| {
+    ///     values.sort();
+    ///     values.truncate(2);
+    ///     k != 2
+    /// });
+    /// assert_eq!(m.len(), 2);
+    /// assert_eq!(m.get_vec(&1), Some(&vec![1, 2]));
+    /// ```
+    pub fn retain_vec<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&K, &mut Vec<V>) -> bool,
+    {
+        self.inner
+            .retain(|key, values| f(key, values) && !values.is_empty());
+    }
+
+    /// Moves all keys and values from `other` into `self`, leaving `other`
+    /// empty. Values of keys present in both maps are appended to the vector
+    /// in `self`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiMap;
+    ///
+    /// let mut a = MultiMap::new();
+    /// a.insert(1, 42);
+    /// let mut b = MultiMap::new();
+    /// b.insert(1, 43);
+    /// b.insert(2, 44);
+    ///
+    /// a.append(&mut b);
+    /// assert!(b.is_empty());
+    /// assert_eq!(a.get_vec(&1), Some(&vec![42, 43]));
+    /// assert_eq!(a.get_vec(&2), Some(&vec![44]));
+    /// ```
+    pub fn append(&mut self, other: &mut MultiMap<K, V, S>) {
+        use std::collections::hash_map::Entry as HashMapEntry;
+        self.inner.reserve(other.len());
+        for (k, mut values) in other.inner.drain() {
+            match self.inner.entry(k) {
+                HashMapEntry::Occupied(entry) => entry.into_mut().append(&mut values),
+                HashMapEntry::Vacant(entry) => {
+                    entry.insert(values);
+                }
+            }
+        }
+    }
+
+    /// Merges `other` into `self`. Keys only present in `other` are moved over
+    /// as they are; for keys present in both maps, `f` decides how the vector
+    /// in `self` is combined with the one from `other`. A key is removed if its
+    /// vector is empty after `f` returns.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiMap;
+    ///
+    /// let mut a = MultiMap::new();
+    /// a.insert_many("x", vec![1, 2]);
+    /// let mut b = MultiMap::new();
+    /// b.insert_many("x", vec![2, 3]);
+    /// b.insert("y", 4);
+    ///
+    /// a.merge_with(b, |_, mine, theirs| {
+    ///     for value in theirs {
+    ///         if !mine.contains(&value) {
+    ///             mine.push(value);
+    ///         }
+    ///     }
+    /// });
+    /// assert_eq!(a.get_vec("x"), Some(&vec![1, 2, 3]));
+    /// assert_eq!(a.get_vec("y"), Some(&vec![4]));
+    /// ```
+    pub fn merge_with<F>(&mut self, other: MultiMap<K, V, S>, mut f: F)
+    where
+        F: FnMut(&K, &mut Vec<V>, Vec<V>),
+    {
+        for (k, values) in other.inner {
+            match self.inner.get_mut(&k) {
+                Some(mine) => {
+                    f(&k, mine, values);
+                    if mine.is_empty() {
+                        self.inner.remove(&k);
+                    }
+                }
+                None => {
+                    self.inner.insert(k, values);
+                }
+            }
+        }
+    }
+
+    /// Returns a map with every key-value pair contained in either map. A pair
+    /// contained several times in both maps is included as often as in the map
+    /// containing it the most.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiMap;
+    ///
+    /// let mut a = MultiMap::new();
+    /// a.insert_many(1, vec![1, 1, 2]);
+    /// let mut b = MultiMap::new();
+    /// b.insert_many(1, vec![1, 2, 3]);
+    /// b.insert(2, 4);
+    ///
+    /// let union = a.union(&b);
+    /// assert_eq!(union.get_vec(&1), Some(&vec![1, 1, 2, 3]));
+    /// assert_eq!(union.get_vec(&2), Some(&vec![4]));
+    /// ```
+    pub fn union(&self, other: &MultiMap<K, V, S>) -> MultiMap<K, V, S>
+    where
+        K: Clone,
+        V: Clone + PartialEq,
+        S: Clone,
+    {
+        self.combine(other, true, true, true)
+    }
+
+    /// Returns a map with the key-value pairs contained in both maps. A pair
+    /// contained several times in both maps is included as often as in the map
+    /// containing it the least.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiMap;
+    ///
+    /// let mut a = MultiMap::new();
+    /// a.insert_many(1, vec![1, 1, 2]);
+    /// let mut b = MultiMap::new();
+    /// b.insert_many(1, vec![1, 2, 3]);
+    /// b.insert(2, 4);
+    ///
+    /// let intersection = a.intersection(&b);
+    /// assert_eq!(intersection.get_vec(&1), Some(&vec![1, 2]));
+    /// assert!(!intersection.contains_key(&2));
+    /// ```
+    pub fn intersection(&self, other: &MultiMap<K, V, S>) -> MultiMap<K, V, S>
+    where
+        K: Clone,
+        V: Clone + PartialEq,
+        S: Clone,
+    {
+        self.combine(other, true, false, false)
+    }
+
+    /// Returns a map with the key-value pairs contained in `self` but not in
+    /// `other`. Every occurrence of a pair in `other` cancels out one occurrence
+    /// in `self`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiMap;
+    ///
+    /// let mut a = MultiMap::new();
+    /// a.insert_many(1, vec![1, 1, 2]);
+    /// let mut b = MultiMap::new();
+    /// b.insert_many(1, vec![1, 2, 3]);
+    /// b.insert(2, 4);
+    ///
+    /// let difference = a.difference(&b);
+    /// assert_eq!(difference.get_vec(&1), Some(&vec![1]));
+    /// assert_eq!(difference.len(), 1);
+    /// ```
+    pub fn difference(&self, other: &MultiMap<K, V, S>) -> MultiMap<K, V, S>
+    where
+        K: Clone,
+        V: Clone + PartialEq,
+        S: Clone,
+    {
+        self.combine(other, false, true, false)
+    }
+
+    /// Returns a map with the key-value pairs contained in exactly one of the
+    /// maps, i.e. the difference of `self` and `other` combined with the
+    /// difference of `other` and `self`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiMap;
+    ///
+    /// let mut a = MultiMap::new();
+    /// a.insert_many(1, vec![1, 1, 2]);
+    /// let mut b = MultiMap::new();
+    /// b.insert_many(1, vec![1, 2, 3]);
+    /// b.insert(2, 4);
+    ///
+    /// let symmetric_difference = a.symmetric_difference(&b);
+    /// assert_eq!(symmetric_difference.get_vec(&1), Some(&vec![1, 3]));
+    /// assert_eq!(symmetric_difference.get_vec(&2), Some(&vec![4]));
+    /// ```
+    pub fn symmetric_difference(&self, other: &MultiMap<K, V, S>) -> MultiMap<K, V, S>
+    where
+        K: Clone,
+        V: Clone + PartialEq,
+        S: Clone,
+    {
+        self.combine(other, false, true, true)
+    }
+
+    /// Swaps keys and values: every value becomes a key mapping to all keys it
+    /// was stored under. A key is repeated if it held the same value more
+    /// than once.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiMap;
+    ///
+    /// let mut authors = MultiMap::new();
+    /// authors.insert("alice", "book a");
+    /// authors.insert("alice", "book b");
+    /// authors.insert("bob", "book b");
+    ///
+    /// let mut books = authors.invert();
+    /// books.get_vec_mut("book b").unwrap().sort();
+    /// assert_eq!(books.get_vec("book a"), Some(&vec!["alice"]));
+    /// assert_eq!(books.get_vec("book b"), Some(&vec!["alice", "bob"]));
+    /// ```
+    pub fn invert(self) -> MultiMap<V, K, S>
+    where
+        K: Clone,
+        V: Eq + Hash,
+        S: Clone,
+    {
+        let mut inverted = MultiMap::with_hasher(self.inner.hasher().clone());
+        for (key, values) in self.inner {
+            for value in values {
+                inverted.insert(value, key.clone());
+            }
+        }
+        inverted
+    }
+
+    /// Returns the changes which turn `self` into `other`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiMap;
+    ///
+    /// let mut a = MultiMap::new();
+    /// a.insert(1, 42);
+    /// a.insert(2, 43);
+    /// let mut b = MultiMap::new();
+    /// b.insert(1, 42);
+    /// b.insert(1, 44);
+    ///
+    /// let diff = a.diff(&b);
+    /// assert!(diff.added.is_empty());
+    /// assert_eq!(diff.removed, [2]);
+    /// assert_eq!(diff.changed, [(1, vec![42, 44])]);
+    /// ```
+    pub fn diff(&self, other: &MultiMap<K, V, S>) -> MultiMapDiff<K, V>
+    where
+        K: Clone,
+        V: Clone + PartialEq,
+    {
+        let mut diff = MultiMapDiff::default();
+        for (key, values) in &self.inner {
+            match other.inner.get(key) {
+                None => diff.removed.push(key.clone()),
+                Some(theirs) if theirs != values => {
+                    diff.changed.push((key.clone(), theirs.clone()));
+                }
+                Some(_) => {}
+            }
+        }
+        for (key, values) in &other.inner {
+            if !self.inner.contains_key(key) {
+                diff.added.push((key.clone(), values.clone()));
+            }
+        }
+        diff
+    }
+
+    /// Applies the changes of a diff created by `diff`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiMap;
+    ///
+    /// let mut a = MultiMap::new();
+    /// a.insert(1, 42);
+    /// let mut b = MultiMap::new();
+    /// b.insert(2, 43);
+    ///
+    /// let diff = a.diff(&b);
+    /// a.apply(diff);
+    /// assert_eq!(a, b);
+    /// ```
+    pub fn apply(&mut self, diff: MultiMapDiff<K, V>) {
+        for key in diff.removed {
+            self.inner.remove(&key);
+        }
+        for (key, values) in diff.added.into_iter().chain(diff.changed) {
+            self.inner.insert(key, values);
+        }
+    }
+
+    /// Returns true if both maps have the same keys and every key holds the
+    /// same values, regardless of the order the values are stored in. Values
+    /// are compared as multisets, so duplicates have to match in number.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiMap;
+    ///
+    /// let mut a = MultiMap::new();
+    /// a.insert_many(1, vec![1, 2, 2]);
+    /// let mut b = MultiMap::new();
+    /// b.insert_many(1, vec![2, 1, 2]);
+    ///
+    /// assert_ne!(a, b);
+    /// assert!(a.eq_ignore_value_order(&b));
+    ///
+    /// b.insert(1, 1);
+    /// assert!(!a.eq_ignore_value_order(&b));
+    /// ```
+    pub fn eq_ignore_value_order(&self, other: &MultiMap<K, V, S>) -> bool
+    where
+        V: PartialEq,
+    {
+        if self.len() != other.len() {
+            return false;
+        }
+
+        self.inner.iter().all(|(key, values)| {
+            let theirs = match other.inner.get(key) {
+                Some(theirs) if theirs.len() == values.len() => theirs,
+                _ => return false,
+            };
+            let mut used = vec![false; theirs.len()];
+            values.iter().all(|value| {
+                let matched = theirs
+                    .iter()
+                    .zip(&used)
+                    .position(|(theirs, &used)| !used && theirs == value);
+                if let Some(i) = matched {
+                    used[i] = true;
+                }
+                matched.is_some()
+            })
+        })
+    }
+
+    /// Combines the values of both maps pair by pair. Each value in `self` is
+    /// matched with at most one equal value in `other`; the flags select which
+    /// of the matched and unmatched values end up in the result.
+    fn combine(
+        &self,
+        other: &MultiMap<K, V, S>,
+        keep_matched: bool,
+        keep_unmatched: bool,
+        keep_other_unmatched: bool,
+    ) -> MultiMap<K, V, S>
+    where
+        K: Clone,
+        V: Clone + PartialEq,
+        S: Clone,
+    {
+        let mut result = MultiMap::with_hasher(self.inner.hasher().clone());
+        for (key, values) in &self.inner {
+            let theirs = other.inner.get(key).map_or(&[][..], |v| &v[..]);
+            let mut used = vec![false; theirs.len()];
+            let mut kept = Vec::new();
+            for value in values {
+                let matched = theirs
+                    .iter()
+                    .zip(&used)
+                    .position(|(theirs, &used)| !used && theirs == value);
+                if let Some(i) = matched {
+                    used[i] = true;
+                }
+                if matched.map_or(keep_unmatched, |_| keep_matched) {
+                    kept.push(value.clone());
+                }
+            }
+            if keep_other_unmatched {
+                kept.extend(
+                    theirs
+                        .iter()
+                        .zip(&used)
+                        .filter(|&(_, &used)| !used)
+                        .map(|(value, _)| value.clone()),
+                );
+            }
+            if !kept.is_empty() {
+                result.inner.insert(key.clone(), kept);
+            }
+        }
+        if keep_other_unmatched {
+            for (key, values) in &other.inner {
+                if !values.is_empty() && !self.inner.contains_key(key) {
+                    result.inner.insert(key.clone(), values.clone());
+                }
+            }
+        }
+        result
+    }
+
+    /// Removes all key-value pairs for which the predicate returns true and
+    /// returns them. Keys whose vector becomes empty are removed.
+    ///
+    /// Unlike `HashMap::extract_if` this is eager: every matching pair is
+    /// removed before the method returns, and each returned pair holds a clone
+    /// of its key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiMap;
+    ///
+    /// let mut m = MultiMap::new();
+    /// m.insert_many(1, vec![1, 2, 3]);
+    /// m.insert_many(2, vec![4]);
+    ///
+    /// let mut even = m.remove_values_if(|_, &v| v % 2 == 0);
+    /// even.sort();
+    /// assert_eq!(even, [(1, 2), (2, 4)]);
+    /// assert_eq!(m.get_vec(&1), Some(&vec![1, 3]));
+    /// assert!(!m.contains_key(&2));
+    /// ```
+    pub fn remove_values_if<F>(&mut self, mut pred: F) -> Vec<(K, V)>
+    where
+        K: Clone,
+        F: FnMut(&K, &V) -> bool,
+    {
+        let mut removed = Vec::new();
+        self.inner.retain(|key, values| {
+            for value in ::std::mem::take(values) {
+                if pred(key, &value) {
+                    removed.push((key.clone(), value));
+                } else {
+                    values.push(value);
+                }
+            }
+            !values.is_empty()
+        });
+        removed
+    }
+}
+
+impl<K, V, C> MultiMap<K, V, RandomState, C>
+where
+    K: Eq + Hash,
+    C: Container<V>,
+{
+    /// Creates an empty MultiMap which stores the values of each key in the
+    /// container `C` instead of a `Vec`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiMap;
+    /// use std::collections::hash_map::RandomState;
+    /// use std::collections::VecDeque;
+    ///
+    /// let mut map: MultiMap<&str, isize, RandomState, VecDeque<isize>> = MultiMap::with_container();
+    /// map.insert("key", 42);
+    /// assert_eq!(map.remove("key"), Some(VecDeque::from(vec![42])));
+    /// ```
+    pub fn with_container() -> MultiMap<K, V, RandomState, C> {
+        MultiMap {
+            inner: HashMap::new(),
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<K, V, S, C> MultiMap<K, V, S, C>
+where
+    K: Eq + Hash,
+    S: BuildHasher,
+    C: Container<V>,
+{
+    /// Creates an empty MultiMap which stores the values of each key in the
+    /// container `C` and uses the given hash builder to hash keys.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiMap;
+    /// use std::collections::hash_map::RandomState;
+    /// use std::collections::BTreeSet;
+    ///
+    /// let s = RandomState::new();
+    /// let mut map: MultiMap<&str, isize, RandomState, BTreeSet<isize>> =
+    ///     MultiMap::with_container_and_hasher(s);
+    /// map.insert("key", 42);
+    /// map.insert("key", 42);
+    /// assert_eq!(map.remove("key").map(|s| s.len()), Some(1));
+    /// ```
+    pub fn with_container_and_hasher(hash_builder: S) -> MultiMap<K, V, S, C> {
+        MultiMap {
+            inner: HashMap::with_hasher(hash_builder),
+            marker: PhantomData,
+        }
+    }
+
+    /// Inserts a key-value pair into the multimap. If the key does exist in
+    /// the map then the value is pushed to that key's vector. If the key doesn't
+    /// exist in the map a new vector with the given value is inserted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiMap;
+    ///
+    /// let mut map = MultiMap::new();
+    /// map.insert("key", 42);
+    /// ```
+    pub fn insert(&mut self, k: K, v: V) {
+        use std::collections::hash_map::Entry as HashMapEntry;
+        match self.inner.entry(k) {
+            HashMapEntry::Occupied(mut entry) => {
+                entry.get_mut().push(v);
+            }
+            HashMapEntry::Vacant(entry) => {
+                entry.insert(C::from_values(Some(v)));
+            }
+        }
+    }
+
+    /// Inserts a key-value pair into the multimap unless an equal value is
+    /// already stored under the key. Returns true if the value was inserted.
+    ///
+    /// The key is only hashed once.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiMap;
+    ///
+    /// let mut map = MultiMap::new();
+    /// assert!(map.insert_unique("key", 42));
+    /// assert!(!map.insert_unique("key", 42));
+    /// assert!(map.insert_unique("key", 43));
+    /// assert_eq!(map.get_vec("key"), Some(&vec![42, 43]));
+    /// ```
+    pub fn insert_unique(&mut self, k: K, v: V) -> bool
+    where
+        V: PartialEq,
+    {
+        use std::collections::hash_map::Entry as HashMapEntry;
+        match self.inner.entry(k) {
+            HashMapEntry::Occupied(mut entry) => {
+                let values = entry.get_mut();
+                if values.iter().any(|value| *value == v) {
+                    return false;
+                }
+                values.push(v);
+            }
+            HashMapEntry::Vacant(entry) => {
+                entry.insert(C::from_values(Some(v)));
+            }
+        }
+        true
+    }
+
+    /// Inserts multiple key-value pairs into the multimap. If the key does exist in
+    /// the map then the values are extended into that key's vector. If the key
+    /// doesn't exist in the map a new vector collected from the given values is inserted.
+    ///
+    /// This may be more efficient than inserting values independently.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiMap;
+    ///
+    /// let mut map = MultiMap::<&str, &usize>::new();
+    /// map.insert_many("key", &[42, 43]);
+    /// ```
+    pub fn insert_many<I: IntoIterator<Item = V>>(&mut self, k: K, v: I) {
+        use std::collections::hash_map::Entry as HashMapEntry;
+        match self.inner.entry(k) {
+            HashMapEntry::Occupied(mut entry) => {
+                entry.get_mut().extend(v);
+            }
+            HashMapEntry::Vacant(entry) => {
+                entry.insert(C::from_values(v));
+            }
+        }
+    }
+
+    /// Returns true if the map contains a value for the specified key.
+    ///
+    /// The key may be any borrowed form of the map's key type, but Hash and Eq
+    /// on the borrowed form must match those for the key type.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiMap;
+    ///
+    /// let mut map = MultiMap::new();
+    /// map.insert(1, 42);
+    /// assert_eq!(map.contains_key(&1), true);
+    /// assert_eq!(map.contains_key(&2), false);
+    /// ```
+    pub fn contains_key<Q>(&self, k: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        self.inner.contains_key(k)
+    }
+
+    /// Returns true if the specified key holds the given value.
+    ///
+    /// The key may be any borrowed form of the map's key type, but Hash and Eq
+    /// on the borrowed form must match those for the key type.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiMap;
+    ///
+    /// let mut map = MultiMap::new();
+    /// map.insert(1, 42);
+    /// map.insert(1, 1337);
+    /// assert_eq!(map.contains_pair(&1, &1337), true);
+    /// assert_eq!(map.contains_pair(&1, &7), false);
+    /// assert_eq!(map.contains_pair(&2, &42), false);
+    /// ```
+    pub fn contains_pair<Q>(&self, k: &Q, v: &V) -> bool
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+        V: PartialEq,
+    {
+        self.inner
+            .get(k)
+            .is_some_and(|values| values.iter().any(|value| value == v))
+    }
+
+    /// Returns true if any key in the map holds the given value.
+    ///
+    /// This has to look through the values of every key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiMap;
+    ///
+    /// let mut map = MultiMap::new();
+    /// map.insert(1, 42);
+    /// map.insert(2, 1337);
+    /// assert_eq!(map.contains_value(&1337), true);
+    /// assert_eq!(map.contains_value(&7), false);
+    /// ```
+    pub fn contains_value(&self, v: &V) -> bool
+    where
+        V: PartialEq,
+    {
+        self.inner
+            .values()
+            .any(|values| values.iter().any(|value| value == v))
+    }
+
+    /// Returns the number of elements in the map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiMap;
+    ///
+    /// let mut map = MultiMap::new();
+    /// map.insert(1, 42);
+    /// map.insert(2, 1337);
+    /// assert_eq!(map.len(), 2);
+    /// ```
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// Returns the number of distinct keys in the map. This is the same as
+    /// `len`, spelled out for symmetry with `total_len`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiMap;
+    ///
+    /// let mut map = MultiMap::new();
+    /// map.insert(1, 42);
+    /// map.insert(1, 1337);
+    /// assert_eq!(map.keys_len(), 1);
+    /// ```
+    pub fn keys_len(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// Returns the number of (key, value) pairs in the map.
+    ///
+    /// Unlike `len`, this takes time linear in the number of keys. The map
+    /// deliberately keeps no cached count: `get_vec_mut`, `iter_all_mut`,
+    /// `iter_groups_mut` and the entry API hand out mutable references to the
+    /// vectors, whose length can change without the map noticing. Keeping a
+    /// count up to date would mean wrapping all of them in guard types, which
+    /// would break every caller of those methods.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiMap;
+    ///
+    /// let mut map = MultiMap::new();
+    /// map.insert(1, 42);
+    /// map.insert(1, 1337);
+    /// map.insert(2, 7);
+    /// assert_eq!(map.total_len(), 3);
+    /// ```
+    pub fn total_len(&self) -> usize {
+        self.inner.values().map(Container::len).sum()
+    }
+
+    /// Removes a key from the map, returning the vector of values at
+    /// the key if the key was previously in the map.
+    ///
+    /// The key may be any borrowed form of the map's key type, but Hash and Eq
+    /// on the borrowed form must match those for the key type.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiMap;
+    ///
+    /// let mut map = MultiMap::new();
+    /// map.insert(1, 42);
+    /// map.insert(1, 1337);
+    /// assert_eq!(map.remove(&1), Some(vec![42, 1337]));
+    /// assert_eq!(map.remove(&1), None);
+    /// ```
+    pub fn remove<Q>(&mut self, k: &Q) -> Option<C>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        self.inner.remove(k)
+    }
+
+    /// Returns the values corresponding to the key as a slice, which is empty
+    /// if the key is not in the map. Works with any container which derefs
+    /// to a slice, e.g. `SmallVec`, so keys with a single value can be read
+    /// without ever allocating a `Vec`.
+    ///
+    /// The key may be any borrowed form of the map's key type, but Hash and Eq
+    /// on the borrowed form must match those for the key type.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiMap;
+    ///
+    /// let mut map = MultiMap::new();
+    /// map.insert(1, 42);
+    /// map.insert(1, 1337);
+    /// assert_eq!(map.values_of(&1), [42, 1337]);
+    /// assert!(map.values_of(&2).is_empty());
+    /// ```
+    pub fn values_of<Q>(&self, k: &Q) -> &[V]
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+        C: AsRef<[V]>,
+    {
+        self.inner.get(k).map_or(&[], |values| values.as_ref())
+    }
+
+    /// Returns a reference to the first item in the vector corresponding to
+    /// the key.
+    ///
+    /// The key may be any borrowed form of the map's key type, but Hash and Eq
+    /// on the borrowed form must match those for the key type.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiMap;
+    ///
+    /// let mut map = MultiMap::new();
+    /// map.insert(1, 42);
+    /// map.insert(1, 1337);
+    /// assert_eq!(map.get(&1), Some(&42));
+    /// ```
+    pub fn get<Q>(&self, k: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        self.inner.get(k)?.iter().next()
+    }
+
+    /// Returns true if the key is multi-valued.
+    ///
+    /// The key may be any borrowed form of the map's key type, but Hash and Eq
+    /// on the borrowed form must match those for the key type.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiMap;
+    ///
+    /// let mut map = MultiMap::new();
+    /// map.insert(1, 42);
+    /// map.insert(1, 1337);
+    /// map.insert(2, 2332);
+    ///
+    /// assert_eq!(map.is_vec(&1), true);   // key is multi-valued
+    /// assert_eq!(map.is_vec(&2), false);  // key is single-valued
+    /// assert_eq!(map.is_vec(&3), false);  // key not in map
+    /// ```
+    pub fn is_vec<Q>(&self, k: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        match self.inner.get(k) {
+            Some(val) => val.len() > 1,
+            None => false,
+        }
+    }
+
+    /// Returns the number of elements the map can hold without reallocating.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiMap;
+    ///
+    /// let map: MultiMap<usize, usize> = MultiMap::new();
+    /// assert!(map.capacity() >= 0);
+    /// ```
+    pub fn capacity(&self) -> usize {
+        self.inner.capacity()
+    }
+
+    /// Returns a reference to the map's `BuildHasher`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiMap;
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let map: MultiMap<usize, usize> = MultiMap::with_hasher(RandomState::new());
+    /// let hasher: &RandomState = map.hasher();
+    /// ```
+    pub fn hasher(&self) -> &S {
+        self.inner.hasher()
+    }
+
+    /// Creates a raw entry builder for looking up keys with a precomputed hash,
+    /// e.g. one from `hasher().hash_one(&key)`. See hashbrown's
+    /// `HashMap::raw_entry` for details.
+    ///
+    /// Only available with the `raw-entry` feature on the hashbrown backend,
+    /// i.e. without the `std` feature.
+    #[cfg(all(feature = "raw-entry", not(feature = "std")))]
+    pub fn raw_entry(&self) -> ::hashbrown::hash_map::RawEntryBuilder<'_, K, C, S> {
+        self.inner.raw_entry()
+    }
+
+    /// Creates a raw entry builder for looking up and inserting keys with a
+    /// precomputed hash and a custom equality check. See hashbrown's
+    /// `HashMap::raw_entry_mut` for details.
+    ///
+    /// Only available with the `raw-entry` feature on the hashbrown backend,
+    /// i.e. without the `std` feature.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[cfg(not(feature = "std"))]
+    /// # {
+    /// use multimap::MultiMap;
+    /// use std::hash::BuildHasher;
+    ///
+    /// let mut map: MultiMap<&str, usize> = MultiMap::with_hasher(Default::default());
+    /// let hash = map.hasher().hash_one("key");
+    ///
+    /// map.raw_entry_mut()
+    ///     .from_key_hashed_nocheck(hash, "key")
+    ///     .or_insert("key", vec![])
+    ///     .1
+    ///     .push(42);
+    /// assert_eq!(map.get_vec("key"), Some(&vec![42]));
+    /// # }
+    /// ```
+    #[cfg(all(feature = "raw-entry", not(feature = "std")))]
+    pub fn raw_entry_mut(&mut self) -> ::hashbrown::hash_map::RawEntryBuilderMut<'_, K, C, S> {
+        self.inner.raw_entry_mut()
+    }
+
+    /// Returns true if the map contains a value for the specified key, looking
+    /// it up through the `Equivalent` trait instead of `Borrow`.
+    ///
+    /// Only available on the hashbrown backend, i.e. without the `std`
+    /// feature.
+    #[cfg(not(feature = "std"))]
+    pub fn contains_key_equivalent<Q>(&self, k: &Q) -> bool
+    where
+        Q: ?Sized + Hash + Equivalent<K>,
+    {
+        self.inner.contains_key(k)
+    }
+
+    /// Returns a reference to the first item in the container corresponding
+    /// to the key, looking it up through the `Equivalent` trait instead of
+    /// `Borrow`.
+    ///
+    /// Only available on the hashbrown backend, i.e. without the `std`
+    /// feature.
+    #[cfg(not(feature = "std"))]
+    pub fn get_equivalent<Q>(&self, k: &Q) -> Option<&V>
+    where
+        Q: ?Sized + Hash + Equivalent<K>,
+    {
+        self.inner.get(k)?.iter().next()
+    }
+
+    /// Removes a key from the map, returning its values if the key was in the
+    /// map, looking it up through the `Equivalent` trait instead of `Borrow`.
+    ///
+    /// Only available on the hashbrown backend, i.e. without the `std`
+    /// feature.
+    #[cfg(not(feature = "std"))]
+    pub fn remove_equivalent<Q>(&mut self, k: &Q) -> Option<C>
+    where
+        Q: ?Sized + Hash + Equivalent<K>,
+    {
+        self.inner.remove(k)
+    }
+
+    /// Tries to reserve capacity for at least `additional` more keys, returning
+    /// an error instead of aborting if the allocation fails.
+    ///
+    /// Only available with the `std` feature, see `try_insert`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiMap;
+    ///
+    /// let mut map: MultiMap<usize, usize> = MultiMap::new();
+    /// map.try_reserve(10).expect("out of memory");
+    /// assert!(map.capacity() >= 10);
+    /// assert!(map.try_reserve(usize::MAX).is_err());
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn try_reserve(&mut self, additional: usize) -> Result<(), TryReserveError> {
+        self.inner.try_reserve(additional)
+    }
+
+    /// Returns true if the map contains no elements.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiMap;
+    ///
+    /// let mut map = MultiMap::new();
+    /// assert!(map.is_empty());
+    /// map.insert(1,42);
+    /// assert!(!map.is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    /// Returns true if the map holds no values, even if it still contains
+    /// keys whose vectors are empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiMap;
+    ///
+    /// let mut map = MultiMap::new();
+    /// map.insert(1, 42);
+    /// map.get_vec_mut(&1).unwrap().clear();
+    ///
+    /// assert!(!map.is_empty());
+    /// assert!(map.is_effectively_empty());
+    /// ```
+    pub fn is_effectively_empty(&self) -> bool {
+        self.inner.values().all(Container::is_empty)
+    }
+
+    /// Returns the number of keys which hold at least one value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiMap;
+    ///
+    /// let mut map = MultiMap::new();
+    /// map.insert(1, 42);
+    /// map.insert(2, 1337);
+    /// map.get_vec_mut(&1).unwrap().clear();
+    ///
+    /// assert_eq!(map.len(), 2);
+    /// assert_eq!(map.non_empty_len(), 1);
+    /// ```
+    pub fn non_empty_len(&self) -> usize {
+        self.inner
+            .values()
+            .filter(|values| !values.is_empty())
+            .count()
+    }
+
+    /// Removes all keys without any values, returning how many were removed.
+    ///
+    /// Keys can be left without values through e.g. `get_vec_mut` or
+    /// `drain_vec`. Such keys still count towards `len` and are visited by
+    /// `iter_all`, which this method can be used to avoid.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiMap;
+    ///
+    /// let mut map = MultiMap::new();
+    /// map.insert(1, 42);
+    /// map.insert(2, 1337);
+    /// map.get_vec_mut(&1).unwrap().pop();
+    ///
+    /// assert_eq!(map.prune_empty(), 1);
+    /// assert!(!map.contains_key(&1));
+    /// assert_eq!(map.len(), 1);
+    /// ```
+    pub fn prune_empty(&mut self) -> usize {
+        let before = self.inner.len();
+        self.inner.retain(|_, values| !values.is_empty());
+        before - self.inner.len()
+    }
+
+    /// Clears the map, removing all key-value pairs.
+    /// Keeps the allocated memory for reuse.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiMap;
+    ///
+    /// let mut map = MultiMap::new();
+    /// map.insert(1,42);
+    /// map.clear();
+    /// assert!(map.is_empty());
+    /// ```
+    pub fn clear(&mut self) {
+        self.inner.clear();
+    }
+
+    /// Clears the map, returning all keys and their vectors as an iterator.
+    /// Keeps the allocated memory for reuse.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiMap;
+    ///
+    /// let mut map = MultiMap::new();
+    /// map.insert(1, 42);
+    /// map.insert(1, 1337);
+    /// map.insert(2, 2332);
+    ///
+    /// let mut drained: Vec<_> = map.drain().collect();
+    /// drained.sort();
+    /// assert_eq!(drained, [(1, vec![42, 1337]), (2, vec![2332])]);
+    /// assert!(map.is_empty());
+    /// ```
+    pub fn drain(&mut self) -> Drain<'_, K, C> {
+        self.inner.drain()
+    }
+
+    /// An iterator visiting all keys in arbitrary order.
+    /// Iterator element type is &'a K.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiMap;
+    ///
+    /// let mut map = MultiMap::new();
+    /// map.insert(1,42);
+    /// map.insert(1,1337);
+    /// map.insert(2,1337);
+    /// map.insert(4,1991);
+    ///
+    /// let mut keys: Vec<_> = map.keys().collect();
+    /// keys.sort();
+    /// assert_eq!(keys, [&1, &2, &4]);
+    /// ```
+    pub fn keys(&'_ self) -> Keys<'_, K, C> {
+        self.inner.keys()
+    }
+
+    /// Consumes the map, returning an iterator over its keys in arbitrary
+    /// order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiMap;
+    ///
+    /// let mut map = MultiMap::new();
+    /// map.insert(1,42);
+    /// map.insert(1,1337);
+    /// map.insert(2,1337);
+    ///
+    /// let mut keys: Vec<_> = map.into_keys().collect();
+    /// keys.sort();
+    /// assert_eq!(keys, [1, 2]);
+    /// ```
+    pub fn into_keys(self) -> IntoKeys<K, C> {
+        self.inner.into_keys()
+    }
+
+    /// Consumes the map, returning an iterator over the values (vector) of
+    /// every key in arbitrary order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiMap;
+    ///
+    /// let mut map = MultiMap::new();
+    /// map.insert(1,42);
+    /// map.insert(1,1337);
+    /// map.insert(2,7);
+    ///
+    /// let mut values: Vec<_> = map.into_values().collect();
+    /// values.sort();
+    /// assert_eq!(values, [vec![7], vec![42, 1337]]);
+    /// ```
+    pub fn into_values(self) -> IntoValues<K, C> {
+        self.inner.into_values()
+    }
+
+    /// Consumes the map, returning the underlying map of keys to their
+    /// containers.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiMap;
+    ///
+    /// let mut map = MultiMap::new();
+    /// map.insert_many(1, vec![42, 1337]);
+    ///
+    /// let hash_map = map.into_hashmap();
+    /// assert_eq!(hash_map[&1], [42, 1337]);
+    /// ```
+    pub fn into_hashmap(self) -> HashMap<K, C, S> {
+        self.inner
+    }
+
+    /// Returns a copy of the map as a map of keys to their containers.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiMap;
+    ///
+    /// let mut map = MultiMap::new();
+    /// map.insert_many(1, vec![42, 1337]);
+    ///
+    /// let hash_map = map.to_hashmap();
+    /// assert_eq!(hash_map[&1], [42, 1337]);
+    /// assert_eq!(map, hash_map);
+    /// ```
+    pub fn to_hashmap(&self) -> HashMap<K, C, S>
+    where
+        K: Clone,
+        C: Clone,
+        S: Clone,
+    {
+        self.inner.clone()
+    }
+
+    /// Consumes the map, returning a map of keys to their containers sorted
+    /// by key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiMap;
+    ///
+    /// let mut map = MultiMap::new();
+    /// map.insert(2, 7);
+    /// map.insert_many(1, vec![42, 1337]);
+    ///
+    /// let btree_map = map.into_btreemap();
+    /// assert_eq!(btree_map.into_iter().collect::<Vec<_>>(), [(1, vec![42, 1337]), (2, vec![7])]);
+    /// ```
+    pub fn into_btreemap(self) -> BTreeMap<K, C>
+    where
+        K: Ord,
+    {
+        self.inner.into_iter().collect()
+    }
+
+    /// Returns a copy of the map as a map of keys to their containers sorted
+    /// by key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiMap;
+    ///
+    /// let mut map = MultiMap::new();
+    /// map.insert(2, 7);
+    /// map.insert_many(1, vec![42, 1337]);
+    ///
+    /// let btree_map = map.to_btreemap();
+    /// assert_eq!(btree_map.keys().collect::<Vec<_>>(), [&1, &2]);
+    /// assert_eq!(btree_map[&1], [42, 1337]);
+    /// ```
+    pub fn to_btreemap(&self) -> BTreeMap<K, C>
+    where
+        K: Ord + Clone,
+        C: Clone,
+    {
+        self.inner
+            .iter()
+            .map(|(key, values)| (key.clone(), values.clone()))
+            .collect()
+    }
+
+    /// An iterator visiting all key-value pairs in arbitrary order. The iterator returns
+    /// a reference to the key and the corresponding key's vector.
+    /// Iterator element type is (&'a K, &'a V).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiMap;
+    ///
+    /// let mut map = MultiMap::new();
+    /// map.insert(1,42);
+    /// map.insert(1,1337);
+    /// map.insert(3,2332);
+    /// map.insert(4,1991);
+    ///
+    /// let mut pairs: Vec<_> = map.iter_all().collect();
+    /// pairs.sort_by_key(|p| p.0);
+    /// assert_eq!(pairs, [(&1, &vec![42, 1337]), (&3, &vec![2332]), (&4, &vec![1991])]);
+    /// ```
+    pub fn iter_all(&self) -> IterAll<'_, K, C> {
+        self.inner.iter()
+    }
+
+    /// An iterator visiting all key-value pairs in arbitrary order. The iterator returns
+    /// a reference to the key and the corresponding key's vector.
+    /// Iterator element type is (&'a K, &'a V).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiMap;
+    ///
+    /// let mut map = MultiMap::new();
+    /// map.insert(1,42);
+    /// map.insert(1,1337);
+    /// map.insert(3,2332);
+    /// map.insert(4,1991);
+    ///
+    /// for (key, values) in map.iter_all_mut() {
+    ///     for value in values.iter_mut() {
+    ///         *value = 99;
+    ///     }
+    /// }
+    ///
+    /// let mut pairs: Vec<_> = map.iter_all_mut().collect();
+    /// pairs.sort_by_key(|p| p.0);
+    /// assert_eq!(pairs, [(&1, &mut vec![99, 99]), (&3, &mut vec![99]), (&4, &mut vec![99])]);
+    /// ```
+    pub fn iter_all_mut(&mut self) -> IterAllMut<'_, K, C> {
+        self.inner.iter_mut()
+    }
+
+    /// An iterator visiting all key-value pairs in arbitrary order. The iterator returns
+    /// a reference to the key and the first element in the corresponding key's vector.
+    /// Iterator element type is (&'a K, &'a V).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiMap;
+    ///
+    /// let mut map = MultiMap::new();
+    /// map.insert(1,42);
+    /// map.insert(1,1337);
+    /// map.insert(3,2332);
+    /// map.insert(4,1991);
+    ///
+    /// for (key, value) in map.flat_iter() {
+    ///     println!("key: {:?}, val: {:?}", key, value);
+    /// }
+    /// ```
+    pub fn flat_iter(&self) -> impl FusedIterator<Item = (&K, &V)> {
+        self.iter_all()
+            .flat_map(|(k, v)| v.iter().map(move |i| (k, i)))
+    }
+
+    /// An iterator visiting every value in the map, regardless of key. Keys are
+    /// visited in arbitrary order, values of a key in insertion order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiMap;
+    ///
+    /// let mut map = MultiMap::new();
+    /// map.insert(1,42);
+    /// map.insert(1,1337);
+    /// map.insert(3,2332);
+    ///
+    /// assert_eq!(map.flat_values().sum::<i32>(), 3711);
+    /// ```
+    pub fn flat_values(&self) -> impl FusedIterator<Item = &V> {
+        self.inner.values().flat_map(|values| values.iter())
+    }
+
+    /// An iterator visiting all keys in sorted order, together with their
+    /// values. The keys are collected and sorted up front.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiMap;
+    ///
+    /// let mut map = MultiMap::new();
+    /// map.insert(3,2332);
+    /// map.insert(1,42);
+    /// map.insert(1,1337);
+    ///
+    /// let pairs: Vec<_> = map.iter_all_sorted().collect();
+    /// assert_eq!(pairs, [(&1, &vec![42, 1337]), (&3, &vec![2332])]);
+    /// ```
+    pub fn iter_all_sorted(&self) -> ::std::vec::IntoIter<(&K, &C)>
+    where
+        K: Ord,
+    {
+        let mut pairs: Vec<_> = self.inner.iter().collect();
+        pairs.sort_unstable_by(|a, b| a.0.cmp(b.0));
+        pairs.into_iter()
+    }
+
+    /// An iterator visiting all keys in sorted order, together with the first
+    /// value of each key, like `iter`. Keys without values are skipped. The
+    /// keys are collected and sorted up front.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiMap;
+    ///
+    /// let mut map = MultiMap::new();
+    /// map.insert(3,2332);
+    /// map.insert(1,42);
+    /// map.insert(1,1337);
+    ///
+    /// let pairs: Vec<_> = map.iter_sorted().collect();
+    /// assert_eq!(pairs, [(&1, &42), (&3, &2332)]);
+    /// ```
+    pub fn iter_sorted(&self) -> ::std::vec::IntoIter<(&K, &V)>
+    where
+        K: Ord,
+    {
+        let mut pairs: Vec<_> = self
+            .inner
+            .iter()
+            .filter_map(|(key, values)| values.iter().next().map(|value| (key, value)))
+            .collect();
+        pairs.sort_unstable_by(|a, b| a.0.cmp(b.0));
+        pairs.into_iter()
+    }
+
+    /// An iterator visiting all keys in arbitrary order, together with the
+    /// number of values stored under each key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiMap;
+    ///
+    /// let mut map = MultiMap::new();
+    /// map.insert(1, 42);
+    /// map.insert(1, 1337);
+    /// map.insert(2, 7);
+    ///
+    /// let mut counts: Vec<_> = map.counts().collect();
+    /// counts.sort();
+    /// assert_eq!(counts, [(&1, 2), (&2, 1)]);
+    /// ```
+    pub fn counts(&self) -> impl Iterator<Item = (&K, usize)> {
+        self.iter_all().map(|(k, v)| (k, v.len()))
+    }
+
+    /// Returns how many keys hold each number of values, ordered by the
+    /// number of values.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiMap;
+    ///
+    /// let mut map = MultiMap::new();
+    /// map.insert_many(1, vec![1, 2]);
+    /// map.insert_many(2, vec![3, 4]);
+    /// map.insert(3, 5);
+    ///
+    /// let histogram = map.values_histogram();
+    /// assert_eq!(histogram.into_iter().collect::<Vec<_>>(), [(1, 1), (2, 2)]);
+    /// ```
+    pub fn values_histogram(&self) -> BTreeMap<usize, usize> {
+        let mut histogram = BTreeMap::new();
+        for (_, count) in self.counts() {
+            *histogram.entry(count).or_insert(0) += 1;
+        }
+        histogram
+    }
+
+    /// Returns the `k` keys with the most values together with their number of
+    /// values, ordered from most to fewest values. Ties are broken
+    /// arbitrarily.
+    ///
+    /// Only `k` keys are kept in memory while looking through the map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiMap;
+    ///
+    /// let mut map = MultiMap::new();
+    /// map.insert_many("a", vec![1]);
+    /// map.insert_many("b", vec![1, 2, 3]);
+    /// map.insert_many("c", vec![1, 2]);
+    ///
+    /// assert_eq!(map.top_keys_by_count(2), [(&"b", 3), (&"c", 2)]);
+    /// ```
+    pub fn top_keys_by_count(&self, k: usize) -> Vec<(&K, usize)> {
+        if k == 0 {
+            return Vec::new();
+        }
+
+        let mut heap = BinaryHeap::with_capacity(k + 1);
+        for (key, count) in self.counts() {
+            heap.push(Reverse(ByCount(count, key)));
+            if heap.len() > k {
+                heap.pop();
+            }
+        }
+
+        heap.into_sorted_vec()
+            .into_iter()
+            .map(|Reverse(ByCount(count, key))| (key, count))
+            .collect()
+    }
+
+    /// Retains only the elements specified by the predicate.
+    ///
+    /// In other words, remove all pairs `(k, v)` such that `f(&k,&mut v)` returns `false`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiMap;
+    ///
+    /// let mut m = MultiMap::new();
+    /// m.insert(1, 42);
+    /// m.insert(1, 99);
+    /// m.insert(2, 42);
+    /// m.retain(|&k, &v| { k == 1 && v == 42 });
+    /// assert_eq!(1, m.len());
+    /// assert_eq!(Some(&42), m.get(&1));
+    /// ```
+    pub fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&K, &V) -> bool,
+    {
+        for (key, vector) in &mut self.inner {
+            vector.retain(|value| f(key, value));
+        }
+        self.inner.retain(|_, v| !v.is_empty());
+    }
+
+    /// Retains only the elements specified by the predicate, like `retain`, and
+    /// returns the number of key-value pairs which were removed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiMap;
+    ///
+    /// let mut m = MultiMap::new();
+    /// m.insert(1, 42);
+    /// m.insert(1, 99);
+    /// m.insert(2, 42);
+    /// assert_eq!(m.retain_count(|_, &v| v == 42), 1);
+    /// assert_eq!(m.retain_count(|&k, _| k == 1), 1);
+    /// assert_eq!(m.len(), 1);
+    /// ```
+    pub fn retain_count<F>(&mut self, mut f: F) -> usize
+    where
+        F: FnMut(&K, &V) -> bool,
+    {
+        let mut removed = 0;
+        for (key, vector) in &mut self.inner {
+            let before = vector.len();
+            vector.retain(|value| f(key, value));
+            removed += before - vector.len();
+        }
+        self.inner.retain(|_, v| !v.is_empty());
+        removed
+    }
+
+    /// Splits the map in two by key. The first map contains the keys for which
+    /// the predicate returns true, the second map all other keys. The vectors
+    /// are moved, but every key is hashed again.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiMap;
+    ///
+    /// let mut m = MultiMap::new();
+    /// m.insert(1, 42);
+    /// m.insert(1, 43);
+    /// m.insert(2, 44);
+    ///
+    /// let (odd, even) = m.partition(|&k| k % 2 == 1);
+    /// assert_eq!(odd.get_vec(&1), Some(&vec![42, 43]));
+    /// assert_eq!(even.get_vec(&2), Some(&vec![44]));
+    /// assert_eq!((odd.len(), even.len()), (1, 1));
+    /// ```
+    pub fn partition<F>(self, mut f: F) -> (Self, Self)
+    where
+        F: FnMut(&K) -> bool,
+        S: Clone,
+    {
+        let mut matching = MultiMap::with_container_and_hasher(self.inner.hasher().clone());
+        let mut other = MultiMap::with_container_and_hasher(self.inner.hasher().clone());
+        for (key, values) in self.inner {
+            if f(&key) {
+                matching.inner.insert(key, values);
+            } else {
+                other.inner.insert(key, values);
+            }
+        }
+        (matching, other)
+    }
+}
+
+/// Indexing returns the first value of the key and panics if the key is not
+/// in the map or holds no values. Use `values_of` to get all of them.
+impl<K, V, S, Q> Index<&Q> for MultiMap<K, V, S>
+where
+    K: Eq + Hash + Borrow<Q>,
+    Q: ?Sized + Eq + Hash,
+    S: BuildHasher,
+{
+    type Output = V;
+
+    fn index(&self, index: &Q) -> &V {
+        self.inner
+            .get(index)
+            .expect("no entry found for key")
+            .first()
+            .expect("no value found for key")
+    }
+}
+
+impl<K, V, S, C> Debug for MultiMap<K, V, S, C>
+where
+    K: Eq + Hash + Debug,
+    S: BuildHasher,
+    C: Container<V> + Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_map().entries(self.iter_all()).finish()
+    }
+}
+
+impl<K, V, S, C> PartialEq for MultiMap<K, V, S, C>
+where
+    K: Eq + Hash,
+    S: BuildHasher,
+    C: Container<V> + PartialEq,
+{
+    fn eq(&self, other: &MultiMap<K, V, S, C>) -> bool {
+        if self.len() != other.len() {
+            return false;
+        }
+
+        self.iter_all()
+            .all(|(key, value)| other.inner.get(key).is_some_and(|v| *value == *v))
+    }
+}
+
+impl<K, V, S, S2, C> PartialEq<HashMap<K, C, S2>> for MultiMap<K, V, S, C>
+where
+    K: Eq + Hash,
+    S: BuildHasher,
+    S2: BuildHasher,
+    C: Container<V> + PartialEq,
+{
+    fn eq(&self, other: &HashMap<K, C, S2>) -> bool {
+        if self.len() != other.len() {
+            return false;
+        }
+
+        self.iter_all()
+            .all(|(key, value)| other.get(key).is_some_and(|v| *value == *v))
+    }
+}
+
+impl<K, V, S, S2, C> PartialEq<MultiMap<K, V, S, C>> for HashMap<K, C, S2>
+where
+    K: Eq + Hash,
+    S: BuildHasher,
+    S2: BuildHasher,
+    C: Container<V> + PartialEq,
+{
+    fn eq(&self, other: &MultiMap<K, V, S, C>) -> bool {
+        *other == *self
+    }
+}
+
+impl<K, V, S, C> Eq for MultiMap<K, V, S, C>
+where
+    K: Eq + Hash,
+    S: BuildHasher,
+    C: Container<V> + Eq,
+{
+}
+
+/// Hashes the map independently of the order its keys are stored in, so
+/// maps which compare equal hash equally even if their keys were inserted
+/// in a different order or they use different hasher instances. The values
+/// of each key are hashed in order, matching `PartialEq`.
+///
+/// Every key and its values are hashed on their own with std's
+/// `DefaultHasher` and the results are combined with a wrapping sum, which
+/// is then fed to the given hasher together with the number of keys. The
+/// result is stable within a program, but `DefaultHasher` may change between
+/// Rust releases, so it should not be persisted.
+///
+/// # Examples
+///
+/// ```
+/// use multimap::MultiMap;
+/// use std::collections::HashSet;
+///
+/// let mut a = MultiMap::new();
+/// a.insert("a", 1);
+/// a.insert("b", 2);
+/// let mut b = MultiMap::new();
+/// b.insert("b", 2);
+/// b.insert("a", 1);
+///
+/// let mut set = HashSet::new();
+/// set.insert(a);
+/// assert!(!set.insert(b));
+/// ```
+#[cfg(feature = "std")]
+impl<K, V, S, C> Hash for MultiMap<K, V, S, C>
+where
+    K: Eq + Hash,
+    S: BuildHasher,
+    C: Container<V> + Hash,
+{
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        let sum = self.inner.iter().fold(0u64, |sum, entry| {
+            let mut hasher = ::std::collections::hash_map::DefaultHasher::new();
+            entry.hash(&mut hasher);
+            sum.wrapping_add(hasher.finish())
+        });
+        state.write_usize(self.inner.len());
+        state.write_u64(sum);
+    }
+}
+
+impl<K, V, S> Default for MultiMap<K, V, S>
+where
+    K: Eq + Hash,
+    S: BuildHasher + Default,
+{
+    fn default() -> MultiMap<K, V, S> {
+        MultiMap {
+            inner: Default::default(),
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<K, V, S> FromIterator<(K, V)> for MultiMap<K, V, S>
+where
+    K: Eq + Hash,
+    S: BuildHasher + Default,
+{
+    fn from_iter<T: IntoIterator<Item = (K, V)>>(iterable: T) -> MultiMap<K, V, S> {
+        let iter = iterable.into_iter();
+        let hint = iter.size_hint().0;
+
+        let mut multimap = MultiMap::with_capacity_and_hasher(hint, S::default());
+        for (k, v) in iter {
+            multimap.insert(k, v);
+        }
+
+        multimap
+    }
+}
+
+impl<K, V, S> FromIterator<(K, Vec<V>)> for MultiMap<K, V, S>
+where
+    K: Eq + Hash,
+    S: BuildHasher + Default,
+{
+    fn from_iter<T: IntoIterator<Item = (K, Vec<V>)>>(iterable: T) -> MultiMap<K, V, S> {
+        let iter = iterable.into_iter();
+        let hint = iter.size_hint().0;
+
+        let mut multimap = MultiMap::with_capacity_and_hasher(hint, S::default());
+        multimap.extend(iter);
+        multimap
+    }
+}
+
+/// Creates a multimap from an array of key-value pairs. Values of repeated
+/// keys are kept in order.
+///
+/// # Examples
+///
+/// ```
+/// use multimap::MultiMap;
+///
+/// let map: MultiMap<_, _> = MultiMap::from([("a", 1), ("b", 2), ("a", 3)]);
+/// assert_eq!(map.get_vec("a"), Some(&vec![1, 3]));
+/// ```
+impl<K, V, S, const N: usize> From<[(K, V); N]> for MultiMap<K, V, S>
+where
+    K: Eq + Hash,
+    S: BuildHasher + Default,
+{
+    fn from(pairs: [(K, V); N]) -> MultiMap<K, V, S> {
+        MultiMap::from_iter(pairs)
+    }
+}
+
+/// Creates a multimap from a vector of key-value pairs. Values of repeated
+/// keys are kept in order.
+///
+/// # Examples
+///
+/// ```
+/// use multimap::MultiMap;
+///
+/// let map: MultiMap<_, _> = MultiMap::from(vec![("a", 1), ("b", 2), ("a", 3)]);
+/// assert_eq!(map.get_vec("a"), Some(&vec![1, 3]));
+/// ```
+impl<K, V, S> From<Vec<(K, V)>> for MultiMap<K, V, S>
+where
+    K: Eq + Hash,
+    S: BuildHasher + Default,
+{
+    fn from(pairs: Vec<(K, V)>) -> MultiMap<K, V, S> {
+        MultiMap::from_iter(pairs)
+    }
+}
+
+/// Wraps a map of keys to their vectors of values without copying it. Use
+/// `MultiMap::from_single_valued` for a map holding a single value per key.
+///
+/// # Examples
+///
+/// ```
+/// use multimap::MultiMap;
+/// use std::collections::HashMap;
+///
+/// let mut hash_map = HashMap::new();
+/// hash_map.insert("a", vec![1, 2]);
+///
+/// let map = MultiMap::from(hash_map);
+/// assert_eq!(map.get_vec("a"), Some(&vec![1, 2]));
+/// ```
+impl<K, V, S> From<HashMap<K, Vec<V>, S>> for MultiMap<K, V, S>
+where
+    K: Eq + Hash,
+    S: BuildHasher,
+{
+    fn from(map: HashMap<K, Vec<V>, S>) -> MultiMap<K, V, S> {
+        MultiMap::from_group_map(map)
+    }
+}
+
+/// Unwraps the map of keys to their vectors of values.
+///
+/// # Examples
+///
+/// ```
+/// use multimap::MultiMap;
+/// use std::collections::HashMap;
+///
+/// let mut map = MultiMap::new();
+/// map.insert_many("a", vec![1, 2]);
+///
+/// let hash_map: HashMap<_, _> = map.into();
+/// assert_eq!(hash_map["a"], [1, 2]);
+/// ```
+impl<K, V, S> From<MultiMap<K, V, S>> for HashMap<K, Vec<V>, S>
+where
+    K: Eq + Hash,
+    S: BuildHasher,
+{
+    fn from(map: MultiMap<K, V, S>) -> HashMap<K, Vec<V>, S> {
+        map.inner
+    }
+}
+
+impl<'a, K, V, S, C> IntoIterator for &'a MultiMap<K, V, S, C>
+where
+    K: Eq + Hash,
+    S: BuildHasher,
+    C: Container<V>,
+{
+    type Item = (&'a K, &'a C);
+    type IntoIter = IterAll<'a, K, C>;
+
+    fn into_iter(self) -> IterAll<'a, K, C> {
+        self.iter_all()
+    }
+}
+
+/// Iterates over every key and its values mutably, like `iter_all_mut` and
+/// like iterating over `&MultiMap`. To update the values one by one, iterate
+/// over `flat_iter_mut` instead.
+///
+/// # Examples
+///
+/// ```
+/// use multimap::MultiMap;
+///
+/// let mut map = MultiMap::new();
+/// map.insert_many(1, vec![1, 2]);
+///
+/// for (_, values) in &mut map {
+///     values.push(3);
+/// }
+/// for (_, value) in map.flat_iter_mut() {
+///     *value *= 10;
+/// }
+/// assert_eq!(map.get_vec(&1), Some(&vec![10, 20, 30]));
+/// ```
+impl<'a, K, V, S, C> IntoIterator for &'a mut MultiMap<K, V, S, C>
+where
+    K: Eq + Hash,
+    S: BuildHasher,
+    C: Container<V>,
+{
+    type Item = (&'a K, &'a mut C);
+    type IntoIter = IterAllMut<'a, K, C>;
+
+    fn into_iter(self) -> IterAllMut<'a, K, C> {
+        self.inner.iter_mut()
+    }
+}
+
+impl<K, V, S, C> IntoIterator for MultiMap<K, V, S, C>
+where
+    K: Eq + Hash,
+    S: BuildHasher,
+    C: Container<V>,
+{
+    type Item = (K, C);
+    type IntoIter = IntoIter<K, C>;
+
+    fn into_iter(self) -> IntoIter<K, C> {
+        self.inner.into_iter()
+    }
+}
+
+impl<K, V, S, C> Extend<(K, V)> for MultiMap<K, V, S, C>
+where
+    K: Eq + Hash,
+    S: BuildHasher,
+    C: Container<V>,
+{
+    fn extend<T: IntoIterator<Item = (K, V)>>(&mut self, iter: T) {
+        for (k, v) in iter {
+            self.insert(k, v);
+        }
+    }
+}
+
+impl<'a, K, V, S> Extend<(&'a K, &'a V)> for MultiMap<K, V, S>
+where
+    K: Eq + Hash + Clone,
+    V: Clone,
+    S: BuildHasher,
+{
+    fn extend<T: IntoIterator<Item = (&'a K, &'a V)>>(&mut self, iter: T) {
+        self.extend(
+            iter.into_iter()
+                .map(|(key, value)| (key.clone(), value.clone())),
+        );
+    }
+}
+
+impl<K, V, S> Extend<(K, Vec<V>)> for MultiMap<K, V, S>
+where
+    K: Eq + Hash,
+    S: BuildHasher,
+{
+    fn extend<T: IntoIterator<Item = (K, Vec<V>)>>(&mut self, iter: T) {
+        for (k, values) in iter {
+            match self.entry(k) {
+                Entry::Occupied(mut entry) => {
+                    entry.get_vec_mut().extend(values);
+                }
+                Entry::Vacant(entry) => {
+                    entry.insert_vec(values);
+                }
+            }
+        }
+    }
+}
+
+impl<'a, K, V, S> Extend<(&'a K, &'a Vec<V>)> for MultiMap<K, V, S>
+where
+    K: Eq + Hash + Clone,
+    V: Clone,
+    S: BuildHasher,
+{
+    fn extend<T: IntoIterator<Item = (&'a K, &'a Vec<V>)>>(&mut self, iter: T) {
+        self.extend(
+            iter.into_iter()
+                .map(|(key, values)| (key.clone(), values.clone())),
+        );
+    }
+}
+
+#[derive(Clone)]
+pub struct Iter<'a, K: 'a, V: 'a> {
+    inner: IterAll<'a, K, Vec<V>>,
+    // The number of keys left with a non-empty vector.
+    len: usize,
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<(&'a K, &'a V)> {
+        loop {
+            let (k, v) = self.inner.next()?;
+            if let Some(v) = v.first() {
+                self.len -= 1;
+                return Some((k, v));
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<'a, K, V> ExactSizeIterator for Iter<'a, K, V> {
+    fn len(&self) -> usize {
+        self.len
+    }
+}
+
+impl<'a, K, V> FusedIterator for Iter<'a, K, V> {}
+
+pub struct IterMut<'a, K: 'a, V: 'a> {
+    inner: IterAllMut<'a, K, Vec<V>>,
+    // The number of keys left with a non-empty vector.
+    len: usize,
+}
+
+impl<'a, K, V> Iterator for IterMut<'a, K, V> {
+    type Item = (&'a K, &'a mut V);
+
+    fn next(&mut self) -> Option<(&'a K, &'a mut V)> {
+        loop {
+            let (k, v) = self.inner.next()?;
+            if let Some(v) = v.first_mut() {
+                self.len -= 1;
+                return Some((k, v));
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<'a, K, V> ExactSizeIterator for IterMut<'a, K, V> {
+    fn len(&self) -> usize {
+        self.len
+    }
+}
+
+impl<'a, K, V> FusedIterator for IterMut<'a, K, V> {}
+
+/// Groups the items of an iterator into a MultiMap by the key returned by
+/// `f`. This is a shorthand for `MultiMap::from_grouped_by`.
+///
+/// # Examples
+///
+/// ```
+/// let map = multimap::group_by(1..=6, |n| n % 3);
+///
+/// assert_eq!(map.get_vec(&0), Some(&vec![3, 6]));
+/// assert_eq!(map.get_vec(&1), Some(&vec![1, 4]));
+/// assert_eq!(map.get_vec(&2), Some(&vec![2, 5]));
+/// ```
+pub fn group_by<I, K, F>(iter: I, f: F) -> MultiMap<K, I::Item>
+where
+    I: IntoIterator,
+    K: Eq + Hash,
+    F: FnMut(&I::Item) -> K,
+{
+    MultiMap::from_grouped_by(iter, f)
+}
+
+/// Turns groups of values into flat pairs, cloning the key for every value but
+/// the last of its group.
+fn flatten_groups<K, V, I>(mut groups: I) -> impl Iterator<Item = (K, V)>
+where
+    K: Clone,
+    I: Iterator<Item = (K, Vec<V>)>,
+{
+    let mut current: Option<(K, ::std::vec::IntoIter<V>)> = None;
+    ::std::iter::from_fn(move || loop {
+        if let Some((key, values)) = current.as_mut() {
+            if let Some(value) = values.next() {
+                if values.len() > 0 {
+                    return Some((key.clone(), value));
+                }
+                let (key, _) = current.take()?;
+                return Some((key, value));
+            }
+        }
+        let (key, values) = groups.next()?;
+        current = Some((key, values.into_iter()));
+    })
+}
+
+/// Removes all but the first occurrence of each value, keeping the order.
+fn dedup_vec<V: Eq + Hash>(values: &mut Vec<V>) {
+    if values.len() < 2 {
+        return;
+    }
+    let keep: Vec<bool> = {
+        let mut seen = HashSet::with_capacity(values.len());
+        values.iter().map(|value| seen.insert(value)).collect()
+    };
+    let mut keep = keep.into_iter();
+    values.retain(|_| keep.next().unwrap_or(true));
+}
+
+/// A key ordered only by its number of values, for `top_keys_by_count`.
+struct ByCount<'a, K: 'a>(usize, &'a K);
+
+impl<'a, K> PartialEq for ByCount<'a, K> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl<'a, K> Eq for ByCount<'a, K> {}
+
+impl<'a, K> PartialOrd for ByCount<'a, K> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<'a, K> Ord for ByCount<'a, K> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.cmp(&other.0)
+    }
+}
+
+#[macro_export]
+/// Create a `MultiMap` from a list of key value pairs
+///
+/// The values of a key can also be grouped in braces, and grouped and single
+/// values can be mixed freely; a key may appear more than once either way. A
+/// group holds at least one value: `{}` is a unit value. A block value like
+/// `{ x }` gives the same map either way.
+///
+/// Lists in which a value spans several tokens, like `-1` or `a + b`, are
+/// matched one entry at a time, so a very long list of that kind may need a
+/// higher `recursion_limit`.
+///
+/// ## Example
+///
+/// ```
+/// # use multimap::MultiMap;
+/// #[macro_use] extern crate multimap;
+/// # fn main(){
+///
+/// let map = multimap!(
+///     "dog" => "husky",
+///     "dog" => "retreaver",
+///     "dog" => "shiba inu",
+///     "cat" => "cat"
+///     );
+///
+/// let grouped = multimap!{
+///     "dog" => {"husky", "retreaver", "shiba inu"},
+///     "cat" => {"cat"},
+/// };
+/// assert_eq!(map, grouped);
+///
+/// let mixed = multimap!{
+///     "dog" => {"husky", "retreaver"},
+///     "cat" => "cat",
+///     "dog" => "shiba inu",
+/// };
+/// assert_eq!(map, mixed);
+/// # }
+///
+/// ```
+macro_rules! multimap{
+    (@replace_with_unit $_t:tt) => { () };
+    (@count $($key:expr),*) => { <[()]>::len(&[$($crate::multimap! { @replace_with_unit $key }),*]) };
+
+    (@new BTreeMultiMap, $len:expr) => { $crate::BTreeMultiMap::new() };
+    (@new $kind:ident, $len:expr) => { $crate::$kind::with_capacity($len) };
+
+    (@insert $map:ident, $key:expr, {$($value:expr),+ $(,)?}) => {
+        $map.insert_many($key, [$($value),*]);
+    };
+    (@insert $map:ident, $key:expr, $value:expr) => {
+        $map.insert($key, $value);
+    };
+
+    (@build $kind:ident $(($key:expr, $value:tt))*) => {
+        {
+            #[allow(unused_mut)]
+            let mut map = $crate::multimap! { @new $kind, $crate::multimap! { @count $($key),* } };
+            $(
+                $crate::multimap! { @insert map, $key, $value }
+             )*
+            map
+        }
+    };
+
+    (@munch $kind:ident [$($done:tt)*]) => {
+        $crate::multimap! { @build $kind $($done)* }
+    };
+    (@munch $kind:ident [$($done:tt)*] $key:expr => {$($value:expr),+ $(,)?} $(, $($rest:tt)*)?) => {
+        $crate::multimap! { @munch $kind [$($done)* ($key, {$($value),+})] $($($rest)*)? }
+    };
+    (@munch $kind:ident [$($done:tt)*] $key:expr => $value:expr $(, $($rest:tt)*)?) => {
+        $crate::multimap! { @munch $kind [$($done)* ($key, ($value))] $($($rest)*)? }
+    };
+
+    // Entries whose values are single token trees, i.e. groups, literals,
+    // names or bracketed values, are matched at once; anything else entry by
+    // entry.
+    (@entries $kind:ident $($key:expr => $value:tt),* $(,)?) => {
+        $crate::multimap! { @build $kind $(($key, $value))* }
+    };
+    (@entries $kind:ident $($entries:tt)*) => {
+        $crate::multimap! { @munch $kind [] $($entries)* }
+    };
+
+    ($($entries:tt)*)=>{
+        $crate::multimap! { @entries MultiMap $($entries)* }
+    };
+}
+
+#[macro_export]
+/// Create a `BTreeMultiMap` from a list of key value pairs and of keys with
+/// grouped values like `multimap!`.
+///
+/// ## Example
+///
+/// ```
+/// #[macro_use] extern crate multimap;
+/// # fn main(){
+///
+/// let map = btreemultimap!(
+///     "dog" => "husky",
+///     "dog" => "retreaver",
+///     "cat" => "cat"
+///     );
+///
+/// let grouped = btreemultimap!{
+///     "dog" => {"husky", "retreaver"},
+///     "cat" => {"cat"},
+/// };
+/// assert_eq!(map, grouped);
+/// # }
+/// ```
+macro_rules! btreemultimap{
+    ($($entries:tt)*)=>{
+        $crate::multimap! { @entries BTreeMultiMap $($entries)* }
+    };
+}
+
+#[macro_export]
+/// Create a `SetMultiMap` from a list of key value pairs and of keys with
+/// grouped values like `multimap!`. Repeated values of a key are only stored
+/// once.
+///
+/// ## Example
+///
+/// ```
+/// #[macro_use] extern crate multimap;
+/// # fn main(){
+///
+/// let map = setmultimap!(
+///     "dog" => "husky",
+///     "dog" => "husky",
+///     "cat" => "cat"
+///     );
+///
+/// let grouped = setmultimap!{
+///     "dog" => {"husky"},
+///     "cat" => {"cat"},
+/// };
+/// assert_eq!(map, grouped);
+/// # }
+/// ```
+macro_rules! setmultimap{
+    ($($entries:tt)*)=>{
+        $crate::multimap! { @entries SetMultiMap $($entries)* }
+    };
+}
+
+#[cfg(feature = "std")]
+#[macro_export]
+/// Declare read-only `FrozenMultiMap` statics for lookup tables, with the
+/// entries written like in `multimap!`.
+///
+/// The map is built on first access and kept for the rest of the program;
+/// it is not hashed at compile time. Each static is a
+/// `std::sync::LazyLock<FrozenMultiMap<K, V>>`, which the crate's minimum
+/// Rust version of 1.86 covers in the calling crate as well.
+///
+/// ## Example
+///
+/// ```
+/// #[macro_use] extern crate multimap;
+///
+/// static_multimap! {
+///     static EXTENSIONS: &'static str => &'static str = {
+///         "text/html" => {"html", "htm"},
+///         "image/jpeg" => {"jpg", "jpeg"},
+///     };
+/// }
+///
+/// # fn main(){
+/// assert_eq!(EXTENSIONS.get_slice("text/html"), Some(&["html", "htm"][..]));
+/// assert_eq!(EXTENSIONS.get_slice("text/plain"), None);
+/// # }
+/// ```
+macro_rules! static_multimap{
+    ($($(#[$attr:meta])* $vis:vis static $name:ident: $key:ty => $value:ty = { $($entries:tt)* };)+)=>{
+        $(
+            $(#[$attr])*
+            $vis static $name: ::std::sync::LazyLock<$crate::FrozenMultiMap<$key, $value>> =
+                ::std::sync::LazyLock::new(|| {
+                    let map: $crate::MultiMap<$key, $value> = $crate::multimap! { $($entries)* };
+                    $crate::FrozenMultiMap::from(map)
+                });
+        )+
     }
 }
 
-impl<'a, K, V, S, Q: ?Sized> Index<&'a Q> for MultiMap<K, V, S>
-where
-    K: Eq + Hash + Borrow<Q>,
-    Q: Eq + Hash,
-    S: BuildHasher,
-{
-    type Output = V;
+#[cfg(test)]
+#[allow(clippy::useless_vec)]
+mod tests {
+    use std::collections::HashMap;
+    use std::iter::FromIterator;
+    use std::string::{String, ToString};
+
+    use super::*;
+
+    #[test]
+    fn create() {
+        let _: MultiMap<usize, usize> = MultiMap {
+            inner: HashMap::new(),
+            marker: PhantomData,
+        };
+    }
+
+    #[test]
+    fn new() {
+        let _: MultiMap<usize, usize> = MultiMap::new();
+    }
+
+    #[test]
+    fn with_capacity() {
+        let _: MultiMap<usize, usize> = MultiMap::with_capacity(20);
+    }
+
+    #[test]
+    fn insert() {
+        let mut m: MultiMap<usize, usize> = MultiMap::new();
+        m.insert(1, 3);
+    }
+
+    #[test]
+    fn insert_identical() {
+        let mut m = MultiMap::new();
+        m.insert(1, 42);
+        m.insert(1, 42);
+        assert_eq!(m.get_vec(&1), Some(&vec![42, 42]));
+    }
+
+    #[test]
+    fn insert_many() {
+        let mut m: MultiMap<usize, usize> = MultiMap::new();
+        m.insert_many(1, vec![3, 4]);
+        assert_eq!(Some(&vec![3, 4]), m.get_vec(&1));
+    }
+
+    #[test]
+    fn insert_many_again() {
+        let mut m: MultiMap<usize, usize> = MultiMap::new();
+        m.insert(1, 2);
+        m.insert_many(1, vec![3, 4]);
+        assert_eq!(Some(&vec![2, 3, 4]), m.get_vec(&1));
+    }
+
+    #[test]
+    fn insert_many_overlap() {
+        let mut m: MultiMap<usize, usize> = MultiMap::new();
+        m.insert_many(1, vec![2, 3]);
+        m.insert_many(1, vec![3, 4]);
+        assert_eq!(Some(&vec![2, 3, 3, 4]), m.get_vec(&1));
+    }
+
+    #[test]
+    fn insert_many_from_slice() {
+        let mut m: MultiMap<usize, usize> = MultiMap::new();
+        m.insert_many_from_slice(1, &[3, 4]);
+        assert_eq!(Some(&vec![3, 4]), m.get_vec(&1));
+    }
+
+    #[test]
+    fn insert_many_from_slice_again() {
+        let mut m: MultiMap<usize, usize> = MultiMap::new();
+        m.insert(1, 2);
+        m.insert_many_from_slice(1, &[3, 4]);
+        assert_eq!(Some(&vec![2, 3, 4]), m.get_vec(&1));
+    }
+
+    #[test]
+    fn insert_existing() {
+        let mut m: MultiMap<usize, usize> = MultiMap::new();
+        m.insert(1, 3);
+        m.insert(1, 4);
+        assert_eq!(Some(&vec![3, 4]), m.get_vec(&1));
+    }
+
+    #[test]
+    #[should_panic(expected = "no entry found for key")]
+    fn index_no_entry() {
+        let m: MultiMap<usize, usize> = MultiMap::new();
+        let _ = &m[&1];
+    }
+
+    #[test]
+    fn index() {
+        let mut m: MultiMap<usize, usize> = MultiMap::new();
+        m.insert(1, 41);
+        m.insert(2, 42);
+        m.insert(3, 43);
+        let values = m[&2];
+        assert_eq!(values, 42);
+    }
+
+    #[test]
+    #[should_panic(expected = "no value found for key")]
+    fn index_empty_vec() {
+        let mut m: MultiMap<usize, usize> = MultiMap::new();
+        m.insert(1, 42);
+        m.get_vec_mut(&1).unwrap().clear();
+        let values = m[&1];
+        assert_eq!(values, 42);
+    }
+
+    #[test]
+    fn contains_key_true() {
+        let mut m: MultiMap<usize, usize> = MultiMap::new();
+        m.insert(1, 42);
+        assert!(m.contains_key(&1));
+    }
+
+    #[test]
+    fn contains_key_false() {
+        let m: MultiMap<usize, usize> = MultiMap::new();
+        assert!(!m.contains_key(&1));
+    }
+
+    #[test]
+    fn len() {
+        let mut m: MultiMap<usize, usize> = MultiMap::new();
+        m.insert(1, 42);
+        m.insert(2, 1337);
+        m.insert(3, 99);
+        assert_eq!(m.len(), 3);
+    }
+
+    #[test]
+    fn remove_not_present() {
+        let mut m: MultiMap<usize, usize> = MultiMap::new();
+        let v = m.remove(&1);
+        assert_eq!(v, None);
+    }
+
+    #[test]
+    fn remove_present() {
+        let mut m: MultiMap<usize, usize> = MultiMap::new();
+        m.insert(1, 42);
+        let v = m.remove(&1);
+        assert_eq!(v, Some(vec![42]));
+    }
+
+    #[test]
+    fn get_not_present() {
+        let m: MultiMap<usize, usize> = MultiMap::new();
+        assert_eq!(m.get(&1), None);
+    }
+
+    #[test]
+    fn get_present() {
+        let mut m: MultiMap<usize, usize> = MultiMap::new();
+        m.insert(1, 42);
+        assert_eq!(m.get(&1), Some(&42));
+    }
+
+    #[test]
+    fn get_empty() {
+        let mut m: MultiMap<usize, usize> = MultiMap::new();
+        m.insert(1, 42);
+        m.get_vec_mut(&1).and_then(Vec::pop);
+        assert_eq!(m.get(&1), None);
+    }
+
+    #[test]
+    fn get_vec_not_present() {
+        let m: MultiMap<usize, usize> = MultiMap::new();
+        assert_eq!(m.get_vec(&1), None);
+    }
+
+    #[test]
+    fn get_vec_present() {
+        let mut m: MultiMap<usize, usize> = MultiMap::new();
+        m.insert(1, 42);
+        m.insert(1, 1337);
+        assert_eq!(m.get_vec(&1), Some(&vec![42, 1337]));
+    }
+
+    #[test]
+    fn capacity() {
+        let m: MultiMap<usize, usize> = MultiMap::with_capacity(20);
+        assert!(m.capacity() >= 20);
+    }
+
+    #[test]
+    fn is_empty_true() {
+        let m: MultiMap<usize, usize> = MultiMap::new();
+        assert!(m.is_empty());
+    }
+
+    #[test]
+    fn is_empty_false() {
+        let mut m: MultiMap<usize, usize> = MultiMap::new();
+        m.insert(1, 42);
+        assert!(!m.is_empty());
+    }
+
+    #[test]
+    fn clear() {
+        let mut m: MultiMap<usize, usize> = MultiMap::new();
+        m.insert(1, 42);
+        m.clear();
+        assert!(m.is_empty());
+    }
+
+    #[test]
+    fn get_mut() {
+        let mut m: MultiMap<usize, usize> = MultiMap::new();
+        m.insert(1, 42);
+        if let Some(v) = m.get_mut(&1) {
+            *v = 1337;
+        }
+        assert_eq!(m[&1], 1337)
+    }
+
+    #[test]
+    fn get_vec_mut() {
+        let mut m: MultiMap<usize, usize> = MultiMap::new();
+        m.insert(1, 42);
+        m.insert(1, 1337);
+        if let Some(v) = m.get_vec_mut(&1) {
+            (*v)[0] = 5;
+            (*v)[1] = 10;
+        }
+        assert_eq!(m.get_vec(&1), Some(&vec![5, 10]))
+    }
+
+    #[test]
+    fn get_mut_empty() {
+        let mut m: MultiMap<usize, usize> = MultiMap::new();
+        m.insert(1, 42);
+        m.get_vec_mut(&1).and_then(Vec::pop);
+        assert_eq!(m.get_mut(&1), None);
+    }
+
+    #[test]
+    fn keys() {
+        let mut m: MultiMap<usize, usize> = MultiMap::new();
+        m.insert(1, 42);
+        m.insert(2, 42);
+        m.insert(4, 42);
+        m.insert(8, 42);
+
+        let keys: Vec<_> = m.keys().cloned().collect();
+        assert_eq!(keys.len(), 4);
+        assert!(keys.contains(&1));
+        assert!(keys.contains(&2));
+        assert!(keys.contains(&4));
+        assert!(keys.contains(&8));
+    }
+
+    #[test]
+    fn iter() {
+        let mut m: MultiMap<usize, usize> = MultiMap::new();
+        m.insert(1, 42);
+        m.insert(1, 42);
+        m.insert(4, 42);
+        m.insert(8, 42);
+
+        assert!(m.iter().all(|(_, &v)| v == 42));
+
+        let mut iter = m.iter();
+
+        for _ in iter.by_ref().take(2) {}
+
+        assert_eq!(iter.len(), 1);
+    }
+
+    #[test]
+    fn iter_empty_vec() {
+        let mut m: MultiMap<usize, usize> = MultiMap::new();
+        m.insert(42, 42);
+        m.get_vec_mut(&42).unwrap().clear();
+
+        assert!(m.iter().next().is_none());
+    }
+
+    #[test]
+    fn flat_iter() {
+        let mut m: MultiMap<usize, usize> = MultiMap::new();
+        m.insert(1, 42);
+        m.insert(1, 43);
+        m.insert(4, 42);
+        m.insert(8, 42);
+
+        let keys = vec![1, 4, 8];
+
+        for (key, value) in m.flat_iter() {
+            assert!(keys.contains(key));
+
+            if key == &1 {
+                assert!(value == &42 || value == &43);
+            } else {
+                assert_eq!(value, &42);
+            }
+        }
+    }
+
+    #[test]
+    fn flat_iter_mut() {
+        let mut m: MultiMap<usize, usize> = MultiMap::new();
+        m.insert(1, 42);
+        m.insert(1, 43);
+        m.insert(4, 42);
+        m.insert(8, 42);
+
+        let keys = vec![1, 4, 8];
+
+        for (key, value) in m.flat_iter_mut() {
+            assert!(keys.contains(key));
+
+            if key == &1 {
+                assert!(value == &42 || value == &43);
+
+                *value = 55;
+                assert_eq!(value, &55);
+            } else {
+                assert_eq!(value, &42);
+
+                *value = 76;
+                assert_eq!(value, &76);
+            }
+        }
+    }
+
+    #[test]
+    fn intoiterator_for_reference_type() {
+        let mut m: MultiMap<usize, usize> = MultiMap::new();
+        m.insert(1, 42);
+        m.insert(1, 43);
+        m.insert(4, 42);
+        m.insert(8, 42);
+
+        let keys = vec![1, 4, 8];
 
-    fn index(&self, index: &Q) -> &V {
-        self.inner
-            .get(index)
-            .expect("no entry found for key")
-            .first()
-            .expect("no value found for key")
-    }
-}
+        for (key, value) in &m {
+            assert!(keys.contains(key));
 
-impl<K, V, S> Debug for MultiMap<K, V, S>
-where
-    K: Eq + Hash + Debug,
-    V: Debug,
-    S: BuildHasher,
-{
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        f.debug_map().entries(self.iter_all()).finish()
+            if key == &1 {
+                assert_eq!(value, &vec![42, 43]);
+            } else {
+                assert_eq!(value, &vec![42]);
+            }
+        }
     }
-}
 
-impl<K, V, S> PartialEq for MultiMap<K, V, S>
-where
-    K: Eq + Hash,
-    V: PartialEq,
-    S: BuildHasher,
-{
-    fn eq(&self, other: &MultiMap<K, V, S>) -> bool {
-        if self.len() != other.len() {
-            return false;
+    #[test]
+    fn intoiterator_for_mutable_reference_type() {
+        let mut m: MultiMap<usize, usize> = MultiMap::new();
+        m.insert(1, 42);
+        m.insert(1, 43);
+        m.insert(4, 42);
+        m.insert(8, 42);
+
+        let keys = vec![1, 4, 8];
+
+        for (key, value) in &mut m {
+            assert!(keys.contains(key));
+
+            if key == &1 {
+                assert_eq!(value, &vec![42, 43]);
+                value.push(666);
+            } else {
+                assert_eq!(value, &vec![42]);
+            }
         }
 
-        self.iter_all()
-            .all(|(key, value)| other.get_vec(key).map_or(false, |v| *value == *v))
+        assert_eq!(m.get_vec(&1), Some(&vec![42, 43, 666]));
     }
-}
 
-impl<K, V, S> Eq for MultiMap<K, V, S>
-where
-    K: Eq + Hash,
-    V: Eq,
-    S: BuildHasher,
-{
-}
+    #[test]
+    fn intoiterator_consuming() {
+        let mut m: MultiMap<usize, usize> = MultiMap::new();
+        m.insert(1, 42);
+        m.insert(1, 43);
+        m.insert(4, 42);
+        m.insert(8, 42);
 
-impl<K, V, S> Default for MultiMap<K, V, S>
-where
-    K: Eq + Hash,
-    S: BuildHasher + Default,
-{
-    fn default() -> MultiMap<K, V, S> {
-        MultiMap {
-            inner: Default::default(),
+        let keys = vec![1, 4, 8];
+
+        for (key, value) in m {
+            assert!(keys.contains(&key));
+
+            if key == 1 {
+                assert_eq!(value, vec![42, 43]);
+            } else {
+                assert_eq!(value, vec![42]);
+            }
         }
     }
-}
 
-impl<K, V, S> FromIterator<(K, V)> for MultiMap<K, V, S>
-where
-    K: Eq + Hash,
-    S: BuildHasher + Default,
-{
-    fn from_iter<T: IntoIterator<Item = (K, V)>>(iterable: T) -> MultiMap<K, V, S> {
-        let iter = iterable.into_iter();
-        let hint = iter.size_hint().0;
+    #[test]
+    fn test_fmt_debug() {
+        let mut map = MultiMap::new();
+        let empty: MultiMap<i32, i32> = MultiMap::new();
 
-        let mut multimap = MultiMap::with_capacity_and_hasher(hint, S::default());
-        for (k, v) in iter {
-            multimap.insert(k, v);
-        }
+        map.insert(1, 2);
+        map.insert(1, 5);
+        map.insert(1, -1);
+        map.insert(3, 4);
 
-        multimap
-    }
-}
+        let map_str = format!("{:?}", map);
 
-impl<K, V, S> FromIterator<(K, Vec<V>)> for MultiMap<K, V, S>
-where
-    K: Eq + Hash,
-    V: Clone,
-    S: BuildHasher + Default,
-{
-    fn from_iter<T: IntoIterator<Item = (K, Vec<V>)>>(iterable: T) -> MultiMap<K, V, S> {
-        let iter = iterable.into_iter();
-        let hint = iter.size_hint().0;
+        assert!(map_str == "{1: [2, 5, -1], 3: [4]}" || map_str == "{3: [4], 1: [2, 5, -1]}");
+        assert_eq!(format!("{:?}", empty), "{}");
+    }
 
-        let mut multimap = MultiMap::with_capacity_and_hasher(hint, S::default());
-        for (k, v) in iter {
-            multimap.insert_many_from_slice(k, &v[..])
-        }
+    #[test]
+    fn test_eq() {
+        let mut m1 = MultiMap::new();
+        m1.insert(1, 2);
+        m1.insert(2, 3);
+        m1.insert(3, 4);
+        let mut m2 = MultiMap::new();
+        m2.insert(1, 2);
+        m2.insert(2, 3);
+        assert_ne!(m1, m2);
+        m2.insert(3, 4);
+        assert_eq!(m1, m2);
+        m2.insert(3, 4);
+        assert_ne!(m1, m2);
+        m1.insert(3, 4);
+        assert_eq!(m1, m2);
+    }
 
-        multimap
+    #[test]
+    fn test_eq_empty_key() {
+        let mut m1 = MultiMap::new();
+        m1.insert(1, 2);
+        m1.insert(2, 3);
+        let mut m2 = MultiMap::new();
+        m2.insert(1, 2);
+        m2.insert_many(2, []);
+        assert_ne!(m1, m2);
+        m2.insert_many(2, [3]);
+        assert_eq!(m1, m2);
     }
-}
 
-impl<'a, K, V, S> IntoIterator for &'a MultiMap<K, V, S>
-where
-    K: Eq + Hash,
-    S: BuildHasher,
-{
-    type Item = (&'a K, &'a Vec<V>);
-    type IntoIter = IterAll<'a, K, Vec<V>>;
+    #[test]
+    fn test_eq_hashmap() {
+        let mut map = MultiMap::new();
+        map.insert_many(1, vec![2, 3]);
+        map.insert(4, 5);
 
-    fn into_iter(self) -> IterAll<'a, K, Vec<V>> {
-        self.iter_all()
+        let mut expected = HashMap::new();
+        expected.insert(1, vec![2, 3]);
+        assert_ne!(map, expected);
+        assert_ne!(expected, map);
+
+        expected.insert(4, vec![5]);
+        assert_eq!(map, expected);
+        assert_eq!(expected, map);
+
+        expected.insert(1, vec![3, 2]);
+        assert_ne!(map, expected);
+        assert_ne!(expected, map);
     }
-}
 
-impl<'a, K, V, S> IntoIterator for &'a mut MultiMap<K, V, S>
-where
-    K: Eq + Hash,
-    S: BuildHasher,
-{
-    type Item = (&'a K, &'a mut Vec<V>);
-    type IntoIter = IterAllMut<'a, K, Vec<V>>;
+    #[test]
+    fn test_eq_ignore_value_order() {
+        let mut m1 = MultiMap::new();
+        m1.insert_many(1, vec![1, 2, 1]);
+        m1.insert_many(2, vec![]);
+        let mut m2 = MultiMap::new();
+        m2.insert_many(1, vec![1, 1, 2]);
+        assert!(!m1.eq_ignore_value_order(&m2));
+        m2.insert_many(2, vec![]);
+        assert!(m1.eq_ignore_value_order(&m2));
+        assert!(m2.eq_ignore_value_order(&m1));
+
+        m2.insert_many(1, vec![3]);
+        assert!(!m1.eq_ignore_value_order(&m2));
+        m1.insert_many(1, vec![2]);
+        assert!(!m1.eq_ignore_value_order(&m2));
+        assert!(!m2.eq_ignore_value_order(&m1));
+    }
 
-    fn into_iter(self) -> IterAllMut<'a, K, Vec<V>> {
-        self.inner.iter_mut()
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_hash_ignores_key_order() {
+        fn hash_of<T: Hash>(value: &T) -> u64 {
+            let mut hasher = ::std::collections::hash_map::DefaultHasher::new();
+            value.hash(&mut hasher);
+            hasher.finish()
+        }
+
+        let mut m1 = MultiMap::new();
+        let mut m2 = MultiMap::new();
+        for key in 0..100 {
+            m1.insert_many(key, vec![key, key + 1]);
+            m2.insert_many(99 - key, vec![99 - key, 100 - key]);
+        }
+        assert_eq!(m1, m2);
+        assert_eq!(hash_of(&m1), hash_of(&m2));
+
+        m2.insert_many(100, vec![]);
+        assert_ne!(hash_of(&m1), hash_of(&m2));
+        m1.insert_many(100, vec![]);
+        assert_eq!(hash_of(&m1), hash_of(&m2));
+
+        m1.insert_many(0, vec![1, 0]);
+        m2.insert_many(0, vec![0, 1]);
+        assert_ne!(hash_of(&m1), hash_of(&m2));
     }
-}
 
-impl<K, V, S> IntoIterator for MultiMap<K, V, S>
-where
-    K: Eq + Hash,
-    S: BuildHasher,
-{
-    type Item = (K, Vec<V>);
-    type IntoIter = IntoIter<K, Vec<V>>;
+    #[test]
+    fn test_from_conversions() {
+        let expected: MultiMap<&str, i32> =
+            vec![("a", 1), ("b", 2), ("a", 3)].into_iter().collect();
+        assert_eq!(MultiMap::from([("a", 1), ("b", 2), ("a", 3)]), expected);
+        assert_eq!(MultiMap::from(vec![("a", 1), ("b", 2), ("a", 3)]), expected);
+
+        let mut grouped = HashMap::new();
+        grouped.insert("a", vec![1, 3]);
+        grouped.insert("b", vec![2]);
+        assert_eq!(MultiMap::from(grouped.clone()), expected);
+        assert_eq!(HashMap::from(expected), grouped);
+
+        let mut single = HashMap::new();
+        single.insert("a", vec![1]);
+        single.insert("b", vec![]);
+        let map = MultiMap::from(single);
+        assert_eq!(map.get_vec("a"), Some(&vec![1]));
+        assert_eq!(map.get_vec("b"), Some(&vec![]));
+
+        let empty: MultiMap<&str, i32> = HashMap::new().into();
+        assert!(empty.is_empty());
+
+        let mut single = HashMap::new();
+        single.insert("a", 1);
+        let map = MultiMap::from_single_valued(single);
+        assert_eq!(map.get_vec("a"), Some(&vec![1]));
+    }
 
-    fn into_iter(self) -> IntoIter<K, Vec<V>> {
-        self.inner.into_iter()
+    #[test]
+    fn test_into_and_to_maps() {
+        use std::collections::VecDeque;
+
+        let mut map: MultiMap<usize, usize, RandomState, VecDeque<usize>> =
+            MultiMap::with_container();
+        map.insert_many(2, vec![3]);
+        map.insert_many(1, vec![1, 2]);
+
+        let hash_map = map.to_hashmap();
+        assert_eq!(map, hash_map);
+        assert_eq!(map.clone().into_hashmap(), hash_map);
+
+        let btree_map = map.to_btreemap();
+        assert_eq!(
+            btree_map.iter().collect::<Vec<_>>(),
+            [
+                (&1, &VecDeque::from(vec![1, 2])),
+                (&2, &VecDeque::from(vec![3]))
+            ]
+        );
+        assert_eq!(map.into_btreemap(), btree_map);
     }
-}
 
-impl<K, V, S> Extend<(K, V)> for MultiMap<K, V, S>
-where
-    K: Eq + Hash,
-    S: BuildHasher,
-{
-    fn extend<T: IntoIterator<Item = (K, V)>>(&mut self, iter: T) {
-        for (k, v) in iter {
-            self.insert(k, v);
-        }
+    #[test]
+    fn test_default() {
+        let _: MultiMap<u8, u8> = Default::default();
     }
-}
 
-impl<'a, K, V, S> Extend<(&'a K, &'a V)> for MultiMap<K, V, S>
-where
-    K: Eq + Hash + Copy,
-    V: Copy,
-    S: BuildHasher,
-{
-    fn extend<T: IntoIterator<Item = (&'a K, &'a V)>>(&mut self, iter: T) {
-        self.extend(iter.into_iter().map(|(&key, &value)| (key, value)));
+    #[test]
+    fn test_from_iterator() {
+        let vals: Vec<(&str, i64)> = vec![("foo", 123), ("bar", 456), ("foo", 789)];
+        let multimap: MultiMap<&str, i64> = MultiMap::from_iter(vals);
+
+        let foo_vals: &Vec<i64> = multimap.get_vec("foo").unwrap();
+        assert!(foo_vals.contains(&123));
+        assert!(foo_vals.contains(&789));
+
+        let bar_vals: &Vec<i64> = multimap.get_vec("bar").unwrap();
+        assert!(bar_vals.contains(&456));
+    }
+
+    #[test]
+    fn test_from_vec_iterator() {
+        let vals: Vec<(&str, Vec<i64>)> = vec![
+            ("foo", vec![123, 456]),
+            ("bar", vec![234]),
+            ("foobar", vec![567, 678, 789]),
+            ("bar", vec![12, 23, 34]),
+        ];
+
+        let multimap: MultiMap<&str, i64> = MultiMap::from_iter(vals);
+
+        let foo_vals: &Vec<i64> = multimap.get_vec("foo").unwrap();
+        assert!(foo_vals.contains(&123));
+        assert!(foo_vals.contains(&456));
+
+        let bar_vals: &Vec<i64> = multimap.get_vec("bar").unwrap();
+        assert!(bar_vals.contains(&234));
+        assert!(bar_vals.contains(&12));
+        assert!(bar_vals.contains(&23));
+        assert!(bar_vals.contains(&34));
+
+        let foobar_vals: &Vec<i64> = multimap.get_vec("foobar").unwrap();
+        assert!(foobar_vals.contains(&567));
+        assert!(foobar_vals.contains(&678));
+        assert!(foobar_vals.contains(&789));
     }
-}
 
-impl<K, V, S> Extend<(K, Vec<V>)> for MultiMap<K, V, S>
-where
-    K: Eq + Hash,
-    S: BuildHasher,
-{
-    fn extend<T: IntoIterator<Item = (K, Vec<V>)>>(&mut self, iter: T) {
-        for (k, values) in iter {
-            match self.entry(k) {
-                Entry::Occupied(mut entry) => {
-                    entry.get_vec_mut().extend(values);
-                }
-                Entry::Vacant(entry) => {
-                    entry.insert_vec(values);
-                }
-            }
-        }
-    }
-}
+    #[test]
+    fn test_extend_consuming_hashmap() {
+        let mut a = MultiMap::new();
+        a.insert(1, 42);
 
-impl<'a, K, V, S> Extend<(&'a K, &'a Vec<V>)> for MultiMap<K, V, S>
-where
-    K: Eq + Hash + Copy,
-    V: Copy,
-    S: BuildHasher,
-{
-    fn extend<T: IntoIterator<Item = (&'a K, &'a Vec<V>)>>(&mut self, iter: T) {
-        self.extend(
-            iter.into_iter()
-                .map(|(&key, values)| (key, values.to_owned())),
-        );
+        let mut b = HashMap::new();
+        b.insert(1, 43);
+        b.insert(2, 666);
+
+        a.extend(b);
+
+        assert_eq!(a.len(), 2);
+        assert_eq!(a.get_vec(&1), Some(&vec![42, 43]));
     }
-}
 
-#[derive(Clone)]
-pub struct Iter<'a, K: 'a, V: 'a> {
-    inner: IterAll<'a, K, Vec<V>>,
-}
+    #[test]
+    fn test_extend_ref_hashmap() {
+        let mut a = MultiMap::new();
+        a.insert(1, 42);
 
-impl<'a, K, V> Iterator for Iter<'a, K, V> {
-    type Item = (&'a K, &'a V);
+        let mut b = HashMap::new();
+        b.insert(1, 43);
+        b.insert(2, 666);
 
-    fn next(&mut self) -> Option<(&'a K, &'a V)> {
-        let (k, v) = self.inner.next()?;
-        let v = v.first()?;
-        Some((k, v))
-    }
+        a.extend(&b);
 
-    fn size_hint(&self) -> (usize, Option<usize>) {
-        self.inner.size_hint()
+        assert_eq!(a.len(), 2);
+        assert_eq!(a.get_vec(&1), Some(&vec![42, 43]));
+        assert_eq!(b.len(), 2);
+        assert_eq!(b[&1], 43);
     }
-}
 
-impl<'a, K, V> ExactSizeIterator for Iter<'a, K, V> {
-    fn len(&self) -> usize {
-        self.inner.len()
-    }
-}
+    #[test]
+    fn test_extend_consuming_multimap() {
+        let mut a = MultiMap::new();
+        a.insert(1, 42);
 
-pub struct IterMut<'a, K: 'a, V: 'a> {
-    inner: IterAllMut<'a, K, Vec<V>>,
-}
+        let mut b = MultiMap::new();
+        b.insert(1, 43);
+        b.insert(1, 44);
+        b.insert(2, 666);
 
-impl<'a, K, V> Iterator for IterMut<'a, K, V> {
-    type Item = (&'a K, &'a mut V);
+        a.extend(b);
 
-    fn next(&mut self) -> Option<(&'a K, &'a mut V)> {
-        let (k, v) = self.inner.next()?;
-        let v = v.first_mut()?;
-        Some((k, v))
+        assert_eq!(a.len(), 2);
+        assert_eq!(a.get_vec(&1), Some(&vec![42, 43, 44]));
     }
 
-    fn size_hint(&self) -> (usize, Option<usize>) {
-        self.inner.size_hint()
-    }
-}
+    #[test]
+    fn test_extend_ref_multimap() {
+        let mut a = MultiMap::new();
+        a.insert(1, 42);
 
-impl<'a, K, V> ExactSizeIterator for IterMut<'a, K, V> {
-    fn len(&self) -> usize {
-        self.inner.len()
+        let mut b = MultiMap::new();
+        b.insert(1, 43);
+        b.insert(1, 44);
+        b.insert(2, 666);
+
+        a.extend(&b);
+
+        assert_eq!(a.len(), 2);
+        assert_eq!(a.get_vec(&1), Some(&vec![42, 43, 44]));
+        assert_eq!(b.len(), 2);
+        assert_eq!(b.get_vec(&1), Some(&vec![43, 44]));
     }
-}
 
-#[macro_export]
-/// Create a `MultiMap` from a list of key value pairs
-///
-/// ## Example
-///
-/// ```
-/// # use multimap::MultiMap;
-/// #[macro_use] extern crate multimap;
-/// # fn main(){
-///
-/// let map = multimap!(
-///     "dog" => "husky",
-///     "dog" => "retreaver",
-///     "dog" => "shiba inu",
-///     "cat" => "cat"
-///     );
-/// # }
-///
-/// ```
-macro_rules! multimap{
-    (@replace_with_unit $_t:tt) => { () };
-    (@count $($key:expr),*) => { <[()]>::len(&[$($crate::multimap! { @replace_with_unit $key }),*]) };
+    #[test]
+    fn test_entry() {
+        let mut m = MultiMap::new();
+        m.insert(1, 42);
 
-    ($($key:expr => $value:expr),* $(,)?)=>{
         {
-            let mut map = $crate::MultiMap::with_capacity($crate::multimap! { @count $($key),* });
-            $(
-                map.insert($key,$value);
-             )*
-            map
+            let v = m.entry(1).or_insert(43);
+            assert_eq!(v, &42);
+            *v = 44;
         }
+        assert_eq!(m.entry(2).or_insert(666), &666);
+
+        assert_eq!(m[&1], 44);
+        assert_eq!(m[&2], 666);
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use std::collections::HashMap;
-    use std::iter::FromIterator;
+    #[test]
+    fn test_entry_vec() {
+        let mut m = MultiMap::new();
+        m.insert(1, 42);
 
-    use super::*;
+        {
+            let v = m.entry(1).or_insert_vec(vec![43]);
+            assert_eq!(v, &vec![42]);
+            *v.first_mut().unwrap() = 44;
+        }
+        assert_eq!(m.entry(2).or_insert_vec(vec![666]), &vec![666]);
 
-    #[test]
-    fn create() {
-        let _: MultiMap<usize, usize> = MultiMap {
-            inner: HashMap::new(),
-        };
+        assert_eq!(m[&1], 44);
+        assert_eq!(m[&2], 666);
     }
 
     #[test]
-    fn new() {
-        let _: MultiMap<usize, usize> = MultiMap::new();
-    }
+    fn test_is_vec() {
+        let mut m = MultiMap::new();
+        m.insert(1, 42);
+        m.insert(1, 1337);
+        m.insert(2, 2332);
 
-    #[test]
-    fn with_capacity() {
-        let _: MultiMap<usize, usize> = MultiMap::with_capacity(20);
+        assert!(m.is_vec(&1));
+        assert!(!m.is_vec(&2));
+        assert!(!m.is_vec(&3));
     }
 
     #[test]
-    fn insert() {
-        let mut m: MultiMap<usize, usize> = MultiMap::new();
-        m.insert(1, 3);
+    fn test_macro() {
+        let mut manual_map = MultiMap::new();
+        manual_map.insert("key1", 42);
+        assert_eq!(manual_map, multimap!("key1" => 42));
+
+        manual_map.insert("key1", 1337);
+        manual_map.insert("key2", 2332);
+        let macro_map = multimap! {
+            "key1" =>    42,
+            "key1" =>  1337,
+            "key2" =>  2332
+        };
+        assert_eq!(manual_map, macro_map);
     }
 
     #[test]
-    fn insert_identical() {
-        let mut m = MultiMap::new();
-        m.insert(1, 42);
-        m.insert(1, 42);
-        assert_eq!(m.get_vec(&1), Some(&vec![42, 42]));
+    fn test_btree_and_set_macros() {
+        let mut btree_map = BTreeMultiMap::new();
+        btree_map.insert_many("key1", vec![42, 1337]);
+        btree_map.insert("key2", 2332);
+        assert_eq!(
+            btreemultimap!("key1" => 42, "key2" => 2332, "key1" => 1337),
+            btree_map
+        );
+        assert_eq!(
+            btreemultimap! { "key1" => {42, 1337}, "key2" => {2332} },
+            btree_map
+        );
+        assert_eq!(
+            btreemultimap! { "key1" => {42, 1337}, "key2" => 2332 },
+            btree_map
+        );
+        assert_eq!(btreemultimap!(), BTreeMultiMap::<u8, u8>::new());
+        assert_eq!(setmultimap!(), SetMultiMap::<u8, u8>::new());
+        assert_eq!(multimap!(), MultiMap::<u8, u8>::new());
+
+        let mut set_map = SetMultiMap::new();
+        set_map.insert_many("key1", vec![42, 1337]);
+        set_map.insert("key2", 2332);
+        assert_eq!(
+            setmultimap!("key1" => 42, "key2" => 2332, "key1" => 1337, "key1" => 42),
+            set_map
+        );
+        assert_eq!(
+            setmultimap! { "key1" => {42, 1337, 42}, "key2" => {2332} },
+            set_map
+        );
+        assert_eq!(
+            setmultimap! { "key1" => {42, 1337}, "key2" => 2332, "key1" => 42 },
+            set_map
+        );
     }
 
     #[test]
-    fn insert_many() {
-        let mut m: MultiMap<usize, usize> = MultiMap::new();
-        m.insert_many(1, vec![3, 4]);
-        assert_eq!(Some(&vec![3, 4]), m.get_vec(&1));
+    #[cfg(feature = "std")]
+    fn test_static_macro() {
+        static_multimap! {
+            static GROUPED: &'static str => u32 = {
+                "a" => {1, 2},
+                "b" => {4},
+                "a" => {3},
+            };
+            /// Flat entries.
+            pub(crate) static FLAT: u32 => u32 = { 1 => 2, 1 => 3 };
+        }
+
+        assert_eq!(GROUPED.get_slice("a"), Some(&[1, 2, 3][..]));
+        assert_eq!(GROUPED.get_slice("b"), Some(&[4][..]));
+        assert_eq!(GROUPED.len(), 2);
+        assert_eq!(FLAT.get_slice(&1), Some(&[2, 3][..]));
     }
 
     #[test]
-    fn insert_many_again() {
-        let mut m: MultiMap<usize, usize> = MultiMap::new();
-        m.insert(1, 2);
-        m.insert_many(1, vec![3, 4]);
-        assert_eq!(Some(&vec![2, 3, 4]), m.get_vec(&1));
+    fn test_macro_grouped() {
+        let mut manual_map = MultiMap::new();
+        manual_map.insert_many("key1", vec![42, 1337, 7]);
+        manual_map.insert("key3", 2332);
+
+        let macro_map = multimap! {
+            "key1" => {42},
+            "key3" => {2332,},
+            "key1" => {1337, 7},
+        };
+        assert_eq!(manual_map, macro_map);
+        assert!(macro_map.capacity() >= 3);
+
+        let arrays = multimap!("key1" => [1, 2], "key1" => [3, 4]);
+        assert_eq!(arrays.get_vec("key1"), Some(&vec![[1, 2], [3, 4]]));
+
+        let blocks = multimap! {
+            "key1" => { let x = 1; x + 1 },
+            "key1" => 3,
+        };
+        assert_eq!(blocks.get_vec("key1"), Some(&vec![2, 3]));
     }
 
     #[test]
-    fn insert_many_overlap() {
-        let mut m: MultiMap<usize, usize> = MultiMap::new();
-        m.insert_many(1, vec![2, 3]);
-        m.insert_many(1, vec![3, 4]);
-        assert_eq!(Some(&vec![2, 3, 3, 4]), m.get_vec(&1));
+    fn test_macro_mixed() {
+        let mut manual_map = MultiMap::new();
+        manual_map.insert_many("a", vec![1, 2, -3, 4]);
+        manual_map.insert("b", 3);
+
+        assert_eq!(
+            multimap! {"a" => {1, 2}, "b" => 3, "a" => {-3}, "a" => 4},
+            manual_map
+        );
+        assert_eq!(
+            multimap! {"a" => {1, 2}, "b" => 1 + 2, "a" => -3, "a" => {4,},},
+            manual_map
+        );
+        assert!(multimap! {"a" => {1, 2}, "b" => -3}.capacity() >= 2);
     }
 
     #[test]
-    fn insert_many_from_slice() {
-        let mut m: MultiMap<usize, usize> = MultiMap::new();
-        m.insert_many_from_slice(1, &[3, 4]);
-        assert_eq!(Some(&vec![3, 4]), m.get_vec(&1));
+    fn retain_removes_element() {
+        let mut m = MultiMap::new();
+        m.insert(1, 42);
+        m.insert(1, 99);
+        m.retain(|&k, &v| k == 1 && v == 42);
+        assert_eq!(1, m.len());
+        assert_eq!(Some(&42), m.get(&1));
     }
 
     #[test]
-    fn insert_many_from_slice_again() {
-        let mut m: MultiMap<usize, usize> = MultiMap::new();
-        m.insert(1, 2);
-        m.insert_many_from_slice(1, &[3, 4]);
-        assert_eq!(Some(&vec![2, 3, 4]), m.get_vec(&1));
+    fn retain_also_removes_empty_vector() {
+        let mut m = MultiMap::new();
+        m.insert(1, 42);
+        m.insert(1, 99);
+        m.insert(2, 42);
+        m.retain(|&k, &v| k == 1 && v == 42);
+        assert_eq!(1, m.len());
+        assert_eq!(Some(&42), m.get(&1));
     }
 
     #[test]
-    fn insert_existing() {
-        let mut m: MultiMap<usize, usize> = MultiMap::new();
-        m.insert(1, 3);
-        m.insert(1, 4);
-        assert_eq!(Some(&vec![3, 4]), m.get_vec(&1));
+    fn vec_deque_container() {
+        use std::collections::VecDeque;
+
+        let mut m: MultiMap<usize, usize, RandomState, VecDeque<usize>> =
+            MultiMap::with_container();
+        m.insert(1, 42);
+        m.insert_many(1, vec![43, 44]);
+        assert_eq!(m.get(&1), Some(&42));
+        assert!(m.is_vec(&1));
+        assert_eq!(m.flat_iter().count(), 3);
+
+        m.retain(|_, &v| v != 42);
+        assert_eq!(m.remove(&1), Some(VecDeque::from(vec![43, 44])));
     }
 
     #[test]
-    #[should_panic(expected = "no entry found for key")]
-    fn index_no_entry() {
-        let m: MultiMap<usize, usize> = MultiMap::new();
-        let _ = &m[&1];
+    fn set_container() {
+        use std::collections::HashSet;
+
+        let mut m: MultiMap<usize, usize, RandomState, HashSet<usize>> = MultiMap::with_container();
+        m.insert(1, 42);
+        m.insert(1, 42);
+        m.insert(2, 43);
+        assert_eq!(m.len(), 2);
+        assert_eq!(m.get(&1), Some(&42));
+        assert!(!m.is_vec(&1));
+
+        m.retain(|&k, _| k == 2);
+        assert!(!m.contains_key(&1));
+        assert_eq!(format!("{:?}", m), "{2: {43}}");
     }
 
     #[test]
-    fn index() {
+    #[cfg(feature = "std")]
+    fn try_insert() {
         let mut m: MultiMap<usize, usize> = MultiMap::new();
-        m.insert(1, 41);
-        m.insert(2, 42);
-        m.insert(3, 43);
-        let values = m[&2];
-        assert_eq!(values, 42);
+        assert!(m.try_insert(1, 42).is_ok());
+        assert!(m.try_insert(1, 43).is_ok());
+        assert!(m.try_insert(2, 44).is_ok());
+        assert_eq!(m.get_vec(&1), Some(&vec![42, 43]));
+        assert_eq!(m.get_vec(&2), Some(&vec![44]));
+
+        assert!(m.try_reserve(usize::MAX).is_err());
+        assert_eq!(m.len(), 2);
     }
 
     #[test]
-    #[should_panic(expected = "no value found for key")]
-    fn index_empty_vec() {
+    fn insert_unique() {
         let mut m: MultiMap<usize, usize> = MultiMap::new();
+        assert!(m.insert_unique(1, 42));
+        assert!(!m.insert_unique(1, 42));
         m.insert(1, 42);
-        m.get_vec_mut(&1).unwrap().clear();
-        let values = m[&1];
-        assert_eq!(values, 42);
+        assert!(!m.insert_unique(1, 42));
+        assert!(m.insert_unique(2, 42));
+        assert_eq!(m.get_vec(&1), Some(&vec![42, 42]));
+        assert_eq!(m.get_vec(&2), Some(&vec![42]));
     }
 
     #[test]
-    fn contains_key_true() {
+    fn remove_first_and_last() {
         let mut m: MultiMap<usize, usize> = MultiMap::new();
-        m.insert(1, 42);
-        assert!(m.contains_key(&1));
+        m.insert_many(1, vec![1, 2, 3]);
+        assert_eq!(m.remove_first(&1), Some(1));
+        assert_eq!(m.remove_last(&1), Some(3));
+        assert_eq!(m.get_vec(&1), Some(&vec![2]));
+        assert_eq!(m.remove_last(&1), Some(2));
+        assert!(m.is_empty());
+        assert_eq!(m.remove_first(&1), None);
     }
 
     #[test]
-    fn contains_key_false() {
-        let m: MultiMap<usize, usize> = MultiMap::new();
+    fn remove_first_drops_empty_vec() {
+        let mut m: MultiMap<usize, usize> = MultiMap::new();
+        m.insert_many(1, vec![]);
+        assert_eq!(m.remove_first(&1), None);
         assert!(!m.contains_key(&1));
     }
 
     #[test]
-    fn len() {
+    fn remove_value() {
         let mut m: MultiMap<usize, usize> = MultiMap::new();
-        m.insert(1, 42);
-        m.insert(2, 1337);
-        m.insert(3, 99);
-        assert_eq!(m.len(), 3);
+        m.insert_many(1, vec![1, 2, 1]);
+        m.insert(2, 1);
+        assert_eq!(m.remove_value(&1, &1), Some(1));
+        assert_eq!(m.get_vec(&1), Some(&vec![2, 1]));
+        assert_eq!(m.remove_value(&1, &3), None);
+        assert_eq!(m.remove_value(&3, &1), None);
+        assert_eq!(m.remove_value(&2, &1), Some(1));
+        assert!(!m.contains_key(&2));
     }
 
     #[test]
-    fn remove_not_present() {
+    fn retain_count() {
         let mut m: MultiMap<usize, usize> = MultiMap::new();
-        let v = m.remove(&1);
-        assert_eq!(v, None);
+        m.insert_many(1, vec![1, 2, 3]);
+        m.insert_many(2, vec![4, 5]);
+        assert_eq!(m.retain_count(|_, &v| v % 2 == 0), 3);
+        assert_eq!(m.retain_count(|_, _| true), 0);
+        assert_eq!(m.get_vec(&1), Some(&vec![2]));
+        assert_eq!(m.get_vec(&2), Some(&vec![4]));
     }
 
     #[test]
-    fn remove_present() {
+    fn retain_vec_removes_empty() {
         let mut m: MultiMap<usize, usize> = MultiMap::new();
-        m.insert(1, 42);
-        let v = m.remove(&1);
-        assert_eq!(v, Some(vec![42]));
+        m.insert_many(1, vec![1, 2]);
+        m.insert_many(2, vec![3]);
+        m.retain_vec(|&k, values| {
+            if k == 1 {
+                values.clear();
+            }
+            true
+        });
+        assert!(!m.contains_key(&1));
+        assert_eq!(m.get_vec(&2), Some(&vec![3]));
     }
 
     #[test]
-    fn get_not_present() {
-        let m: MultiMap<usize, usize> = MultiMap::new();
-        assert_eq!(m.get(&1), None);
+    fn drain_keeps_capacity() {
+        let mut m: MultiMap<usize, usize> = MultiMap::with_capacity(16);
+        m.insert_many(1, vec![1, 2]);
+        m.insert(2, 3);
+        let capacity = m.capacity();
+
+        let mut drained: Vec<_> = m.drain().collect();
+        drained.sort();
+        assert_eq!(drained, [(1, vec![1, 2]), (2, vec![3])]);
+        assert!(m.is_empty());
+        assert_eq!(m.capacity(), capacity);
     }
 
     #[test]
-    fn get_present() {
+    fn remove_values_if() {
         let mut m: MultiMap<usize, usize> = MultiMap::new();
-        m.insert(1, 42);
-        assert_eq!(m.get(&1), Some(&42));
+        m.insert_many(1, vec![1, 2, 3, 4]);
+        m.insert_many(2, vec![5]);
+
+        let mut odd = m.remove_values_if(|_, &v| v % 2 == 1);
+        odd.sort();
+        assert_eq!(odd, [(1, 1), (1, 3), (2, 5)]);
+        assert_eq!(m.get_vec(&1), Some(&vec![2, 4]));
+        assert!(!m.contains_key(&2));
+
+        m.remove_values_if(|&k, _| k == 1);
+        assert!(m.is_empty());
     }
 
     #[test]
-    fn get_empty() {
+    fn drain_vec_keeps_capacity() {
         let mut m: MultiMap<usize, usize> = MultiMap::new();
-        m.insert(1, 42);
-        m.get_vec_mut(&1).and_then(Vec::pop);
-        assert_eq!(m.get(&1), None);
+        m.insert_many(1, vec![1, 2, 3]);
+        let capacity = m.get_vec(&1).unwrap().capacity();
+
+        assert_eq!(m.drain_vec(&1).unwrap().collect::<Vec<_>>(), [1, 2, 3]);
+        let values = m.get_vec(&1).unwrap();
+        assert!(values.is_empty());
+        assert_eq!(values.capacity(), capacity);
     }
 
     #[test]
-    fn get_vec_not_present() {
-        let m: MultiMap<usize, usize> = MultiMap::new();
-        assert_eq!(m.get_vec(&1), None);
+    fn append_preserves_multiplicity() {
+        let mut a: MultiMap<usize, usize> = MultiMap::new();
+        a.insert_many(1, vec![1, 1]);
+        let mut b: MultiMap<usize, usize> = MultiMap::new();
+        b.insert_many(1, vec![1]);
+        b.insert_many(2, vec![2]);
+
+        a.append(&mut b);
+        assert!(b.is_empty());
+        assert_eq!(a.get_vec(&1), Some(&vec![1, 1, 1]));
+        assert_eq!(a.get_vec(&2), Some(&vec![2]));
     }
 
     #[test]
-    fn get_vec_present() {
-        let mut m: MultiMap<usize, usize> = MultiMap::new();
-        m.insert(1, 42);
-        m.insert(1, 1337);
-        assert_eq!(m.get_vec(&1), Some(&vec![42, 1337]));
+    fn merge_with_conflicts_only() {
+        let mut a: MultiMap<usize, usize> = MultiMap::new();
+        a.insert_many(1, vec![1]);
+        a.insert_many(2, vec![2]);
+        let mut b: MultiMap<usize, usize> = MultiMap::new();
+        b.insert_many(1, vec![10]);
+        b.insert_many(2, vec![20]);
+        b.insert_many(3, vec![30]);
+
+        let mut conflicts = Vec::new();
+        a.merge_with(b, |&k, mine, theirs| {
+            conflicts.push(k);
+            if k == 1 {
+                *mine = theirs;
+            } else {
+                mine.clear();
+            }
+        });
+        conflicts.sort();
+        assert_eq!(conflicts, [1, 2]);
+        assert_eq!(a.get_vec(&1), Some(&vec![10]));
+        assert!(!a.contains_key(&2));
+        assert_eq!(a.get_vec(&3), Some(&vec![30]));
     }
 
     #[test]
-    fn capacity() {
-        let m: MultiMap<usize, usize> = MultiMap::with_capacity(20);
-        assert!(m.capacity() >= 20);
+    fn partition() {
+        let mut m: MultiMap<usize, usize> = MultiMap::new();
+        m.insert_many(1, vec![1, 2]);
+        m.insert_many(2, vec![3]);
+        m.insert_many(3, vec![4]);
+
+        let (small, large) = m.partition(|&k| k < 3);
+        assert_eq!(small.len(), 2);
+        assert_eq!(small.get_vec(&1), Some(&vec![1, 2]));
+        assert_eq!(small.get_vec(&2), Some(&vec![3]));
+        assert_eq!(large.len(), 1);
+        assert_eq!(large.get_vec(&3), Some(&vec![4]));
     }
 
     #[test]
-    fn is_empty_true() {
-        let m: MultiMap<usize, usize> = MultiMap::new();
-        assert!(m.is_empty());
+    fn set_operations() {
+        let a: MultiMap<&str, usize> = vec![("x", 1), ("x", 1), ("x", 2), ("y", 5)]
+            .into_iter()
+            .collect();
+        let b: MultiMap<&str, usize> = vec![("x", 1), ("x", 3), ("z", 6)].into_iter().collect();
+
+        let union = a.union(&b);
+        assert_eq!(union.get_vec("x"), Some(&vec![1, 1, 2, 3]));
+        assert_eq!(union.get_vec("y"), Some(&vec![5]));
+        assert_eq!(union.get_vec("z"), Some(&vec![6]));
+
+        let intersection = a.intersection(&b);
+        assert_eq!(intersection.len(), 1);
+        assert_eq!(intersection.get_vec("x"), Some(&vec![1]));
+
+        let difference = a.difference(&b);
+        assert_eq!(difference.get_vec("x"), Some(&vec![1, 2]));
+        assert_eq!(difference.get_vec("y"), Some(&vec![5]));
+        assert!(!difference.contains_key("z"));
+
+        let symmetric_difference = a.symmetric_difference(&b);
+        assert_eq!(symmetric_difference.get_vec("x"), Some(&vec![1, 2, 3]));
+        assert_eq!(symmetric_difference.len(), 3);
+        let mut reversed = b.symmetric_difference(&a);
+        reversed.get_vec_mut("x").unwrap().sort();
+        assert_eq!(symmetric_difference, reversed);
     }
 
     #[test]
-    fn is_empty_false() {
-        let mut m: MultiMap<usize, usize> = MultiMap::new();
-        m.insert(1, 42);
-        assert!(!m.is_empty());
+    fn diff_and_apply() {
+        let mut a: MultiMap<usize, usize> = MultiMap::new();
+        a.insert_many(1, vec![1, 2]);
+        a.insert_many(2, vec![3]);
+        a.insert_many(3, vec![4]);
+        let mut b: MultiMap<usize, usize> = MultiMap::new();
+        b.insert_many(1, vec![2, 1]);
+        b.insert_many(3, vec![4]);
+        b.insert_many(4, vec![5]);
+
+        let diff = a.diff(&b);
+        assert_eq!(diff.added, [(4, vec![5])]);
+        assert_eq!(diff.removed, [2]);
+        assert_eq!(diff.changed, [(1, vec![2, 1])]);
+        assert_eq!(diff.len(), 3);
+
+        let mut c = a.clone();
+        c.apply(diff);
+        assert_eq!(c, b);
+        assert!(c.diff(&b).is_empty());
     }
 
     #[test]
-    fn clear() {
+    fn invert_twice() {
         let mut m: MultiMap<usize, usize> = MultiMap::new();
-        m.insert(1, 42);
-        m.clear();
-        assert!(m.is_empty());
+        m.insert_many(1, vec![10, 20, 10]);
+        m.insert_many(2, vec![20]);
+
+        let inverted = m.clone().invert();
+        assert_eq!(inverted.get_vec(&10), Some(&vec![1, 1]));
+        assert_eq!(inverted.get_vec(&20).map(|v| v.len()), Some(2));
+
+        let mut back = inverted.invert();
+        back.get_vec_mut(&1).unwrap().sort();
+        assert_eq!(back.get_vec(&1), Some(&vec![10, 10, 20]));
+        assert_eq!(back.get_vec(&2), Some(&vec![20]));
     }
 
     #[test]
-    fn get_mut() {
-        let mut m: MultiMap<usize, usize> = MultiMap::new();
-        m.insert(1, 42);
-        if let Some(v) = m.get_mut(&1) {
-            *v = 1337;
-        }
-        assert_eq!(m[&1], 1337)
+    fn from_grouped_by_keeps_order() {
+        let map = MultiMap::from_grouped_by(vec![5, 2, 8, 3, 6], |n| n % 2 == 0);
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.get_vec(&true), Some(&vec![2, 8, 6]));
+        assert_eq!(map.get_vec(&false), Some(&vec![5, 3]));
+
+        let empty = group_by(Vec::<u8>::new(), |n| *n);
+        assert!(empty.is_empty());
     }
 
     #[test]
-    fn get_vec_mut() {
-        let mut m: MultiMap<usize, usize> = MultiMap::new();
-        m.insert(1, 42);
-        m.insert(1, 1337);
-        if let Some(v) = m.get_vec_mut(&1) {
-            (*v)[0] = 5;
-            (*v)[1] = 10;
-        }
-        assert_eq!(m.get_vec(&1), Some(&vec![5, 10]))
+    fn from_vec_iterator_moves_values() {
+        #[derive(Debug, PartialEq)]
+        struct NotClone(u8);
+
+        let mut map: MultiMap<&str, NotClone> = vec![
+            ("a", vec![NotClone(1), NotClone(2)]),
+            ("b", vec![NotClone(3)]),
+            ("a", vec![NotClone(4)]),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(
+            map.get_vec("a"),
+            Some(&vec![NotClone(1), NotClone(2), NotClone(4)])
+        );
+
+        map.extend(vec![("b", vec![NotClone(5)]), ("c", vec![NotClone(6)])]);
+        assert_eq!(map.get_vec("b"), Some(&vec![NotClone(3), NotClone(5)]));
+        assert_eq!(map.get_vec("c"), Some(&vec![NotClone(6)]));
     }
 
     #[test]
-    fn get_mut_empty() {
-        let mut m: MultiMap<usize, usize> = MultiMap::new();
-        m.insert(1, 42);
-        m.get_vec_mut(&1).and_then(Vec::pop);
-        assert_eq!(m.get_mut(&1), None);
+    fn extend_from_borrowed_clones() {
+        let mut source: MultiMap<String, String> = MultiMap::new();
+        source.insert("a".to_string(), "1".to_string());
+        source.insert("a".to_string(), "2".to_string());
+        source.insert("b".to_string(), "3".to_string());
+
+        let mut flat: MultiMap<String, String> = MultiMap::new();
+        flat.extend(source.flat_iter());
+        assert_eq!(flat, source);
+
+        let mut grouped: MultiMap<String, String> = MultiMap::new();
+        grouped.extend(source.iter_all());
+        assert_eq!(grouped, source);
     }
 
     #[test]
-    fn keys() {
-        let mut m: MultiMap<usize, usize> = MultiMap::new();
-        m.insert(1, 42);
-        m.insert(2, 42);
-        m.insert(4, 42);
-        m.insert(8, 42);
+    fn total_len_tracks_mutable_access() {
+        let mut map: MultiMap<usize, usize> = MultiMap::new();
+        assert_eq!(map.total_len(), 0);
 
-        let keys: Vec<_> = m.keys().cloned().collect();
-        assert_eq!(keys.len(), 4);
-        assert!(keys.contains(&1));
-        assert!(keys.contains(&2));
-        assert!(keys.contains(&4));
-        assert!(keys.contains(&8));
+        map.insert_many(1, vec![1, 2, 3]);
+        map.insert(2, 4);
+        assert_eq!(map.keys_len(), 2);
+        assert_eq!(map.total_len(), 4);
+
+        map.get_vec_mut(&1).unwrap().truncate(1);
+        assert_eq!(map.total_len(), 2);
     }
 
     #[test]
-    fn iter() {
-        let mut m: MultiMap<usize, usize> = MultiMap::new();
-        m.insert(1, 42);
-        m.insert(1, 42);
-        m.insert(4, 42);
-        m.insert(8, 42);
-
-        assert!(m.iter().all(|(_, &v)| v == 42));
+    fn counts_and_histogram() {
+        let mut map: MultiMap<&str, usize> = MultiMap::new();
+        assert_eq!(map.counts().count(), 0);
+        assert!(map.values_histogram().is_empty());
+
+        map.insert_many("a", vec![1, 2, 3]);
+        map.insert_many("b", vec![4, 5, 6]);
+        map.insert("c", 7);
+        map.insert_many("d", vec![]);
+
+        let counts: HashMap<_, _> = map.counts().collect();
+        assert_eq!(counts[&"a"], 3);
+        assert_eq!(counts[&"c"], 1);
+        assert_eq!(counts[&"d"], 0);
+
+        let histogram = map.values_histogram();
+        assert_eq!(histogram.get(&0), Some(&1));
+        assert_eq!(histogram.get(&1), Some(&1));
+        assert_eq!(histogram.get(&3), Some(&2));
+        assert_eq!(histogram.values().sum::<usize>(), map.keys_len());
+    }
 
-        let mut iter = m.iter();
+    #[test]
+    fn contains_pair_and_value() {
+        let mut map: MultiMap<String, usize> = MultiMap::new();
+        map.insert_many("a".to_string(), vec![1, 2]);
+        map.insert("b".to_string(), 3);
 
-        for _ in iter.by_ref().take(2) {}
+        assert!(map.contains_pair("a", &2));
+        assert!(!map.contains_pair("a", &3));
+        assert!(!map.contains_pair("c", &1));
 
-        assert_eq!(iter.len(), 1);
+        assert!(map.contains_value(&3));
+        assert!(!map.contains_value(&4));
     }
 
     #[test]
-    fn iter_empty_vec() {
-        let mut m: MultiMap<usize, usize> = MultiMap::new();
-        m.insert(42, 42);
-        m.get_vec_mut(&42).unwrap().clear();
-
-        assert!(m.iter().next().is_none());
+    fn get_key_value_returns_stored_key() {
+        use std::rc::Rc;
+
+        let stored: Rc<str> = Rc::from("key");
+        let mut map: MultiMap<Rc<str>, usize> = MultiMap::new();
+        map.insert(stored.clone(), 1);
+        map.insert(Rc::from("key"), 2);
+
+        let (key, values) = map.get_key_value("key").unwrap();
+        assert!(Rc::ptr_eq(key, &stored));
+        assert_eq!(values, &[1, 2]);
+        assert!(map.get_key_value("missing").is_none());
     }
 
     #[test]
-    fn flat_iter() {
-        let mut m: MultiMap<usize, usize> = MultiMap::new();
-        m.insert(1, 42);
-        m.insert(1, 43);
-        m.insert(4, 42);
-        m.insert(8, 42);
+    fn get_nth_bounds() {
+        let mut map: MultiMap<usize, usize> = MultiMap::new();
+        map.insert_many(1, vec![10, 20, 30]);
+        map.insert_many(2, vec![]);
+
+        assert_eq!(map.get_nth(&1, 0), Some(&10));
+        assert_eq!(map.get_nth(&1, 2), Some(&30));
+        assert_eq!(map.get_nth(&1, 3), None);
+        assert_eq!(map.get_nth(&2, 0), None);
+        assert_eq!(map.get_nth(&3, 0), None);
+
+        *map.get_nth_mut(&1, 2).unwrap() = 31;
+        assert!(map.get_nth_mut(&1, 3).is_none());
+        assert_eq!(map.get_vec(&1), Some(&vec![10, 20, 31]));
+    }
 
-        let keys = vec![1, 4, 8];
+    #[test]
+    fn first_and_last() {
+        let mut map: MultiMap<usize, usize> = MultiMap::new();
+        map.insert(1, 10);
+        assert_eq!(map.first(&1), map.last(&1));
+
+        map.insert(1, 20);
+        map.insert_many(2, vec![]);
+        assert_eq!(map.first(&1), Some(&10));
+        assert_eq!(map.last(&1), Some(&20));
+        assert_eq!(map.first(&2), None);
+        assert_eq!(map.last(&3), None);
+
+        *map.first_mut(&1).unwrap() += 1;
+        *map.last_mut(&1).unwrap() += 1;
+        assert!(map.last_mut(&2).is_none());
+        assert_eq!(map.get_vec(&1), Some(&vec![11, 21]));
+    }
 
-        for (key, value) in m.flat_iter() {
-            assert!(keys.contains(key));
+    #[test]
+    fn get_many_mut_disjoint() {
+        let mut map: MultiMap<String, usize> = MultiMap::new();
+        map.insert_many("a".to_string(), vec![1, 2]);
+        map.insert_many("b".to_string(), vec![3]);
+        map.insert_many("c".to_string(), vec![4]);
 
-            if key == &1 {
-                assert!(value == &42 || value == &43);
-            } else {
-                assert_eq!(value, &42);
-            }
+        {
+            let [c, a, b] = map.get_many_mut(["c", "a", "b"]).unwrap();
+            a.push(c.pop().unwrap());
+            b.clear();
         }
+        assert_eq!(map.get_vec("a"), Some(&vec![1, 2, 4]));
+        assert_eq!(map.get_vec("b"), Some(&vec![]));
+        assert_eq!(map.get_vec("c"), Some(&vec![]));
+
+        assert!(map.get_many_mut(["a", "b", "a"]).is_none());
+        assert!(map.get_many_mut(["a", "missing"]).is_none());
+        assert!(map.get_many_mut::<str, 0>([]).is_some());
     }
 
     #[test]
-    fn flat_iter_mut() {
-        let mut m: MultiMap<usize, usize> = MultiMap::new();
-        m.insert(1, 42);
-        m.insert(1, 43);
-        m.insert(4, 42);
-        m.insert(8, 42);
+    fn move_values_between_keys() {
+        let mut map: MultiMap<String, usize> = MultiMap::new();
+        map.insert_many("a".to_string(), vec![1, 2]);
 
-        let keys = vec![1, 4, 8];
+        map.move_values("a", "b".to_string());
+        assert!(!map.contains_key("a"));
+        assert_eq!(map.get_vec("b"), Some(&vec![1, 2]));
 
-        for (key, value) in m.flat_iter_mut() {
-            assert!(keys.contains(key));
+        map.move_values("b", "b".to_string());
+        assert_eq!(map.get_vec("b"), Some(&vec![1, 2]));
 
-            if key == &1 {
-                assert!(value == &42 || value == &43);
+        map.move_values("missing", "b".to_string());
+        assert_eq!(map.len(), 1);
+        assert_eq!(map.get_vec("b"), Some(&vec![1, 2]));
+    }
 
-                *value = 55;
-                assert_eq!(value, &55);
-            } else {
-                assert_eq!(value, &42);
+    #[test]
+    fn replace_returns_old_values() {
+        let mut map: MultiMap<usize, usize> = MultiMap::new();
+        map.insert_many(1, vec![1, 2]);
+
+        assert_eq!(map.replace(1, vec![3]), Some(vec![1, 2]));
+        assert_eq!(map.replace(2, vec![]), None);
+        assert_eq!(map.get_vec(&1), Some(&vec![3]));
+        assert_eq!(map.get_vec(&2), Some(&vec![]));
+        assert_eq!(map.len(), 2);
+    }
 
-                *value = 76;
-                assert_eq!(value, &76);
-            }
-        }
+    #[test]
+    fn truncate_and_keep_last() {
+        let mut map: MultiMap<usize, usize> = MultiMap::new();
+        map.insert_many(1, vec![1, 2, 3, 4, 5]);
+        map.insert_many(2, vec![1, 2, 3]);
+
+        map.truncate_values(&1, 10);
+        map.keep_last(&2, 10);
+        assert_eq!(map.get_vec(&1), Some(&vec![1, 2, 3, 4, 5]));
+        assert_eq!(map.get_vec(&2), Some(&vec![1, 2, 3]));
+
+        map.truncate_values(&1, 3);
+        map.keep_last(&2, 1);
+        assert_eq!(map.get_vec(&1), Some(&vec![1, 2, 3]));
+        assert_eq!(map.get_vec(&2), Some(&vec![3]));
+
+        map.keep_last(&1, 0);
+        map.truncate_values(&3, 0);
+        assert_eq!(map.get_vec(&1), Some(&vec![]));
+        assert!(!map.contains_key(&3));
     }
 
     #[test]
-    fn intoiterator_for_reference_type() {
-        let mut m: MultiMap<usize, usize> = MultiMap::new();
-        m.insert(1, 42);
-        m.insert(1, 43);
-        m.insert(4, 42);
-        m.insert(8, 42);
+    fn dedup_keeps_first_occurrence() {
+        let mut map: MultiMap<usize, &str> = MultiMap::new();
+        map.insert_many(1, vec!["b", "a", "b", "c", "a", "b"]);
+        map.insert_many(2, vec!["x", "x"]);
+        map.insert_many(3, vec![]);
+
+        map.dedup_values(&1);
+        assert_eq!(map.get_vec(&1), Some(&vec!["b", "a", "c"]));
+        assert_eq!(map.get_vec(&2), Some(&vec!["x", "x"]));
+
+        map.dedup_values(&4);
+        map.dedup_all();
+        assert_eq!(map.get_vec(&1), Some(&vec!["b", "a", "c"]));
+        assert_eq!(map.get_vec(&2), Some(&vec!["x"]));
+        assert_eq!(map.get_vec(&3), Some(&vec![]));
+    }
 
-        let keys = vec![1, 4, 8];
+    #[test]
+    fn sort_values_is_stable() {
+        let mut map: MultiMap<usize, (u8, char)> = MultiMap::new();
+        map.insert_many(1, vec![(2, 'a'), (1, 'b'), (2, 'c'), (1, 'd')]);
+        map.insert_many(2, vec![(9, 'z'), (0, 'y')]);
+
+        map.sort_all_values_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(
+            map.get_vec(&1),
+            Some(&vec![(1, 'b'), (1, 'd'), (2, 'a'), (2, 'c')])
+        );
+        assert_eq!(map.get_vec(&2), Some(&vec![(0, 'y'), (9, 'z')]));
 
-        for (key, value) in &m {
-            assert!(keys.contains(key));
+        map.insert(2, (5, 'x'));
+        map.sort_values(&2);
+        map.sort_values(&3);
+        assert_eq!(map.get_vec(&2), Some(&vec![(0, 'y'), (5, 'x'), (9, 'z')]));
+    }
 
-            if key == &1 {
-                assert_eq!(value, &vec![42, 43]);
-            } else {
-                assert_eq!(value, &vec![42]);
-            }
-        }
+    #[test]
+    fn prune_empty_removes_only_empty_keys() {
+        let mut map: MultiMap<usize, usize> = MultiMap::new();
+        assert_eq!(map.prune_empty(), 0);
+
+        map.insert_many(1, vec![1]);
+        map.insert_many(2, vec![]);
+        map.insert_many(3, vec![2, 3]);
+        map.drain_vec(&3);
+
+        assert_eq!(map.prune_empty(), 2);
+        assert_eq!(map.len(), 1);
+        assert_eq!(map.iter().count(), 1);
+        assert_eq!(map.prune_empty(), 0);
     }
 
     #[test]
-    fn intoiterator_for_mutable_reference_type() {
-        let mut m: MultiMap<usize, usize> = MultiMap::new();
-        m.insert(1, 42);
-        m.insert(1, 43);
-        m.insert(4, 42);
-        m.insert(8, 42);
+    fn effectively_empty_ignores_empty_vectors() {
+        let mut map: MultiMap<usize, usize> = MultiMap::new();
+        assert!(map.is_effectively_empty());
+        assert_eq!(map.non_empty_len(), 0);
+
+        map.insert_many(1, vec![]);
+        map.insert_many(2, vec![]);
+        assert!(!map.is_empty());
+        assert!(map.is_effectively_empty());
+        assert_eq!(map.non_empty_len(), 0);
+
+        map.insert(2, 3);
+        assert!(!map.is_effectively_empty());
+        assert_eq!(map.non_empty_len(), 1);
+        assert_eq!(map.len(), 2);
+    }
 
-        let keys = vec![1, 4, 8];
+    #[test]
+    fn clear_values_keeps_capacity() {
+        let mut map: MultiMap<usize, usize> = MultiMap::new();
+        map.insert_many(1, vec![1, 2, 3]);
+        let capacity = map.get_vec(&1).unwrap().capacity();
+
+        assert_eq!(map.clear_values(&1), 3);
+        assert!(map.contains_key(&1));
+        assert_eq!(map.get_vec(&1).unwrap().capacity(), capacity);
+        assert_eq!(map.clear_values(&1), 0);
+        assert!(!map.contains_key(&2));
+    }
 
-        for (key, value) in &mut m {
-            assert!(keys.contains(key));
+    #[test]
+    fn values_of_missing_key_is_empty() {
+        let mut map: MultiMap<String, usize> = MultiMap::new();
+        map.insert_many("a".to_string(), vec![1, 2]);
+        map.insert_many("b".to_string(), vec![]);
+
+        assert_eq!(map.values_of("a"), [1, 2]);
+        assert!(map.values_of("b").is_empty());
+        assert!(map.values_of("missing").is_empty());
+    }
 
-            if key == &1 {
-                assert_eq!(value, &vec![42, 43]);
-                value.push(666);
-            } else {
-                assert_eq!(value, &vec![42]);
-            }
-        }
+    #[test]
+    fn reserve_values_only_touches_existing_keys() {
+        let mut map: MultiMap<String, usize> = MultiMap::new();
+        map.insert_many("a".to_string(), vec![1, 2]);
+
+        map.reserve_values("a", 50);
+        map.reserve_values("missing", 50);
+        assert!(map.get_vec("a").unwrap().capacity() >= 52);
+        assert!(!map.contains_key("missing"));
+
+        let capacity = map.get_vec("a").unwrap().capacity();
+        map.insert_many("a".to_string(), 3..50);
+        assert_eq!(map.get_vec("a").unwrap().capacity(), capacity);
+    }
 
-        assert_eq!(m.get_vec(&1), Some(&vec![42, 43, 666]));
+    #[test]
+    fn with_key_and_value_capacity_presizes_vectors() {
+        let mut map: MultiMap<&str, usize> =
+            MultiMap::with_key_and_value_capacity(vec!["a", "b", "a"], 20);
+        assert_eq!(map.len(), 2);
+        assert!(map.is_effectively_empty());
+
+        let capacity = map.get_vec("a").unwrap().capacity();
+        assert!(capacity >= 20);
+        for v in 0..20 {
+            map.insert("a", v);
+        }
+        assert_eq!(map.get_vec("a").unwrap().capacity(), capacity);
+        assert_eq!(map.iter().count(), 1);
     }
 
     #[test]
-    fn intoiterator_consuming() {
-        let mut m: MultiMap<usize, usize> = MultiMap::new();
-        m.insert(1, 42);
-        m.insert(1, 43);
-        m.insert(4, 42);
-        m.insert(8, 42);
+    fn shrink_to_fit_releases_dead_capacity() {
+        let mut map: MultiMap<usize, usize> = MultiMap::new();
+        for key in 0..1000 {
+            map.insert_many(key, 0..10);
+        }
+        map.retain(|&k, &v| k < 10 && v < 1);
+        let capacity = map.capacity();
 
-        let keys = vec![1, 4, 8];
+        map.shrink_values_to_fit();
+        assert_eq!(map.capacity(), capacity);
+        assert!(map.iter_all().all(|(_, values)| values.capacity() == 1));
 
-        for (key, value) in m {
-            assert!(keys.contains(&key));
+        map.shrink_to_fit();
+        assert!(map.capacity() < capacity);
+        assert!(map.capacity() >= 10);
+        assert_eq!(map.total_len(), 10);
+    }
+
+    #[test]
+    fn from_sorted_pairs_merges_repeated_runs() {
+        let pairs = vec![(1, 1), (1, 2), (2, 3), (1, 4), (3, 5), (3, 6)];
+        let map = MultiMap::from_sorted_pairs(pairs.clone());
+        assert_eq!(map, pairs.into_iter().collect::<MultiMap<_, _>>());
+        assert_eq!(map.get_vec(&1), Some(&vec![1, 2, 4]));
+
+        let empty: MultiMap<u8, u8> = MultiMap::from_sorted_pairs(vec![]);
+        assert!(empty.is_empty());
+    }
 
-            if key == 1 {
-                assert_eq!(value, vec![42, 43]);
-            } else {
-                assert_eq!(value, vec![42]);
-            }
+    #[test]
+    fn from_sorted_pairs_allocates_exact_vectors() {
+        let pairs = (0..100).map(|i| (i / 10, i));
+        let map = MultiMap::from_sorted_pairs_with_hasher(pairs, RandomState::default());
+        assert_eq!(map.len(), 10);
+        assert!(map.capacity() >= 100);
+        for (key, values) in map.iter_all() {
+            assert_eq!(values.len(), 10);
+            assert_eq!(values.capacity(), 10);
+            assert_eq!(values[0], key * 10);
         }
     }
 
     #[test]
-    fn test_fmt_debug() {
-        let mut map = MultiMap::new();
-        let empty: MultiMap<i32, i32> = MultiMap::new();
+    fn get_all_skips_missing_keys() {
+        let mut map: MultiMap<String, usize> = MultiMap::new();
+        map.insert_many("a".to_string(), vec![1, 2]);
+        map.insert("b".to_string(), 3);
+
+        let found: Vec<_> = map.get_all(&["b", "missing", "a", "b"]).collect();
+        assert_eq!(
+            found,
+            [
+                (&"b".to_string(), &[3][..]),
+                (&"a".to_string(), &[1, 2][..]),
+                (&"b".to_string(), &[3][..]),
+            ]
+        );
+        assert_eq!(map.get_all::<str>(&[]).count(), 0);
+    }
 
-        map.insert(1, 2);
-        map.insert(1, 5);
-        map.insert(1, -1);
-        map.insert(3, 4);
+    #[test]
+    fn top_keys_by_count_bounded() {
+        let mut map: MultiMap<usize, usize> = MultiMap::new();
+        assert!(map.top_keys_by_count(3).is_empty());
 
-        let map_str = format!("{:?}", map);
+        for key in 0..10 {
+            map.insert_many(key, 0..key);
+        }
 
-        assert!(map_str == "{1: [2, 5, -1], 3: [4]}" || map_str == "{3: [4], 1: [2, 5, -1]}");
-        assert_eq!(format!("{:?}", empty), "{}");
+        assert!(map.top_keys_by_count(0).is_empty());
+        assert_eq!(map.top_keys_by_count(3), [(&9, 9), (&8, 8), (&7, 7)]);
+        assert_eq!(map.top_keys_by_count(20).len(), 10);
+        assert_eq!(map.top_keys_by_count(20).last(), Some(&(&0, 0)));
     }
 
     #[test]
-    fn test_eq() {
-        let mut m1 = MultiMap::new();
-        m1.insert(1, 2);
-        m1.insert(2, 3);
-        m1.insert(3, 4);
-        let mut m2 = MultiMap::new();
-        m2.insert(1, 2);
-        m2.insert(2, 3);
-        assert_ne!(m1, m2);
-        m2.insert(3, 4);
-        assert_eq!(m1, m2);
-        m2.insert(3, 4);
-        assert_ne!(m1, m2);
-        m1.insert(3, 4);
-        assert_eq!(m1, m2);
+    fn entry_and_modify() {
+        let mut m: MultiMap<usize, usize> = MultiMap::new();
+        m.insert(1, 42);
+
+        m.entry(1).and_modify(|values| values.push(43)).or_insert(0);
+        m.entry(2).and_modify(|values| values.push(43)).or_insert(0);
+
+        assert_eq!(m.get_vec(&1), Some(&vec![42, 43]));
+        assert_eq!(m.get_vec(&2), Some(&vec![0]));
     }
 
     #[test]
-    fn test_eq_empty_key() {
-        let mut m1 = MultiMap::new();
-        m1.insert(1, 2);
-        m1.insert(2, 3);
-        let mut m2 = MultiMap::new();
-        m2.insert(1, 2);
-        m2.insert_many(2, []);
-        assert_ne!(m1, m2);
-        m2.insert_many(2, [3]);
-        assert_eq!(m1, m2);
+    fn entry_or_insert_with() {
+        let mut m: MultiMap<usize, usize> = MultiMap::new();
+        m.insert(1, 42);
+
+        assert_eq!(*m.entry(1).or_insert_with(|| panic!("occupied")), 42);
+        assert_eq!(*m.entry(2).or_insert_with(|| 7), 7);
+        assert_eq!(*m.entry(3).or_insert_with_key(|key| key * 10), 30);
+        assert_eq!(*m.entry(4).or_default(), 0);
+        assert_eq!(
+            m.entry(1).or_insert_vec_with(|| panic!("occupied")),
+            &vec![42]
+        );
+        assert_eq!(m.entry(5).or_insert_vec_with(|| vec![1, 2]), &vec![1, 2]);
+        assert_eq!(m.len(), 5);
     }
 
     #[test]
-    fn test_default() {
-        let _: MultiMap<u8, u8> = Default::default();
+    #[cfg(feature = "std")]
+    fn entry_methods_need_no_key_bounds() {
+        fn first_or<K, V>(entry: Entry<'_, K, V>, default: V) -> &mut V {
+            entry.or_insert(default)
+        }
+
+        let mut m = MultiMap::new();
+        *first_or(m.entry(1), 2) += 1;
+        assert_eq!(m.get(&1), Some(&3));
     }
 
     #[test]
-    fn test_from_iterator() {
-        let vals: Vec<(&str, i64)> = vec![("foo", 123), ("bar", 456), ("foo", 789)];
-        let multimap: MultiMap<&str, i64> = MultiMap::from_iter(vals);
+    fn entry_key() {
+        let mut m: MultiMap<String, usize> = MultiMap::new();
+        m.insert("a".to_string(), 1);
 
-        let foo_vals: &Vec<i64> = multimap.get_vec("foo").unwrap();
-        assert!(foo_vals.contains(&123));
-        assert!(foo_vals.contains(&789));
+        assert_eq!(m.entry("a".to_string()).key(), "a");
+        assert_eq!(m.entry("b".to_string()).key(), "b");
 
-        let bar_vals: &Vec<i64> = multimap.get_vec("bar").unwrap();
-        assert!(bar_vals.contains(&456));
+        match m.entry("a".to_string()) {
+            Entry::Occupied(entry) => assert_eq!(entry.key(), "a"),
+            Entry::Vacant(_) => unreachable!(),
+        }
+        match m.entry("b".to_string()) {
+            Entry::Vacant(entry) => {
+                assert_eq!(entry.key(), "b");
+                assert_eq!(entry.into_key(), "b");
+            }
+            Entry::Occupied(_) => unreachable!(),
+        }
+        assert!(!m.contains_key("b"));
     }
 
     #[test]
-    fn test_from_vec_iterator() {
-        let vals: Vec<(&str, Vec<i64>)> = vec![
-            ("foo", vec![123, 456]),
-            ("bar", vec![234]),
-            ("foobar", vec![567, 678, 789]),
-            ("bar", vec![12, 23, 34]),
-        ];
+    fn occupied_entry_removes_empty_vec() {
+        let mut m: MultiMap<usize, usize> = MultiMap::new();
+        m.insert_many(1, vec![1, 2, 3, 4]);
 
-        let multimap: MultiMap<&str, i64> = MultiMap::from_iter(vals);
+        fn occupied(m: &mut MultiMap<usize, usize>) -> OccupiedEntry<'_, usize, usize> {
+            match m.entry(1) {
+                Entry::Occupied(entry) => entry,
+                Entry::Vacant(_) => panic!("key should be occupied"),
+            }
+        }
 
-        let foo_vals: &Vec<i64> = multimap.get_vec("foo").unwrap();
-        assert!(foo_vals.contains(&123));
-        assert!(foo_vals.contains(&456));
+        assert_eq!(occupied(&mut m).pop(), Some(4));
+        assert_eq!(occupied(&mut m).remove_first(), Some(1));
+        assert_eq!(m.get_vec(&1), Some(&vec![2, 3]));
 
-        let bar_vals: &Vec<i64> = multimap.get_vec("bar").unwrap();
-        assert!(bar_vals.contains(&234));
-        assert!(bar_vals.contains(&12));
-        assert!(bar_vals.contains(&23));
-        assert!(bar_vals.contains(&34));
+        occupied(&mut m).retain(|v| *v == 3);
+        assert_eq!(m.get_vec(&1), Some(&vec![3]));
 
-        let foobar_vals: &Vec<i64> = multimap.get_vec("foobar").unwrap();
-        assert!(foobar_vals.contains(&567));
-        assert!(foobar_vals.contains(&678));
-        assert!(foobar_vals.contains(&789));
-    }
+        assert_eq!(occupied(&mut m).pop(), Some(3));
+        assert!(!m.contains_key(&1));
 
-    #[test]
-    fn test_extend_consuming_hashmap() {
-        let mut a = MultiMap::new();
-        a.insert(1, 42);
+        m.insert_many(1, vec![5]);
+        occupied(&mut m).retain(|_| false);
+        assert!(!m.contains_key(&1));
 
-        let mut b = HashMap::new();
-        b.insert(1, 43);
-        b.insert(2, 666);
+        m.insert_many(1, vec![]);
+        assert_eq!(occupied(&mut m).remove_first(), None);
+        assert!(!m.contains_key(&1));
+    }
 
-        a.extend(b);
+    #[test]
+    fn occupied_entry_replace_values() {
+        let mut m: MultiMap<usize, usize> = MultiMap::new();
+        m.insert_many(1, vec![3, 1, 2]);
 
-        assert_eq!(a.len(), 2);
-        assert_eq!(a.get_vec(&1), Some(&vec![42, 43]));
+        if let Entry::Occupied(mut entry) = m.entry(1) {
+            entry.get_slice_mut().sort();
+            assert_eq!(entry.get_vec(), &vec![1, 2, 3]);
+            assert_eq!(entry.replace_values(vec![4]), vec![1, 2, 3]);
+        }
+        assert_eq!(m.get_vec(&1), Some(&vec![4]));
     }
 
     #[test]
-    fn test_extend_ref_hashmap() {
-        let mut a = MultiMap::new();
-        a.insert(1, 42);
+    fn occupied_entry_iter() {
+        let mut m: MultiMap<usize, usize> = MultiMap::new();
+        m.insert_many(1, vec![1, 2, 3]);
 
-        let mut b = HashMap::new();
-        b.insert(1, 43);
-        b.insert(2, 666);
+        if let Entry::Occupied(mut entry) = m.entry(1) {
+            assert_eq!(entry.len(), 3);
+            assert!(!entry.is_empty());
+            for value in entry.iter_mut() {
+                *value *= 10;
+            }
+            assert_eq!(entry.iter().sum::<usize>(), 60);
+        }
+        assert_eq!(m.get_vec(&1), Some(&vec![10, 20, 30]));
+    }
 
-        a.extend(&b);
+    #[test]
+    fn vacant_entry_insert_iter() {
+        let mut m: MultiMap<usize, usize> = MultiMap::new();
 
-        assert_eq!(a.len(), 2);
-        assert_eq!(a.get_vec(&1), Some(&vec![42, 43]));
-        assert_eq!(b.len(), 2);
-        assert_eq!(b[&1], 43);
+        if let Entry::Vacant(entry) = m.entry(1) {
+            assert_eq!(entry.insert_iter((1..4).map(|v| v * 2)), &vec![2, 4, 6]);
+        }
+        assert_eq!(m.get_vec(&1), Some(&vec![2, 4, 6]));
     }
 
     #[test]
-    fn test_extend_consuming_multimap() {
-        let mut a = MultiMap::new();
-        a.insert(1, 42);
+    fn entry_ref_only_converts_key_on_insert() {
+        use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
 
-        let mut b = MultiMap::new();
-        b.insert(1, 43);
-        b.insert(1, 44);
-        b.insert(2, 666);
+        static CONVERSIONS: AtomicUsize = AtomicUsize::new(0);
 
-        a.extend(b);
+        #[derive(PartialEq, Eq, Hash)]
+        struct Key(String);
 
-        assert_eq!(a.len(), 2);
-        assert_eq!(a.get_vec(&1), Some(&vec![42, 43, 44]));
+        impl Borrow<str> for Key {
+            fn borrow(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl<'a> From<&'a str> for Key {
+            fn from(key: &'a str) -> Key {
+                CONVERSIONS.fetch_add(1, AtomicOrdering::SeqCst);
+                Key(key.to_string())
+            }
+        }
+
+        let mut m: MultiMap<Key, usize> = MultiMap::new();
+        *m.entry_ref("a").or_default() += 1;
+        m.entry_ref("a").and_modify(|v| v.push(5)).or_insert(0);
+        m.entry_ref("b").or_insert_with(|| 7);
+
+        match m.entry_ref("c") {
+            EntryRef::Vacant(entry) => assert_eq!(entry.key(), "c"),
+            EntryRef::Occupied(_) => unreachable!(),
+        }
+        match m.entry_ref("a") {
+            EntryRef::Occupied(mut entry) => {
+                entry.insert(6);
+                assert_eq!(entry.get(), &1);
+            }
+            EntryRef::Vacant(_) => unreachable!(),
+        }
+
+        assert_eq!(CONVERSIONS.load(AtomicOrdering::SeqCst), 2);
+        assert_eq!(m.get_vec("a"), Some(&vec![1, 5, 6]));
+        assert_eq!(m.get_vec("b"), Some(&vec![7]));
+        assert!(!m.contains_key("c"));
     }
 
     #[test]
-    fn test_extend_ref_multimap() {
-        let mut a = MultiMap::new();
-        a.insert(1, 42);
+    fn entries_mut_removes_unused_missing_keys() {
+        let mut map: MultiMap<String, usize> = MultiMap::new();
+        map.insert("a".to_string(), 1);
+
+        map.entries_mut(
+            [
+                "a".to_string(),
+                "b".to_string(),
+                "c".to_string(),
+                "e".to_string(),
+            ],
+            |[a, b, c, _]| {
+                b.push(a[0] + 1);
+                c.extend(b.iter().map(|v| v + 1));
+            },
+        )
+        .unwrap();
+        assert_eq!(map.get_vec("a"), Some(&vec![1]));
+        assert_eq!(map.get_vec("b"), Some(&vec![2]));
+        assert_eq!(map.get_vec("c"), Some(&vec![3]));
+        assert!(!map.contains_key("e"));
+        assert_eq!(map.len(), 3);
+
+        map.insert_many("f".to_string(), vec![]);
+        assert_eq!(map.entries_mut(["f".to_string()], |[f]| f.len()), Some(0));
+        assert!(map.contains_key("f"));
+
+        assert!(map
+            .entries_mut(["d".to_string(), "d".to_string()], |_| unreachable!())
+            .is_none());
+        assert!(!map.contains_key("d"));
+    }
 
-        let mut b = MultiMap::new();
-        b.insert(1, 43);
-        b.insert(1, 44);
-        b.insert(2, 666);
+    #[test]
+    fn iter_skips_empty_vecs() {
+        let mut m: MultiMap<usize, usize> = MultiMap::new();
+        for key in 0..8 {
+            m.insert(key, key);
+        }
+        for key in (0..8).filter(|key| key % 2 == 0) {
+            m.get_vec_mut(&key).unwrap().clear();
+        }
 
-        a.extend(&b);
+        let mut keys: Vec<_> = m
+            .iter()
+            .map(|(k, v)| {
+                assert_eq!(k, v);
+                *k
+            })
+            .collect();
+        keys.sort();
+        assert_eq!(keys, [1, 3, 5, 7]);
+
+        for (_, v) in m.iter_mut() {
+            *v += 1;
+        }
+        let mut iter = m.iter();
+        while iter.next().is_some() {}
+        assert!(iter.next().is_none());
+        assert_eq!(m.flat_iter().map(|(_, v)| v).sum::<usize>(), 20);
+    }
 
-        assert_eq!(a.len(), 2);
-        assert_eq!(a.get_vec(&1), Some(&vec![42, 43, 44]));
-        assert_eq!(b.len(), 2);
-        assert_eq!(b.get_vec(&1), Some(&vec![43, 44]));
+    #[test]
+    #[cfg(not(feature = "std"))]
+    fn equivalent_lookups() {
+        #[derive(Hash)]
+        struct Lookup<'a>(&'a str, u32);
+
+        impl<'a> Equivalent<(String, u32)> for Lookup<'a> {
+            fn equivalent(&self, key: &(String, u32)) -> bool {
+                self.0 == key.0 && self.1 == key.1
+            }
+        }
+
+        let mut m: MultiMap<(String, u32), i32> = MultiMap::new();
+        m.insert(("a".to_string(), 1), 42);
+        m.insert(("a".to_string(), 1), 43);
+        m.insert(("b".to_string(), 2), 44);
+
+        assert!(m.contains_key_equivalent(&Lookup("a", 1)));
+        assert!(!m.contains_key_equivalent(&Lookup("a", 2)));
+        assert_eq!(m.get_equivalent(&Lookup("a", 1)), Some(&42));
+        assert_eq!(m.get_vec_equivalent(&Lookup("b", 2)), Some(&vec![44]));
+
+        m.get_vec_mut_equivalent(&Lookup("b", 2)).unwrap().push(45);
+        assert_eq!(m.remove_equivalent(&Lookup("b", 2)), Some(vec![44, 45]));
+        assert_eq!(m.remove_equivalent(&Lookup("b", 2)), None);
+        assert_eq!(m.get_equivalent(&Lookup("c", 3)), None);
+        assert_eq!(m.len(), 1);
     }
 
     #[test]
-    fn test_entry() {
-        let mut m = MultiMap::new();
-        m.insert(1, 42);
+    #[cfg(all(feature = "raw-entry", not(feature = "std")))]
+    fn raw_entry_with_precomputed_hash() {
+        let mut m: MultiMap<&str, usize> = MultiMap::new();
+        let hash = m.hasher().hash_one("key");
+
+        assert!(m.raw_entry().from_key_hashed_nocheck(hash, "key").is_none());
+        m.raw_entry_mut()
+            .from_key_hashed_nocheck(hash, "key")
+            .or_insert("key", vec![])
+            .1
+            .push(1);
+        m.raw_entry_mut()
+            .from_hash(hash, |key| *key == "key")
+            .or_insert("key", vec![])
+            .1
+            .push(2);
+
+        assert_eq!(
+            m.raw_entry().from_hash(hash, |key| *key == "key"),
+            Some((&"key", &vec![1, 2]))
+        );
+        assert_eq!(m.get_vec("key"), Some(&vec![1, 2]));
+    }
 
-        {
-            let v = m.entry(1).or_insert(43);
-            assert_eq!(v, &42);
-            *v = 44;
-        }
-        assert_eq!(m.entry(2).or_insert(666), &666);
+    #[test]
+    fn insert_allocates_one_value_for_new_keys() {
+        let mut m: MultiMap<usize, usize> = MultiMap::new();
+        m.insert(1, 1);
+        m.insert_unique(2, 2);
+        m.insert_many(3, 3..6);
 
-        assert_eq!(m[&1], 44);
-        assert_eq!(m[&2], 666);
+        assert_eq!(m.get_vec(&1).unwrap().capacity(), 1);
+        assert_eq!(m.get_vec(&2).unwrap().capacity(), 1);
+        assert_eq!(m.get_vec(&3).unwrap().capacity(), 3);
+
+        m.insert(1, 2);
+        assert_eq!(m.get_vec(&1), Some(&vec![1, 2]));
     }
 
     #[test]
-    fn test_entry_vec() {
-        let mut m = MultiMap::new();
-        m.insert(1, 42);
+    fn iter_size_hint_with_empty_vec() {
+        let mut m: MultiMap<usize, usize> = MultiMap::new();
+        m.insert(1, 1);
+        m.insert(2, 2);
+        m.clear_values(&1);
+
+        assert_eq!(m.iter().len(), 1);
+        assert_eq!(m.iter().count(), 1);
+        assert_eq!(m.iter_mut().len(), 1);
+        assert_eq!(m.iter_mut().count(), 1);
+    }
 
-        {
-            let v = m.entry(1).or_insert_vec(vec![43]);
-            assert_eq!(v, &vec![42]);
-            *v.first_mut().unwrap() = 44;
-        }
-        assert_eq!(m.entry(2).or_insert_vec(vec![666]), &vec![666]);
+    #[test]
+    fn into_flat_iter_clones_all_but_last() {
+        use std::rc::Rc;
+
+        let key = Rc::new(1);
+        let mut m: MultiMap<Rc<usize>, usize> = MultiMap::new();
+        m.insert_many(key.clone(), vec![1, 2, 3]);
+        m.insert_many(Rc::new(2), vec![]);
+        m.insert_many(Rc::new(3), vec![4]);
+
+        let mut pairs: Vec<_> = m.into_flat_iter().collect();
+        pairs.sort();
+        assert_eq!(pairs.len(), 4);
+        assert_eq!(pairs.iter().map(|p| p.1).collect::<Vec<_>>(), [1, 2, 3, 4]);
+        assert_eq!(Rc::strong_count(&key), 4);
+
+        drop(pairs);
+        assert_eq!(Rc::strong_count(&key), 1);
+    }
 
-        assert_eq!(m[&1], 44);
-        assert_eq!(m[&2], 666);
+    #[test]
+    fn consuming_projections() {
+        let mut m: MultiMap<&str, usize> = MultiMap::new();
+        m.insert_many("a", vec![1, 2]);
+        m.insert_many("b", vec![]);
+        m.insert("c", 3);
+
+        let mut keys: Vec<_> = m.clone().into_keys().collect();
+        keys.sort();
+        assert_eq!(keys, ["a", "b", "c"]);
+
+        let mut values: Vec<_> = m.clone().into_values().collect();
+        values.sort();
+        assert_eq!(values, [vec![], vec![1, 2], vec![3]]);
+
+        let mut flat: Vec<_> = m.into_flat_values().collect();
+        flat.sort();
+        assert_eq!(flat, [1, 2, 3]);
     }
 
     #[test]
-    fn test_is_vec() {
-        let mut m = MultiMap::new();
-        m.insert(1, 42);
-        m.insert(1, 1337);
-        m.insert(2, 2332);
+    fn flat_values_visit_every_value() {
+        let mut m: MultiMap<usize, usize> = MultiMap::new();
+        m.insert_many(1, vec![1, 2]);
+        m.insert_many(2, vec![]);
+        m.insert(3, 3);
 
-        assert!(m.is_vec(&1));
-        assert!(!m.is_vec(&2));
-        assert!(!m.is_vec(&3));
+        assert_eq!(m.flat_values().count(), 3);
+        for value in m.flat_values_mut() {
+            *value *= 2;
+        }
+        let mut values: Vec<_> = m.flat_values().cloned().collect();
+        values.sort();
+        assert_eq!(values, [2, 4, 6]);
     }
 
     #[test]
-    fn test_macro() {
-        let mut manual_map = MultiMap::new();
-        manual_map.insert("key1", 42);
-        assert_eq!(manual_map, multimap!("key1" => 42));
+    fn into_iter_yields_owned_groups() {
+        #[derive(Debug, PartialEq)]
+        struct NotClone(usize);
+
+        let mut m: MultiMap<&str, NotClone> = MultiMap::new();
+        m.insert("a", NotClone(1));
+        m.insert("a", NotClone(2));
+        m.insert("b", NotClone(3));
+
+        let mut groups: Vec<(&str, Vec<NotClone>)> = m.into_iter().collect();
+        groups.sort_by_key(|group| group.0);
+        assert_eq!(
+            groups,
+            [
+                ("a", vec![NotClone(1), NotClone(2)]),
+                ("b", vec![NotClone(3)])
+            ]
+        );
+    }
 
-        manual_map.insert("key1", 1337);
-        manual_map.insert("key2", 2332);
-        let macro_map = multimap! {
-            "key1" =>    42,
-            "key1" =>  1337,
-            "key2" =>  2332
-        };
-        assert_eq!(manual_map, macro_map);
+    #[test]
+    fn iter_slices_matches_iter_all() {
+        let mut m: MultiMap<usize, usize> = MultiMap::new();
+        m.insert_many(1, vec![1, 2]);
+        m.insert_many(2, vec![]);
+
+        let iter = m.iter_slices();
+        assert_eq!(iter.len(), 2);
+        let mut slices: Vec<_> = iter.collect();
+        slices.sort();
+        let mut all: Vec<_> = m.iter_all().map(|(k, v)| (k, &v[..])).collect();
+        all.sort();
+        assert_eq!(slices, all);
     }
 
     #[test]
-    fn retain_removes_element() {
-        let mut m = MultiMap::new();
-        m.insert(1, 42);
-        m.insert(1, 99);
-        m.retain(|&k, &v| k == 1 && v == 42);
-        assert_eq!(1, m.len());
-        assert_eq!(Some(&42), m.get(&1));
+    fn sorted_iteration() {
+        let mut m: MultiMap<usize, usize> = MultiMap::new();
+        for key in (0..20).rev() {
+            m.insert(key % 7, key);
+        }
+        m.insert_many(10, vec![]);
+
+        let keys: Vec<_> = m.iter_all_sorted().map(|(k, _)| *k).collect();
+        assert_eq!(keys, [0, 1, 2, 3, 4, 5, 6, 10]);
+
+        let pairs: Vec<_> = m.iter_sorted().map(|(k, v)| (*k, *v)).collect();
+        assert_eq!(
+            pairs,
+            [
+                (0, 14),
+                (1, 15),
+                (2, 16),
+                (3, 17),
+                (4, 18),
+                (5, 19),
+                (6, 13)
+            ]
+        );
     }
 
     #[test]
-    fn retain_also_removes_empty_vector() {
-        let mut m = MultiMap::new();
-        m.insert(1, 42);
-        m.insert(1, 99);
-        m.insert(2, 42);
-        m.retain(|&k, &v| k == 1 && v == 42);
-        assert_eq!(1, m.len());
-        assert_eq!(Some(&42), m.get(&1));
+    fn drain_flat_empties_map() {
+        let mut m: MultiMap<String, usize> = MultiMap::new();
+        m.insert_many("a".to_string(), vec![1, 2]);
+        m.insert_many("b".to_string(), vec![]);
+        m.insert("c".to_string(), 3);
+        let capacity = m.capacity();
+
+        let mut pairs: Vec<_> = m.drain_flat().collect();
+        pairs.sort();
+        assert_eq!(
+            pairs,
+            [
+                ("a".to_string(), 1),
+                ("a".to_string(), 2),
+                ("c".to_string(), 3)
+            ]
+        );
+        assert!(m.is_empty());
+        assert_eq!(m.capacity(), capacity);
+
+        m.insert_many("d".to_string(), vec![4, 5]);
+        assert_eq!(m.drain_flat().next().map(|p| p.1), Some(4));
+        assert!(m.is_empty());
     }
 }
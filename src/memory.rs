@@ -0,0 +1,239 @@
+// Copyright (c) 2016 multimap developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! Approximate heap usage of a MultiMap.
+//!
+//! `MultiMap::memory_usage` reports the bytes allocated for the hash table
+//! and for the vector of every key, based on their capacities. Memory owned
+//! by the keys and values themselves, e.g. the buffer of a `String`, is only
+//! counted by `MultiMap::deep_memory_usage`, which requires both to
+//! implement `HeapSize`. The numbers are estimates: allocator overhead and
+//! the exact layout of the hash table are not taken into account.
+//!
+//! # Examples
+//!
+//! ```
+//! use multimap::MultiMap;
+//!
+//! let mut map: MultiMap<u32, String> = MultiMap::new();
+//! map.insert(1, "a".repeat(100));
+//!
+//! let shallow = map.memory_usage();
+//! assert!(shallow.values >= std::mem::size_of::<String>());
+//! assert_eq!(shallow.deep, 0);
+//!
+//! let deep = map.deep_memory_usage();
+//! assert!(deep.deep >= 100);
+//! assert_eq!(deep.total(), shallow.total() + deep.deep);
+//! ```
+
+use std::boxed::Box;
+use std::hash::{BuildHasher, Hash};
+use std::mem::size_of;
+use std::string::String;
+use std::vec::Vec;
+
+use MultiMap;
+
+/// The approximate heap usage of a MultiMap in bytes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MemoryStats {
+    /// The hash table holding the keys and their vectors, including unused
+    /// capacity.
+    pub table: usize,
+
+    /// The buffers of the key's vectors, including unused capacity.
+    pub values: usize,
+
+    /// The heap memory owned by the keys and values themselves. Zero unless
+    /// computed by `deep_memory_usage`.
+    pub deep: usize,
+}
+
+impl MemoryStats {
+    /// Returns the sum of all parts.
+    pub fn total(&self) -> usize {
+        self.table + self.values + self.deep
+    }
+}
+
+/// A type which can report the heap memory it owns, not counting its own
+/// size.
+pub trait HeapSize {
+    /// Returns the number of heap bytes owned by the value.
+    fn heap_size(&self) -> usize;
+}
+
+macro_rules! impl_no_heap {
+    ($($t:ty),*) => {
+        $(
+            impl HeapSize for $t {
+                fn heap_size(&self) -> usize {
+                    0
+                }
+            }
+        )*
+    };
+}
+
+impl_no_heap!(
+    (),
+    bool,
+    char,
+    u8,
+    u16,
+    u32,
+    u64,
+    u128,
+    usize,
+    i8,
+    i16,
+    i32,
+    i64,
+    i128,
+    isize,
+    f32,
+    f64
+);
+
+impl<T: ?Sized> HeapSize for &T {
+    fn heap_size(&self) -> usize {
+        0
+    }
+}
+
+impl HeapSize for String {
+    fn heap_size(&self) -> usize {
+        self.capacity()
+    }
+}
+
+impl<T: HeapSize> HeapSize for Box<T> {
+    fn heap_size(&self) -> usize {
+        size_of::<T>() + (**self).heap_size()
+    }
+}
+
+impl<T: HeapSize> HeapSize for Option<T> {
+    fn heap_size(&self) -> usize {
+        self.as_ref().map_or(0, HeapSize::heap_size)
+    }
+}
+
+impl<T: HeapSize> HeapSize for Vec<T> {
+    fn heap_size(&self) -> usize {
+        self.capacity() * size_of::<T>() + self.iter().map(HeapSize::heap_size).sum::<usize>()
+    }
+}
+
+impl<A: HeapSize, B: HeapSize> HeapSize for (A, B) {
+    fn heap_size(&self) -> usize {
+        self.0.heap_size() + self.1.heap_size()
+    }
+}
+
+impl<K, V, S> MultiMap<K, V, S>
+where
+    K: Eq + Hash,
+    S: BuildHasher,
+{
+    /// Returns the approximate heap usage of the hash table and of the
+    /// vectors of the keys. See the `memory` module for details.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiMap;
+    ///
+    /// let mut map: MultiMap<u32, u64> = MultiMap::new();
+    /// map.insert_many(1, vec![1, 2, 3, 4]);
+    ///
+    /// let stats = map.memory_usage();
+    /// assert!(stats.table > 0);
+    /// assert_eq!(stats.values, 4 * 8);
+    /// ```
+    pub fn memory_usage(&self) -> MemoryStats {
+        // Every bucket holds a key and its vector plus one control byte.
+        let bucket = size_of::<(K, Vec<V>)>() + 1;
+        MemoryStats {
+            table: self.inner.capacity() * bucket,
+            values: self
+                .inner
+                .values()
+                .map(|values| values.capacity() * size_of::<V>())
+                .sum(),
+            deep: 0,
+        }
+    }
+
+    /// Returns the approximate heap usage like `memory_usage`, including the
+    /// heap memory owned by the keys and values.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiMap;
+    ///
+    /// let mut map = MultiMap::new();
+    /// map.insert("key".to_string(), vec![0u8; 1000]);
+    ///
+    /// assert!(map.deep_memory_usage().deep >= 1003);
+    /// ```
+    pub fn deep_memory_usage(&self) -> MemoryStats
+    where
+        K: HeapSize,
+        V: HeapSize,
+    {
+        let deep = self
+            .inner
+            .iter()
+            .map(|(key, values)| {
+                key.heap_size() + values.iter().map(HeapSize::heap_size).sum::<usize>()
+            })
+            .sum();
+        MemoryStats {
+            deep,
+            ..self.memory_usage()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_capacity() {
+        let mut map: MultiMap<u32, u32> = MultiMap::new();
+        assert_eq!(map.memory_usage(), MemoryStats::default());
+
+        map.insert(1, 1);
+        map.insert_many(2, vec![1, 2]);
+        let stats = map.memory_usage();
+        assert!(stats.table >= 2 * size_of::<(u32, Vec<u32>)>());
+        assert!(stats.values >= 2 * size_of::<u32>());
+
+        map.reserve_values(&2, 100);
+        assert!(map.memory_usage().values >= 102 * size_of::<u32>());
+
+        map.clear();
+        map.shrink_to_fit();
+        assert_eq!(map.memory_usage().values, 0);
+    }
+
+    #[test]
+    fn deep_counts_owned_buffers() {
+        let mut map: MultiMap<String, Option<Box<u64>>> = MultiMap::new();
+        map.insert(String::with_capacity(10), Some(Box::new(1)));
+        map.insert(String::with_capacity(10), None);
+
+        let stats = map.deep_memory_usage();
+        assert_eq!(stats.deep, 10 + size_of::<u64>());
+        assert_eq!(stats.total(), map.memory_usage().total() + stats.deep);
+    }
+}
@@ -0,0 +1,537 @@
+// Copyright (c) 2016 multimap developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! A set which counts how many times each element was inserted, also known as
+//! a bag.
+//!
+//! MultiSet is implemented as a thin wrapper around std::collections::HashMap
+//! which maps every distinct element to its number of occurrences.
+//!
+//! # Examples
+//!
+//! ```
+//! use multimap::MultiSet;
+//!
+//! let mut words = MultiSet::new();
+//! for word in "the cat and the hat".split(' ') {
+//!     words.insert(word);
+//! }
+//!
+//! assert_eq!(words.count("the"), 2);
+//! assert_eq!(words.count("dog"), 0);
+//! assert_eq!(words.len(), 5);
+//! assert_eq!(words.distinct_len(), 4);
+//! ```
+
+use std::borrow::Borrow;
+use std::collections::hash_map::{self, RandomState};
+use std::collections::HashMap;
+use std::fmt::{self, Debug};
+use std::hash::{BuildHasher, Hash};
+use std::iter::{FromIterator, IntoIterator, Iterator};
+
+#[derive(Clone)]
+pub struct MultiSet<T, S = RandomState> {
+    inner: HashMap<T, usize, S>,
+    len: usize,
+}
+
+impl<T> MultiSet<T>
+where
+    T: Eq + Hash,
+{
+    /// Creates an empty MultiSet
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiSet;
+    ///
+    /// let mut set: MultiSet<&str> = MultiSet::new();
+    /// ```
+    pub fn new() -> MultiSet<T> {
+        MultiSet {
+            inner: HashMap::new(),
+            len: 0,
+        }
+    }
+
+    /// Creates an empty MultiSet with room for the given number of distinct
+    /// elements.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiSet;
+    ///
+    /// let mut set: MultiSet<&str> = MultiSet::with_capacity(20);
+    /// ```
+    pub fn with_capacity(capacity: usize) -> MultiSet<T> {
+        MultiSet {
+            inner: HashMap::with_capacity(capacity),
+            len: 0,
+        }
+    }
+}
+
+impl<T, S> MultiSet<T, S>
+where
+    T: Eq + Hash,
+    S: BuildHasher,
+{
+    /// Creates an empty MultiSet which will use the given hash builder to hash
+    /// elements.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiSet;
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let s = RandomState::new();
+    /// let mut set: MultiSet<&str> = MultiSet::with_hasher(s);
+    /// ```
+    pub fn with_hasher(hash_builder: S) -> MultiSet<T, S> {
+        MultiSet {
+            inner: HashMap::with_hasher(hash_builder),
+            len: 0,
+        }
+    }
+
+    /// Inserts one occurrence of the element, returning the number of
+    /// occurrences after the insertion.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiSet;
+    ///
+    /// let mut set = MultiSet::new();
+    /// assert_eq!(set.insert("a"), 1);
+    /// assert_eq!(set.insert("a"), 2);
+    /// ```
+    pub fn insert(&mut self, t: T) -> usize {
+        self.insert_many(t, 1)
+    }
+
+    /// Inserts the given number of occurrences of the element, returning the
+    /// number of occurrences after the insertion. Inserting zero occurrences
+    /// of an element which isn't present leaves the set unchanged.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiSet;
+    ///
+    /// let mut set = MultiSet::new();
+    /// assert_eq!(set.insert_many("a", 3), 3);
+    /// assert_eq!(set.insert_many("b", 0), 0);
+    /// assert!(!set.contains("b"));
+    /// ```
+    pub fn insert_many(&mut self, t: T, n: usize) -> usize {
+        if n == 0 {
+            return self.count(&t);
+        }
+        let count = self.inner.entry(t).or_insert(0);
+        *count += n;
+        self.len += n;
+        *count
+    }
+
+    /// Removes one occurrence of the element. Returns true if the element was
+    /// present. The element is removed entirely when its last occurrence is
+    /// removed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiSet;
+    ///
+    /// let mut set = MultiSet::new();
+    /// set.insert_many("a", 2);
+    /// assert!(set.remove_one("a"));
+    /// assert_eq!(set.count("a"), 1);
+    /// assert!(set.remove_one("a"));
+    /// assert!(!set.remove_one("a"));
+    /// assert!(set.is_empty());
+    /// ```
+    pub fn remove_one<Q>(&mut self, t: &Q) -> bool
+    where
+        T: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        let now_zero = match self.inner.get_mut(t) {
+            Some(count) => {
+                *count -= 1;
+                *count == 0
+            }
+            None => return false,
+        };
+        if now_zero {
+            self.inner.remove(t);
+        }
+        self.len -= 1;
+        true
+    }
+
+    /// Removes every occurrence of the element, returning how many there were.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiSet;
+    ///
+    /// let mut set = MultiSet::new();
+    /// set.insert_many("a", 2);
+    /// assert_eq!(set.remove_all("a"), 2);
+    /// assert_eq!(set.remove_all("a"), 0);
+    /// ```
+    pub fn remove_all<Q>(&mut self, t: &Q) -> usize
+    where
+        T: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        let count = self.inner.remove(t).unwrap_or(0);
+        self.len -= count;
+        count
+    }
+
+    /// Returns the number of occurrences of the element.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiSet;
+    ///
+    /// let mut set = MultiSet::new();
+    /// set.insert("a");
+    /// set.insert("a");
+    /// assert_eq!(set.count("a"), 2);
+    /// assert_eq!(set.count("b"), 0);
+    /// ```
+    pub fn count<Q>(&self, t: &Q) -> usize
+    where
+        T: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        self.inner.get(t).cloned().unwrap_or(0)
+    }
+
+    /// Returns true if the set contains at least one occurrence of the element.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiSet;
+    ///
+    /// let mut set = MultiSet::new();
+    /// set.insert(1);
+    /// assert!(set.contains(&1));
+    /// assert!(!set.contains(&2));
+    /// ```
+    pub fn contains<Q>(&self, t: &Q) -> bool
+    where
+        T: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        self.inner.contains_key(t)
+    }
+
+    /// Returns the total number of occurrences in the set.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiSet;
+    ///
+    /// let mut set = MultiSet::new();
+    /// set.insert(1);
+    /// set.insert(1);
+    /// set.insert(2);
+    /// assert_eq!(set.len(), 3);
+    /// ```
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns the number of distinct elements in the set.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiSet;
+    ///
+    /// let mut set = MultiSet::new();
+    /// set.insert(1);
+    /// set.insert(1);
+    /// set.insert(2);
+    /// assert_eq!(set.distinct_len(), 2);
+    /// ```
+    pub fn distinct_len(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// Returns true if the set contains no elements.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiSet;
+    ///
+    /// let mut set = MultiSet::new();
+    /// assert!(set.is_empty());
+    /// set.insert(1);
+    /// assert!(!set.is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    /// Clears the set, removing all elements.
+    /// Keeps the allocated memory for reuse.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiSet;
+    ///
+    /// let mut set = MultiSet::new();
+    /// set.insert(1);
+    /// set.clear();
+    /// assert!(set.is_empty());
+    /// ```
+    pub fn clear(&mut self) {
+        self.inner.clear();
+        self.len = 0;
+    }
+
+    /// An iterator visiting all distinct elements and their number of
+    /// occurrences in arbitrary order. Iterator element type is (&'a T, usize).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiSet;
+    ///
+    /// let mut set = MultiSet::new();
+    /// set.insert("a");
+    /// set.insert("b");
+    /// set.insert("a");
+    ///
+    /// let mut counts: Vec<_> = set.iter().collect();
+    /// counts.sort();
+    /// assert_eq!(counts, [(&"a", 2), (&"b", 1)]);
+    /// ```
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            inner: self.inner.iter(),
+        }
+    }
+
+    /// Retains only the elements specified by the predicate, which is given
+    /// each distinct element and its number of occurrences.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiSet;
+    ///
+    /// let mut set = MultiSet::new();
+    /// set.insert_many("a", 3);
+    /// set.insert("b");
+    /// set.retain(|_, count| count > 1);
+    /// assert_eq!(set.len(), 3);
+    /// assert!(!set.contains("b"));
+    /// ```
+    pub fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&T, usize) -> bool,
+    {
+        let len = &mut self.len;
+        self.inner.retain(|t, count| {
+            let keep = f(t, *count);
+            if !keep {
+                *len -= *count;
+            }
+            keep
+        });
+    }
+}
+
+impl<T, S> Debug for MultiSet<T, S>
+where
+    T: Eq + Hash + Debug,
+    S: BuildHasher,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<T, S> PartialEq for MultiSet<T, S>
+where
+    T: Eq + Hash,
+    S: BuildHasher,
+{
+    fn eq(&self, other: &MultiSet<T, S>) -> bool {
+        self.len == other.len && self.inner == other.inner
+    }
+}
+
+impl<T, S> Eq for MultiSet<T, S>
+where
+    T: Eq + Hash,
+    S: BuildHasher,
+{
+}
+
+impl<T, S> Default for MultiSet<T, S>
+where
+    T: Eq + Hash,
+    S: BuildHasher + Default,
+{
+    fn default() -> MultiSet<T, S> {
+        MultiSet {
+            inner: Default::default(),
+            len: 0,
+        }
+    }
+}
+
+impl<T, S> FromIterator<T> for MultiSet<T, S>
+where
+    T: Eq + Hash,
+    S: BuildHasher + Default,
+{
+    fn from_iter<I: IntoIterator<Item = T>>(iterable: I) -> MultiSet<T, S> {
+        let mut set = MultiSet::default();
+        set.extend(iterable);
+        set
+    }
+}
+
+impl<T, S> Extend<T> for MultiSet<T, S>
+where
+    T: Eq + Hash,
+    S: BuildHasher,
+{
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for t in iter {
+            self.insert(t);
+        }
+    }
+}
+
+impl<T, S> Extend<(T, usize)> for MultiSet<T, S>
+where
+    T: Eq + Hash,
+    S: BuildHasher,
+{
+    fn extend<I: IntoIterator<Item = (T, usize)>>(&mut self, iter: I) {
+        for (t, n) in iter {
+            self.insert_many(t, n);
+        }
+    }
+}
+
+impl<'a, T, S> IntoIterator for &'a MultiSet<T, S>
+where
+    T: Eq + Hash,
+    S: BuildHasher,
+{
+    type Item = (&'a T, usize);
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Iter<'a, T> {
+        self.iter()
+    }
+}
+
+impl<T, S> IntoIterator for MultiSet<T, S> {
+    type Item = (T, usize);
+    type IntoIter = hash_map::IntoIter<T, usize>;
+
+    fn into_iter(self) -> hash_map::IntoIter<T, usize> {
+        self.inner.into_iter()
+    }
+}
+
+#[derive(Clone)]
+pub struct Iter<'a, T: 'a> {
+    inner: hash_map::Iter<'a, T, usize>,
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = (&'a T, usize);
+
+    fn next(&mut self) -> Option<(&'a T, usize)> {
+        let (t, count) = self.inner.next()?;
+        Some((t, *count))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<T> ExactSizeIterator for Iter<'_, T> {
+    fn len(&self) -> usize {
+        self.inner.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::vec::Vec;
+
+    #[test]
+    fn len_tracks_occurrences() {
+        let mut set = MultiSet::new();
+        set.insert_many(1, 3);
+        set.insert(2);
+        assert_eq!(set.len(), 4);
+        set.remove_one(&1);
+        assert_eq!(set.len(), 3);
+        set.remove_all(&1);
+        assert_eq!(set.len(), 1);
+        set.retain(|_, _| false);
+        assert_eq!(set.len(), 0);
+        assert!(set.is_empty());
+    }
+
+    #[test]
+    fn remove_one_missing() {
+        let mut set: MultiSet<u8> = MultiSet::new();
+        assert!(!set.remove_one(&1));
+        assert_eq!(set.len(), 0);
+    }
+
+    #[test]
+    fn from_iterator_counts() {
+        let set: MultiSet<char> = "abracadabra".chars().collect();
+        assert_eq!(set.count(&'a'), 5);
+        assert_eq!(set.count(&'b'), 2);
+        assert_eq!(set.count(&'r'), 2);
+        assert_eq!(set.count(&'c'), 1);
+        assert_eq!(set.len(), 11);
+
+        let mut counts: Vec<_> = set.into_iter().collect();
+        counts.sort();
+        assert_eq!(counts, [('a', 5), ('b', 2), ('c', 1), ('d', 1), ('r', 2)]);
+    }
+
+    #[test]
+    fn test_eq() {
+        let a: MultiSet<u8> = vec![1, 2, 1].into_iter().collect();
+        let mut b: MultiSet<u8> = vec![2, 1].into_iter().collect();
+        assert_ne!(a, b);
+        b.insert(1);
+        assert_eq!(a, b);
+    }
+}
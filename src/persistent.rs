@@ -0,0 +1,593 @@
+// Copyright (c) 2016 multimap developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! A multimap which can be cloned in constant time.
+//!
+//! PersistentMultiMap keeps its table and every value vector behind an `Arc`.
+//! Cloning the map only bumps a reference count, and clones share all of
+//! their data until one of them is modified. A modification copies the table
+//! of the modified map (which only clones the `Arc` of every key's vector,
+//! never the values) plus the single vector being modified. This makes it
+//! cheap to hand out snapshots of a large multimap to readers while a writer
+//! keeps updating its own copy.
+//!
+//! # Examples
+//!
+//! ```
+//! use multimap::PersistentMultiMap;
+//!
+//! let mut map = PersistentMultiMap::new();
+//! map.insert("urls", "http://rust-lang.org");
+//!
+//! let snapshot = map.clone();
+//! map.insert("urls", "http://mozilla.org");
+//!
+//! assert_eq!(snapshot.get_vec("urls"), Some(&vec!["http://rust-lang.org"]));
+//! assert_eq!(map.get_vec("urls").map(|v| v.len()), Some(2));
+//! ```
+
+use std::borrow::Borrow;
+use std::collections::hash_map::{self, RandomState};
+use std::collections::HashMap;
+use std::fmt::{self, Debug};
+use std::hash::{BuildHasher, Hash};
+use std::iter::{FromIterator, IntoIterator, Iterator};
+use std::sync::Arc;
+use std::vec::Vec;
+
+use MultiMap;
+
+pub struct PersistentMultiMap<K, V, S = RandomState> {
+    inner: Arc<HashMap<K, Arc<Vec<V>>, S>>,
+}
+
+impl<K, V> PersistentMultiMap<K, V>
+where
+    K: Eq + Hash + Clone,
+    V: Clone,
+{
+    /// Creates an empty PersistentMultiMap
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::PersistentMultiMap;
+    ///
+    /// let mut map: PersistentMultiMap<&str, isize> = PersistentMultiMap::new();
+    /// ```
+    pub fn new() -> PersistentMultiMap<K, V> {
+        PersistentMultiMap {
+            inner: Arc::new(HashMap::new()),
+        }
+    }
+}
+
+impl<K, V, S> PersistentMultiMap<K, V, S>
+where
+    K: Eq + Hash + Clone,
+    V: Clone,
+    S: BuildHasher + Clone,
+{
+    /// Creates an empty PersistentMultiMap which will use the given hash builder
+    /// to hash keys.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::PersistentMultiMap;
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let s = RandomState::new();
+    /// let mut map: PersistentMultiMap<&str, isize> = PersistentMultiMap::with_hasher(s);
+    /// ```
+    pub fn with_hasher(hash_builder: S) -> PersistentMultiMap<K, V, S> {
+        PersistentMultiMap {
+            inner: Arc::new(HashMap::with_hasher(hash_builder)),
+        }
+    }
+
+    /// Inserts a key-value pair into the multimap. Only the key's vector is
+    /// copied if it is shared with another clone of the map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::PersistentMultiMap;
+    ///
+    /// let mut map = PersistentMultiMap::new();
+    /// map.insert("key", 42);
+    /// ```
+    pub fn insert(&mut self, k: K, v: V) {
+        let values = Arc::make_mut(&mut self.inner).entry(k).or_default();
+        Arc::make_mut(values).push(v);
+    }
+
+    /// Inserts multiple values for a key into the multimap.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::PersistentMultiMap;
+    ///
+    /// let mut map = PersistentMultiMap::new();
+    /// map.insert_many("key", vec![42, 43]);
+    /// assert_eq!(map.get_vec("key"), Some(&vec![42, 43]));
+    /// ```
+    pub fn insert_many<I: IntoIterator<Item = V>>(&mut self, k: K, v: I) {
+        let values = Arc::make_mut(&mut self.inner).entry(k).or_default();
+        Arc::make_mut(values).extend(v);
+    }
+
+    /// Returns true if the map contains a value for the specified key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::PersistentMultiMap;
+    ///
+    /// let mut map = PersistentMultiMap::new();
+    /// map.insert(1, 42);
+    /// assert_eq!(map.contains_key(&1), true);
+    /// assert_eq!(map.contains_key(&2), false);
+    /// ```
+    pub fn contains_key<Q>(&self, k: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        self.inner.contains_key(k)
+    }
+
+    /// Returns a reference to the first item in the vector corresponding to
+    /// the key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::PersistentMultiMap;
+    ///
+    /// let mut map = PersistentMultiMap::new();
+    /// map.insert(1, 42);
+    /// map.insert(1, 1337);
+    /// assert_eq!(map.get(&1), Some(&42));
+    /// ```
+    pub fn get<Q>(&self, k: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        self.inner.get(k)?.first()
+    }
+
+    /// Returns a reference to the vector corresponding to the key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::PersistentMultiMap;
+    ///
+    /// let mut map = PersistentMultiMap::new();
+    /// map.insert(1, 42);
+    /// map.insert(1, 1337);
+    /// assert_eq!(map.get_vec(&1), Some(&vec![42, 1337]));
+    /// ```
+    pub fn get_vec<Q>(&self, k: &Q) -> Option<&Vec<V>>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        self.inner.get(k).map(|values| &**values)
+    }
+
+    /// Returns a mutable reference to the vector corresponding to the key,
+    /// copying the vector first if it is shared with another clone of the map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::PersistentMultiMap;
+    ///
+    /// let mut map = PersistentMultiMap::new();
+    /// map.insert(1, 42);
+    /// let snapshot = map.clone();
+    /// map.get_vec_mut(&1).unwrap()[0] = 43;
+    /// assert_eq!(map.get(&1), Some(&43));
+    /// assert_eq!(snapshot.get(&1), Some(&42));
+    /// ```
+    pub fn get_vec_mut<Q>(&mut self, k: &Q) -> Option<&mut Vec<V>>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        if !self.inner.contains_key(k) {
+            return None;
+        }
+        Arc::make_mut(&mut self.inner).get_mut(k).map(Arc::make_mut)
+    }
+
+    /// Removes a key from the map, returning the vector of values at
+    /// the key if the key was previously in the map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::PersistentMultiMap;
+    ///
+    /// let mut map = PersistentMultiMap::new();
+    /// map.insert(1, 42);
+    /// map.insert(1, 1337);
+    /// assert_eq!(map.remove(&1), Some(vec![42, 1337]));
+    /// assert_eq!(map.remove(&1), None);
+    /// ```
+    pub fn remove<Q>(&mut self, k: &Q) -> Option<Vec<V>>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        if !self.inner.contains_key(k) {
+            return None;
+        }
+        Arc::make_mut(&mut self.inner)
+            .remove(k)
+            .map(Arc::unwrap_or_clone)
+    }
+
+    /// Returns the number of keys in the map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::PersistentMultiMap;
+    ///
+    /// let mut map = PersistentMultiMap::new();
+    /// map.insert(1, 42);
+    /// map.insert(2, 1337);
+    /// assert_eq!(map.len(), 2);
+    /// ```
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// Returns true if the map contains no elements.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::PersistentMultiMap;
+    ///
+    /// let mut map = PersistentMultiMap::new();
+    /// assert!(map.is_empty());
+    /// map.insert(1, 42);
+    /// assert!(!map.is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    /// Clears the map, removing all key-value pairs. Clones of the map are
+    /// not affected.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::PersistentMultiMap;
+    ///
+    /// let mut map = PersistentMultiMap::new();
+    /// map.insert(1, 42);
+    /// let snapshot = map.clone();
+    /// map.clear();
+    /// assert!(map.is_empty());
+    /// assert!(!snapshot.is_empty());
+    /// ```
+    pub fn clear(&mut self) {
+        match Arc::get_mut(&mut self.inner) {
+            Some(inner) => inner.clear(),
+            None => {
+                let hasher = self.inner.hasher().clone();
+                self.inner = Arc::new(HashMap::with_hasher(hasher));
+            }
+        }
+    }
+
+    /// Returns true if both maps share the same table, i.e. one is an
+    /// unmodified clone of the other.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::PersistentMultiMap;
+    ///
+    /// let mut map = PersistentMultiMap::new();
+    /// map.insert(1, 42);
+    /// let snapshot = map.clone();
+    /// assert!(map.ptr_eq(&snapshot));
+    /// map.insert(2, 42);
+    /// assert!(!map.ptr_eq(&snapshot));
+    /// ```
+    pub fn ptr_eq(&self, other: &PersistentMultiMap<K, V, S>) -> bool {
+        Arc::ptr_eq(&self.inner, &other.inner)
+    }
+
+    /// An iterator visiting all keys in arbitrary order.
+    pub fn keys(&self) -> hash_map::Keys<'_, K, Arc<Vec<V>>> {
+        self.inner.keys()
+    }
+
+    /// An iterator visiting all keys and their vectors in arbitrary order.
+    /// Iterator element type is (&'a K, &'a Vec<V>).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::PersistentMultiMap;
+    ///
+    /// let mut map = PersistentMultiMap::new();
+    /// map.insert(1, 42);
+    /// map.insert(1, 1337);
+    ///
+    /// let pairs: Vec<_> = map.iter_all().collect();
+    /// assert_eq!(pairs, [(&1, &vec![42, 1337])]);
+    /// ```
+    pub fn iter_all(&self) -> IterAll<'_, K, V> {
+        IterAll {
+            inner: self.inner.iter(),
+        }
+    }
+
+    /// An iterator visiting every key-value pair in arbitrary order.
+    pub fn flat_iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.iter_all()
+            .flat_map(|(k, v)| v.iter().map(move |i| (k, i)))
+    }
+
+    /// Retains only the elements specified by the predicate. Only the vectors
+    /// which actually change are copied.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::PersistentMultiMap;
+    ///
+    /// let mut map = PersistentMultiMap::new();
+    /// map.insert(1, 42);
+    /// map.insert(1, 99);
+    /// map.insert(2, 42);
+    /// map.retain(|&k, &v| k == 1 && v == 42);
+    /// assert_eq!(map.len(), 1);
+    /// assert_eq!(map.get(&1), Some(&42));
+    /// ```
+    pub fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&K, &V) -> bool,
+    {
+        let mut changed = Vec::new();
+        for (key, values) in self.inner.iter() {
+            let keep: Vec<bool> = values.iter().map(|v| f(key, v)).collect();
+            if keep.contains(&false) {
+                changed.push((key.clone(), keep));
+            }
+        }
+        if changed.is_empty() {
+            return;
+        }
+        let inner = Arc::make_mut(&mut self.inner);
+        for (key, keep) in changed {
+            let values = inner.get_mut(&key).unwrap();
+            let mut keep = keep.into_iter();
+            Arc::make_mut(values).retain(|_| keep.next().unwrap());
+            if values.is_empty() {
+                inner.remove(&key);
+            }
+        }
+    }
+}
+
+impl<K, V, S> Clone for PersistentMultiMap<K, V, S> {
+    fn clone(&self) -> PersistentMultiMap<K, V, S> {
+        PersistentMultiMap {
+            inner: Arc::clone(&self.inner),
+        }
+    }
+}
+
+impl<K, V, S> Debug for PersistentMultiMap<K, V, S>
+where
+    K: Eq + Hash + Debug,
+    V: Debug,
+    S: BuildHasher,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_map().entries(self.inner.iter()).finish()
+    }
+}
+
+impl<K, V, S> PartialEq for PersistentMultiMap<K, V, S>
+where
+    K: Eq + Hash,
+    V: PartialEq,
+    S: BuildHasher,
+{
+    fn eq(&self, other: &PersistentMultiMap<K, V, S>) -> bool {
+        Arc::ptr_eq(&self.inner, &other.inner) || self.inner == other.inner
+    }
+}
+
+impl<K, V, S> Eq for PersistentMultiMap<K, V, S>
+where
+    K: Eq + Hash,
+    V: Eq,
+    S: BuildHasher,
+{
+}
+
+impl<K, V, S> Default for PersistentMultiMap<K, V, S>
+where
+    K: Eq + Hash,
+    S: BuildHasher + Default,
+{
+    fn default() -> PersistentMultiMap<K, V, S> {
+        PersistentMultiMap {
+            inner: Default::default(),
+        }
+    }
+}
+
+impl<K, V, S> From<MultiMap<K, V, S>> for PersistentMultiMap<K, V, S>
+where
+    K: Eq + Hash,
+    S: BuildHasher + Clone,
+{
+    fn from(map: MultiMap<K, V, S>) -> PersistentMultiMap<K, V, S> {
+        let hasher = map.inner.hasher().clone();
+        let mut inner = HashMap::with_capacity_and_hasher(map.len(), hasher);
+        inner.extend(map.inner.into_iter().map(|(k, v)| (k, Arc::new(v))));
+        PersistentMultiMap {
+            inner: Arc::new(inner),
+        }
+    }
+}
+
+impl<K, V, S> FromIterator<(K, V)> for PersistentMultiMap<K, V, S>
+where
+    K: Eq + Hash + Clone,
+    V: Clone,
+    S: BuildHasher + Clone + Default,
+{
+    fn from_iter<T: IntoIterator<Item = (K, V)>>(iterable: T) -> PersistentMultiMap<K, V, S> {
+        let mut map = PersistentMultiMap::default();
+        map.extend(iterable);
+        map
+    }
+}
+
+impl<K, V, S> Extend<(K, V)> for PersistentMultiMap<K, V, S>
+where
+    K: Eq + Hash + Clone,
+    V: Clone,
+    S: BuildHasher + Clone,
+{
+    fn extend<T: IntoIterator<Item = (K, V)>>(&mut self, iter: T) {
+        for (k, v) in iter {
+            self.insert(k, v);
+        }
+    }
+}
+
+impl<'a, K, V, S> IntoIterator for &'a PersistentMultiMap<K, V, S>
+where
+    K: Eq + Hash + Clone,
+    V: Clone,
+    S: BuildHasher + Clone,
+{
+    type Item = (&'a K, &'a Vec<V>);
+    type IntoIter = IterAll<'a, K, V>;
+
+    fn into_iter(self) -> IterAll<'a, K, V> {
+        self.iter_all()
+    }
+}
+
+#[derive(Clone)]
+pub struct IterAll<'a, K: 'a, V: 'a> {
+    inner: hash_map::Iter<'a, K, Arc<Vec<V>>>,
+}
+
+impl<'a, K, V> Iterator for IterAll<'a, K, V> {
+    type Item = (&'a K, &'a Vec<V>);
+
+    fn next(&mut self) -> Option<(&'a K, &'a Vec<V>)> {
+        let (k, v) = self.inner.next()?;
+        Some((k, &**v))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<K, V> ExactSizeIterator for IterAll<'_, K, V> {
+    fn len(&self) -> usize {
+        self.inner.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+    use SeededState;
+
+    #[test]
+    fn clone_shares_vectors() {
+        let mut m = PersistentMultiMap::new();
+        m.insert(1, 42);
+        m.insert(2, 43);
+        let snapshot = m.clone();
+        m.insert(1, 44);
+
+        assert!(!m.ptr_eq(&snapshot));
+        assert!(Arc::ptr_eq(&m.inner[&2], &snapshot.inner[&2]));
+        assert!(!Arc::ptr_eq(&m.inner[&1], &snapshot.inner[&1]));
+        assert_eq!(snapshot.get_vec(&1), Some(&vec![42]));
+    }
+
+    #[test]
+    fn remove_keeps_snapshot() {
+        let mut m = PersistentMultiMap::new();
+        m.insert(1, 42);
+        let snapshot = m.clone();
+        assert_eq!(m.remove(&1), Some(vec![42]));
+        assert_eq!(m.remove(&1), None);
+        assert_eq!(snapshot.get(&1), Some(&42));
+    }
+
+    #[test]
+    fn retain_unchanged_keeps_sharing() {
+        let mut m = PersistentMultiMap::new();
+        m.insert(1, 42);
+        let snapshot = m.clone();
+        m.retain(|_, _| true);
+        assert!(m.ptr_eq(&snapshot));
+    }
+
+    #[test]
+    fn retain_calls_predicate_once_per_value() {
+        let mut m = PersistentMultiMap::new();
+        m.insert(1, 7);
+        m.insert(2, 7);
+        m.insert(3, 8);
+        let snapshot = m.clone();
+
+        let mut seen = HashSet::new();
+        m.retain(|_, &v| seen.insert(v));
+        assert_eq!(m.flat_iter().count(), 2);
+        assert_eq!(m.get(&3), Some(&8));
+        assert_eq!(snapshot.len(), 3);
+        assert!(Arc::ptr_eq(&m.inner[&3], &snapshot.inner[&3]));
+    }
+
+    #[test]
+    fn from_multimap() {
+        let mut map = MultiMap::new();
+        map.insert(1, 42);
+        map.insert(1, 43);
+        let m = PersistentMultiMap::from(map);
+        assert_eq!(m.get_vec(&1), Some(&vec![42, 43]));
+        assert_eq!(m, m.clone());
+    }
+
+    #[test]
+    fn from_multimap_keeps_hasher() {
+        let mut map = MultiMap::with_seed(7);
+        map.insert(1, 42);
+        let m = PersistentMultiMap::from(map);
+        assert_eq!(m.inner.hasher(), &SeededState::new(7));
+        assert_eq!(m.get(&1), Some(&42));
+    }
+}
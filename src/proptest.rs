@@ -0,0 +1,396 @@
+// Copyright (c) 2016 multimap developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! proptest strategies for MultiMap, enabled by the `proptest` feature.
+//!
+//! Shrinking first removes values from keys holding more than one, then
+//! removes whole keys, and finally shrinks the remaining values and keys
+//! themselves.
+//!
+//! # Examples
+//!
+//! ```
+//! # extern crate multimap;
+//! # extern crate proptest;
+//! use multimap::proptest::multi_map;
+//! use proptest::prelude::*;
+//!
+//! proptest! {
+//!     // Add #[test] to run this as part of the test suite.
+//!     fn total_len_counts_every_value(map in multi_map(0..10u8, any::<u32>(), 0..5)) {
+//!         prop_assert_eq!(map.total_len(), map.flat_iter().count());
+//!     }
+//! }
+//! # fn main() {
+//! #     total_len_counts_every_value();
+//! # }
+//! ```
+
+extern crate proptest;
+
+use std::collections::HashSet;
+use std::fmt;
+use std::hash::Hash;
+use std::vec::Vec;
+
+use self::proptest::collection::SizeRange;
+use self::proptest::strategy::{NewTree, Strategy, ValueTree};
+use self::proptest::test_runner::TestRunner;
+
+use MultiMap;
+
+/// Creates a strategy generating multimaps with a number of distinct keys in
+/// `size`, each holding one to four values. Use
+/// `MultiMapStrategy::values_per_key` to change the number of values.
+///
+/// Keys are drawn until there are enough distinct ones; a duplicate key counts
+/// as a local rejection, so a key strategy with too few values makes the test
+/// fail. Keys shrunk to the same value have their values merged, so a shrunk
+/// map may end up with fewer keys.
+pub fn multi_map<K, V>(key: K, value: V, size: impl Into<SizeRange>) -> MultiMapStrategy<K, V>
+where
+    K: Strategy,
+    K::Value: Eq + Hash,
+    V: Strategy,
+{
+    MultiMapStrategy {
+        key,
+        value,
+        size: size.into(),
+        values_per_key: (1..5).into(),
+    }
+}
+
+/// The strategy returned by `multi_map`.
+#[derive(Clone, Debug)]
+pub struct MultiMapStrategy<K, V> {
+    key: K,
+    value: V,
+    size: SizeRange,
+    values_per_key: SizeRange,
+}
+
+impl<K, V> MultiMapStrategy<K, V> {
+    /// Sets the range of the number of values of each key. Keys always hold at
+    /// least one value, so a lower bound of zero counts as one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate multimap;
+    /// # extern crate proptest;
+    /// use multimap::proptest::multi_map;
+    /// use proptest::prelude::*;
+    ///
+    /// proptest! {
+    ///     fn keys_hold_fewer_than_20_values(
+    ///         map in multi_map(any::<u8>(), any::<u32>(), 0..5).values_per_key(1..20)
+    ///     ) {
+    ///         prop_assert!(map.iter_all().all(|(_, values)| values.len() < 20));
+    ///     }
+    /// }
+    /// # fn main() {
+    /// #     keys_hold_fewer_than_20_values();
+    /// # }
+    /// ```
+    pub fn values_per_key(mut self, values_per_key: impl Into<SizeRange>) -> Self {
+        self.values_per_key = values_per_key.into();
+        self
+    }
+}
+
+impl<K, V> Strategy for MultiMapStrategy<K, V>
+where
+    K: Strategy,
+    K::Value: Eq + Hash,
+    V: Strategy,
+{
+    type Tree = MultiMapValueTree<K::Tree, V::Tree>;
+    type Value = MultiMap<K::Value, V::Value>;
+
+    fn new_tree(&self, runner: &mut TestRunner) -> NewTree<Self> {
+        let (start, end) = (self.size.start(), self.size.end_excl());
+        let keys = (start..end.max(start + 1)).new_tree(runner)?.current();
+        let min_values = self.values_per_key.start().max(1);
+        let max_values = self.values_per_key.end_excl().max(min_values + 1);
+
+        let mut seen = HashSet::with_capacity(keys);
+        let mut groups = Vec::with_capacity(keys);
+        while groups.len() < keys {
+            let key = self.key.new_tree(runner)?;
+            if !seen.insert(key.current()) {
+                runner.reject_local("duplicate key")?;
+                continue;
+            }
+            let len = (min_values..max_values).new_tree(runner)?.current();
+            let mut values = Vec::with_capacity(len);
+            for _ in 0..len {
+                values.push((self.value.new_tree(runner)?, true));
+            }
+            groups.push(Group {
+                key,
+                values,
+                included: true,
+            });
+        }
+
+        Ok(MultiMapValueTree {
+            groups,
+            min_keys: start,
+            shrink: Shrink::RemoveValue(0, 0),
+            prev: None,
+        })
+    }
+}
+
+struct Group<K, V> {
+    key: K,
+    values: Vec<(V, bool)>,
+    included: bool,
+}
+
+impl<K, V> Group<K, V> {
+    fn included_values(&self) -> usize {
+        self.values.iter().filter(|value| value.1).count()
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Shrink {
+    RemoveValue(usize, usize),
+    RemoveKey(usize),
+    SimplifyValue(usize, usize),
+    SimplifyKey(usize),
+    Done,
+}
+
+/// The value tree of `MultiMapStrategy`.
+pub struct MultiMapValueTree<K, V> {
+    groups: Vec<Group<K, V>>,
+    min_keys: usize,
+    shrink: Shrink,
+    prev: Option<Shrink>,
+}
+
+impl<K, V> fmt::Debug for MultiMapValueTree<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("MultiMapValueTree")
+            .field("keys", &self.groups.len())
+            .field("shrink", &self.shrink)
+            .finish()
+    }
+}
+
+impl<K, V> MultiMapValueTree<K, V> {
+    /// Returns the position after `(key, value)` when walking every value
+    /// of every key, or `None` past the last key.
+    fn next_value(&self, key: usize, value: usize) -> Option<(usize, usize)> {
+        if value + 1 < self.groups[key].values.len() {
+            Some((key, value + 1))
+        } else if key + 1 < self.groups.len() {
+            Some((key + 1, 0))
+        } else {
+            None
+        }
+    }
+}
+
+impl<K, V> ValueTree for MultiMapValueTree<K, V>
+where
+    K: ValueTree,
+    K::Value: Eq + Hash,
+    V: ValueTree,
+{
+    type Value = MultiMap<K::Value, V::Value>;
+
+    fn current(&self) -> Self::Value {
+        let mut map = MultiMap::new();
+        for group in self.groups.iter().filter(|group| group.included) {
+            map.insert_many(
+                group.key.current(),
+                group
+                    .values
+                    .iter()
+                    .filter(|value| value.1)
+                    .map(|value| value.0.current()),
+            );
+        }
+        map
+    }
+
+    fn simplify(&mut self) -> bool {
+        loop {
+            match self.shrink {
+                Shrink::RemoveValue(key, _) | Shrink::SimplifyValue(key, _)
+                    if key >= self.groups.len() =>
+                {
+                    self.shrink = match self.shrink {
+                        Shrink::RemoveValue(..) => Shrink::RemoveKey(0),
+                        _ => Shrink::SimplifyKey(0),
+                    };
+                }
+                Shrink::RemoveValue(key, value) => {
+                    let next = self
+                        .next_value(key, value)
+                        .map_or(Shrink::RemoveKey(0), |(k, v)| Shrink::RemoveValue(k, v));
+                    let group = &mut self.groups[key];
+                    if value < group.values.len()
+                        && group.values[value].1
+                        && group.included_values() > 1
+                    {
+                        group.values[value].1 = false;
+                        self.prev = Some(self.shrink);
+                        self.shrink = next;
+                        return true;
+                    }
+                    self.shrink = next;
+                }
+                Shrink::RemoveKey(key) => {
+                    if key >= self.groups.len() {
+                        self.shrink = Shrink::SimplifyValue(0, 0);
+                        continue;
+                    }
+                    let included = self.groups.iter().filter(|group| group.included).count();
+                    self.shrink = Shrink::RemoveKey(key + 1);
+                    if self.groups[key].included && included > self.min_keys {
+                        self.groups[key].included = false;
+                        self.prev = Some(Shrink::RemoveKey(key));
+                        return true;
+                    }
+                }
+                Shrink::SimplifyValue(key, value) => {
+                    let group = &mut self.groups[key];
+                    if group.included
+                        && value < group.values.len()
+                        && group.values[value].1
+                        && group.values[value].0.simplify()
+                    {
+                        self.prev = Some(self.shrink);
+                        return true;
+                    }
+                    self.shrink = self
+                        .next_value(key, value)
+                        .map_or(Shrink::SimplifyKey(0), |(k, v)| Shrink::SimplifyValue(k, v));
+                }
+                Shrink::SimplifyKey(key) => {
+                    if key >= self.groups.len() {
+                        self.shrink = Shrink::Done;
+                        continue;
+                    }
+                    if self.groups[key].included && self.groups[key].key.simplify() {
+                        self.prev = Some(self.shrink);
+                        return true;
+                    }
+                    self.shrink = Shrink::SimplifyKey(key + 1);
+                }
+                Shrink::Done => return false,
+            }
+        }
+    }
+
+    fn complicate(&mut self) -> bool {
+        match self.prev.take() {
+            Some(Shrink::RemoveValue(key, value)) => {
+                self.groups[key].values[value].1 = true;
+                true
+            }
+            Some(Shrink::RemoveKey(key)) => {
+                self.groups[key].included = true;
+                true
+            }
+            Some(Shrink::SimplifyValue(key, value)) => {
+                if self.groups[key].values[value].0.complicate() {
+                    self.prev = Some(Shrink::SimplifyValue(key, value));
+                    true
+                } else {
+                    false
+                }
+            }
+            Some(Shrink::SimplifyKey(key)) => {
+                if self.groups[key].key.complicate() {
+                    self.prev = Some(Shrink::SimplifyKey(key));
+                    true
+                } else {
+                    false
+                }
+            }
+            Some(Shrink::Done) | None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::proptest::prelude::*;
+    use super::*;
+
+    #[test]
+    fn generates_non_empty_groups() {
+        let mut runner = TestRunner::deterministic();
+        let strategy = multi_map(0..100u32, any::<u8>(), 1..6);
+        for _ in 0..100 {
+            let map = strategy.new_tree(&mut runner).unwrap().current();
+            assert!(!map.is_empty() && map.len() < 6);
+            assert!(map.iter_all().all(|(_, values)| !values.is_empty()));
+        }
+    }
+
+    #[test]
+    fn generates_distinct_keys() {
+        let mut runner = TestRunner::deterministic();
+        let strategy = multi_map(0..5u8, any::<u8>(), 5).values_per_key(3);
+        for _ in 0..100 {
+            let map = strategy.new_tree(&mut runner).unwrap().current();
+            assert_eq!(map.len(), 5);
+            assert!(map.iter_all().all(|(_, values)| values.len() == 3));
+        }
+
+        let strategy = multi_map(0..2u8, any::<u8>(), 3);
+        assert!(strategy.new_tree(&mut runner).is_err());
+    }
+
+    #[test]
+    fn shrinks_values_before_keys() {
+        let mut runner = TestRunner::deterministic();
+        let strategy = multi_map(0..1000u32, 0..1000u32, 3..8);
+        let mut tree = strategy.new_tree(&mut runner).unwrap();
+        let keys = tree.current().len();
+
+        // Accept every simplification while the map still has values to lose
+        // under some key: no key may be removed before that.
+        while tree
+            .current()
+            .iter_all()
+            .any(|(_, values)| values.len() > 1)
+        {
+            assert!(tree.simplify());
+            assert_eq!(tree.current().len(), keys);
+        }
+
+        while tree.simplify() {}
+        let map = tree.current();
+        assert_eq!(map.total_len(), 3);
+        assert!(map.flat_iter().all(|(&k, &v)| k == 0 && v == 0));
+    }
+
+    #[test]
+    fn finds_minimal_failing_map() {
+        let mut runner = TestRunner::default();
+        let result = runner.run(&multi_map(0..10u8, 0..100u32, 0..10), |map| {
+            prop_assert!(map.total_len() < 3);
+            Ok(())
+        });
+        match result {
+            Err(proptest::test_runner::TestError::Fail(_, map)) => {
+                assert_eq!(map.total_len(), 3);
+                assert!(map.flat_iter().all(|(_, &v)| v == 0));
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+}
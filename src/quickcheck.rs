@@ -0,0 +1,174 @@
+// Copyright (c) 2016 multimap developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! quickcheck Arbitrary implementation for MultiMap, enabled by the
+//! `quickcheck` feature.
+//!
+//! Generated maps never hold keys with empty vectors. Shrinking first
+//! removes values from keys holding more than one, then removes whole keys,
+//! and finally shrinks the remaining values and keys themselves.
+//!
+//! # Examples
+//!
+//! ```
+//! # extern crate multimap;
+//! # extern crate quickcheck;
+//! use multimap::MultiMap;
+//! use quickcheck::quickcheck;
+//!
+//! fn total_len_counts_every_value(map: MultiMap<u8, u32>) -> bool {
+//!     map.total_len() == map.flat_iter().count()
+//! }
+//!
+//! # fn main() {
+//! quickcheck(total_len_counts_every_value as fn(MultiMap<u8, u32>) -> bool);
+//! # }
+//! ```
+
+extern crate quickcheck;
+
+use std::hash::{BuildHasher, Hash};
+use std::rc::Rc;
+
+use self::quickcheck::{Arbitrary, Gen};
+
+use MultiMap;
+
+impl<K, V, S> Arbitrary for MultiMap<K, V, S>
+where
+    K: Arbitrary + Eq + Hash,
+    V: Arbitrary,
+    S: BuildHasher + Default + Clone + 'static,
+{
+    fn arbitrary(g: &mut Gen) -> Self {
+        let mut map = MultiMap::with_hasher(S::default());
+        for (key, values) in Vec::<(K, Vec<V>)>::arbitrary(g) {
+            if !values.is_empty() {
+                map.inner.entry(key).or_default().extend(values);
+            }
+        }
+        map
+    }
+
+    fn shrink(&self) -> Box<dyn Iterator<Item = Self>> {
+        let groups: Rc<Vec<(K, Vec<V>)>> = Rc::new(
+            self.inner
+                .iter()
+                .map(|(key, values)| (key.clone(), values.clone()))
+                .collect(),
+        );
+        let hasher = self.inner.hasher().clone();
+        let len = groups.len();
+
+        let sizes: Vec<usize> = groups.iter().map(|(_, values)| values.len()).collect();
+        let remove_values = (0..len).flat_map(move |i| {
+            let size = sizes[i];
+            (0..size)
+                .filter(move |_| size > 1)
+                .map(move |j| Edit::RemoveValue(i, j))
+        });
+        let remove_keys = (0..len).map(Edit::RemoveKey);
+
+        let shrink_values = {
+            let groups = groups.clone();
+            (0..len).flat_map(move |i| {
+                let groups = groups.clone();
+                (0..groups[i].1.len()).flat_map(move |j| {
+                    groups[i].1[j]
+                        .shrink()
+                        .map(move |value| Edit::ReplaceValue(i, j, value))
+                })
+            })
+        };
+        let shrink_keys = {
+            let groups = groups.clone();
+            (0..len).flat_map(move |i| {
+                groups[i]
+                    .0
+                    .shrink()
+                    .map(move |key| Edit::ReplaceKey(i, key))
+            })
+        };
+
+        Box::new(
+            remove_values
+                .chain(remove_keys)
+                .chain(shrink_values)
+                .chain(shrink_keys)
+                .map(move |edit| apply(&groups, hasher.clone(), edit)),
+        )
+    }
+}
+
+/// A single shrinking step, addressing values by the position of their key
+/// in the list of groups and their position in the key's vector.
+enum Edit<K, V> {
+    RemoveValue(usize, usize),
+    RemoveKey(usize),
+    ReplaceValue(usize, usize, V),
+    ReplaceKey(usize, K),
+}
+
+/// Builds the map described by `groups` with `edit` applied.
+fn apply<K, V, S>(groups: &[(K, Vec<V>)], hasher: S, edit: Edit<K, V>) -> MultiMap<K, V, S>
+where
+    K: Clone + Eq + Hash,
+    V: Clone,
+    S: BuildHasher,
+{
+    let mut groups = groups.to_vec();
+    match edit {
+        Edit::RemoveValue(i, j) => {
+            groups[i].1.remove(j);
+        }
+        Edit::RemoveKey(i) => {
+            groups.remove(i);
+        }
+        Edit::ReplaceValue(i, j, value) => groups[i].1[j] = value,
+        Edit::ReplaceKey(i, key) => groups[i].0 = key,
+    }
+
+    let mut map = MultiMap::with_capacity_and_hasher(groups.len(), hasher);
+    for (key, values) in groups {
+        map.inner.entry(key).or_default().extend(values);
+    }
+    map
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generates_non_empty_groups() {
+        let mut g = Gen::new(20);
+        for _ in 0..100 {
+            let map = MultiMap::<u8, u8>::arbitrary(&mut g);
+            assert!(map.iter_all().all(|(_, values)| !values.is_empty()));
+        }
+    }
+
+    #[test]
+    fn shrinks_values_before_keys() {
+        let mut map: MultiMap<u8, u8> = MultiMap::new();
+        map.insert_many(1, vec![1, 2]);
+        map.insert(2, 3);
+
+        let shrunk: Vec<MultiMap<u8, u8>> = map.shrink().collect();
+        assert_eq!(shrunk[0].total_len(), 2);
+        assert_eq!(shrunk[0].len(), 2);
+        assert_eq!(shrunk[1].total_len(), 2);
+        assert_eq!(shrunk[1].len(), 2);
+        assert_eq!(shrunk[2].len(), 1);
+        assert_eq!(shrunk[3].len(), 1);
+        assert!(shrunk[4..].iter().all(|map| map.total_len() == 3));
+        assert!(shrunk
+            .iter()
+            .all(|map| map.iter_all().all(|(_, values)| !values.is_empty())));
+    }
+}
@@ -0,0 +1,142 @@
+// Copyright (c) 2016 multimap developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! Random sampling of values, enabled by the `rand` feature.
+
+extern crate rand;
+
+use std::borrow::Borrow;
+use std::hash::{BuildHasher, Hash};
+
+use self::rand::Rng;
+
+use MultiMap;
+
+impl<K, V, S> MultiMap<K, V, S>
+where
+    K: Eq + Hash,
+    S: BuildHasher,
+{
+    /// Returns a value of the key chosen uniformly at random, or `None` if the
+    /// key is not in the map or holds no values.
+    ///
+    /// The key may be any borrowed form of the map's key type, but Hash and Eq
+    /// on the borrowed form must match those for the key type.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate multimap;
+    /// # extern crate rand;
+    /// use multimap::MultiMap;
+    ///
+    /// # fn main() {
+    /// let mut map = MultiMap::new();
+    /// map.insert_many("backend", vec!["10.0.0.1", "10.0.0.2"]);
+    ///
+    /// let picked = map.sample_value("backend", &mut rand::rng()).unwrap();
+    /// assert!(map.contains_pair("backend", picked));
+    /// assert!(map.sample_value("frontend", &mut rand::rng()).is_none());
+    /// # }
+    /// ```
+    pub fn sample_value<Q, R>(&self, k: &Q, rng: &mut R) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+        R: Rng + ?Sized,
+    {
+        let values = self.inner.get(k)?;
+        if values.is_empty() {
+            return None;
+        }
+        values.get(rng.random_range(0..values.len()))
+    }
+
+    /// Returns a (key, value) pair chosen uniformly at random among all pairs
+    /// in the map, so keys with more values are picked more often. Returns
+    /// `None` if the map holds no values.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate multimap;
+    /// # extern crate rand;
+    /// use multimap::MultiMap;
+    ///
+    /// # fn main() {
+    /// let mut map = MultiMap::new();
+    /// map.insert_many("a", vec![1, 2, 3]);
+    /// map.insert("b", 4);
+    ///
+    /// let (key, value) = map.sample_pair(&mut rand::rng()).unwrap();
+    /// assert!(map.contains_pair(key, value));
+    /// # }
+    /// ```
+    pub fn sample_pair<R>(&self, rng: &mut R) -> Option<(&K, &V)>
+    where
+        R: Rng + ?Sized,
+    {
+        let total = self.total_len();
+        if total == 0 {
+            return None;
+        }
+
+        let mut index = rng.random_range(0..total);
+        for (key, values) in self.inner.iter() {
+            if index < values.len() {
+                return Some((key, &values[index]));
+            }
+            index -= values.len();
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::rand::rngs::SmallRng;
+    use super::rand::SeedableRng;
+    use super::*;
+
+    #[test]
+    fn sample_value_stays_within_key() {
+        let mut rng = SmallRng::seed_from_u64(7);
+        let mut map: MultiMap<&str, usize> = MultiMap::new();
+        map.insert_many("a", vec![1, 2, 3]);
+        map.insert_many("b", vec![]);
+
+        let mut seen = Vec::new();
+        for _ in 0..100 {
+            let value = *map.sample_value("a", &mut rng).unwrap();
+            if !seen.contains(&value) {
+                seen.push(value);
+            }
+        }
+        seen.sort();
+        assert_eq!(seen, [1, 2, 3]);
+
+        assert!(map.sample_value("b", &mut rng).is_none());
+        assert!(map.sample_value("c", &mut rng).is_none());
+    }
+
+    #[test]
+    fn sample_pair_weighted_by_values() {
+        let mut rng = SmallRng::seed_from_u64(7);
+        let mut map: MultiMap<&str, usize> = MultiMap::new();
+        assert!(map.sample_pair(&mut rng).is_none());
+
+        map.insert_many("heavy", 0..99);
+        map.insert("light", 99);
+        map.insert_many("empty", vec![]);
+
+        let heavy = (0..1000)
+            .filter(|_| *map.sample_pair(&mut rng).unwrap().0 == "heavy")
+            .count();
+        assert!(heavy > 900);
+    }
+}
@@ -0,0 +1,443 @@
+// Copyright (c) 2016 multimap developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! Rayon parallel iterators, enabled by the `rayon` feature.
+//!
+//! `par_iter`, `par_iter_mut` and `into_par_iter` come from rayon's traits and
+//! visit the map grouped by key, like iterating over `&map` does. The
+//! `par_flat_*` methods visit every (key, value) pair.
+//!
+//! Collecting or extending from a parallel iterator groups the pairs in
+//! per-thread maps which are merged afterwards. Values of a key keep the
+//! order of the parallel iterator.
+
+extern crate rayon;
+
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hash};
+
+use self::rayon::collections::hash_map::{IntoIter, Iter, IterMut};
+use self::rayon::iter::{
+    FromParallelIterator, IntoParallelIterator, IntoParallelRefIterator,
+    IntoParallelRefMutIterator, ParallelExtend, ParallelIterator,
+};
+
+use flatten_groups;
+use Container;
+use MultiMap;
+
+impl<K, V> MultiMap<K, V>
+where
+    K: Eq + Hash + Send,
+    V: Send,
+{
+    /// Creates a multimap from a parallel iterator of items, grouping each
+    /// item under the key returned by `f`, like `from_grouped_by`. The items
+    /// are grouped in per-job maps which are merged afterwards, and keep the
+    /// order of the parallel iterator within each key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate multimap;
+    /// # extern crate rayon;
+    /// use multimap::MultiMap;
+    /// use rayon::prelude::*;
+    ///
+    /// # fn main() {
+    /// let words = vec!["apple", "avocado", "banana", "blueberry", "cherry"];
+    /// let map = MultiMap::from_par_grouped_by(words.into_par_iter(), |word| word.as_bytes()[0]);
+    ///
+    /// assert_eq!(map.get_vec(&b'a'), Some(&vec!["apple", "avocado"]));
+    /// assert_eq!(map.get_vec(&b'b'), Some(&vec!["banana", "blueberry"]));
+    /// assert_eq!(map.get_vec(&b'c'), Some(&vec!["cherry"]));
+    /// # }
+    /// ```
+    pub fn from_par_grouped_by<I, F>(par_iter: I, f: F) -> MultiMap<K, V>
+    where
+        I: IntoParallelIterator<Item = V>,
+        F: Fn(&V) -> K + Sync + Send,
+    {
+        MultiMap {
+            inner: group_par(par_iter, |value| (f(&value), value)),
+            marker: ::std::marker::PhantomData,
+        }
+    }
+}
+
+impl<K, V, S> MultiMap<K, V, S>
+where
+    K: Eq + Hash,
+    S: BuildHasher,
+{
+    /// A parallel iterator visiting all key-value pairs in arbitrary order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate multimap;
+    /// # extern crate rayon;
+    /// use multimap::MultiMap;
+    /// use rayon::prelude::*;
+    ///
+    /// # fn main() {
+    /// let mut map = MultiMap::new();
+    /// map.insert_many(1, vec![1, 2, 3]);
+    /// map.insert_many(2, vec![4]);
+    ///
+    /// let sum: usize = map.par_flat_iter().map(|(k, v)| k * v).sum();
+    /// assert_eq!(sum, 14);
+    /// # }
+    /// ```
+    pub fn par_flat_iter(&self) -> impl ParallelIterator<Item = (&K, &V)>
+    where
+        K: Sync,
+        V: Sync,
+    {
+        self.inner
+            .par_iter()
+            .flat_map_iter(|(k, v)| v.iter().map(move |i| (k, i)))
+    }
+
+    /// A parallel iterator visiting all key-value pairs in arbitrary order,
+    /// with mutable references to the values.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate multimap;
+    /// # extern crate rayon;
+    /// use multimap::MultiMap;
+    /// use rayon::prelude::*;
+    ///
+    /// # fn main() {
+    /// let mut map = MultiMap::new();
+    /// map.insert_many(1, vec![1, 2, 3]);
+    ///
+    /// map.par_flat_iter_mut().for_each(|(k, v)| *v += k);
+    /// assert_eq!(map.get_vec(&1), Some(&vec![2, 3, 4]));
+    /// # }
+    /// ```
+    pub fn par_flat_iter_mut(&mut self) -> impl ParallelIterator<Item = (&K, &mut V)>
+    where
+        K: Sync,
+        V: Send,
+    {
+        self.inner
+            .par_iter_mut()
+            .flat_map_iter(|(k, v)| v.iter_mut().map(move |i| (&*k, i)))
+    }
+
+    /// Consumes the map into a parallel iterator over all key-value pairs in
+    /// arbitrary order. The key is cloned for every value but the last.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate multimap;
+    /// # extern crate rayon;
+    /// use multimap::MultiMap;
+    /// use rayon::prelude::*;
+    ///
+    /// # fn main() {
+    /// let mut map = MultiMap::new();
+    /// map.insert_many("a", vec![1, 2]);
+    /// map.insert("b", 3);
+    ///
+    /// let mut pairs: Vec<_> = map.into_par_flat_iter().collect();
+    /// pairs.sort();
+    /// assert_eq!(pairs, [("a", 1), ("a", 2), ("b", 3)]);
+    /// # }
+    /// ```
+    pub fn into_par_flat_iter(self) -> impl ParallelIterator<Item = (K, V)>
+    where
+        K: Clone + Send,
+        V: Send,
+    {
+        self.inner
+            .into_par_iter()
+            .flat_map_iter(|group| flatten_groups(::std::iter::once(group)))
+    }
+
+    /// Retains only the elements specified by the predicate, like `retain`,
+    /// evaluating the predicate for different keys in parallel. Keys left
+    /// without values are removed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiMap;
+    ///
+    /// let mut map = MultiMap::new();
+    /// map.insert_many(1, vec![1, 2, 3]);
+    /// map.insert_many(2, vec![4]);
+    ///
+    /// map.par_retain(|_, &v| v % 2 == 1);
+    /// assert_eq!(map.get_vec(&1), Some(&vec![1, 3]));
+    /// assert!(!map.contains_key(&2));
+    /// ```
+    pub fn par_retain<F>(&mut self, f: F)
+    where
+        K: Sync,
+        V: Send,
+        F: Fn(&K, &V) -> bool + Sync,
+    {
+        self.inner
+            .par_iter_mut()
+            .for_each(|(key, values)| values.retain(|value| f(key, value)));
+        self.inner.retain(|_, values| !values.is_empty());
+    }
+
+    /// Removes and returns the key-value pairs for which the predicate returns
+    /// true, like `remove_values_if`, evaluating the predicate for different
+    /// keys in parallel. Keys left without values are removed. Like
+    /// `remove_values_if`, this is eager and clones the key of every pair.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiMap;
+    ///
+    /// let mut map = MultiMap::new();
+    /// map.insert_many(1, vec![1, 2, 3]);
+    /// map.insert_many(2, vec![4]);
+    ///
+    /// let mut removed = map.par_remove_values_if(|_, &v| v % 2 == 0);
+    /// removed.sort();
+    /// assert_eq!(removed, [(1, 2), (2, 4)]);
+    /// assert_eq!(map.get_vec(&1), Some(&vec![1, 3]));
+    /// assert!(!map.contains_key(&2));
+    /// ```
+    pub fn par_remove_values_if<F>(&mut self, pred: F) -> Vec<(K, V)>
+    where
+        K: Clone + Send + Sync,
+        V: Send,
+        F: Fn(&K, &V) -> bool + Sync,
+    {
+        let removed = self
+            .inner
+            .par_iter_mut()
+            .flat_map_iter(|(key, values)| {
+                let mut removed = Vec::new();
+                for value in ::std::mem::take(values) {
+                    if pred(key, &value) {
+                        removed.push((key.clone(), value));
+                    } else {
+                        values.push(value);
+                    }
+                }
+                removed
+            })
+            .collect();
+        self.inner.retain(|_, values| !values.is_empty());
+        removed
+    }
+}
+
+impl<'a, K, V, S, C> IntoParallelIterator for &'a MultiMap<K, V, S, C>
+where
+    K: Eq + Hash + Sync,
+    S: BuildHasher,
+    C: Container<V> + Sync,
+{
+    type Item = (&'a K, &'a C);
+    type Iter = Iter<'a, K, C>;
+
+    fn into_par_iter(self) -> Iter<'a, K, C> {
+        self.inner.par_iter()
+    }
+}
+
+impl<'a, K, V, S, C> IntoParallelIterator for &'a mut MultiMap<K, V, S, C>
+where
+    K: Eq + Hash + Sync,
+    S: BuildHasher,
+    C: Container<V> + Send,
+{
+    type Item = (&'a K, &'a mut C);
+    type Iter = IterMut<'a, K, C>;
+
+    fn into_par_iter(self) -> IterMut<'a, K, C> {
+        self.inner.par_iter_mut()
+    }
+}
+
+impl<K, V, S, C> IntoParallelIterator for MultiMap<K, V, S, C>
+where
+    K: Eq + Hash + Send,
+    S: BuildHasher,
+    C: Container<V> + Send,
+{
+    type Item = (K, C);
+    type Iter = IntoIter<K, C>;
+
+    fn into_par_iter(self) -> IntoIter<K, C> {
+        self.inner.into_par_iter()
+    }
+}
+
+impl<K, V, S> FromParallelIterator<(K, V)> for MultiMap<K, V, S>
+where
+    K: Eq + Hash + Send,
+    V: Send,
+    S: BuildHasher + Default + Send,
+{
+    fn from_par_iter<I>(par_iter: I) -> Self
+    where
+        I: IntoParallelIterator<Item = (K, V)>,
+    {
+        MultiMap {
+            inner: group_par(par_iter, |pair| pair),
+            marker: ::std::marker::PhantomData,
+        }
+    }
+}
+
+impl<K, V, S> ParallelExtend<(K, V)> for MultiMap<K, V, S>
+where
+    K: Eq + Hash + Send,
+    V: Send,
+    S: BuildHasher + Default + Send,
+{
+    fn par_extend<I>(&mut self, par_iter: I)
+    where
+        I: IntoParallelIterator<Item = (K, V)>,
+    {
+        let groups: HashMap<K, Vec<V>, S> = group_par(par_iter, |pair| pair);
+        for (key, values) in groups {
+            self.inner.entry(key).or_default().extend(values);
+        }
+    }
+}
+
+/// Groups the items of a parallel iterator by key, using one map per rayon
+/// job and merging them pairwise. Values keep the order of the iterator.
+fn group_par<I, F, K, V, S>(par_iter: I, f: F) -> HashMap<K, Vec<V>, S>
+where
+    I: IntoParallelIterator,
+    F: Fn(I::Item) -> (K, V) + Sync + Send,
+    K: Eq + Hash + Send,
+    V: Send,
+    S: BuildHasher + Default + Send,
+{
+    par_iter
+        .into_par_iter()
+        .fold(HashMap::default, |mut groups, item| {
+            let (key, value) = f(item);
+            groups.entry(key).or_insert_with(Vec::new).push(value);
+            groups
+        })
+        .reduce(HashMap::default, merge_groups)
+}
+
+/// Merges two groupings, with the values of `left` ahead of those of `right`.
+/// The smaller map is moved into the larger one.
+fn merge_groups<K, V, S>(
+    mut left: HashMap<K, Vec<V>, S>,
+    mut right: HashMap<K, Vec<V>, S>,
+) -> HashMap<K, Vec<V>, S>
+where
+    K: Eq + Hash,
+    S: BuildHasher,
+{
+    if left.len() >= right.len() {
+        for (key, values) in right {
+            left.entry(key).or_default().extend(values);
+        }
+        left
+    } else {
+        for (key, mut values) in left {
+            let slot = right.entry(key).or_default();
+            values.append(slot);
+            *slot = values;
+        }
+        right
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::rayon::prelude::*;
+    use super::*;
+
+    #[test]
+    fn grouped_iterators() {
+        let mut map: MultiMap<usize, usize> = (0..1000).map(|i| (i % 10, i)).collect();
+
+        assert_eq!(map.par_iter().map(|(_, v)| v.len()).sum::<usize>(), 1000);
+        map.par_iter_mut().for_each(|(_, v)| v.truncate(1));
+        assert_eq!(map.total_len(), 10);
+
+        let mut keys: Vec<usize> = map.into_par_iter().map(|(k, _)| k).collect();
+        keys.sort();
+        assert_eq!(keys, (0..10).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn collect_keeps_value_order() {
+        let map: MultiMap<usize, usize> =
+            (0..100_000).into_par_iter().map(|i| (i % 7, i)).collect();
+        let sequential: MultiMap<usize, usize> = (0..100_000).map(|i| (i % 7, i)).collect();
+        assert_eq!(map, sequential);
+    }
+
+    #[test]
+    fn par_extend_appends() {
+        let mut map: MultiMap<usize, usize> = MultiMap::new();
+        map.insert(1, 0);
+        map.par_extend((1..10_000).into_par_iter().map(|i| (i % 2, i)));
+
+        assert_eq!(map.total_len(), 10_000);
+        assert_eq!(map.get_vec(&1).unwrap()[..3], [0, 1, 3]);
+        assert_eq!(map.get_vec(&0).unwrap()[..3], [2, 4, 6]);
+    }
+
+    #[test]
+    fn flat_iterators() {
+        let mut map: MultiMap<usize, usize> = MultiMap::new();
+        map.insert_many(1, vec![1, 2]);
+        map.insert_many(2, vec![]);
+
+        assert_eq!(map.par_flat_iter().count(), 2);
+        map.par_flat_iter_mut().for_each(|(_, v)| *v *= 10);
+
+        let mut pairs: Vec<(usize, usize)> = map.into_par_flat_iter().collect();
+        pairs.sort();
+        assert_eq!(pairs, [(1, 10), (1, 20)]);
+    }
+    #[test]
+    fn par_retain_matches_retain() {
+        let mut map: MultiMap<usize, usize> = (0..10_000).map(|i| (i % 100, i)).collect();
+        let mut sequential = map.clone();
+
+        map.par_retain(|&k, &v| k < 50 && v % 3 == 0);
+        sequential.retain(|&k, &v| k < 50 && v % 3 == 0);
+        assert_eq!(map, sequential);
+        assert_eq!(map.len(), 50);
+    }
+
+    #[test]
+    fn par_remove_values_if_matches_remove_values_if() {
+        let mut map: MultiMap<usize, usize> = (0..10_000).map(|i| (i % 100, i)).collect();
+        let mut sequential = map.clone();
+
+        let mut removed = map.par_remove_values_if(|&k, &v| k < 50 || v % 3 == 0);
+        let mut expected = sequential.remove_values_if(|&k, &v| k < 50 || v % 3 == 0);
+        removed.sort();
+        expected.sort();
+        assert_eq!(removed, expected);
+        assert_eq!(map, sequential);
+        assert_eq!(map.len(), 50);
+    }
+    #[test]
+    fn from_par_grouped_by_matches_from_grouped_by() {
+        let parallel = MultiMap::from_par_grouped_by(0..100_000usize, |v| v % 13);
+        let sequential = MultiMap::from_grouped_by(0..100_000usize, |v| v % 13);
+        assert_eq!(parallel, sequential);
+    }
+}
@@ -0,0 +1,145 @@
+// Copyright (c) 2016 multimap developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! rkyv trait implementations for MultiMap, enabled by the `rkyv` feature.
+//!
+//! A MultiMap is archived as an `ArchivedMultiMap`, an archived hash map from
+//! each key to the archived vector of its values. It can be used in place,
+//! e.g. from a memory-mapped file, and looked up by key without
+//! deserializing.
+//!
+//! To serve a large map from disk without loading it, write the bytes from
+//! `rkyv::to_bytes` to a file, memory-map it (e.g. with the memmap2 crate)
+//! and pass the mapped bytes to `rkyv::access`. Mapping the file is left to
+//! the caller, since it requires unsafe code and this crate forbids it.
+//!
+//! # Examples
+//!
+//! ```
+//! # extern crate multimap;
+//! # extern crate rkyv;
+//! use multimap::rkyv::ArchivedMultiMap;
+//! use multimap::MultiMap;
+//! use rkyv::rancor::Error;
+//! use rkyv::string::ArchivedString;
+//!
+//! # fn main() {
+//! let mut map: MultiMap<String, u32> = MultiMap::new();
+//! map.insert_many("ports".to_string(), vec![80, 443]);
+//!
+//! let bytes = rkyv::to_bytes::<Error>(&map).unwrap();
+//! let archived =
+//!     rkyv::access::<ArchivedMultiMap<ArchivedString, rkyv::Archived<u32>>, Error>(&bytes).unwrap();
+//! let ports = archived.get("ports").unwrap();
+//! assert_eq!(ports.len(), 2);
+//! assert_eq!(ports[1], 443);
+//!
+//! let map: MultiMap<String, u32> = rkyv::deserialize::<_, Error>(archived).unwrap();
+//! assert_eq!(map.get_vec("ports"), Some(&vec![80, 443]));
+//! # }
+//! ```
+
+extern crate rkyv;
+
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hash};
+use std::marker::PhantomData;
+
+use self::rkyv::collections::swiss_table::ArchivedHashMap;
+use self::rkyv::rancor::Fallible;
+use self::rkyv::vec::ArchivedVec;
+use self::rkyv::{Archive, Deserialize, Place, Serialize};
+
+use MultiMap;
+
+/// The archived form of a `MultiMap<K, V>`, where `K` and `V` are the
+/// archived key and value types. Lookups by key return the archived vector
+/// of the key's values.
+pub type ArchivedMultiMap<K, V> = ArchivedHashMap<K, ArchivedVec<V>>;
+
+impl<K, V, S> Archive for MultiMap<K, V, S>
+where
+    K: Archive + Hash + Eq,
+    K::Archived: Hash + Eq,
+    V: Archive,
+{
+    type Archived = ArchivedMultiMap<K::Archived, V::Archived>;
+    type Resolver = <HashMap<K, Vec<V>, S> as Archive>::Resolver;
+
+    fn resolve(&self, resolver: Self::Resolver, out: Place<Self::Archived>) {
+        self.inner.resolve(resolver, out)
+    }
+}
+
+impl<K, V, S, Ser> Serialize<Ser> for MultiMap<K, V, S>
+where
+    K: Archive + Hash + Eq,
+    K::Archived: Hash + Eq,
+    V: Archive,
+    HashMap<K, Vec<V>, S>: Serialize<Ser>,
+    Ser: Fallible + ?Sized,
+{
+    fn serialize(&self, serializer: &mut Ser) -> Result<Self::Resolver, Ser::Error> {
+        self.inner.serialize(serializer)
+    }
+}
+
+impl<K, V, S, D> Deserialize<MultiMap<K, V, S>, D> for ArchivedMultiMap<K::Archived, V::Archived>
+where
+    K: Archive + Hash + Eq,
+    K::Archived: Hash + Eq,
+    V: Archive,
+    S: BuildHasher + Default,
+    ArchivedMultiMap<K::Archived, V::Archived>: Deserialize<HashMap<K, Vec<V>, S>, D>,
+    D: Fallible + ?Sized,
+{
+    fn deserialize(&self, deserializer: &mut D) -> Result<MultiMap<K, V, S>, D::Error> {
+        Ok(MultiMap {
+            inner: Deserialize::<HashMap<K, Vec<V>, S>, D>::deserialize(self, deserializer)?,
+            marker: PhantomData,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::rkyv::rancor::Error;
+    use super::rkyv::Archived;
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let mut map: MultiMap<u32, u32> = MultiMap::new();
+        map.insert_many(1, vec![1, 2, 3]);
+        map.insert_many(2, vec![]);
+        map.insert(3, 4);
+
+        let bytes = super::rkyv::to_bytes::<Error>(&map).unwrap();
+        let archived =
+            super::rkyv::access::<ArchivedMultiMap<Archived<u32>, Archived<u32>>, Error>(&bytes)
+                .unwrap();
+        assert_eq!(archived.len(), 3);
+        assert!(archived
+            .get(&Archived::<u32>::from_native(2))
+            .unwrap()
+            .is_empty());
+        assert!(archived.get(&Archived::<u32>::from_native(4)).is_none());
+
+        let values: Vec<u32> = archived
+            .get(&Archived::<u32>::from_native(1))
+            .unwrap()
+            .iter()
+            .map(|value| value.to_native())
+            .collect();
+        assert_eq!(values, [1, 2, 3]);
+
+        let deserialized: MultiMap<u32, u32> =
+            super::rkyv::deserialize::<_, Error>(archived).unwrap();
+        assert_eq!(deserialized, map);
+    }
+}
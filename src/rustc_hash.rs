@@ -0,0 +1,85 @@
+// Copyright (c) 2016 multimap developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! MultiMap hashing keys with FxHash from `rustc-hash`, enabled by the
+//! `rustc-hash` feature.
+//!
+//! FxHash is very fast for small keys such as integers, but it is not seeded
+//! and so offers no protection against HashDoS; only use it for keys which
+//! are not controlled by an attacker. The default hasher of `MultiMap` is
+//! left unchanged, since a feature switching it would change the type of
+//! `MultiMap<K, V>` for every crate in the build.
+
+extern crate rustc_hash;
+
+use std::hash::Hash;
+
+use self::rustc_hash::FxBuildHasher;
+
+use MultiMap;
+
+/// A MultiMap using FxHash to hash keys.
+pub type FxMultiMap<K, V> = MultiMap<K, V, FxBuildHasher>;
+
+impl<K, V> MultiMap<K, V, FxBuildHasher>
+where
+    K: Eq + Hash,
+{
+    /// Creates an empty MultiMap hashing keys with FxHash.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::rustc_hash::FxMultiMap;
+    /// use multimap::MultiMap;
+    ///
+    /// let mut map: FxMultiMap<u32, &str> = MultiMap::with_fx_hasher();
+    /// map.insert(1, "a");
+    /// assert_eq!(map.get_vec(&1), Some(&vec!["a"]));
+    /// ```
+    pub fn with_fx_hasher() -> Self {
+        MultiMap::with_hasher(FxBuildHasher)
+    }
+
+    /// Creates an empty MultiMap with the given initial capacity, hashing keys
+    /// with FxHash.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::rustc_hash::FxMultiMap;
+    /// use multimap::MultiMap;
+    ///
+    /// let map: FxMultiMap<u32, &str> = MultiMap::with_capacity_and_fx_hasher(20);
+    /// assert!(map.capacity() >= 20);
+    /// ```
+    pub fn with_capacity_and_fx_hasher(capacity: usize) -> Self {
+        MultiMap::with_capacity_and_hasher(capacity, FxBuildHasher)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn iteration_order_is_deterministic() {
+        let build = || {
+            let mut map = MultiMap::with_fx_hasher();
+            for i in 0..100u32 {
+                map.insert(i % 17, i);
+            }
+            map
+        };
+        let keys: Vec<u32> = build().keys().cloned().collect();
+        assert_eq!(keys.len(), 17);
+        for _ in 0..10 {
+            assert!(build().keys().eq(keys.iter()));
+        }
+    }
+}
@@ -0,0 +1,104 @@
+// Copyright (c) 2016 multimap developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! JsonSchema implementations for MultiMap and BTreeMultiMap, enabled by the
+//! `schemars` feature.
+//!
+//! The schema describes the default serde representation: an object mapping
+//! each key to an array of values. Fields using one of the other
+//! representations in the `serde` module can describe themselves with
+//! `#[schemars(with = "...")]`, e.g. `Vec<(K, V)>` for `pairs` or
+//! `BTreeMap<K, V>` for `duplicate_keys`.
+//!
+//! # Examples
+//!
+//! ```
+//! # extern crate multimap;
+//! # extern crate schemars;
+//! use multimap::MultiMap;
+//!
+//! # fn main() {
+//! let schema = schemars::schema_for!(MultiMap<String, u32>);
+//! assert_eq!(schema.get("type").unwrap(), "object");
+//! assert_eq!(schema.get("additionalProperties").unwrap().get("type").unwrap(), "array");
+//! # }
+//! ```
+
+extern crate schemars;
+
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::vec::Vec;
+
+use self::schemars::{JsonSchema, Schema, SchemaGenerator};
+
+use BTreeMultiMap;
+use Container;
+use MultiMap;
+
+impl<K, V, S, C> JsonSchema for MultiMap<K, V, S, C>
+where
+    K: JsonSchema,
+    C: Container<V> + JsonSchema,
+{
+    fn inline_schema() -> bool {
+        <BTreeMap<K, C>>::inline_schema()
+    }
+
+    fn schema_name() -> Cow<'static, str> {
+        <BTreeMap<K, C>>::schema_name()
+    }
+
+    fn schema_id() -> Cow<'static, str> {
+        <BTreeMap<K, C>>::schema_id()
+    }
+
+    fn json_schema(generator: &mut SchemaGenerator) -> Schema {
+        <BTreeMap<K, C>>::json_schema(generator)
+    }
+}
+
+impl<K, V> JsonSchema for BTreeMultiMap<K, V>
+where
+    K: JsonSchema,
+    V: JsonSchema,
+{
+    fn inline_schema() -> bool {
+        <BTreeMap<K, Vec<V>>>::inline_schema()
+    }
+
+    fn schema_name() -> Cow<'static, str> {
+        <BTreeMap<K, Vec<V>>>::schema_name()
+    }
+
+    fn schema_id() -> Cow<'static, str> {
+        <BTreeMap<K, Vec<V>>>::schema_id()
+    }
+
+    fn json_schema(generator: &mut SchemaGenerator) -> Schema {
+        <BTreeMap<K, Vec<V>>>::json_schema(generator)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::schemars::schema_for;
+    use super::*;
+
+    #[test]
+    fn matches_map_of_lists() {
+        assert_eq!(
+            schema_for!(MultiMap<String, u32>),
+            schema_for!(BTreeMap<String, Vec<u32>>)
+        );
+        assert_eq!(
+            schema_for!(BTreeMultiMap<u8, bool>),
+            schema_for!(BTreeMap<u8, Vec<bool>>)
+        );
+    }
+}
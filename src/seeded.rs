@@ -0,0 +1,150 @@
+// Copyright (c) 2016 multimap developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! A hash builder with an explicit seed, for reproducible iteration order.
+//!
+//! `SeededState` hashes keys with SipHash-2-4 keyed by the given seed, so
+//! the same seed gives the same hashes, and therefore the same iteration
+//! order, in every process. Integers are hashed in native byte order, so
+//! this only holds between platforms of the same endianness. The order is
+//! only guaranteed for the same build: it is built on std's deprecated
+//! `SipHasher`, and may change with a new version of this crate or of Rust.
+//! As long as the seed is kept secret it offers the same protection against
+//! HashDoS as the default hasher; a fixed, public seed does not.
+//!
+//! # Examples
+//!
+//! ```
+//! use multimap::MultiMap;
+//!
+//! let build = || {
+//!     let mut map = MultiMap::with_seed(42);
+//!     for i in 0..10 {
+//!         map.insert(i, i * 2);
+//!     }
+//!     map
+//! };
+//!
+//! assert!(build().keys().eq(build().keys()));
+//! ```
+
+#[allow(deprecated)]
+use std::hash::SipHasher;
+use std::hash::{BuildHasher, Hash, Hasher};
+
+use MultiMap;
+
+/// A hash builder creating SipHash-2-4 hashers keyed by a seed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SeededState {
+    k0: u64,
+    k1: u64,
+}
+
+impl SeededState {
+    /// Creates a hash builder from the given seed.
+    pub fn new(seed: u64) -> SeededState {
+        SeededState {
+            k0: seed,
+            k1: seed.rotate_left(32) ^ 0x9e37_79b9_7f4a_7c15,
+        }
+    }
+}
+
+impl BuildHasher for SeededState {
+    type Hasher = SeededHasher;
+
+    fn build_hasher(&self) -> SeededHasher {
+        #[allow(deprecated)]
+        SeededHasher(SipHasher::new_with_keys(self.k0, self.k1))
+    }
+}
+
+/// The hasher created by `SeededState`.
+#[derive(Clone, Debug)]
+pub struct SeededHasher(#[allow(deprecated)] SipHasher);
+
+impl Hasher for SeededHasher {
+    fn finish(&self) -> u64 {
+        self.0.finish()
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        self.0.write(bytes)
+    }
+}
+
+impl<K, V> MultiMap<K, V, SeededState>
+where
+    K: Eq + Hash,
+{
+    /// Creates an empty MultiMap hashing keys with the given seed, which
+    /// makes its iteration order the same in every process for the same
+    /// operations. See the `seeded` module for details.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiMap;
+    ///
+    /// let mut map = MultiMap::with_seed(7);
+    /// map.insert("key", 42);
+    /// assert_eq!(map.get("key"), Some(&42));
+    /// ```
+    pub fn with_seed(seed: u64) -> Self {
+        MultiMap::with_hasher(SeededState::new(seed))
+    }
+
+    /// Creates an empty MultiMap with the given initial capacity, hashing
+    /// keys with the given seed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::MultiMap;
+    ///
+    /// let map: MultiMap<&str, u32, _> = MultiMap::with_capacity_and_seed(20, 7);
+    /// assert!(map.capacity() >= 20);
+    /// ```
+    pub fn with_capacity_and_seed(capacity: usize, seed: u64) -> Self {
+        MultiMap::with_capacity_and_hasher(capacity, SeededState::new(seed))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::vec::Vec;
+
+    #[test]
+    fn hashes_depend_only_on_the_seed() {
+        let hash = |seed: u64, value: &str| SeededState::new(seed).hash_one(value);
+
+        assert_eq!(hash(0, "a"), hash(0, "a"));
+        assert_ne!(hash(0, "a"), hash(1, "a"));
+        assert_ne!(hash(0, "a"), hash(0, "b"));
+        // Pinned so that a change of the hashes, and with it of the iteration
+        // order, doesn't go unnoticed.
+        assert_eq!(hash(0, "a"), 8608919046067677826);
+        assert_eq!(hash(42, "a"), 1074650262952626149);
+    }
+
+    #[test]
+    fn iteration_order_is_reproducible() {
+        let build = |seed| {
+            let mut map = MultiMap::with_seed(seed);
+            for i in 0..100u32 {
+                map.insert(i % 31, i);
+            }
+            map
+        };
+        let order: Vec<u32> = build(1).keys().cloned().collect();
+        assert!(build(1).keys().eq(order.iter()));
+        assert!((2..10).any(|seed| !build(seed).keys().eq(order.iter())));
+    }
+}
@@ -0,0 +1,165 @@
+// Copyright (c) 2016 multimap developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! (De)serializes a MultiMap as a map holding one value per entry, where a
+//! key is repeated once for each of its values, e.g. `{"k": v1, "k": v2}`.
+//! Deserializing accumulates the values of repeated keys instead of keeping
+//! only the last one, in the order they appear.
+//!
+//! # Examples
+//!
+//! ```
+//! # extern crate multimap;
+//! # extern crate serde;
+//! # extern crate serde_json;
+//! use multimap::MultiMap;
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct Request {
+//!     #[serde(with = "multimap::serde::duplicate_keys")]
+//!     headers: MultiMap<String, String>,
+//! }
+//!
+//! # fn main() {
+//! let json = r#"{"headers":{"accept":"text/html","accept":"text/plain"}}"#;
+//! let request: Request = serde_json::from_str(json).unwrap();
+//! assert_eq!(request.headers.get_vec("accept").unwrap(), &["text/html", "text/plain"]);
+//!
+//! assert_eq!(serde_json::to_string(&request).unwrap(), json);
+//! # }
+//! ```
+
+use std::fmt;
+use std::hash::{BuildHasher, Hash};
+use std::marker::PhantomData;
+
+use super::serde::de::{MapAccess, Visitor};
+use super::serde::ser::SerializeMap;
+use super::serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use Container;
+use MultiMap;
+
+/// Serializes the map with a key repeated once for each of its values.
+pub fn serialize<K, V, BS, C, S>(
+    map: &MultiMap<K, V, BS, C>,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    K: Serialize + Eq + Hash,
+    V: Serialize,
+    BS: BuildHasher,
+    C: Container<V>,
+    S: Serializer,
+{
+    let len = map.iter_all().map(|(_, values)| values.len()).sum();
+    let mut entries = serializer.serialize_map(Some(len))?;
+    for (key, value) in map.flat_iter() {
+        entries.serialize_entry(key, value)?;
+    }
+    entries.end()
+}
+
+/// Deserializes the map from a map which may repeat keys, accumulating their
+/// values.
+pub fn deserialize<'de, K, V, BS, C, D>(deserializer: D) -> Result<MultiMap<K, V, BS, C>, D::Error>
+where
+    K: Deserialize<'de> + Eq + Hash,
+    V: Deserialize<'de>,
+    BS: BuildHasher + Default,
+    C: Container<V>,
+    D: Deserializer<'de>,
+{
+    deserializer.deserialize_map(DuplicateKeysVisitor {
+        marker: PhantomData,
+    })
+}
+
+struct DuplicateKeysVisitor<K, V, BS, C> {
+    marker: PhantomData<MultiMap<K, V, BS, C>>,
+}
+
+impl<'de, K, V, BS, C> Visitor<'de> for DuplicateKeysVisitor<K, V, BS, C>
+where
+    K: Deserialize<'de> + Eq + Hash,
+    V: Deserialize<'de>,
+    BS: BuildHasher + Default,
+    C: Container<V>,
+{
+    type Value = MultiMap<K, V, BS, C>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a map")
+    }
+
+    fn visit_map<M>(self, mut access: M) -> Result<Self::Value, M::Error>
+    where
+        M: MapAccess<'de>,
+    {
+        let mut map = MultiMap::with_container_and_hasher(BS::default());
+        while let Some((key, value)) = access.next_entry()? {
+            map.insert(key, value);
+        }
+        Ok(map)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate serde_test;
+
+    use self::serde_test::{assert_de_tokens, assert_tokens, Token};
+
+    use super::*;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Wrapper(#[serde(with = "super")] MultiMap<char, u8>);
+
+    #[test]
+    fn round_trip() {
+        let mut map = MultiMap::new();
+        map.insert('x', 1);
+        map.insert('x', 3);
+
+        assert_tokens(
+            &Wrapper(map),
+            &[
+                Token::NewtypeStruct { name: "Wrapper" },
+                Token::Map { len: Some(2) },
+                Token::Char('x'),
+                Token::U8(1),
+                Token::Char('x'),
+                Token::U8(3),
+                Token::MapEnd,
+            ],
+        );
+    }
+
+    #[test]
+    fn accumulates_interleaved_keys() {
+        let mut map = MultiMap::new();
+        map.insert_many('x', vec![1, 3]);
+        map.insert('y', 2);
+
+        assert_de_tokens(
+            &Wrapper(map),
+            &[
+                Token::NewtypeStruct { name: "Wrapper" },
+                Token::Map { len: None },
+                Token::Char('x'),
+                Token::U8(1),
+                Token::Char('y'),
+                Token::U8(2),
+                Token::Char('x'),
+                Token::U8(3),
+                Token::MapEnd,
+            ],
+        );
+    }
+}
@@ -0,0 +1,67 @@
+// Copyright (c) 2016 multimap developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! (De)serializes a MultiMap as a map from each key to the list of its
+//! values, e.g. `{"k": [v1, v2]}`. This is the default representation; the
+//! module exists to state it explicitly next to `pairs`.
+//!
+//! # Examples
+//!
+//! ```
+//! # extern crate multimap;
+//! # extern crate serde;
+//! # extern crate serde_json;
+//! use multimap::MultiMap;
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct Headers {
+//!     #[serde(with = "multimap::serde::map_of_lists")]
+//!     values: MultiMap<String, String>,
+//! }
+//!
+//! # fn main() {
+//! let mut values = MultiMap::new();
+//! values.insert_many("accept".to_string(), vec!["a".to_string(), "b".to_string()]);
+//!
+//! let json = serde_json::to_string(&Headers { values }).unwrap();
+//! assert_eq!(json, r#"{"values":{"accept":["a","b"]}}"#);
+//! # }
+//! ```
+
+use std::hash::{BuildHasher, Hash};
+
+use super::serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use Container;
+use MultiMap;
+
+/// Serializes the map as a map of lists.
+pub fn serialize<K, V, BS, C, S>(
+    map: &MultiMap<K, V, BS, C>,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    K: Serialize + Eq + Hash,
+    BS: BuildHasher,
+    C: Serialize,
+    S: Serializer,
+{
+    map.serialize(serializer)
+}
+
+/// Deserializes the map from a map of lists.
+pub fn deserialize<'de, K, V, BS, C, D>(deserializer: D) -> Result<MultiMap<K, V, BS, C>, D::Error>
+where
+    K: Deserialize<'de> + Eq + Hash,
+    BS: BuildHasher + Default,
+    C: Container<V> + Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    MultiMap::deserialize(deserializer)
+}
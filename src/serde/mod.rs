@@ -0,0 +1,311 @@
+// Copyright (c) 2016 multimap developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! Serde trait implementations for MultiMap and BTreeMultiMap
+//!
+//! By default a MultiMap is represented as a map from each key to the list
+//! of its values. The `map_of_lists`, `pairs`, `duplicate_keys` and
+//! `one_or_many` modules can be used with `#[serde(with = "...")]` to pick
+//! the representation of a field explicitly.
+//!
+//! Keys and values may borrow from the input, e.g. `MultiMap<&str, &str>`,
+//! in every representation when the format supports borrowing.
+
+extern crate serde;
+
+pub mod duplicate_keys;
+pub mod map_of_lists;
+pub mod one_or_many;
+pub mod pairs;
+
+use std::fmt;
+use std::hash::{BuildHasher, Hash};
+use std::marker::PhantomData;
+
+use self::serde::de::{MapAccess, Visitor};
+use self::serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use BTreeMultiMap;
+use Container;
+use MultiMap;
+
+impl<K, V, BS, C> Serialize for MultiMap<K, V, BS, C>
+where
+    K: Serialize + Eq + Hash,
+    BS: BuildHasher,
+    C: Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.inner.serialize(serializer)
+    }
+}
+
+impl<K, V, S, C> MultiMapVisitor<K, V, S, C>
+where
+    K: Hash + Eq,
+{
+    fn new() -> Self {
+        MultiMapVisitor {
+            marker: PhantomData,
+        }
+    }
+}
+
+struct MultiMapVisitor<K, V, S, C> {
+    marker: PhantomData<MultiMap<K, V, S, C>>,
+}
+
+impl<'a, K, V, S, C> Visitor<'a> for MultiMapVisitor<K, V, S, C>
+where
+    K: Deserialize<'a> + Eq + Hash,
+    S: BuildHasher + Default,
+    C: Container<V> + Deserialize<'a>,
+{
+    type Value = MultiMap<K, V, S, C>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("expected a map")
+    }
+
+    fn visit_map<M>(self, mut visitor: M) -> Result<Self::Value, M::Error>
+    where
+        M: MapAccess<'a>,
+    {
+        let mut values = MultiMap::with_container_and_hasher(S::default());
+        values.inner.reserve(visitor.size_hint().unwrap_or(0));
+
+        while let Some((key, value)) = visitor.next_entry()? {
+            values.inner.insert(key, value);
+        }
+
+        Ok(values)
+    }
+}
+
+impl<'a, K, V, S, C> Deserialize<'a> for MultiMap<K, V, S, C>
+where
+    K: Deserialize<'a> + Eq + Hash,
+    S: BuildHasher + Default,
+    C: Container<V> + Deserialize<'a>,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'a>,
+    {
+        deserializer.deserialize_map(MultiMapVisitor::<K, V, S, C>::new())
+    }
+}
+
+impl<K, V> Serialize for BTreeMultiMap<K, V>
+where
+    K: Serialize + Ord,
+    V: Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.inner.serialize(serializer)
+    }
+}
+
+struct BTreeMultiMapVisitor<K, V> {
+    marker: PhantomData<BTreeMultiMap<K, V>>,
+}
+
+impl<'a, K, V> Visitor<'a> for BTreeMultiMapVisitor<K, V>
+where
+    K: Deserialize<'a> + Ord,
+    V: Deserialize<'a>,
+{
+    type Value = BTreeMultiMap<K, V>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("expected a map")
+    }
+
+    fn visit_map<M>(self, mut visitor: M) -> Result<Self::Value, M::Error>
+    where
+        M: MapAccess<'a>,
+    {
+        let mut values = BTreeMultiMap::new();
+
+        while let Some((key, value)) = visitor.next_entry()? {
+            values.inner.insert(key, value);
+        }
+
+        Ok(values)
+    }
+}
+
+impl<'a, K, V> Deserialize<'a> for BTreeMultiMap<K, V>
+where
+    K: Deserialize<'a> + Ord,
+    V: Deserialize<'a>,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'a>,
+    {
+        deserializer.deserialize_map(BTreeMultiMapVisitor {
+            marker: PhantomData,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    extern crate serde_test;
+
+    use self::serde_test::{assert_tokens, Token};
+
+    use super::*;
+    use std::string::String;
+
+    #[test]
+    fn test_empty() {
+        let map = MultiMap::<char, u8>::new();
+
+        assert_tokens(&map, &[Token::Map { len: Some(0) }, Token::MapEnd]);
+    }
+
+    #[test]
+    fn test_single() {
+        let mut map = MultiMap::<char, u8>::new();
+        map.insert('x', 1);
+
+        assert_tokens(
+            &map,
+            &[
+                Token::Map { len: Some(1) },
+                Token::Char('x'),
+                Token::Seq { len: Some(1) },
+                Token::U8(1),
+                Token::SeqEnd,
+                Token::MapEnd,
+            ],
+        );
+    }
+
+    #[test]
+    fn test_multiple() {
+        let mut map = MultiMap::<char, u8>::new();
+        map.insert('x', 1);
+        map.insert('x', 3);
+        map.insert('x', 1);
+        map.insert('x', 5);
+
+        assert_tokens(
+            &map,
+            &[
+                Token::Map { len: Some(1) },
+                Token::Char('x'),
+                Token::Seq { len: Some(4) },
+                Token::U8(1),
+                Token::U8(3),
+                Token::U8(1),
+                Token::U8(5),
+                Token::SeqEnd,
+                Token::MapEnd,
+            ],
+        );
+    }
+
+    #[test]
+    fn test_btree_sorted() {
+        let mut map = BTreeMultiMap::<char, u8>::new();
+        map.insert('y', 2);
+        map.insert('x', 1);
+        map.insert('x', 3);
+
+        assert_tokens(
+            &map,
+            &[
+                Token::Map { len: Some(2) },
+                Token::Char('x'),
+                Token::Seq { len: Some(2) },
+                Token::U8(1),
+                Token::U8(3),
+                Token::SeqEnd,
+                Token::Char('y'),
+                Token::Seq { len: Some(1) },
+                Token::U8(2),
+                Token::SeqEnd,
+                Token::MapEnd,
+            ],
+        );
+    }
+
+    #[test]
+    fn test_set_container() {
+        use std::collections::hash_map::RandomState;
+        use std::collections::BTreeSet;
+
+        let mut map: MultiMap<char, u8, RandomState, BTreeSet<u8>> = MultiMap::with_container();
+        map.insert('x', 3);
+        map.insert('x', 1);
+        map.insert('x', 3);
+
+        assert_tokens(
+            &map,
+            &[
+                Token::Map { len: Some(1) },
+                Token::Char('x'),
+                Token::Seq { len: Some(2) },
+                Token::U8(1),
+                Token::U8(3),
+                Token::SeqEnd,
+                Token::MapEnd,
+            ],
+        );
+    }
+
+    #[test]
+    fn test_borrowed() {
+        extern crate serde_json;
+
+        let json = String::from(r#"{"x":["a","b"],"y":["c"]}"#);
+        let map: MultiMap<&str, &str> = serde_json::from_str(&json).unwrap();
+        assert_eq!(map.get_vec("x"), Some(&vec!["a", "b"]));
+
+        let map: BTreeMultiMap<&str, &str> = serde_json::from_str(&json).unwrap();
+        assert_eq!(map.get_vec("y"), Some(&vec!["c"]));
+    }
+
+    #[test]
+    fn test_borrowed_representations() {
+        extern crate serde_json;
+
+        #[derive(Deserialize)]
+        struct Borrowed<'a> {
+            #[serde(borrow, with = "pairs")]
+            pairs: MultiMap<&'a str, &'a str>,
+            #[serde(borrow, with = "duplicate_keys")]
+            duplicate_keys: MultiMap<&'a str, &'a str>,
+            #[serde(borrow, with = "one_or_many")]
+            one_or_many: MultiMap<&'a str, &'a str>,
+        }
+
+        let json = String::from(
+            r#"{
+                "pairs": [["x", "a"], ["x", "b"]],
+                "duplicate_keys": {"x": "a", "x": "b"},
+                "one_or_many": {"x": ["a", "b"], "y": "c"}
+            }"#,
+        );
+        let borrowed: Borrowed = serde_json::from_str(&json).unwrap();
+        assert_eq!(borrowed.pairs.get_vec("x"), Some(&vec!["a", "b"]));
+        assert_eq!(borrowed.duplicate_keys.get_vec("x"), Some(&vec!["a", "b"]));
+        assert_eq!(borrowed.one_or_many.get_vec("x"), Some(&vec!["a", "b"]));
+        assert_eq!(borrowed.one_or_many.get_vec("y"), Some(&vec!["c"]));
+    }
+}
@@ -0,0 +1,324 @@
+// Copyright (c) 2016 multimap developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! (De)serializes a MultiMap as a map whose values are either a single value
+//! or a list of values, e.g. `{"a": v1, "b": [v2, v3]}`, as commonly found in
+//! configuration files.
+//!
+//! Keys with exactly one value are serialized without the list. Telling a
+//! single value from a list needs a self-describing format such as JSON,
+//! YAML or TOML, and a value which is itself a sequence is always read as a
+//! list of values.
+//!
+//! # Examples
+//!
+//! ```
+//! # extern crate multimap;
+//! # extern crate serde;
+//! # extern crate serde_json;
+//! use multimap::MultiMap;
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct Config {
+//!     #[serde(with = "multimap::serde::one_or_many")]
+//!     listen: MultiMap<String, u16>,
+//! }
+//!
+//! # fn main() {
+//! let json = r#"{"listen":{"http":80,"https":[443,8443]}}"#;
+//! let config: Config = serde_json::from_str(json).unwrap();
+//! assert_eq!(config.listen.get_vec("http"), Some(&vec![80]));
+//! assert_eq!(config.listen.get_vec("https"), Some(&vec![443, 8443]));
+//! # }
+//! ```
+
+use std::fmt;
+use std::hash::{BuildHasher, Hash};
+use std::marker::PhantomData;
+use std::string::String;
+use std::vec::Vec;
+
+use super::serde::de::value::{
+    BorrowedBytesDeserializer, BorrowedStrDeserializer, BytesDeserializer, EnumAccessDeserializer,
+    MapAccessDeserializer,
+};
+use super::serde::de::{EnumAccess, Error, IntoDeserializer, MapAccess, SeqAccess, Visitor};
+use super::serde::ser::SerializeMap;
+use super::serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use Container;
+use MultiMap;
+
+/// Serializes the map, writing keys with a single value without a list.
+pub fn serialize<K, V, BS, C, S>(
+    map: &MultiMap<K, V, BS, C>,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    K: Serialize + Eq + Hash,
+    V: Serialize,
+    BS: BuildHasher,
+    C: Container<V>,
+    S: Serializer,
+{
+    let mut entries = serializer.serialize_map(Some(map.len()))?;
+    for (key, values) in map.iter_all() {
+        entries.serialize_entry(key, &OneOrManyRef(values, PhantomData))?;
+    }
+    entries.end()
+}
+
+/// Deserializes the map from a map whose values are either a single value or
+/// a list of values.
+pub fn deserialize<'de, K, V, BS, C, D>(deserializer: D) -> Result<MultiMap<K, V, BS, C>, D::Error>
+where
+    K: Deserialize<'de> + Eq + Hash,
+    V: Deserialize<'de>,
+    BS: BuildHasher + Default,
+    C: Container<V>,
+    D: Deserializer<'de>,
+{
+    deserializer.deserialize_map(OneOrManyMapVisitor {
+        marker: PhantomData,
+    })
+}
+
+struct OneOrManyRef<'a, V, C: 'a>(&'a C, PhantomData<V>);
+
+impl<'a, V, C> Serialize for OneOrManyRef<'a, V, C>
+where
+    V: Serialize,
+    C: Container<V>,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut values = self.0.iter();
+        match (values.next(), self.0.len()) {
+            (Some(value), 1) => value.serialize(serializer),
+            _ => serializer.collect_seq(self.0.iter()),
+        }
+    }
+}
+
+struct OneOrManyMapVisitor<K, V, BS, C> {
+    marker: PhantomData<MultiMap<K, V, BS, C>>,
+}
+
+impl<'de, K, V, BS, C> Visitor<'de> for OneOrManyMapVisitor<K, V, BS, C>
+where
+    K: Deserialize<'de> + Eq + Hash,
+    V: Deserialize<'de>,
+    BS: BuildHasher + Default,
+    C: Container<V>,
+{
+    type Value = MultiMap<K, V, BS, C>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a map")
+    }
+
+    fn visit_map<M>(self, mut access: M) -> Result<Self::Value, M::Error>
+    where
+        M: MapAccess<'de>,
+    {
+        let mut map = MultiMap::with_container_and_hasher(BS::default());
+        while let Some((key, OneOrMany(values))) = access.next_entry::<K, OneOrMany<V>>()? {
+            map.insert_many(key, values);
+        }
+        Ok(map)
+    }
+}
+
+/// A single value or a list of values, read into a vector.
+struct OneOrMany<V>(Vec<V>);
+
+impl<'de, V> Deserialize<'de> for OneOrMany<V>
+where
+    V: Deserialize<'de>,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer
+            .deserialize_any(OneOrManyVisitor(PhantomData))
+            .map(OneOrMany)
+    }
+}
+
+/// Collects a sequence into a vector, and hands anything else back to `V`.
+struct OneOrManyVisitor<V>(PhantomData<V>);
+
+impl<V> OneOrManyVisitor<V> {
+    fn one<'de, D>(deserializer: D) -> Result<Vec<V>, D::Error>
+    where
+        V: Deserialize<'de>,
+        D: Deserializer<'de>,
+    {
+        V::deserialize(deserializer).map(|value| vec![value])
+    }
+}
+
+impl<'de, V> Visitor<'de> for OneOrManyVisitor<V>
+where
+    V: Deserialize<'de>,
+{
+    type Value = Vec<V>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a value or a list of values")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Vec<V>, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut values = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(value) = seq.next_element()? {
+            values.push(value);
+        }
+        Ok(values)
+    }
+
+    fn visit_map<A>(self, map: A) -> Result<Vec<V>, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        Self::one(MapAccessDeserializer::new(map))
+    }
+
+    fn visit_enum<A>(self, data: A) -> Result<Vec<V>, A::Error>
+    where
+        A: EnumAccess<'de>,
+    {
+        Self::one(EnumAccessDeserializer::new(data))
+    }
+
+    fn visit_bool<E: Error>(self, v: bool) -> Result<Vec<V>, E> {
+        Self::one(v.into_deserializer())
+    }
+
+    fn visit_i64<E: Error>(self, v: i64) -> Result<Vec<V>, E> {
+        Self::one(v.into_deserializer())
+    }
+
+    fn visit_i128<E: Error>(self, v: i128) -> Result<Vec<V>, E> {
+        Self::one(v.into_deserializer())
+    }
+
+    fn visit_u64<E: Error>(self, v: u64) -> Result<Vec<V>, E> {
+        Self::one(v.into_deserializer())
+    }
+
+    fn visit_u128<E: Error>(self, v: u128) -> Result<Vec<V>, E> {
+        Self::one(v.into_deserializer())
+    }
+
+    fn visit_f64<E: Error>(self, v: f64) -> Result<Vec<V>, E> {
+        Self::one(v.into_deserializer())
+    }
+
+    fn visit_char<E: Error>(self, v: char) -> Result<Vec<V>, E> {
+        Self::one(v.into_deserializer())
+    }
+
+    fn visit_str<E: Error>(self, v: &str) -> Result<Vec<V>, E> {
+        Self::one(v.into_deserializer())
+    }
+
+    fn visit_borrowed_str<E: Error>(self, v: &'de str) -> Result<Vec<V>, E> {
+        Self::one(BorrowedStrDeserializer::new(v))
+    }
+
+    fn visit_string<E: Error>(self, v: String) -> Result<Vec<V>, E> {
+        Self::one(v.into_deserializer())
+    }
+
+    fn visit_bytes<E: Error>(self, v: &[u8]) -> Result<Vec<V>, E> {
+        Self::one(BytesDeserializer::new(v))
+    }
+
+    fn visit_borrowed_bytes<E: Error>(self, v: &'de [u8]) -> Result<Vec<V>, E> {
+        Self::one(BorrowedBytesDeserializer::new(v))
+    }
+
+    fn visit_unit<E: Error>(self) -> Result<Vec<V>, E> {
+        Self::one(().into_deserializer())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate serde_test;
+
+    use self::serde_test::{assert_de_tokens, assert_tokens, Token};
+
+    use super::*;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Wrapper(#[serde(with = "super")] MultiMap<char, u8>);
+
+    #[test]
+    fn round_trip() {
+        let mut map = MultiMap::new();
+        map.insert_many('x', vec![1, 3]);
+
+        assert_tokens(
+            &Wrapper(map.clone()),
+            &[
+                Token::NewtypeStruct { name: "Wrapper" },
+                Token::Map { len: Some(1) },
+                Token::Char('x'),
+                Token::Seq { len: Some(2) },
+                Token::U8(1),
+                Token::U8(3),
+                Token::SeqEnd,
+                Token::MapEnd,
+            ],
+        );
+
+        map.remove(&'x');
+        map.insert('y', 2);
+        assert_tokens(
+            &Wrapper(map),
+            &[
+                Token::NewtypeStruct { name: "Wrapper" },
+                Token::Map { len: Some(1) },
+                Token::Char('y'),
+                Token::U8(2),
+                Token::MapEnd,
+            ],
+        );
+    }
+
+    #[test]
+    fn mixed_values() {
+        let mut map = MultiMap::new();
+        map.insert('x', 1);
+        map.insert_many('y', vec![2, 3]);
+
+        assert_de_tokens(
+            &Wrapper(map),
+            &[
+                Token::NewtypeStruct { name: "Wrapper" },
+                Token::Map { len: None },
+                Token::Char('x'),
+                Token::U64(1),
+                Token::Char('y'),
+                Token::Seq { len: None },
+                Token::U64(2),
+                Token::U64(3),
+                Token::SeqEnd,
+                Token::MapEnd,
+            ],
+        );
+    }
+}
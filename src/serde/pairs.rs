@@ -0,0 +1,148 @@
+// Copyright (c) 2016 multimap developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! (De)serializes a MultiMap as a flat list of (key, value) pairs, e.g.
+//! `[["k", v1], ["k", v2]]`. The pairs of a key are written in the order of
+//! its values, and values keep the order of the list when deserializing.
+//!
+//! # Examples
+//!
+//! ```
+//! # extern crate multimap;
+//! # extern crate serde;
+//! # extern crate serde_json;
+//! use multimap::MultiMap;
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct Query {
+//!     #[serde(with = "multimap::serde::pairs")]
+//!     params: MultiMap<String, u32>,
+//! }
+//!
+//! # fn main() {
+//! let mut params = MultiMap::new();
+//! params.insert_many("id".to_string(), vec![1, 2]);
+//!
+//! let json = serde_json::to_string(&Query { params }).unwrap();
+//! assert_eq!(json, r#"{"params":[["id",1],["id",2]]}"#);
+//!
+//! let query: Query = serde_json::from_str(&json).unwrap();
+//! assert_eq!(query.params.get_vec("id"), Some(&vec![1, 2]));
+//! # }
+//! ```
+
+use std::fmt;
+use std::hash::{BuildHasher, Hash};
+use std::marker::PhantomData;
+
+use super::serde::de::{SeqAccess, Visitor};
+use super::serde::ser::SerializeSeq;
+use super::serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use Container;
+use MultiMap;
+
+/// Serializes the map as a list of (key, value) pairs.
+pub fn serialize<K, V, BS, C, S>(
+    map: &MultiMap<K, V, BS, C>,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    K: Serialize + Eq + Hash,
+    V: Serialize,
+    BS: BuildHasher,
+    C: Container<V>,
+    S: Serializer,
+{
+    let len = map.iter_all().map(|(_, values)| values.len()).sum();
+    let mut seq = serializer.serialize_seq(Some(len))?;
+    for pair in map.flat_iter() {
+        seq.serialize_element(&pair)?;
+    }
+    seq.end()
+}
+
+/// Deserializes the map from a list of (key, value) pairs.
+pub fn deserialize<'de, K, V, BS, C, D>(deserializer: D) -> Result<MultiMap<K, V, BS, C>, D::Error>
+where
+    K: Deserialize<'de> + Eq + Hash,
+    V: Deserialize<'de>,
+    BS: BuildHasher + Default,
+    C: Container<V>,
+    D: Deserializer<'de>,
+{
+    deserializer.deserialize_seq(PairsVisitor {
+        marker: PhantomData,
+    })
+}
+
+struct PairsVisitor<K, V, BS, C> {
+    marker: PhantomData<MultiMap<K, V, BS, C>>,
+}
+
+impl<'de, K, V, BS, C> Visitor<'de> for PairsVisitor<K, V, BS, C>
+where
+    K: Deserialize<'de> + Eq + Hash,
+    V: Deserialize<'de>,
+    BS: BuildHasher + Default,
+    C: Container<V>,
+{
+    type Value = MultiMap<K, V, BS, C>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a sequence of key-value pairs")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut map = MultiMap::with_container_and_hasher(BS::default());
+        while let Some((key, value)) = seq.next_element()? {
+            map.insert(key, value);
+        }
+        Ok(map)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate serde_test;
+
+    use self::serde_test::{assert_tokens, Token};
+
+    use super::*;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Wrapper(#[serde(with = "super")] MultiMap<char, u8>);
+
+    #[test]
+    fn round_trip() {
+        let mut map = MultiMap::new();
+        map.insert('x', 1);
+        map.insert('x', 3);
+
+        assert_tokens(
+            &Wrapper(map),
+            &[
+                Token::NewtypeStruct { name: "Wrapper" },
+                Token::Seq { len: Some(2) },
+                Token::Tuple { len: 2 },
+                Token::Char('x'),
+                Token::U8(1),
+                Token::TupleEnd,
+                Token::Tuple { len: 2 },
+                Token::Char('x'),
+                Token::U8(3),
+                Token::TupleEnd,
+                Token::SeqEnd,
+            ],
+        );
+    }
+}
@@ -0,0 +1,540 @@
+// Copyright (c) 2016 multimap developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! A multimap which stores the values of each key in a std::collections::HashSet.
+//!
+//! Contrary to MultiMap, a SetMultiMap never stores the same key-value-pair
+//! twice: inserting a pair that already exists is a no-op. The values of a key
+//! are not ordered.
+//!
+//! # Examples
+//!
+//! ```
+//! use multimap::SetMultiMap;
+//!
+//! let mut map = SetMultiMap::new();
+//! assert!(map.insert("key", 42));
+//! assert!(map.insert("key", 1337));
+//! assert!(!map.insert("key", 42));
+//!
+//! assert_eq!(map.get_set("key").map(|s| s.len()), Some(2));
+//! ```
+
+use std::borrow::Borrow;
+use std::collections::hash_map::{self, Keys, RandomState};
+use std::collections::{HashMap, HashSet};
+use std::fmt::{self, Debug};
+use std::hash::{BuildHasher, Hash};
+use std::iter::{FromIterator, IntoIterator, Iterator};
+
+pub use std::collections::hash_map::Iter as IterAll;
+
+#[derive(Clone)]
+pub struct SetMultiMap<K, V, S = RandomState> {
+    inner: HashMap<K, HashSet<V, S>, S>,
+}
+
+impl<K, V> SetMultiMap<K, V>
+where
+    K: Eq + Hash,
+    V: Eq + Hash,
+{
+    /// Creates an empty SetMultiMap
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::SetMultiMap;
+    ///
+    /// let mut map: SetMultiMap<&str, isize> = SetMultiMap::new();
+    /// ```
+    pub fn new() -> SetMultiMap<K, V> {
+        SetMultiMap {
+            inner: HashMap::new(),
+        }
+    }
+
+    /// Creates an empty SetMultiMap with the given initial capacity.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::SetMultiMap;
+    ///
+    /// let mut map: SetMultiMap<&str, isize> = SetMultiMap::with_capacity(20);
+    /// ```
+    pub fn with_capacity(capacity: usize) -> SetMultiMap<K, V> {
+        SetMultiMap {
+            inner: HashMap::with_capacity(capacity),
+        }
+    }
+}
+
+impl<K, V, S> SetMultiMap<K, V, S>
+where
+    K: Eq + Hash,
+    V: Eq + Hash,
+    S: BuildHasher + Clone,
+{
+    /// Creates an empty SetMultiMap which will use the given hash builder to hash
+    /// both keys and values.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::SetMultiMap;
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let s = RandomState::new();
+    /// let mut map: SetMultiMap<&str, isize> = SetMultiMap::with_hasher(s);
+    /// ```
+    pub fn with_hasher(hash_builder: S) -> SetMultiMap<K, V, S> {
+        SetMultiMap {
+            inner: HashMap::with_hasher(hash_builder),
+        }
+    }
+
+    /// Inserts a key-value pair into the multimap. Returns true if the pair
+    /// was not already present, false if it was, in which case the map is
+    /// left unchanged.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::SetMultiMap;
+    ///
+    /// let mut map = SetMultiMap::new();
+    /// assert!(map.insert("key", 42));
+    /// assert!(!map.insert("key", 42));
+    /// ```
+    pub fn insert(&mut self, k: K, v: V) -> bool {
+        let hasher = self.inner_hasher();
+        match self.inner.entry(k) {
+            hash_map::Entry::Occupied(mut entry) => entry.get_mut().insert(v),
+            hash_map::Entry::Vacant(entry) => {
+                let mut set = HashSet::with_hasher(hasher);
+                set.insert(v);
+                entry.insert(set);
+                true
+            }
+        }
+    }
+
+    /// Inserts multiple values for a key into the multimap. Returns the number
+    /// of values which were not already present.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::SetMultiMap;
+    ///
+    /// let mut map = SetMultiMap::new();
+    /// assert_eq!(map.insert_many("key", vec![1, 2, 2]), 2);
+    /// assert_eq!(map.insert_many("key", vec![2, 3]), 1);
+    /// ```
+    pub fn insert_many<I: IntoIterator<Item = V>>(&mut self, k: K, v: I) -> usize {
+        let hasher = self.inner_hasher();
+        let set = self
+            .inner
+            .entry(k)
+            .or_insert_with(|| HashSet::with_hasher(hasher));
+        let before = set.len();
+        set.extend(v);
+        set.len() - before
+    }
+
+    /// Returns true if the map contains a value for the specified key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::SetMultiMap;
+    ///
+    /// let mut map = SetMultiMap::new();
+    /// map.insert(1, 42);
+    /// assert_eq!(map.contains_key(&1), true);
+    /// assert_eq!(map.contains_key(&2), false);
+    /// ```
+    pub fn contains_key<Q>(&self, k: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        self.inner.contains_key(k)
+    }
+
+    /// Returns true if the map contains the given key-value pair.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::SetMultiMap;
+    ///
+    /// let mut map = SetMultiMap::new();
+    /// map.insert(1, 42);
+    /// assert_eq!(map.contains(&1, &42), true);
+    /// assert_eq!(map.contains(&1, &43), false);
+    /// ```
+    pub fn contains<Q, W>(&self, k: &Q, v: &W) -> bool
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+        V: Borrow<W>,
+        W: ?Sized + Eq + Hash,
+    {
+        self.inner.get(k).is_some_and(|set| set.contains(v))
+    }
+
+    /// Returns the number of keys in the map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::SetMultiMap;
+    ///
+    /// let mut map = SetMultiMap::new();
+    /// map.insert(1, 42);
+    /// map.insert(1, 43);
+    /// map.insert(2, 1337);
+    /// assert_eq!(map.len(), 2);
+    /// ```
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// Returns true if the map contains no elements.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::SetMultiMap;
+    ///
+    /// let mut map = SetMultiMap::new();
+    /// assert!(map.is_empty());
+    /// map.insert(1, 42);
+    /// assert!(!map.is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    /// Returns a reference to the set of values corresponding to the key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::SetMultiMap;
+    ///
+    /// let mut map = SetMultiMap::new();
+    /// map.insert(1, 42);
+    /// map.insert(1, 42);
+    /// assert_eq!(map.get_set(&1).map(|s| s.len()), Some(1));
+    /// ```
+    pub fn get_set<Q>(&self, k: &Q) -> Option<&HashSet<V, S>>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        self.inner.get(k)
+    }
+
+    /// Removes a key from the map, returning the set of values at the key if
+    /// the key was previously in the map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::SetMultiMap;
+    ///
+    /// let mut map = SetMultiMap::new();
+    /// map.insert(1, 42);
+    /// assert_eq!(map.remove(&1).map(|s| s.len()), Some(1));
+    /// assert!(map.remove(&1).is_none());
+    /// ```
+    pub fn remove<Q>(&mut self, k: &Q) -> Option<HashSet<V, S>>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        self.inner.remove(k)
+    }
+
+    /// Removes a single key-value pair from the map. Returns true if the pair
+    /// was present. The key is removed when its last value is removed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::SetMultiMap;
+    ///
+    /// let mut map = SetMultiMap::new();
+    /// map.insert(1, 42);
+    /// assert!(map.remove_value(&1, &42));
+    /// assert!(!map.remove_value(&1, &42));
+    /// assert!(!map.contains_key(&1));
+    /// ```
+    pub fn remove_value<Q, W>(&mut self, k: &Q, v: &W) -> bool
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+        V: Borrow<W>,
+        W: ?Sized + Eq + Hash,
+    {
+        let (removed, now_empty) = match self.inner.get_mut(k) {
+            Some(set) => (set.remove(v), set.is_empty()),
+            None => return false,
+        };
+        if now_empty {
+            self.inner.remove(k);
+        }
+        removed
+    }
+
+    /// Clears the map, removing all key-value pairs.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::SetMultiMap;
+    ///
+    /// let mut map = SetMultiMap::new();
+    /// map.insert(1, 42);
+    /// map.clear();
+    /// assert!(map.is_empty());
+    /// ```
+    pub fn clear(&mut self) {
+        self.inner.clear();
+    }
+
+    /// An iterator visiting all keys in arbitrary order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::SetMultiMap;
+    ///
+    /// let mut map = SetMultiMap::new();
+    /// map.insert(1, 42);
+    /// map.insert(2, 42);
+    ///
+    /// let mut keys: Vec<_> = map.keys().collect();
+    /// keys.sort();
+    /// assert_eq!(keys, [&1, &2]);
+    /// ```
+    pub fn keys(&self) -> Keys<'_, K, HashSet<V, S>> {
+        self.inner.keys()
+    }
+
+    /// An iterator visiting all keys and their sets of values in arbitrary order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::SetMultiMap;
+    ///
+    /// let mut map = SetMultiMap::new();
+    /// map.insert(1, 42);
+    /// map.insert(1, 43);
+    ///
+    /// for (key, values) in map.iter_all() {
+    ///     assert_eq!(values.len(), 2);
+    /// }
+    /// ```
+    pub fn iter_all(&self) -> IterAll<'_, K, HashSet<V, S>> {
+        self.inner.iter()
+    }
+
+    /// An iterator visiting every key-value pair in arbitrary order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::SetMultiMap;
+    ///
+    /// let mut map = SetMultiMap::new();
+    /// map.insert(1, 42);
+    /// map.insert(1, 43);
+    /// map.insert(2, 42);
+    ///
+    /// let mut pairs: Vec<_> = map.flat_iter().collect();
+    /// pairs.sort();
+    /// assert_eq!(pairs, [(&1, &42), (&1, &43), (&2, &42)]);
+    /// ```
+    pub fn flat_iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.iter_all()
+            .flat_map(|(k, v)| v.iter().map(move |i| (k, i)))
+    }
+
+    /// Retains only the key-value pairs specified by the predicate. Keys left
+    /// without values are removed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::SetMultiMap;
+    ///
+    /// let mut map = SetMultiMap::new();
+    /// map.insert(1, 42);
+    /// map.insert(1, 99);
+    /// map.insert(2, 42);
+    /// map.retain(|&k, &v| k == 1 && v == 42);
+    /// assert_eq!(map.len(), 1);
+    /// assert!(map.contains(&1, &42));
+    /// ```
+    pub fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&K, &V) -> bool,
+    {
+        for (key, set) in &mut self.inner {
+            set.retain(|value| f(key, value));
+        }
+        self.inner.retain(|_, v| !v.is_empty());
+    }
+
+    fn inner_hasher(&self) -> S {
+        self.inner.hasher().clone()
+    }
+}
+
+impl<K, V, S> Debug for SetMultiMap<K, V, S>
+where
+    K: Eq + Hash + Debug,
+    V: Eq + Hash + Debug,
+    S: BuildHasher + Clone,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_map().entries(self.iter_all()).finish()
+    }
+}
+
+impl<K, V, S> PartialEq for SetMultiMap<K, V, S>
+where
+    K: Eq + Hash,
+    V: Eq + Hash,
+    S: BuildHasher,
+{
+    fn eq(&self, other: &SetMultiMap<K, V, S>) -> bool {
+        self.inner == other.inner
+    }
+}
+
+impl<K, V, S> Eq for SetMultiMap<K, V, S>
+where
+    K: Eq + Hash,
+    V: Eq + Hash,
+    S: BuildHasher,
+{
+}
+
+impl<K, V, S> Default for SetMultiMap<K, V, S>
+where
+    K: Eq + Hash,
+    S: BuildHasher + Default,
+{
+    fn default() -> SetMultiMap<K, V, S> {
+        SetMultiMap {
+            inner: Default::default(),
+        }
+    }
+}
+
+impl<K, V, S> FromIterator<(K, V)> for SetMultiMap<K, V, S>
+where
+    K: Eq + Hash,
+    V: Eq + Hash,
+    S: BuildHasher + Clone + Default,
+{
+    fn from_iter<T: IntoIterator<Item = (K, V)>>(iterable: T) -> SetMultiMap<K, V, S> {
+        let mut multimap = SetMultiMap::with_hasher(S::default());
+        multimap.extend(iterable);
+        multimap
+    }
+}
+
+impl<K, V, S> Extend<(K, V)> for SetMultiMap<K, V, S>
+where
+    K: Eq + Hash,
+    V: Eq + Hash,
+    S: BuildHasher + Clone,
+{
+    fn extend<T: IntoIterator<Item = (K, V)>>(&mut self, iter: T) {
+        for (k, v) in iter {
+            self.insert(k, v);
+        }
+    }
+}
+
+impl<'a, K, V, S> IntoIterator for &'a SetMultiMap<K, V, S>
+where
+    K: Eq + Hash,
+    V: Eq + Hash,
+    S: BuildHasher + Clone,
+{
+    type Item = (&'a K, &'a HashSet<V, S>);
+    type IntoIter = IterAll<'a, K, HashSet<V, S>>;
+
+    fn into_iter(self) -> IterAll<'a, K, HashSet<V, S>> {
+        self.iter_all()
+    }
+}
+
+impl<K, V, S> IntoIterator for SetMultiMap<K, V, S> {
+    type Item = (K, HashSet<V, S>);
+    type IntoIter = hash_map::IntoIter<K, HashSet<V, S>>;
+
+    fn into_iter(self) -> hash_map::IntoIter<K, HashSet<V, S>> {
+        self.inner.into_iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn insert_duplicate_is_noop() {
+        let mut m = SetMultiMap::new();
+        assert!(m.insert(1, 42));
+        assert!(!m.insert(1, 42));
+        assert!(m.insert(1, 43));
+        assert_eq!(m.get_set(&1).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn remove_value_removes_empty_key() {
+        let mut m = SetMultiMap::new();
+        m.insert(1, 42);
+        m.insert(1, 43);
+        assert!(m.remove_value(&1, &42));
+        assert!(m.contains_key(&1));
+        assert!(m.remove_value(&1, &43));
+        assert!(!m.contains_key(&1));
+        assert!(!m.remove_value(&1, &43));
+    }
+
+    #[test]
+    fn from_iterator_dedups() {
+        let m: SetMultiMap<&str, i32> = vec![("a", 1), ("a", 1), ("b", 2), ("a", 3)]
+            .into_iter()
+            .collect();
+        assert_eq!(m.flat_iter().count(), 3);
+        assert!(m.contains("a", &3));
+    }
+
+    #[test]
+    fn test_eq() {
+        let mut m1 = SetMultiMap::new();
+        m1.insert(1, 2);
+        m1.insert(1, 3);
+        let mut m2 = SetMultiMap::new();
+        m2.insert(1, 3);
+        m2.insert(1, 2);
+        m2.insert(1, 2);
+        assert_eq!(m1, m2);
+    }
+}
@@ -0,0 +1,622 @@
+// Copyright (c) 2016 multimap developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! A multimap which keeps the values of each key in sorted order.
+//!
+//! SortedMultiMap stores its values as a std::Vec per key, just like MultiMap,
+//! but every insertion places the value at its sorted position. Values which
+//! compare equal are kept in insertion order. Since the order is an invariant
+//! of the map, values are only handed out as (immutable) slices.
+//!
+//! # Examples
+//!
+//! ```
+//! use multimap::SortedMultiMap;
+//!
+//! let mut readings = SortedMultiMap::new();
+//! readings.insert("sensor", 30);
+//! readings.insert("sensor", 10);
+//! readings.insert("sensor", 20);
+//!
+//! assert_eq!(readings.get_slice("sensor"), Some(&[10, 20, 30][..]));
+//! assert!(readings.contains_value("sensor", &20));
+//! ```
+
+use std::borrow::Borrow;
+use std::collections::hash_map::{self, Keys, RandomState};
+use std::collections::HashMap;
+use std::fmt::{self, Debug};
+use std::hash::{BuildHasher, Hash};
+use std::iter::{FromIterator, IntoIterator, Iterator};
+use std::ops::{Bound, RangeBounds};
+use std::vec::Vec;
+
+#[derive(Clone)]
+pub struct SortedMultiMap<K, V, S = RandomState> {
+    inner: HashMap<K, Vec<V>, S>,
+}
+
+impl<K, V> SortedMultiMap<K, V>
+where
+    K: Eq + Hash,
+    V: Ord,
+{
+    /// Creates an empty SortedMultiMap
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::SortedMultiMap;
+    ///
+    /// let mut map: SortedMultiMap<&str, isize> = SortedMultiMap::new();
+    /// ```
+    pub fn new() -> SortedMultiMap<K, V> {
+        SortedMultiMap {
+            inner: HashMap::new(),
+        }
+    }
+
+    /// Creates an empty SortedMultiMap with the given initial capacity.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::SortedMultiMap;
+    ///
+    /// let mut map: SortedMultiMap<&str, isize> = SortedMultiMap::with_capacity(20);
+    /// ```
+    pub fn with_capacity(capacity: usize) -> SortedMultiMap<K, V> {
+        SortedMultiMap {
+            inner: HashMap::with_capacity(capacity),
+        }
+    }
+}
+
+impl<K, V, S> SortedMultiMap<K, V, S>
+where
+    K: Eq + Hash,
+    V: Ord,
+    S: BuildHasher,
+{
+    /// Creates an empty SortedMultiMap which will use the given hash builder to hash keys.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::SortedMultiMap;
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let s = RandomState::new();
+    /// let mut map: SortedMultiMap<&str, isize> = SortedMultiMap::with_hasher(s);
+    /// ```
+    pub fn with_hasher(hash_builder: S) -> SortedMultiMap<K, V, S> {
+        SortedMultiMap {
+            inner: HashMap::with_hasher(hash_builder),
+        }
+    }
+
+    /// Inserts a key-value pair into the multimap. The value is placed after
+    /// every value of the key which is less than or equal to it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::SortedMultiMap;
+    ///
+    /// let mut map = SortedMultiMap::new();
+    /// map.insert("key", 42);
+    /// map.insert("key", 7);
+    /// assert_eq!(map.get_slice("key"), Some(&[7, 42][..]));
+    /// ```
+    pub fn insert(&mut self, k: K, v: V) {
+        match self.inner.entry(k) {
+            hash_map::Entry::Occupied(mut entry) => {
+                let values = entry.get_mut();
+                let index = values.partition_point(|x| *x <= v);
+                values.insert(index, v);
+            }
+            hash_map::Entry::Vacant(entry) => {
+                entry.insert(vec![v]);
+            }
+        }
+    }
+
+    /// Inserts multiple values for a key into the multimap, keeping the
+    /// values of the key sorted.
+    ///
+    /// This may be more efficient than inserting values independently.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::SortedMultiMap;
+    ///
+    /// let mut map = SortedMultiMap::new();
+    /// map.insert("key", 2);
+    /// map.insert_many("key", vec![3, 1]);
+    /// assert_eq!(map.get_slice("key"), Some(&[1, 2, 3][..]));
+    /// ```
+    pub fn insert_many<I: IntoIterator<Item = V>>(&mut self, k: K, v: I) {
+        let values = self.inner.entry(k).or_default();
+        values.extend(v);
+        values.sort();
+    }
+
+    /// Returns true if the map contains a value for the specified key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::SortedMultiMap;
+    ///
+    /// let mut map = SortedMultiMap::new();
+    /// map.insert(1, 42);
+    /// assert_eq!(map.contains_key(&1), true);
+    /// assert_eq!(map.contains_key(&2), false);
+    /// ```
+    pub fn contains_key<Q>(&self, k: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        self.inner.contains_key(k)
+    }
+
+    /// Returns true if the given value is stored under the key. The values are
+    /// searched with a binary search.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::SortedMultiMap;
+    ///
+    /// let mut map = SortedMultiMap::new();
+    /// map.insert(1, 42);
+    /// map.insert(1, 7);
+    /// assert!(map.contains_value(&1, &42));
+    /// assert!(!map.contains_value(&1, &8));
+    /// assert!(!map.contains_value(&2, &42));
+    /// ```
+    pub fn contains_value<Q>(&self, k: &Q, v: &V) -> bool
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        self.inner
+            .get(k)
+            .is_some_and(|values| values.binary_search(v).is_ok())
+    }
+
+    /// Returns the number of keys in the map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::SortedMultiMap;
+    ///
+    /// let mut map = SortedMultiMap::new();
+    /// map.insert(1, 42);
+    /// map.insert(2, 1337);
+    /// assert_eq!(map.len(), 2);
+    /// ```
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// Returns true if the map contains no elements.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::SortedMultiMap;
+    ///
+    /// let mut map = SortedMultiMap::new();
+    /// assert!(map.is_empty());
+    /// map.insert(1, 42);
+    /// assert!(!map.is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    /// Returns a reference to the smallest value corresponding to the key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::SortedMultiMap;
+    ///
+    /// let mut map = SortedMultiMap::new();
+    /// map.insert(1, 42);
+    /// map.insert(1, 7);
+    /// assert_eq!(map.get(&1), Some(&7));
+    /// ```
+    pub fn get<Q>(&self, k: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        self.inner.get(k)?.first()
+    }
+
+    /// Returns the sorted values corresponding to the key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::SortedMultiMap;
+    ///
+    /// let mut map = SortedMultiMap::new();
+    /// map.insert(1, 1337);
+    /// map.insert(1, 42);
+    /// assert_eq!(map.get_slice(&1), Some(&[42, 1337][..]));
+    /// ```
+    pub fn get_slice<Q>(&self, k: &Q) -> Option<&[V]>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        self.inner.get(k).map(|values| &values[..])
+    }
+
+    /// Returns the values of the key which fall within the given range.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::SortedMultiMap;
+    ///
+    /// let mut map = SortedMultiMap::new();
+    /// map.insert_many("events", vec![5, 1, 9, 3, 7]);
+    /// assert_eq!(map.range("events", 3..8), Some(&[3, 5, 7][..]));
+    /// assert_eq!(map.range("events", ..=1), Some(&[1][..]));
+    /// assert_eq!(map.range("other", ..), None);
+    /// ```
+    pub fn range<Q, R>(&self, k: &Q, range: R) -> Option<&[V]>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+        R: RangeBounds<V>,
+    {
+        let values = self.inner.get(k)?;
+        let start = match range.start_bound() {
+            Bound::Included(s) => values.partition_point(|x| x < s),
+            Bound::Excluded(s) => values.partition_point(|x| x <= s),
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(e) => values.partition_point(|x| x <= e),
+            Bound::Excluded(e) => values.partition_point(|x| x < e),
+            Bound::Unbounded => values.len(),
+        };
+        Some(&values[start..end.max(start)])
+    }
+
+    /// Removes a key from the map, returning the sorted vector of values at
+    /// the key if the key was previously in the map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::SortedMultiMap;
+    ///
+    /// let mut map = SortedMultiMap::new();
+    /// map.insert(1, 1337);
+    /// map.insert(1, 42);
+    /// assert_eq!(map.remove(&1), Some(vec![42, 1337]));
+    /// assert_eq!(map.remove(&1), None);
+    /// ```
+    pub fn remove<Q>(&mut self, k: &Q) -> Option<Vec<V>>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        self.inner.remove(k)
+    }
+
+    /// Removes one occurrence of the value from the key's values, returning it.
+    /// The key is removed when its last value is removed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::SortedMultiMap;
+    ///
+    /// let mut map = SortedMultiMap::new();
+    /// map.insert(1, 42);
+    /// assert_eq!(map.remove_value(&1, &42), Some(42));
+    /// assert_eq!(map.remove_value(&1, &42), None);
+    /// assert!(!map.contains_key(&1));
+    /// ```
+    pub fn remove_value<Q>(&mut self, k: &Q, v: &V) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        let values = self.inner.get_mut(k)?;
+        let index = values.binary_search(v).ok()?;
+        let removed = values.remove(index);
+        if values.is_empty() {
+            self.inner.remove(k);
+        }
+        Some(removed)
+    }
+
+    /// Clears the map, removing all key-value pairs.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::SortedMultiMap;
+    ///
+    /// let mut map = SortedMultiMap::new();
+    /// map.insert(1, 42);
+    /// map.clear();
+    /// assert!(map.is_empty());
+    /// ```
+    pub fn clear(&mut self) {
+        self.inner.clear();
+    }
+
+    /// An iterator visiting all keys in arbitrary order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::SortedMultiMap;
+    ///
+    /// let mut map = SortedMultiMap::new();
+    /// map.insert(1, 42);
+    /// map.insert(2, 42);
+    ///
+    /// let mut keys: Vec<_> = map.keys().collect();
+    /// keys.sort();
+    /// assert_eq!(keys, [&1, &2]);
+    /// ```
+    pub fn keys(&self) -> Keys<'_, K, Vec<V>> {
+        self.inner.keys()
+    }
+
+    /// An iterator visiting all keys in arbitrary order, together with the
+    /// sorted values of each key. Iterator element type is (&'a K, &'a [V]).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::SortedMultiMap;
+    ///
+    /// let mut map = SortedMultiMap::new();
+    /// map.insert(1, 1337);
+    /// map.insert(1, 42);
+    /// map.insert(3, 2332);
+    ///
+    /// let mut pairs: Vec<_> = map.iter_all().collect();
+    /// pairs.sort_by_key(|p| p.0);
+    /// assert_eq!(pairs, [(&1, &[42, 1337][..]), (&3, &[2332][..])]);
+    /// ```
+    pub fn iter_all(&self) -> IterAll<'_, K, V> {
+        IterAll {
+            inner: self.inner.iter(),
+        }
+    }
+
+    /// An iterator visiting every key-value pair. Keys are visited in arbitrary
+    /// order, the values of a key in sorted order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::SortedMultiMap;
+    ///
+    /// let mut map = SortedMultiMap::new();
+    /// map.insert(1, 1337);
+    /// map.insert(1, 42);
+    ///
+    /// let pairs: Vec<_> = map.flat_iter().collect();
+    /// assert_eq!(pairs, [(&1, &42), (&1, &1337)]);
+    /// ```
+    pub fn flat_iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.iter_all()
+            .flat_map(|(k, v)| v.iter().map(move |i| (k, i)))
+    }
+
+    /// Retains only the key-value pairs specified by the predicate. Keys left
+    /// without values are removed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multimap::SortedMultiMap;
+    ///
+    /// let mut map = SortedMultiMap::new();
+    /// map.insert_many(1, vec![3, 1, 2]);
+    /// map.insert(2, 42);
+    /// map.retain(|&k, &v| k == 1 && v != 2);
+    /// assert_eq!(map.len(), 1);
+    /// assert_eq!(map.get_slice(&1), Some(&[1, 3][..]));
+    /// ```
+    pub fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&K, &V) -> bool,
+    {
+        for (key, vector) in &mut self.inner {
+            vector.retain(|value| f(key, value));
+        }
+        self.inner.retain(|_, v| !v.is_empty());
+    }
+}
+
+impl<K, V, S> Debug for SortedMultiMap<K, V, S>
+where
+    K: Eq + Hash + Debug,
+    V: Ord + Debug,
+    S: BuildHasher,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_map().entries(self.iter_all()).finish()
+    }
+}
+
+impl<K, V, S> PartialEq for SortedMultiMap<K, V, S>
+where
+    K: Eq + Hash,
+    V: Ord,
+    S: BuildHasher,
+{
+    fn eq(&self, other: &SortedMultiMap<K, V, S>) -> bool {
+        self.inner == other.inner
+    }
+}
+
+impl<K, V, S> Eq for SortedMultiMap<K, V, S>
+where
+    K: Eq + Hash,
+    V: Ord,
+    S: BuildHasher,
+{
+}
+
+impl<K, V, S> Default for SortedMultiMap<K, V, S>
+where
+    K: Eq + Hash,
+    S: BuildHasher + Default,
+{
+    fn default() -> SortedMultiMap<K, V, S> {
+        SortedMultiMap {
+            inner: Default::default(),
+        }
+    }
+}
+
+impl<K, V, S> FromIterator<(K, V)> for SortedMultiMap<K, V, S>
+where
+    K: Eq + Hash,
+    V: Ord,
+    S: BuildHasher + Default,
+{
+    fn from_iter<T: IntoIterator<Item = (K, V)>>(iterable: T) -> SortedMultiMap<K, V, S> {
+        let mut multimap = SortedMultiMap::default();
+        multimap.extend(iterable);
+        multimap
+    }
+}
+
+impl<K, V, S> Extend<(K, V)> for SortedMultiMap<K, V, S>
+where
+    K: Eq + Hash,
+    V: Ord,
+    S: BuildHasher,
+{
+    fn extend<T: IntoIterator<Item = (K, V)>>(&mut self, iter: T) {
+        for (k, v) in iter {
+            self.insert(k, v);
+        }
+    }
+}
+
+impl<'a, K, V, S> IntoIterator for &'a SortedMultiMap<K, V, S>
+where
+    K: Eq + Hash,
+    V: Ord,
+    S: BuildHasher,
+{
+    type Item = (&'a K, &'a [V]);
+    type IntoIter = IterAll<'a, K, V>;
+
+    fn into_iter(self) -> IterAll<'a, K, V> {
+        self.iter_all()
+    }
+}
+
+impl<K, V, S> IntoIterator for SortedMultiMap<K, V, S> {
+    type Item = (K, Vec<V>);
+    type IntoIter = hash_map::IntoIter<K, Vec<V>>;
+
+    fn into_iter(self) -> hash_map::IntoIter<K, Vec<V>> {
+        self.inner.into_iter()
+    }
+}
+
+#[derive(Clone)]
+pub struct IterAll<'a, K: 'a, V: 'a> {
+    inner: hash_map::Iter<'a, K, Vec<V>>,
+}
+
+impl<'a, K, V> Iterator for IterAll<'a, K, V> {
+    type Item = (&'a K, &'a [V]);
+
+    fn next(&mut self) -> Option<(&'a K, &'a [V])> {
+        let (k, v) = self.inner.next()?;
+        Some((k, &v[..]))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<K, V> ExactSizeIterator for IterAll<'_, K, V> {
+    fn len(&self) -> usize {
+        self.inner.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn insert_keeps_values_sorted() {
+        let mut m = SortedMultiMap::new();
+        for v in [5, 3, 8, 1, 3] {
+            m.insert(1, v);
+        }
+        assert_eq!(m.get_slice(&1), Some(&[1, 3, 3, 5, 8][..]));
+        assert_eq!(m.get(&1), Some(&1));
+    }
+
+    #[test]
+    fn equal_values_keep_insertion_order() {
+        let mut m = SortedMultiMap::new();
+        m.insert("k", (1, "first"));
+        m.insert("k", (0, "zero"));
+        m.insert("k", (1, "second"));
+        let seconds: Vec<_> = m.flat_iter().map(|(_, v)| v.1).collect();
+        assert_eq!(seconds, ["zero", "first", "second"]);
+    }
+
+    #[test]
+    fn range_excluded_bounds() {
+        let mut m = SortedMultiMap::new();
+        m.insert_many(1, vec![1, 2, 3, 4]);
+        let range = (Bound::Excluded(1), Bound::Excluded(4));
+        assert_eq!(m.range(&1, range), Some(&[2, 3][..]));
+        assert_eq!(
+            m.range(&1, (Bound::Included(3), Bound::Excluded(2))),
+            Some(&[][..])
+        );
+    }
+
+    #[test]
+    fn remove_value_removes_empty_key() {
+        let mut m = SortedMultiMap::new();
+        m.insert(1, 42);
+        m.insert(1, 43);
+        assert_eq!(m.remove_value(&1, &42), Some(42));
+        assert!(m.contains_key(&1));
+        assert_eq!(m.remove_value(&1, &43), Some(43));
+        assert!(!m.contains_key(&1));
+    }
+
+    #[test]
+    fn from_iterator() {
+        let m: SortedMultiMap<&str, i32> = vec![("a", 3), ("b", 2), ("a", 1)].into_iter().collect();
+        assert_eq!(m.get_slice("a"), Some(&[1, 3][..]));
+        assert_eq!(m.get_slice("b"), Some(&[2][..]));
+    }
+}